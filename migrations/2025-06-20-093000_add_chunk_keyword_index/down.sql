@@ -0,0 +1 @@
+DROP INDEX IF EXISTS idx_document_chunks_keyword;
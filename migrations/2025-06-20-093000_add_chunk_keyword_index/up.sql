@@ -0,0 +1,4 @@
+-- Keyword index for hybrid retrieval. The 'simple' configuration keeps identifiers
+-- like function names unstemmed; queries must use the same expression to hit the index.
+CREATE INDEX idx_document_chunks_keyword ON document_chunks
+    USING GIN (to_tsvector('simple', chunk_text));
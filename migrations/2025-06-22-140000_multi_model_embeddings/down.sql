@@ -0,0 +1,54 @@
+DROP INDEX IF EXISTS idx_chunk_embeddings_hnsw_384;
+DROP INDEX IF EXISTS idx_chunk_embeddings_hnsw_768;
+DROP INDEX IF EXISTS idx_chunk_embeddings_hnsw_1024;
+DROP INDEX IF EXISTS idx_chunk_embeddings_hnsw_1536;
+
+-- Only the original model's vectors fit back into the fixed-size column
+DELETE FROM chunk_embeddings WHERE embedding_model <> 'text-embedding-3-small';
+ALTER TABLE chunk_embeddings ALTER COLUMN embedding TYPE VECTOR(1536);
+
+CREATE INDEX idx_chunk_embeddings_hnsw ON chunk_embeddings
+    USING hnsw (embedding vector_cosine_ops)
+    WITH (m = 16, ef_construction = 64);
+
+DROP INDEX IF EXISTS idx_chunk_embeddings_model;
+ALTER TABLE chunk_embeddings DROP CONSTRAINT chunk_embeddings_pkey;
+ALTER TABLE chunk_embeddings ADD PRIMARY KEY (chunk_id);
+ALTER TABLE chunk_embeddings ALTER COLUMN embedding_model DROP NOT NULL;
+
+ALTER TABLE projects DROP COLUMN embedding_model;
+
+CREATE OR REPLACE FUNCTION match_project_chunks(
+    query_embedding VECTOR(1536),
+    project_uuid UUID,
+    match_threshold float DEFAULT 0.25,
+    match_count int DEFAULT 5
+)
+RETURNS TABLE (
+    chunk_id UUID,
+    chunk_text TEXT,
+    similarity float,
+    document_id UUID,
+    filename VARCHAR(255),
+    chunk_index INTEGER
+)
+LANGUAGE plpgsql
+AS $$
+BEGIN
+    RETURN QUERY
+    SELECT
+        dc.id as chunk_id,
+        dc.chunk_text,
+        1 - (ce.embedding <=> query_embedding) as similarity,
+        dc.document_id,
+        pd.filename,
+        dc.chunk_index
+    FROM document_chunks dc
+    JOIN chunk_embeddings ce ON dc.id = ce.chunk_id
+    JOIN project_documents pd ON dc.document_id = pd.id
+    WHERE pd.project_id = project_uuid
+        AND 1 - (ce.embedding <=> query_embedding) > match_threshold
+    ORDER BY ce.embedding <=> query_embedding
+    LIMIT match_count;
+END;
+$$;
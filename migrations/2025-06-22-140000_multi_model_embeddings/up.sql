@@ -0,0 +1,38 @@
+-- Per-project embedding model; existing projects keep the model they were embedded with
+ALTER TABLE projects ADD COLUMN embedding_model VARCHAR(100) NOT NULL DEFAULT 'text-embedding-3-small';
+
+-- Embeddings from several models coexist while a project is being re-embedded
+UPDATE chunk_embeddings SET embedding_model = 'text-embedding-3-small' WHERE embedding_model IS NULL;
+ALTER TABLE chunk_embeddings ALTER COLUMN embedding_model SET NOT NULL;
+ALTER TABLE chunk_embeddings DROP CONSTRAINT chunk_embeddings_pkey;
+ALTER TABLE chunk_embeddings ADD PRIMARY KEY (chunk_id, embedding_model);
+CREATE INDEX idx_chunk_embeddings_model ON chunk_embeddings(embedding_model);
+
+-- match_project_chunks assumes every stored vector is 1536-dimensional and is unused
+DROP FUNCTION IF EXISTS match_project_chunks(VECTOR(1536), UUID, float, int);
+
+-- The column no longer fixes a dimension. HNSW needs one, so each common size gets
+-- a partial expression index; queries cast to vector(N) to use it. Models above
+-- 2000 dimensions (e.g. text-embedding-3-large) fall back to an exact scan.
+DROP INDEX IF EXISTS idx_chunk_embeddings_hnsw;
+ALTER TABLE chunk_embeddings ALTER COLUMN embedding TYPE vector;
+
+CREATE INDEX idx_chunk_embeddings_hnsw_1536 ON chunk_embeddings
+    USING hnsw ((embedding::vector(1536)) vector_cosine_ops)
+    WITH (m = 16, ef_construction = 64)
+    WHERE vector_dims(embedding) = 1536;
+
+CREATE INDEX idx_chunk_embeddings_hnsw_1024 ON chunk_embeddings
+    USING hnsw ((embedding::vector(1024)) vector_cosine_ops)
+    WITH (m = 16, ef_construction = 64)
+    WHERE vector_dims(embedding) = 1024;
+
+CREATE INDEX idx_chunk_embeddings_hnsw_768 ON chunk_embeddings
+    USING hnsw ((embedding::vector(768)) vector_cosine_ops)
+    WITH (m = 16, ef_construction = 64)
+    WHERE vector_dims(embedding) = 768;
+
+CREATE INDEX idx_chunk_embeddings_hnsw_384 ON chunk_embeddings
+    USING hnsw ((embedding::vector(384)) vector_cosine_ops)
+    WITH (m = 16, ef_construction = 64)
+    WHERE vector_dims(embedding) = 384;
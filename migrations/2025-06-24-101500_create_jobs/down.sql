@@ -0,0 +1,6 @@
+ALTER TABLE project_documents
+    DROP COLUMN indexed_at,
+    DROP COLUMN status_error,
+    DROP COLUMN status;
+
+DROP TABLE jobs;
@@ -0,0 +1,27 @@
+-- Durable background work (document indexing, re-embedding). Workers claim rows with
+-- FOR UPDATE SKIP LOCKED; rows that exhaust their attempts stay behind as 'dead'.
+CREATE TABLE jobs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    kind VARCHAR(50) NOT NULL,
+    payload JSONB NOT NULL DEFAULT '{}',
+    status VARCHAR(20) NOT NULL DEFAULT 'queued', -- queued | running | done | dead
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL DEFAULT 5,
+    run_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    locked_at TIMESTAMP,
+    locked_by VARCHAR(100),
+    last_error TEXT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_jobs_ready ON jobs(run_at) WHERE status = 'queued';
+CREATE INDEX idx_jobs_running ON jobs(locked_at) WHERE status = 'running';
+CREATE INDEX idx_jobs_dead ON jobs(updated_at) WHERE status = 'dead';
+
+-- Indexing status per document; documents uploaded before this point are already indexed
+ALTER TABLE project_documents
+    ADD COLUMN status VARCHAR(20) NOT NULL DEFAULT 'indexed', -- queued | processing | indexed | failed
+    ADD COLUMN status_error TEXT,
+    ADD COLUMN indexed_at TIMESTAMP;
+ALTER TABLE project_documents ALTER COLUMN status SET DEFAULT 'queued';
@@ -0,0 +1,3 @@
+ALTER TABLE project_documents
+    DROP COLUMN page_count,
+    DROP COLUMN text_segments;
@@ -0,0 +1,5 @@
+-- Page / heading ranges of text extracted from PDF, DOCX, HTML and similar uploads,
+-- used to tag chunks with the page or section they came from
+ALTER TABLE project_documents
+    ADD COLUMN text_segments JSONB,
+    ADD COLUMN page_count INTEGER;
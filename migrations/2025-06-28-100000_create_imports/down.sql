@@ -0,0 +1,3 @@
+ALTER TABLE project_documents DROP COLUMN import_id;
+
+DROP TABLE imports;
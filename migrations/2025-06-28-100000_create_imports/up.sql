@@ -0,0 +1,21 @@
+-- Bulk imports of an archive or server-side directory into a project, with a
+-- running tally so the UI can show progress while files are stored
+CREATE TABLE imports (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    source VARCHAR(255) NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'running', -- running | done | failed
+    total_files INTEGER NOT NULL DEFAULT 0,
+    imported_files INTEGER NOT NULL DEFAULT 0,
+    skipped_files INTEGER NOT NULL DEFAULT 0,
+    failed_files INTEGER NOT NULL DEFAULT 0,
+    report JSONB NOT NULL DEFAULT '[]', -- [{ "path", "reason" }] for skipped and failed files
+    error TEXT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    finished_at TIMESTAMP
+);
+
+CREATE INDEX idx_imports_project ON imports(project_id, created_at DESC);
+
+ALTER TABLE project_documents
+    ADD COLUMN import_id UUID REFERENCES imports(id) ON DELETE SET NULL;
@@ -0,0 +1,10 @@
+DROP TABLE thread_citations;
+
+ALTER TABLE document_chunks DROP COLUMN content_hash;
+
+DROP TABLE document_versions;
+
+ALTER TABLE project_documents
+    DROP COLUMN content_hash,
+    DROP COLUMN indexed_version,
+    DROP COLUMN version;
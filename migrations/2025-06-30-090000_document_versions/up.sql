@@ -0,0 +1,43 @@
+-- Re-uploading a document under the same path creates a new version instead of a
+-- duplicate. Every version's text is kept; chunks carry a hash of their text so an
+-- update only re-embeds the chunks that actually changed.
+ALTER TABLE project_documents
+    ADD COLUMN version INTEGER NOT NULL DEFAULT 1,
+    ADD COLUMN indexed_version INTEGER, -- version the current chunks were built from
+    ADD COLUMN content_hash VARCHAR(64);
+
+UPDATE project_documents SET indexed_version = 1 WHERE status = 'indexed';
+
+CREATE TABLE document_versions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    document_id UUID NOT NULL REFERENCES project_documents(id) ON DELETE CASCADE,
+    version INTEGER NOT NULL,
+    content TEXT NOT NULL,
+    content_type VARCHAR(100),
+    file_size INTEGER,
+    text_segments JSONB,
+    page_count INTEGER,
+    content_hash VARCHAR(64),
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (document_id, version)
+);
+
+INSERT INTO document_versions (document_id, version, content, content_type, file_size, text_segments, page_count, created_at)
+SELECT id, 1, content, content_type, file_size, text_segments, page_count, created_at
+FROM project_documents;
+
+ALTER TABLE document_chunks ADD COLUMN content_hash VARCHAR(64);
+
+-- Which document version each answer in a thread was grounded on
+CREATE TABLE thread_citations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    thread_id VARCHAR(255) NOT NULL REFERENCES threads(id) ON DELETE CASCADE,
+    document_id UUID REFERENCES project_documents(id) ON DELETE SET NULL,
+    document_version INTEGER NOT NULL,
+    filename VARCHAR(255) NOT NULL,
+    chunk_index INTEGER NOT NULL,
+    location TEXT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_thread_citations_thread ON thread_citations(thread_id, created_at);
@@ -0,0 +1,2 @@
+-- Citations shown under an assistant answer, numbered as the inline [n] markers
+ALTER TABLE messages ADD COLUMN citations JSONB;
@@ -0,0 +1,2 @@
+ALTER TABLE messages DROP COLUMN retrieval_trace_id;
+DROP TABLE retrieval_traces;
@@ -0,0 +1,20 @@
+-- What retrieval produced for each project answer, so "why this answer" can be
+-- inspected after the fact: the ranked chunks with their scores, the strategy
+-- settings in effect and how many tokens the formatted context took.
+CREATE TABLE retrieval_traces (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    thread_id VARCHAR(255) NOT NULL REFERENCES threads(id) ON DELETE CASCADE,
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    query TEXT NOT NULL,
+    strategy JSONB NOT NULL,
+    reranker VARCHAR(255),
+    tokenizer VARCHAR(255) NOT NULL,
+    context_tokens INTEGER NOT NULL,
+    history_tokens INTEGER NOT NULL,
+    documents JSONB NOT NULL DEFAULT '[]',
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX idx_retrieval_traces_thread ON retrieval_traces(thread_id, created_at);
+
+ALTER TABLE messages
+    ADD COLUMN retrieval_trace_id UUID REFERENCES retrieval_traces(id) ON DELETE SET NULL;
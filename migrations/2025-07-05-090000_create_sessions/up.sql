@@ -0,0 +1,18 @@
+-- One row per signed-in browser. The refresh token rotates on every use; only
+-- its SHA-256 is stored. The previous hash is kept so a replayed token can be
+-- told apart from a request that raced the rotation.
+CREATE TABLE sessions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    refresh_token_hash VARCHAR(64) NOT NULL UNIQUE,
+    previous_token_hash VARCHAR(64),
+    rotated_at TIMESTAMP,
+    user_agent TEXT,
+    ip_address VARCHAR(64),
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_seen_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP NOT NULL,
+    revoked_at TIMESTAMP
+);
+CREATE INDEX idx_sessions_user ON sessions(user_id, last_seen_at);
+CREATE INDEX idx_sessions_previous_token ON sessions(previous_token_hash);
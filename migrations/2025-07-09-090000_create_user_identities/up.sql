@@ -0,0 +1,21 @@
+-- Every provider account that can sign in as a user. `users.external_id` and
+-- `users.provider` keep the account the user first signed up with; sign-in
+-- now goes through this table so one user can link several providers.
+CREATE TABLE user_identities (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    provider VARCHAR NOT NULL,
+    external_id VARCHAR NOT NULL,
+    email VARCHAR,
+    email_verified BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_login_at TIMESTAMP,
+    UNIQUE (provider, external_id)
+);
+CREATE INDEX idx_user_identities_user ON user_identities(user_id);
+CREATE INDEX idx_user_identities_email ON user_identities(LOWER(email)) WHERE email_verified;
+
+-- Existing accounts keep signing in with the identity they were created from
+INSERT INTO user_identities (user_id, provider, external_id, email, created_at)
+SELECT id, provider, external_id, email, COALESCE(created_at, CURRENT_TIMESTAMP)
+FROM users;
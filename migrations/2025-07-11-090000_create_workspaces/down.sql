@@ -0,0 +1,4 @@
+ALTER TABLE threads DROP COLUMN visibility;
+ALTER TABLE projects DROP COLUMN workspace_id;
+DROP TABLE workspace_members;
+DROP TABLE workspaces;
@@ -0,0 +1,27 @@
+-- Workspaces share projects between their members. A project with no
+-- workspace stays personal to `projects.user_id`; in a workspace, access comes
+-- from the member's role: viewers read, editors change documents and settings,
+-- owners also manage members and delete projects.
+CREATE TABLE workspaces (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE workspace_members (
+    workspace_id UUID NOT NULL REFERENCES workspaces(id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role VARCHAR(16) NOT NULL CHECK (role IN ('owner', 'editor', 'viewer')),
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (workspace_id, user_id)
+);
+CREATE INDEX idx_workspace_members_user ON workspace_members(user_id);
+
+-- Deleting a workspace hands its projects back to whoever created them
+ALTER TABLE projects ADD COLUMN workspace_id UUID REFERENCES workspaces(id) ON DELETE SET NULL;
+CREATE INDEX idx_projects_workspace ON projects(workspace_id);
+
+-- Threads stay private to their author unless shared with the project's workspace
+ALTER TABLE threads ADD COLUMN visibility VARCHAR(16) NOT NULL DEFAULT 'private'
+    CHECK (visibility IN ('private', 'workspace'));
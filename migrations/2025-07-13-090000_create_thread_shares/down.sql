@@ -0,0 +1 @@
+DROP TABLE thread_shares;
@@ -0,0 +1,15 @@
+-- Public read-only snapshots of a thread. The messages are copied at share
+-- time, so later edits, branches or deleting the thread don't change the page.
+CREATE TABLE thread_shares (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    token VARCHAR(64) NOT NULL UNIQUE,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    thread_id VARCHAR(255) REFERENCES threads(id) ON DELETE SET NULL,
+    title VARCHAR(255),
+    messages JSONB NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP,
+    revoked_at TIMESTAMP
+);
+CREATE INDEX idx_thread_shares_user ON thread_shares(user_id);
+CREATE INDEX idx_thread_shares_thread ON thread_shares(thread_id);
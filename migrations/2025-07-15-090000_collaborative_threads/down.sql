@@ -0,0 +1,4 @@
+UPDATE threads SET visibility = 'workspace' WHERE visibility = 'collaborative';
+ALTER TABLE threads DROP CONSTRAINT threads_visibility_check;
+ALTER TABLE threads ADD CONSTRAINT threads_visibility_check
+    CHECK (visibility IN ('private', 'workspace'));
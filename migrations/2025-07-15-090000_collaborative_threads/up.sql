@@ -0,0 +1,4 @@
+-- collaborative threads: every workspace editor can post, not just the author
+ALTER TABLE threads DROP CONSTRAINT threads_visibility_check;
+ALTER TABLE threads ADD CONSTRAINT threads_visibility_check
+    CHECK (visibility IN ('private', 'workspace', 'collaborative'));
@@ -9,6 +9,7 @@ use std::time::Duration;
 
 use crate::auth::auth_components::{AdminLogin, ProtectedAdminPanel};
 use crate::auth::context::AuthProvider;
+use crate::pages::shared::SharedThread;
 use crate::pages::writersroom::WritersRoom;
 
 pub fn shell(options: LeptosOptions) -> impl IntoView {
@@ -54,6 +55,7 @@ pub fn App() -> impl IntoView {
                         <Route path=StaticSegment("") view=WritersRoom/>
                         <Route path=path!("admin") view=AdminLogin/>
                         <Route path=path!("admin-panel") view=ProtectedAdminPanel/>
+                        <Route path=path!("share/:token") view=SharedThread/>
                     </Routes>
                 </main>
             </Router>
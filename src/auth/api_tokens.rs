@@ -0,0 +1,331 @@
+#[cfg(feature = "ssr")]
+pub mod api_token_server {
+    //! Personal access tokens for the `/api/v1` REST API.
+    //!
+    //! A token looks like `l3c_<prefix>_<secret>`. The prefix is stored in the
+    //! clear to find the row, the whole token only as an argon2 hash.
+
+    use axum::{
+        extract::{Request, State},
+        http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+        middleware::Next,
+        response::{IntoResponse, Response},
+        Json,
+    };
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+    use chrono::{Duration, Utc};
+    use diesel::prelude::*;
+    use diesel_async::{AsyncPgConnection, RunQueryDsl};
+    use log::{debug, warn};
+    use rand::{distributions::Alphanumeric, thread_rng, Rng};
+    use serde_json::json;
+    use std::fmt;
+    use uuid::Uuid;
+
+    use crate::auth::secure::{hash_password, verify_password};
+    use crate::auth::Claims;
+    pub use crate::models::api_tokens::{
+        SCOPES, SCOPE_PROJECTS_READ, SCOPE_PROJECTS_WRITE, SCOPE_THREADS_READ, SCOPE_THREADS_WRITE,
+    };
+    use crate::models::api_tokens::{ApiToken, CreatedApiToken, NewApiToken};
+    use crate::schema::api_tokens;
+    use crate::state::AppState;
+
+    pub const TOKEN_PREFIX: &str = "l3c";
+    const PREFIX_LEN: usize = 8;
+    /// Tokens not used for this long don't need `last_used_at` bumped again
+    const LAST_USED_RESOLUTION_MINUTES: i64 = 5;
+
+    #[derive(Debug)]
+    pub enum ApiTokenError {
+        Malformed,
+        NotFound,
+        Expired,
+        Revoked,
+        UnknownScope(String),
+        InvalidName,
+        Hash(String),
+        Database(String),
+    }
+
+    impl fmt::Display for ApiTokenError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                ApiTokenError::Malformed => write!(f, "Malformed API token"),
+                ApiTokenError::NotFound => write!(f, "API token not found"),
+                ApiTokenError::Expired => write!(f, "API token has expired"),
+                ApiTokenError::Revoked => write!(f, "API token was revoked"),
+                ApiTokenError::UnknownScope(scope) => write!(f, "Unknown scope: {scope}"),
+                ApiTokenError::InvalidName => write!(f, "Token name must be 1-255 characters"),
+                ApiTokenError::Hash(e) => write!(f, "Hashing error: {e}"),
+                ApiTokenError::Database(e) => write!(f, "Database error: {e}"),
+            }
+        }
+    }
+
+    impl std::error::Error for ApiTokenError {}
+
+    impl From<diesel::result::Error> for ApiTokenError {
+        fn from(e: diesel::result::Error) -> Self {
+            ApiTokenError::Database(e.to_string())
+        }
+    }
+
+    /// The token owner and what the token may do, set on API requests
+    #[derive(Debug, Clone)]
+    pub struct ApiPrincipal {
+        pub user_id: i32,
+        pub token_id: Uuid,
+        pub scopes: Vec<String>,
+    }
+
+    impl ApiPrincipal {
+        /// `threads:write` implies `threads:read`, likewise for projects
+        pub fn allows(&self, scope: &str) -> bool {
+            has_scope(&self.scopes, scope)
+        }
+    }
+
+    pub fn has_scope(granted: &[String], scope: &str) -> bool {
+        granted.iter().any(|g| {
+            g == scope || scope.strip_suffix(":read").is_some_and(|resource| *g == format!("{resource}:write"))
+        })
+    }
+
+    /// Returns `(prefix, token)`
+    pub fn generate_token() -> (String, String) {
+        let prefix: String = thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(PREFIX_LEN)
+            .map(|c| char::from(c).to_ascii_lowercase())
+            .collect();
+        let mut secret = [0u8; 32];
+        thread_rng().fill(&mut secret);
+        let token = format!("{TOKEN_PREFIX}_{prefix}_{}", URL_SAFE_NO_PAD.encode(secret));
+        (prefix, token)
+    }
+
+    /// The lookup prefix of a well-formed token
+    pub fn parse_token(token: &str) -> Result<&str, ApiTokenError> {
+        let rest = token.strip_prefix(TOKEN_PREFIX).and_then(|r| r.strip_prefix('_')).ok_or(ApiTokenError::Malformed)?;
+        let (prefix, secret) = rest.split_once('_').ok_or(ApiTokenError::Malformed)?;
+        let valid_prefix = prefix.len() == PREFIX_LEN && prefix.chars().all(|c| c.is_ascii_alphanumeric());
+        if !valid_prefix || secret.len() < 32 {
+            return Err(ApiTokenError::Malformed);
+        }
+        Ok(prefix)
+    }
+
+    /// Deduplicated scopes in canonical order; unknown ones are rejected
+    pub fn normalize_scopes(requested: &[String]) -> Result<Vec<String>, ApiTokenError> {
+        if let Some(unknown) = requested.iter().find(|s| !SCOPES.contains(&s.as_str())) {
+            return Err(ApiTokenError::UnknownScope(unknown.clone()));
+        }
+        Ok(SCOPES.iter().filter(|s| requested.iter().any(|r| r == *s)).map(|s| s.to_string()).collect())
+    }
+
+    /// The token from `Authorization: Bearer …`
+    pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+        headers
+            .get(AUTHORIZATION)?
+            .to_str()
+            .ok()?
+            .strip_prefix("Bearer ")
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+    }
+
+    pub async fn create_api_token(
+        conn: &mut AsyncPgConnection,
+        user_id: i32,
+        name: &str,
+        scopes: &[String],
+        expires_in_days: Option<i64>,
+    ) -> Result<CreatedApiToken, ApiTokenError> {
+        let name = name.trim();
+        if name.is_empty() || name.chars().count() > 255 {
+            return Err(ApiTokenError::InvalidName);
+        }
+        let scopes = normalize_scopes(scopes)?;
+        let (prefix, token) = generate_token();
+        let token_hash = {
+            let token = token.clone();
+            tokio::task::spawn_blocking(move || hash_password(&token))
+                .await
+                .map_err(|e| ApiTokenError::Hash(e.to_string()))?
+                .map_err(ApiTokenError::Hash)?
+        };
+
+        let created = diesel::insert_into(api_tokens::table)
+            .values(NewApiToken {
+                user_id,
+                name: name.to_string(),
+                prefix,
+                token_hash,
+                scopes,
+                expires_at: expires_in_days.filter(|d| *d > 0).map(|d| Utc::now().naive_utc() + Duration::days(d)),
+            })
+            .get_result::<ApiToken>(conn)
+            .await?;
+
+        Ok(CreatedApiToken { token, details: created.into() })
+    }
+
+    pub async fn list_api_tokens(conn: &mut AsyncPgConnection, user_id: i32) -> Result<Vec<ApiToken>, ApiTokenError> {
+        Ok(api_tokens::table
+            .filter(api_tokens::user_id.eq(user_id))
+            .filter(api_tokens::revoked_at.is_null())
+            .order(api_tokens::created_at.desc())
+            .load::<ApiToken>(conn)
+            .await?)
+    }
+
+    /// Revokes one of the user's tokens; false if it isn't theirs or is already revoked
+    pub async fn revoke_api_token(conn: &mut AsyncPgConnection, user_id: i32, token_id: Uuid) -> Result<bool, ApiTokenError> {
+        let revoked = diesel::update(
+            api_tokens::table
+                .find(token_id)
+                .filter(api_tokens::user_id.eq(user_id))
+                .filter(api_tokens::revoked_at.is_null()),
+        )
+        .set(api_tokens::revoked_at.eq(Utc::now().naive_utc()))
+        .execute(conn)
+        .await?;
+        Ok(revoked == 1)
+    }
+
+    /// Checks a presented token and records that it was used
+    pub async fn authenticate(conn: &mut AsyncPgConnection, token: &str) -> Result<ApiPrincipal, ApiTokenError> {
+        let prefix = parse_token(token)?;
+        let stored = api_tokens::table
+            .filter(api_tokens::prefix.eq(prefix))
+            .first::<ApiToken>(conn)
+            .await
+            .optional()?
+            .ok_or(ApiTokenError::NotFound)?;
+
+        let matches = {
+            let token = token.to_string();
+            let hash = stored.token_hash.clone();
+            tokio::task::spawn_blocking(move || verify_password(&token, &hash))
+                .await
+                .map_err(|e| ApiTokenError::Hash(e.to_string()))?
+                .map_err(ApiTokenError::Hash)?
+        };
+        if !matches {
+            return Err(ApiTokenError::NotFound);
+        }
+
+        let now = Utc::now().naive_utc();
+        if stored.revoked_at.is_some() {
+            return Err(ApiTokenError::Revoked);
+        }
+        if stored.expires_at.is_some_and(|expires| expires <= now) {
+            return Err(ApiTokenError::Expired);
+        }
+        let stale = stored.last_used_at.is_none_or(|used| now - used > Duration::minutes(LAST_USED_RESOLUTION_MINUTES));
+        if stale {
+            diesel::update(api_tokens::table.find(stored.id))
+                .set(api_tokens::last_used_at.eq(now))
+                .execute(conn)
+                .await?;
+        }
+
+        Ok(ApiPrincipal { user_id: stored.user_id, token_id: stored.id, scopes: stored.scopes })
+    }
+
+    fn unauthorized(message: &str) -> Response {
+        (StatusCode::UNAUTHORIZED, Json(json!({ "error": message }))).into_response()
+    }
+
+    /// Middleware for `/api/v1`: requires a personal access token as a bearer token.
+    ///
+    /// Besides the `ApiPrincipal`, `Claims` for the owner are added so handlers
+    /// shared with the cookie-authenticated routes work unchanged.
+    pub async fn require_api_token(State(app_state): State<AppState>, mut request: Request, next: Next) -> Response {
+        let Some(token) = bearer_token(request.headers()).map(str::to_string) else {
+            return unauthorized("Missing bearer token");
+        };
+
+        let principal = match app_state.pool.get().await {
+            Ok(mut conn) => authenticate(&mut conn, &token).await,
+            Err(e) => {
+                warn!("API authentication skipped, no database connection: {e}");
+                return StatusCode::SERVICE_UNAVAILABLE.into_response();
+            }
+        };
+
+        match principal {
+            Ok(principal) => {
+                debug!("API request by user {} with token {}", principal.user_id, principal.token_id);
+                let now = Utc::now();
+                request.extensions_mut().insert(Claims {
+                    sub: principal.user_id.to_string(),
+                    sid: None,
+                    exp: now.timestamp(),
+                    iat: now.timestamp(),
+                });
+                request.extensions_mut().insert(principal);
+                next.run(request).await
+            }
+            Err(ApiTokenError::Database(e)) | Err(ApiTokenError::Hash(e)) => {
+                warn!("API authentication failed: {e}");
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+            Err(e) => {
+                debug!("API authentication rejected: {e}");
+                unauthorized(&e.to_string())
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn generated_tokens_parse_back_to_their_prefix() {
+            let (prefix, token) = generate_token();
+            assert_eq!(prefix.len(), PREFIX_LEN);
+            assert!(token.starts_with("l3c_"));
+            assert_eq!(parse_token(&token).unwrap(), prefix);
+            assert_ne!(token, generate_token().1);
+        }
+
+        #[test]
+        fn malformed_tokens_are_rejected() {
+            for token in ["", "l3c_", "l3c_abc_def", "xyz_abcdefgh_0123456789012345678901234567890123", "l3c_abcdefgh"] {
+                assert!(matches!(parse_token(token), Err(ApiTokenError::Malformed)), "{token}");
+            }
+        }
+
+        #[test]
+        fn write_scopes_imply_read() {
+            let granted = vec![SCOPE_THREADS_WRITE.to_string()];
+            assert!(has_scope(&granted, SCOPE_THREADS_READ));
+            assert!(has_scope(&granted, SCOPE_THREADS_WRITE));
+            assert!(!has_scope(&granted, SCOPE_PROJECTS_READ));
+            assert!(!has_scope(&[SCOPE_PROJECTS_READ.to_string()], SCOPE_PROJECTS_WRITE));
+        }
+
+        #[test]
+        fn scopes_are_validated_and_deduplicated() {
+            let requested = vec!["projects:read".to_string(), "threads:read".to_string(), "threads:read".to_string()];
+            assert_eq!(normalize_scopes(&requested).unwrap(), vec!["threads:read", "projects:read"]);
+            assert!(matches!(normalize_scopes(&["admin".to_string()]), Err(ApiTokenError::UnknownScope(s)) if s == "admin"));
+        }
+
+        #[test]
+        fn bearer_tokens_are_read_from_the_header() {
+            let mut headers = HeaderMap::new();
+            assert_eq!(bearer_token(&headers), None);
+            headers.insert(AUTHORIZATION, "Basic abc".parse().unwrap());
+            assert_eq!(bearer_token(&headers), None);
+            headers.insert(AUTHORIZATION, "Bearer l3c_abc".parse().unwrap());
+            assert_eq!(bearer_token(&headers), Some("l3c_abc"));
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub use api_token_server::*;
@@ -1,10 +1,26 @@
 use leptos::prelude::*;
-use leptos_router::hooks::use_navigate;
+use leptos_router::hooks::{use_navigate, use_query_map};
 
-use crate::auth::{context::AuthContext, get_current_user, Logout};
+use crate::auth::{
+    context::AuthContext, get_current_user, get_login_providers, get_merge_candidate, list_api_tokens,
+    list_identities, list_sessions, CreateApiToken, Logout, LogoutEverywhere, MergeAccounts, RevokeApiToken,
+    RevokeSession, StartLinkIdentity, UnlinkIdentity,
+};
+use crate::components::audit::AuditLog;
+use crate::components::workspaces::Workspaces;
+use crate::models::api_tokens::SCOPES;
 
 #[component]
 pub fn AdminLogin() -> impl IntoView {
+    let providers = Resource::new(|| (), |_| get_login_providers());
+    let query = use_query_map();
+    // handed on to the provider round trip; the server checks it stays on this site
+    let return_to = move || {
+        query.with(|q| q.get("return_to"))
+            .map(|path| format!("?return_to={}", urlencoding::encode(&path)))
+            .unwrap_or_default()
+    };
+
     view! {
         <div class="min-h-screen bg-gray-100 dark:bg-teal-900 flex items-center justify-center">
             <div class="max-w-md w-full bg-white dark:bg-teal-800 rounded-lg shadow-md p-6">
@@ -13,49 +29,31 @@ pub fn AdminLogin() -> impl IntoView {
                 </h2>
 
                 <div class="space-y-4">
-                    <a
-
-                        href="/auth/google"
-                        target="_self"
-                        class="w-full flex items-center justify-center px-4 py-2
-                        bg-seafoam-400 dark:bg-teal-700 border border-gray-300 dark:border-teal-600 
-                        rounded-md shadow-sm text-gray-400 dark:text-gray-200 
-                        hover:bg-seafoam-600 dark:hover:bg-teal-600 transition-colors"
-                    >
-                        <svg class="w-5 h-5 mr-2" viewBox="0 0 24 24">
-                            <path
-                                fill="#4285F4"
-                                d="M22.56 12.25c0-.78-.07-1.53-.2-2.25H12v4.26h5.92c-.26 1.37-1.04 2.53-2.21 3.31v2.77h3.57c2.08-1.92 3.28-4.74 3.28-8.09z"
-                            ></path>
-                            <path
-                                fill="#34A853"
-                                d="M12 23c2.97 0 5.46-.98 7.28-2.66l-3.57-2.77c-.98.66-2.23 1.06-3.71 1.06-2.86 0-5.29-1.93-6.16-4.53H2.18v2.84C3.99 20.53 7.7 23 12 23z"
-                            ></path>
-                            <path
-                                fill="#FBBC05"
-                                d="M5.84 14.09c-.22-.66-.35-1.36-.35-2.09s.13-1.43.35-2.09V7.07H2.18C1.43 8.55 1 10.22 1 12s.43 3.45 1.18 4.93l2.85-2.22.81-.62z"
-                            ></path>
-                            <path
-                                fill="#EA4335"
-                                d="M12 5.38c1.62 0 3.06.56 4.21 1.64l3.15-3.15C17.45 2.09 14.97 1 12 1 7.7 1 3.99 3.47 2.18 7.07l3.66 2.84c.87-2.6 3.3-4.53 6.16-4.53z"
-                            ></path>
-                        </svg>
-                        Continue with Google
-                    </a>
-
-                    <a
-                        href="/auth/discord"
-                        target="_self"
-                        class="w-full flex items-center justify-center px-4 py-2
-                        bg-seafoam-400 dark:bg-teal-700 border border-gray-300 dark:border-teal-600 
-                        rounded-md shadow-sm text-gray-400 dark:text-gray-200 
-                        hover:bg-seafoam-600 dark:hover:bg-teal-600 transition-colors"
-                    >
-                        <svg class="w-5 h-5 mr-2 fill-white" viewBox="0 0 24 24">
-                            <path d="M20.317 4.3698a19.7913 19.7913 0 00-4.8851-1.5152.0741.0741 0 00-.0785.0371c-.211.3753-.4447.8648-.6083 1.2495-1.8447-.2762-3.68-.2762-5.4868 0-.1636-.3933-.4058-.8742-.6177-1.2495a.077.077 0 00-.0785-.037 19.7363 19.7363 0 00-4.8852 1.515.0699.0699 0 00-.0321.0277C.5334 9.0458-.319 13.5799.0992 18.0578a.0824.0824 0 00.0312.0561c2.0528 1.5076 4.0413 2.4228 5.9929 3.0294a.0777.0777 0 00.0842-.0276c.4616-.6304.8731-1.2952 1.226-1.9942a.076.076 0 00-.0416-.1057c-.6528-.2476-1.2743-.5495-1.8722-.8923a.077.077 0 01-.0076-.1277c.1258-.0943.2517-.1923.3718-.2914a.0743.0743 0 01.0776-.0105c3.9278 1.7933 8.18 1.7933 12.0614 0a.0739.0739 0 01.0785.0095c.1202.099.246.1981.3728.2924a.077.077 0 01-.0066.1276 12.2986 12.2986 0 01-1.873.8914.0766.0766 0 00-.0407.1067c.3604.698.7719 1.3628 1.225 1.9932a.076.076 0 00.0842.0286c1.961-.6067 3.9495-1.5219 6.0023-3.0294a.077.077 0 00.0313-.0552c.5004-5.177-.8382-9.6739-3.5485-13.6604a.061.061 0 00-.0312-.0286zM8.02 15.3312c-1.1825 0-2.1569-1.0857-2.1569-2.419 0-1.3332.9555-2.4189 2.157-2.4189 1.2108 0 2.1757 1.0952 2.1568 2.419-.0002 1.3332-.9555 2.4189-2.1569 2.4189zm7.9748 0c-1.1825 0-2.1569-1.0857-2.1569-2.419 0-1.3332.9554-2.4189 2.1569-2.4189 1.2108 0 2.1757 1.0952 2.1568 2.419 0 1.3332-.9554 2.4189-2.1568 2.4189Z"></path>
-                        </svg>
-                        Continue with Discord
-                    </a>
+                    <Suspense fallback=|| view! { <div class="text-center text-gray-400">"Loading..."</div> }>
+                        {move || providers.get().map(|result| match result {
+                            Ok(list) if list.is_empty() => view! {
+                                <p class="text-center text-gray-500 dark:text-gray-300">
+                                    "No sign-in providers are configured."
+                                </p>
+                            }.into_any(),
+                            Ok(list) => list.into_iter().map(|provider| view! {
+                                <a
+                                    href=format!("/auth/{}{}", provider.id, return_to())
+                                    target="_self"
+                                    class="w-full flex items-center justify-center px-4 py-2
+                                    bg-seafoam-400 dark:bg-teal-700 border border-gray-300 dark:border-teal-600 
+                                    rounded-md shadow-sm text-gray-400 dark:text-gray-200 
+                                    hover:bg-seafoam-600 dark:hover:bg-teal-600 transition-colors"
+                                >
+                                    <ProviderIcon id=provider.id.clone()/>
+                                    {format!("Continue with {}", provider.display_name)}
+                                </a>
+                            }).collect_view().into_any(),
+                            Err(e) => view! {
+                                <p class="text-center text-red-500">{format!("Could not load providers: {e}")}</p>
+                            }.into_any(),
+                        })}
+                    </Suspense>
                 </div>
 
                 <div class="mt-6 text-center">
@@ -71,6 +69,42 @@ pub fn AdminLogin() -> impl IntoView {
     }.into_any()
 }
 
+#[component]
+fn ProviderIcon(id: String) -> impl IntoView {
+    match id.as_str() {
+        "google" => view! {
+            <svg class="w-5 h-5 mr-2" viewBox="0 0 24 24">
+                <path
+                    fill="#4285F4"
+                    d="M22.56 12.25c0-.78-.07-1.53-.2-2.25H12v4.26h5.92c-.26 1.37-1.04 2.53-2.21 3.31v2.77h3.57c2.08-1.92 3.28-4.74 3.28-8.09z"
+                ></path>
+                <path
+                    fill="#34A853"
+                    d="M12 23c2.97 0 5.46-.98 7.28-2.66l-3.57-2.77c-.98.66-2.23 1.06-3.71 1.06-2.86 0-5.29-1.93-6.16-4.53H2.18v2.84C3.99 20.53 7.7 23 12 23z"
+                ></path>
+                <path
+                    fill="#FBBC05"
+                    d="M5.84 14.09c-.22-.66-.35-1.36-.35-2.09s.13-1.43.35-2.09V7.07H2.18C1.43 8.55 1 10.22 1 12s.43 3.45 1.18 4.93l2.85-2.22.81-.62z"
+                ></path>
+                <path
+                    fill="#EA4335"
+                    d="M12 5.38c1.62 0 3.06.56 4.21 1.64l3.15-3.15C17.45 2.09 14.97 1 12 1 7.7 1 3.99 3.47 2.18 7.07l3.66 2.84c.87-2.6 3.3-4.53 6.16-4.53z"
+                ></path>
+            </svg>
+        }.into_any(),
+        "discord" => view! {
+            <svg class="w-5 h-5 mr-2 fill-white" viewBox="0 0 24 24">
+                <path d="M20.317 4.3698a19.7913 19.7913 0 00-4.8851-1.5152.0741.0741 0 00-.0785.0371c-.211.3753-.4447.8648-.6083 1.2495-1.8447-.2762-3.68-.2762-5.4868 0-.1636-.3933-.4058-.8742-.6177-1.2495a.077.077 0 00-.0785-.037 19.7363 19.7363 0 00-4.8852 1.515.0699.0699 0 00-.0321.0277C.5334 9.0458-.319 13.5799.0992 18.0578a.0824.0824 0 00.0312.0561c2.0528 1.5076 4.0413 2.4228 5.9929 3.0294a.0777.0777 0 00.0842-.0276c.4616-.6304.8731-1.2952 1.226-1.9942a.076.076 0 00-.0416-.1057c-.6528-.2476-1.2743-.5495-1.8722-.8923a.077.077 0 01-.0076-.1277c.1258-.0943.2517-.1923.3718-.2914a.0743.0743 0 01.0776-.0105c3.9278 1.7933 8.18 1.7933 12.0614 0a.0739.0739 0 01.0785.0095c.1202.099.246.1981.3728.2924a.077.077 0 01-.0066.1276 12.2986 12.2986 0 01-1.873.8914.0766.0766 0 00-.0407.1067c.3604.698.7719 1.3628 1.225 1.9932a.076.076 0 00.0842.0286c1.961-.6067 3.9495-1.5219 6.0023-3.0294a.077.077 0 00.0313-.0552c.5004-5.177-.8382-9.6739-3.5485-13.6604a.061.061 0 00-.0312-.0286zM8.02 15.3312c-1.1825 0-2.1569-1.0857-2.1569-2.419 0-1.3332.9555-2.4189 2.157-2.4189 1.2108 0 2.1757 1.0952 2.1568 2.419-.0002 1.3332-.9555 2.4189-2.1569 2.4189zm7.9748 0c-1.1825 0-2.1569-1.0857-2.1569-2.419 0-1.3332.9554-2.4189 2.1569-2.4189 1.2108 0 2.1757 1.0952 2.1568 2.419 0 1.3332-.9554 2.4189-2.1568 2.4189Z"></path>
+            </svg>
+        }.into_any(),
+        _ => view! {
+            <svg class="w-5 h-5 mr-2" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2">
+                <path d="M15 7a2 2 0 0 1 2 2m4 0a6 6 0 0 1-7.743 5.743L11 17H9v2H7v2H4a1 1 0 0 1-1-1v-2.586a1 1 0 0 1 .293-.707l5.964-5.964A6 6 0 1 1 21 9z"></path>
+            </svg>
+        }.into_any(),
+    }
+}
+
 #[component]
 pub fn ProtectedAdminPanel() -> impl IntoView {
     let current_user = Resource::new(|| (), |_| get_current_user());
@@ -139,6 +173,12 @@ pub fn ProtectedAdminPanel() -> impl IntoView {
                                                     </div>
                                                 </div>
                                             </div>
+
+                                            <LinkedAccounts/>
+                                            <ActiveSessions/>
+                                            <ApiTokens/>
+                                            <Workspaces/>
+                                            <AuditLog/>
                                         </div>
                                     </div>
                                 }
@@ -213,3 +253,384 @@ pub fn LogoutButton() -> impl IntoView {
         </button>
     }.into_any()
 }
+
+/// Providers the current user can sign in with, plus the merge offered when a
+/// provider being linked already belongs to another account
+#[component]
+pub fn LinkedAccounts() -> impl IntoView {
+    let link_action = ServerAction::<StartLinkIdentity>::new();
+    let unlink_action = ServerAction::<UnlinkIdentity>::new();
+    let merge_action = ServerAction::<MergeAccounts>::new();
+    let identities = Resource::new(
+        move || (unlink_action.version().get(), merge_action.version().get()),
+        |_| list_identities(),
+    );
+    let providers = Resource::new(|| (), |_| get_login_providers());
+    let query = use_query_map();
+    let merge_token = move || query.with(|q| q.get("merge")).filter(|t| !t.is_empty());
+    let merge_candidate = Resource::new(merge_token, |token| async move {
+        match token {
+            Some(token) => get_merge_candidate(token).await,
+            None => Ok(None),
+        }
+    });
+    let navigate = use_navigate();
+
+    Effect::new(move |_| {
+        if let Some(Ok(url)) = link_action.value().get() {
+            if let Some(window) = web_sys::window() {
+                let _ = window.location().set_href(&url);
+            }
+        }
+    });
+    Effect::new(move |_| {
+        if let Some(Ok(_)) = merge_action.value().get() {
+            navigate("/admin-panel", Default::default());
+        }
+    });
+
+    let notice = move || {
+        query.with(|q| {
+            if let Some(provider) = q.get("linked") {
+                Some(("text-seafoam-600 dark:text-aqua-400", format!("Linked your {provider} account.")))
+            } else if q.get("error").as_deref() == Some("link_failed") {
+                Some(("text-red-500", format!("Could not link the account: {}", q.get("details").unwrap_or_default())))
+            } else {
+                None
+            }
+        })
+    };
+
+    view! {
+        <div class="mt-6 bg-white dark:bg-teal-800 rounded-lg shadow-md p-6">
+            <h3 class="text-lg font-semibold text-gray-800 dark:text-gray-200 mb-4">
+                "Linked Accounts"
+            </h3>
+            {move || notice().map(|(class, text)| view! { <p class=format!("mb-4 text-sm {class}")>{text}</p> })}
+            <Suspense fallback=|| ()>
+                {move || merge_candidate.get().map(|result| match (result, merge_token()) {
+                    (Ok(Some(candidate)), Some(token)) => view! {
+                        <div class="mb-4 p-3 text-sm rounded-md bg-seafoam-100 dark:bg-teal-700 text-gray-800 dark:text-gray-200">
+                            <p class="mb-2">
+                                {format!(
+                                    "That {} account already signs in to another account ({}) with {} threads and {} projects. Merge it into this one? The other account is deleted afterwards.",
+                                    candidate.provider,
+                                    candidate.display_name.or(candidate.email).unwrap_or_else(|| "unnamed".to_string()),
+                                    candidate.threads,
+                                    candidate.projects,
+                                )}
+                            </p>
+                            <button
+                                on:click=move |_| {
+                                    merge_action.dispatch(MergeAccounts { token: token.clone() });
+                                }
+                                disabled=move || merge_action.pending().get()
+                                class="px-3 py-1 text-sm bg-seafoam-600 hover:bg-seafoam-700 text-white rounded-md transition-colors"
+                            >
+                                "Merge accounts"
+                            </button>
+                            <a href="/admin-panel" class="ml-3 text-gray-600 dark:text-gray-300">"Cancel"</a>
+                        </div>
+                    }.into_any(),
+                    (Ok(None), Some(_)) => view! {
+                        <p class="mb-4 text-sm text-red-500">"This merge request has expired. Link the account again to retry."</p>
+                    }.into_any(),
+                    (Err(e), _) => view! {
+                        <p class="mb-4 text-sm text-red-500">{format!("Could not load the other account: {e}")}</p>
+                    }.into_any(),
+                    _ => ().into_any(),
+                })}
+            </Suspense>
+            {move || merge_action.value().get().and_then(|result| result.err()).map(|e| view! {
+                <p class="mb-4 text-sm text-red-500">{format!("Could not merge accounts: {e}")}</p>
+            })}
+            {move || unlink_action.value().get().and_then(|result| result.err()).map(|e| view! {
+                <p class="mb-4 text-sm text-red-500">{e.to_string()}</p>
+            })}
+            <Suspense fallback=|| view! { <div class="text-sm text-gray-500">"Loading accounts..."</div> }>
+                {move || identities.get().map(|result| match result {
+                    Ok(list) => {
+                        let linked: Vec<String> = list.iter().map(|i| i.provider.clone()).collect();
+                        let only_one = list.len() <= 1;
+                        view! {
+                            <ul class="divide-y divide-gray-200 dark:divide-teal-700 mb-4">
+                                {list.into_iter().map(|identity| {
+                                    let identity_id = identity.id;
+                                    view! {
+                                        <li class="py-3 flex justify-between items-center text-sm">
+                                            <div>
+                                                <div class="font-medium text-gray-800 dark:text-gray-200">{identity.provider}</div>
+                                                <div class="text-gray-500 dark:text-gray-400">
+                                                    {identity.email.unwrap_or_else(|| identity.external_id.clone())}
+                                                    {identity.email_verified.then_some(" · verified")}
+                                                    " · linked "
+                                                    {identity.created_at.format("%Y-%m-%d").to_string()}
+                                                </div>
+                                            </div>
+                                            {(!only_one).then(|| view! {
+                                                <button
+                                                    on:click=move |_| {
+                                                        unlink_action.dispatch(UnlinkIdentity { identity_id });
+                                                    }
+                                                    class="px-2 py-1 text-xs border border-gray-300 dark:border-teal-600 text-gray-600 dark:text-gray-300 rounded-md hover:bg-gray-100 dark:hover:bg-teal-700"
+                                                >
+                                                    "Unlink"
+                                                </button>
+                                            })}
+                                        </li>
+                                    }
+                                }).collect_view()}
+                            </ul>
+                            <div class="flex flex-wrap gap-2">
+                                {move || providers.get().and_then(Result::ok).map(|available| {
+                                    available.into_iter()
+                                        .filter(|p| !linked.contains(&p.id))
+                                        .map(|provider| {
+                                            let id = provider.id.clone();
+                                            view! {
+                                                <button
+                                                    on:click=move |_| {
+                                                        link_action.dispatch(StartLinkIdentity { provider: id.clone() });
+                                                    }
+                                                    disabled=move || link_action.pending().get()
+                                                    class="px-3 py-1 text-sm border border-gray-300 dark:border-teal-600 text-gray-700 dark:text-gray-200 rounded-md hover:bg-gray-100 dark:hover:bg-teal-700"
+                                                >
+                                                    {format!("Link {}", provider.display_name)}
+                                                </button>
+                                            }
+                                        })
+                                        .collect_view()
+                                })}
+                            </div>
+                        }.into_any()
+                    }
+                    Err(e) => view! {
+                        <p class="text-sm text-red-500">{format!("Could not load linked accounts: {e}")}</p>
+                    }.into_any(),
+                })}
+            </Suspense>
+            {move || link_action.value().get().and_then(|result| result.err()).map(|e| view! {
+                <p class="mt-2 text-sm text-red-500">{format!("Could not start linking: {e}")}</p>
+            })}
+        </div>
+    }.into_any()
+}
+
+/// Signed-in browsers of the current user, each revocable on its own
+#[component]
+pub fn ActiveSessions() -> impl IntoView {
+    let revoke_action = ServerAction::<RevokeSession>::new();
+    let everywhere_action = ServerAction::<LogoutEverywhere>::new();
+    let sessions = Resource::new(
+        move || revoke_action.version().get(),
+        |_| list_sessions(),
+    );
+    let navigate = use_navigate();
+    let auth = use_context::<AuthContext>().expect("AuthContext not found");
+
+    Effect::new(move |_| {
+        if let Some(Ok(_)) = everywhere_action.value().get() {
+            auth.refresh_auth();
+            navigate("/", Default::default());
+        }
+    });
+
+    view! {
+        <div class="mt-6 bg-white dark:bg-teal-800 rounded-lg shadow-md p-6">
+            <div class="flex justify-between items-center mb-4">
+                <h3 class="text-lg font-semibold text-gray-800 dark:text-gray-200">
+                    "Active Sessions"
+                </h3>
+                <button
+                    on:click=move |_| {
+                        everywhere_action.dispatch(LogoutEverywhere {});
+                    }
+                    disabled=move || everywhere_action.pending().get()
+                    class="px-3 py-1 text-sm bg-salmon-600 hover:bg-salmon-700 text-white rounded-md transition-colors"
+                >
+                    "Log out everywhere"
+                </button>
+            </div>
+            <Suspense fallback=|| view! { <div class="text-sm text-gray-500">"Loading sessions..."</div> }>
+                {move || sessions.get().map(|result| match result {
+                    Ok(list) => view! {
+                        <ul class="divide-y divide-gray-200 dark:divide-teal-700">
+                            {list.into_iter().map(|session| {
+                                let session_id = session.id;
+                                view! {
+                                    <li class="py-3 flex justify-between items-center text-sm">
+                                        <div>
+                                            <div class="font-medium text-gray-800 dark:text-gray-200">
+                                                {session.device}
+                                                {session.current.then(|| view! {
+                                                    <span class="ml-2 text-xs text-seafoam-600 dark:text-aqua-400">"This device"</span>
+                                                })}
+                                            </div>
+                                            <div class="text-gray-500 dark:text-gray-400">
+                                                {session.ip_address.unwrap_or_else(|| "Unknown address".to_string())}
+                                                " · last seen "
+                                                {session.last_seen_at.format("%Y-%m-%d %H:%M UTC").to_string()}
+                                                " · signed in "
+                                                {session.created_at.format("%Y-%m-%d").to_string()}
+                                            </div>
+                                        </div>
+                                        {(!session.current).then(|| view! {
+                                            <button
+                                                on:click=move |_| {
+                                                    revoke_action.dispatch(RevokeSession { session_id });
+                                                }
+                                                class="px-2 py-1 text-xs border border-gray-300 dark:border-teal-600 text-gray-600 dark:text-gray-300 rounded-md hover:bg-gray-100 dark:hover:bg-teal-700"
+                                            >
+                                                "Revoke"
+                                            </button>
+                                        })}
+                                    </li>
+                                }
+                            }).collect_view()}
+                        </ul>
+                    }.into_any(),
+                    Err(e) => view! {
+                        <p class="text-sm text-red-500">{format!("Could not load sessions: {e}")}</p>
+                    }.into_any(),
+                })}
+            </Suspense>
+        </div>
+    }.into_any()
+}
+
+/// Personal access tokens for the `/api/v1` REST API
+#[component]
+pub fn ApiTokens() -> impl IntoView {
+    let create_action = ServerAction::<CreateApiToken>::new();
+    let revoke_action = ServerAction::<RevokeApiToken>::new();
+    let tokens = Resource::new(
+        move || (create_action.version().get(), revoke_action.version().get()),
+        |_| list_api_tokens(),
+    );
+    let (name, set_name) = signal(String::new());
+    let (scopes, set_scopes) = signal(vec![SCOPES[0].to_string()]);
+    let (expires_in_days, set_expires_in_days) = signal(String::from("90"));
+
+    let toggle_scope = move |scope: &'static str| {
+        set_scopes.update(|scopes| {
+            if let Some(index) = scopes.iter().position(|s| s == scope) {
+                scopes.remove(index);
+            } else {
+                scopes.push(scope.to_string());
+            }
+        });
+    };
+
+    view! {
+        <div class="mt-6 bg-white dark:bg-teal-800 rounded-lg shadow-md p-6">
+            <h3 class="text-lg font-semibold text-gray-800 dark:text-gray-200 mb-4">
+                "API Tokens"
+            </h3>
+            <form
+                class="space-y-3 mb-4"
+                on:submit=move |ev| {
+                    ev.prevent_default();
+                    create_action.dispatch(CreateApiToken {
+                        name: name.get_untracked(),
+                        scopes: scopes.get_untracked(),
+                        expires_in_days: expires_in_days.get_untracked().trim().parse().ok(),
+                    });
+                    set_name.set(String::new());
+                }
+            >
+                <div class="flex gap-2">
+                    <input
+                        type="text"
+                        placeholder="Token name, e.g. CI"
+                        prop:value=name
+                        on:input=move |ev| set_name.set(event_target_value(&ev))
+                        class="flex-1 px-3 py-1 text-sm border border-gray-300 dark:border-teal-600 rounded-md dark:bg-teal-700 dark:text-gray-200"
+                    />
+                    <select
+                        on:change=move |ev| set_expires_in_days.set(event_target_value(&ev))
+                        class="px-2 py-1 text-sm border border-gray-300 dark:border-teal-600 rounded-md dark:bg-teal-700 dark:text-gray-200"
+                    >
+                        <option value="30">"30 days"</option>
+                        <option value="90" selected>"90 days"</option>
+                        <option value="365">"1 year"</option>
+                        <option value="">"No expiry"</option>
+                    </select>
+                    <button
+                        type="submit"
+                        disabled=move || create_action.pending().get() || name.get().trim().is_empty()
+                        class="px-3 py-1 text-sm bg-seafoam-600 hover:bg-seafoam-700 text-white rounded-md transition-colors"
+                    >
+                        "Create"
+                    </button>
+                </div>
+                <div class="flex flex-wrap gap-4 text-sm text-gray-700 dark:text-gray-300">
+                    {SCOPES.into_iter().map(|scope| view! {
+                        <label class="flex items-center gap-1">
+                            <input
+                                type="checkbox"
+                                prop:checked=move || scopes.get().iter().any(|s| s == scope)
+                                on:change=move |_| toggle_scope(scope)
+                            />
+                            <code>{scope}</code>
+                        </label>
+                    }).collect_view()}
+                </div>
+            </form>
+            {move || create_action.value().get().map(|result| match result {
+                Ok(created) => view! {
+                    <div class="mb-4 p-3 text-sm rounded-md bg-seafoam-100 dark:bg-teal-700 text-gray-800 dark:text-gray-200">
+                        <p class="mb-1">"Copy this token now, it won't be shown again:"</p>
+                        <code class="break-all select-all">{created.token}</code>
+                    </div>
+                }.into_any(),
+                Err(e) => view! {
+                    <p class="mb-4 text-sm text-red-500">{format!("Could not create token: {e}")}</p>
+                }.into_any(),
+            })}
+            <Suspense fallback=|| view! { <div class="text-sm text-gray-500">"Loading tokens..."</div> }>
+                {move || tokens.get().map(|result| match result {
+                    Ok(list) if list.is_empty() => view! {
+                        <p class="text-sm text-gray-500 dark:text-gray-400">"No API tokens yet."</p>
+                    }.into_any(),
+                    Ok(list) => view! {
+                        <ul class="divide-y divide-gray-200 dark:divide-teal-700">
+                            {list.into_iter().map(|token| {
+                                let token_id = token.id;
+                                view! {
+                                    <li class="py-3 flex justify-between items-center text-sm">
+                                        <div>
+                                            <div class="font-medium text-gray-800 dark:text-gray-200">
+                                                {token.name}
+                                                <code class="ml-2 text-xs text-gray-500">{format!("l3c_{}_…", token.prefix)}</code>
+                                            </div>
+                                            <div class="text-gray-500 dark:text-gray-400">
+                                                {token.scopes.join(", ")}
+                                                " · last used "
+                                                {token.last_used_at.map(|at| at.format("%Y-%m-%d %H:%M UTC").to_string())
+                                                    .unwrap_or_else(|| "never".to_string())}
+                                                " · "
+                                                {token.expires_at.map(|at| format!("expires {}", at.format("%Y-%m-%d")))
+                                                    .unwrap_or_else(|| "no expiry".to_string())}
+                                            </div>
+                                        </div>
+                                        <button
+                                            on:click=move |_| {
+                                                revoke_action.dispatch(RevokeApiToken { token_id });
+                                            }
+                                            class="px-2 py-1 text-xs border border-gray-300 dark:border-teal-600 text-gray-600 dark:text-gray-300 rounded-md hover:bg-gray-100 dark:hover:bg-teal-700"
+                                        >
+                                            "Revoke"
+                                        </button>
+                                    </li>
+                                }
+                            }).collect_view()}
+                        </ul>
+                    }.into_any(),
+                    Err(e) => view! {
+                        <p class="text-sm text-red-500">{format!("Could not load tokens: {e}")}</p>
+                    }.into_any(),
+                })}
+            </Suspense>
+        </div>
+    }.into_any()
+}
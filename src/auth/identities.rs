@@ -0,0 +1,414 @@
+#[cfg(feature = "ssr")]
+pub mod identity_server {
+    //! Provider accounts linked to a user.
+    //!
+    //! Sign-in looks the provider account up in `user_identities`. Signed-in
+    //! users can link more providers, unlink all but the last one, and merge an
+    //! account that already owns a provider they try to link.
+
+    use chrono::{Duration, NaiveDateTime, Utc};
+    use dashmap::DashMap;
+    use diesel::prelude::*;
+    use diesel::sql_types::{Integer, Nullable, Varchar};
+    use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+    use log::info;
+    use std::fmt;
+    use uuid::Uuid;
+
+    use crate::models::identities::{MergeCandidate, NewUserIdentity, UserIdentity};
+    use crate::models::users::{CreateUserView, NewUser, User};
+    use crate::schema::{api_tokens, messages, projects, sessions, thread_shares, threads, user_identities, users};
+
+    /// How long a merge offered after a link attempt can be confirmed
+    pub const PENDING_MERGE_TTL_MINUTES: i64 = 15;
+
+    diesel::define_sql_function!(fn lower(x: Nullable<Varchar>) -> Nullable<Varchar>);
+
+    #[derive(Debug)]
+    pub enum IdentityError {
+        NotFound,
+        LastIdentity,
+        MergeExpired,
+        SameAccount,
+        Database(String),
+    }
+
+    impl fmt::Display for IdentityError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                IdentityError::NotFound => write!(f, "Linked account not found"),
+                IdentityError::LastIdentity => write!(f, "The last linked account can't be removed"),
+                IdentityError::MergeExpired => write!(f, "The merge request has expired; link the account again"),
+                IdentityError::SameAccount => write!(f, "Both accounts are already the same"),
+                IdentityError::Database(e) => write!(f, "Database error: {e}"),
+            }
+        }
+    }
+
+    impl std::error::Error for IdentityError {}
+
+    impl From<diesel::result::Error> for IdentityError {
+        fn from(e: diesel::result::Error) -> Self {
+            IdentityError::Database(e.to_string())
+        }
+    }
+
+    /// Merge of `source_user_id` into `target_user_id`, offered to the target's owner
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct PendingMerge {
+        pub target_user_id: i32,
+        pub source_user_id: i32,
+        pub provider: String,
+        pub expires_at: NaiveDateTime,
+    }
+
+    pub type PendingMerges = DashMap<String, PendingMerge>;
+
+    /// Result of linking a provider account to a signed-in user
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum LinkOutcome {
+        Linked,
+        AlreadyLinked,
+        /// The provider account signs in to another user
+        OwnedBy(i32),
+    }
+
+    /// Whether a first sign-in may attach to an account with the same verified email.
+    ///
+    /// Off by default: it trusts every configured provider to verify emails honestly.
+    pub fn link_verified_emails() -> bool {
+        std::env::var("OAUTH_LINK_VERIFIED_EMAILS")
+            .map(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+            .unwrap_or(false)
+    }
+
+    /// Comparable form of an email, or `None` if it doesn't look like one
+    pub fn normalize_email(email: &str) -> Option<String> {
+        let email = email.trim().to_lowercase();
+        let (local, domain) = email.split_once('@')?;
+        (!local.is_empty() && !domain.is_empty() && !domain.contains('@')).then_some(email)
+    }
+
+    /// The email a provider account may be matched on, if its provider verified it
+    pub fn matchable_email(info: &CreateUserView) -> Option<String> {
+        info.email.as_deref().filter(|_| info.email_verified).and_then(normalize_email)
+    }
+
+    pub fn offer_merge(merges: &PendingMerges, merge: PendingMerge) -> String {
+        let now = Utc::now().naive_utc();
+        merges.retain(|_, pending| pending.expires_at > now);
+        let token = Uuid::new_v4().simple().to_string();
+        merges.insert(token.clone(), merge);
+        token
+    }
+
+    pub fn new_pending_merge(target_user_id: i32, source_user_id: i32, provider: &str) -> PendingMerge {
+        PendingMerge {
+            target_user_id,
+            source_user_id,
+            provider: provider.to_string(),
+            expires_at: Utc::now().naive_utc() + Duration::minutes(PENDING_MERGE_TTL_MINUTES),
+        }
+    }
+
+    /// The merge behind `token`, if it was offered to `user_id` and is still open
+    pub fn pending_merge(merges: &PendingMerges, token: &str, user_id: i32, now: NaiveDateTime) -> Result<PendingMerge, IdentityError> {
+        match merges.get(token).map(|entry| entry.value().clone()) {
+            Some(merge) if merge.target_user_id == user_id && merge.expires_at > now => Ok(merge),
+            _ => Err(IdentityError::MergeExpired),
+        }
+    }
+
+    async fn find_identity(conn: &mut AsyncPgConnection, provider: &str, external_id: &str) -> Result<Option<UserIdentity>, IdentityError> {
+        Ok(user_identities::table
+            .filter(user_identities::provider.eq(provider))
+            .filter(user_identities::external_id.eq(external_id))
+            .first::<UserIdentity>(conn)
+            .await
+            .optional()?)
+    }
+
+    async fn insert_identity(conn: &mut AsyncPgConnection, user_id: i32, info: &CreateUserView) -> Result<UserIdentity, IdentityError> {
+        Ok(diesel::insert_into(user_identities::table)
+            .values(NewUserIdentity {
+                user_id,
+                provider: info.provider.clone(),
+                external_id: info.external_id.clone(),
+                email: info.email.clone(),
+                email_verified: info.email_verified,
+                last_login_at: Some(Utc::now().naive_utc()),
+            })
+            .get_result(conn)
+            .await?)
+    }
+
+    /// The single user holding a verified identity with this email
+    async fn user_with_verified_email(conn: &mut AsyncPgConnection, email: &str) -> Result<Option<i32>, IdentityError> {
+        let owners: Vec<i32> = user_identities::table
+            .filter(user_identities::email_verified.eq(true))
+            .filter(lower(user_identities::email).eq(email))
+            .select(user_identities::user_id)
+            .distinct()
+            .limit(2)
+            .load(conn)
+            .await?;
+        // an email shared by several accounts is ambiguous; don't guess
+        Ok(match owners.as_slice() {
+            [user_id] => Some(*user_id),
+            _ => None,
+        })
+    }
+
+    /// Finds or creates the user a provider account signs in as
+    pub async fn sign_in(conn: &mut AsyncPgConnection, info: CreateUserView, link_by_email: bool) -> Result<User, IdentityError> {
+        if let Some(identity) = find_identity(conn, &info.provider, &info.external_id).await? {
+            diesel::update(user_identities::table.find(identity.id))
+                .set((
+                    user_identities::email.eq(&info.email),
+                    user_identities::email_verified.eq(info.email_verified),
+                    user_identities::last_login_at.eq(diesel::dsl::now),
+                ))
+                .execute(conn)
+                .await?;
+
+            let mut user = users::table.find(identity.user_id).first::<User>(conn).await?;
+            // the profile follows the account the user signed up with
+            if user.provider == info.provider && user.external_id == info.external_id {
+                diesel::update(users::table.find(user.id))
+                    .set((
+                        users::email.eq(&info.email),
+                        users::username.eq(&info.username),
+                        users::display_name.eq(&info.display_name),
+                        users::avatar_url.eq(&info.avatar_url),
+                        users::updated_at.eq(diesel::dsl::now),
+                    ))
+                    .execute(conn)
+                    .await?;
+                user.email = info.email;
+                user.username = info.username;
+                user.display_name = info.display_name;
+                user.avatar_url = info.avatar_url;
+            }
+            return Ok(user);
+        }
+
+        if link_by_email {
+            if let Some(email) = matchable_email(&info) {
+                if let Some(user_id) = user_with_verified_email(conn, &email).await? {
+                    insert_identity(conn, user_id, &info).await?;
+                    info!("Linked {} account to user {user_id} by verified email", info.provider);
+                    return Ok(users::table.find(user_id).first::<User>(conn).await?);
+                }
+            }
+        }
+
+        conn.transaction(|conn| {
+            Box::pin(async move {
+                let user = diesel::insert_into(users::table)
+                    .values(NewUser::from(info.clone()))
+                    .get_result::<User>(conn)
+                    .await?;
+                insert_identity(conn, user.id, &info).await?;
+                info!("Created new user: {:?}", user.id);
+                Ok(user)
+            })
+        })
+        .await
+    }
+
+    /// Attaches a provider account to a signed-in user
+    pub async fn link_identity(conn: &mut AsyncPgConnection, user_id: i32, info: &CreateUserView) -> Result<LinkOutcome, IdentityError> {
+        match find_identity(conn, &info.provider, &info.external_id).await? {
+            Some(identity) if identity.user_id == user_id => Ok(LinkOutcome::AlreadyLinked),
+            Some(identity) => Ok(LinkOutcome::OwnedBy(identity.user_id)),
+            None => {
+                insert_identity(conn, user_id, info).await?;
+                Ok(LinkOutcome::Linked)
+            }
+        }
+    }
+
+    pub async fn list_identities(conn: &mut AsyncPgConnection, user_id: i32) -> Result<Vec<UserIdentity>, IdentityError> {
+        Ok(user_identities::table
+            .filter(user_identities::user_id.eq(user_id))
+            .order(user_identities::created_at.asc())
+            .load(conn)
+            .await?)
+    }
+
+    /// Removes a linked account, keeping at least one way to sign in
+    pub async fn unlink_identity(conn: &mut AsyncPgConnection, user_id: i32, identity_id: i32) -> Result<(), IdentityError> {
+        conn.transaction(|conn| {
+            Box::pin(async move {
+                let owned: Vec<UserIdentity> = user_identities::table
+                    .filter(user_identities::user_id.eq(user_id))
+                    .for_update()
+                    .load(conn)
+                    .await?;
+                let Some(identity) = owned.iter().find(|i| i.id == identity_id) else {
+                    return Err(IdentityError::NotFound);
+                };
+                let Some(remaining) = owned.iter().find(|i| i.id != identity_id) else {
+                    return Err(IdentityError::LastIdentity);
+                };
+
+                diesel::delete(user_identities::table.find(identity_id)).execute(conn).await?;
+                // users keeps (provider, external_id) unique; hand it to an identity that stays
+                diesel::update(users::table.find(user_id))
+                    .filter(users::provider.eq(&identity.provider))
+                    .filter(users::external_id.eq(&identity.external_id))
+                    .set((
+                        users::provider.eq(&remaining.provider),
+                        users::external_id.eq(&remaining.external_id),
+                        users::updated_at.eq(diesel::dsl::now),
+                    ))
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            })
+        })
+        .await
+    }
+
+    /// What merging `source_user_id` would bring over, shown before confirming
+    pub async fn merge_candidate(conn: &mut AsyncPgConnection, merge: &PendingMerge) -> Result<MergeCandidate, IdentityError> {
+        let source = users::table.find(merge.source_user_id).first::<User>(conn).await.optional()?
+            .ok_or(IdentityError::MergeExpired)?;
+        let threads = threads::table.filter(threads::user_id.eq(source.id)).count().get_result(conn).await?;
+        let projects = projects::table.filter(projects::user_id.eq(source.id)).count().get_result(conn).await?;
+        Ok(MergeCandidate {
+            provider: merge.provider.clone(),
+            email: source.email,
+            display_name: source.display_name.or(source.username),
+            threads,
+            projects,
+        })
+    }
+
+    /// Moves everything `source` owns to `target` and deletes `source`.
+    ///
+    /// Returns the source's sessions, which end with it.
+    pub async fn merge_users(conn: &mut AsyncPgConnection, source: i32, target: i32) -> Result<Vec<Uuid>, IdentityError> {
+        if source == target {
+            return Err(IdentityError::SameAccount);
+        }
+        conn.transaction(|conn| {
+            Box::pin(async move {
+                diesel::update(threads::table.filter(threads::user_id.eq(source)))
+                    .set(threads::user_id.eq(target))
+                    .execute(conn)
+                    .await?;
+                diesel::update(messages::table.filter(messages::user_id.eq(source)))
+                    .set(messages::user_id.eq(target))
+                    .execute(conn)
+                    .await?;
+                diesel::update(projects::table.filter(projects::user_id.eq(source)))
+                    .set(projects::user_id.eq(target))
+                    .execute(conn)
+                    .await?;
+                diesel::update(api_tokens::table.filter(api_tokens::user_id.eq(source)))
+                    .set(api_tokens::user_id.eq(target))
+                    .execute(conn)
+                    .await?;
+                diesel::update(thread_shares::table.filter(thread_shares::user_id.eq(source)))
+                    .set(thread_shares::user_id.eq(target))
+                    .execute(conn)
+                    .await?;
+                diesel::update(user_identities::table.filter(user_identities::user_id.eq(source)))
+                    .set(user_identities::user_id.eq(target))
+                    .execute(conn)
+                    .await?;
+                // both accounts may have counted messages on the same day
+                diesel::sql_query(
+                    "INSERT INTO daily_usage (user_id, usage_date, message_count, created_at, updated_at) \
+                     SELECT $2, usage_date, message_count, created_at, NOW() FROM daily_usage WHERE user_id = $1 \
+                     ON CONFLICT (user_id, usage_date) DO UPDATE SET \
+                     message_count = COALESCE(daily_usage.message_count, 0) + COALESCE(EXCLUDED.message_count, 0), \
+                     updated_at = NOW()",
+                )
+                .bind::<Integer, _>(source)
+                .bind::<Integer, _>(target)
+                .execute(conn)
+                .await?;
+                // shared workspaces keep the stronger of the two roles
+                diesel::sql_query(
+                    "INSERT INTO workspace_members (workspace_id, user_id, role, created_at) \
+                     SELECT workspace_id, $2, role, created_at FROM workspace_members WHERE user_id = $1 \
+                     ON CONFLICT (workspace_id, user_id) DO UPDATE SET role = CASE \
+                     WHEN 'owner' IN (workspace_members.role, EXCLUDED.role) THEN 'owner' \
+                     WHEN 'editor' IN (workspace_members.role, EXCLUDED.role) THEN 'editor' \
+                     ELSE 'viewer' END",
+                )
+                .bind::<Integer, _>(source)
+                .bind::<Integer, _>(target)
+                .execute(conn)
+                .await?;
+
+                let ended: Vec<Uuid> = sessions::table
+                    .filter(sessions::user_id.eq(source))
+                    .filter(sessions::revoked_at.is_null())
+                    .select(sessions::id)
+                    .load(conn)
+                    .await?;
+                // sessions, usage rows and old memberships go with the user
+                diesel::delete(users::table.find(source)).execute(conn).await?;
+                info!("Merged user {source} into user {target}");
+                Ok(ended)
+            })
+        })
+        .await
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn info(email: Option<&str>, verified: bool) -> CreateUserView {
+            CreateUserView {
+                external_id: "1".into(),
+                provider: "google".into(),
+                email: email.map(str::to_string),
+                email_verified: verified,
+                username: None,
+                display_name: None,
+                avatar_url: None,
+            }
+        }
+
+        #[test]
+        fn emails_compare_case_insensitively() {
+            assert_eq!(normalize_email("  Ana@Example.COM "), Some("ana@example.com".to_string()));
+            assert_eq!(normalize_email("ana"), None);
+            assert_eq!(normalize_email("@example.com"), None);
+            assert_eq!(normalize_email("a@b@c"), None);
+        }
+
+        #[test]
+        fn only_verified_emails_are_matchable() {
+            assert_eq!(matchable_email(&info(Some("Ana@Example.com"), true)), Some("ana@example.com".to_string()));
+            assert_eq!(matchable_email(&info(Some("ana@example.com"), false)), None);
+            assert_eq!(matchable_email(&info(None, true)), None);
+        }
+
+        #[test]
+        fn pending_merge_belongs_to_its_target() {
+            let merges = PendingMerges::new();
+            let token = offer_merge(&merges, new_pending_merge(1, 2, "github"));
+            let now = Utc::now().naive_utc();
+            assert_eq!(pending_merge(&merges, &token, 1, now).unwrap().source_user_id, 2);
+            assert!(matches!(pending_merge(&merges, &token, 2, now), Err(IdentityError::MergeExpired)));
+            assert!(matches!(pending_merge(&merges, "unknown", 1, now), Err(IdentityError::MergeExpired)));
+        }
+
+        #[test]
+        fn pending_merge_expires() {
+            let merges = PendingMerges::new();
+            let token = offer_merge(&merges, new_pending_merge(1, 2, "github"));
+            let later = Utc::now().naive_utc() + Duration::minutes(PENDING_MERGE_TTL_MINUTES + 1);
+            assert!(matches!(pending_merge(&merges, &token, 1, later), Err(IdentityError::MergeExpired)));
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub use identity_server::*;
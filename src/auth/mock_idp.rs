@@ -0,0 +1,253 @@
+#[cfg(feature = "ssr")]
+pub mod mock_idp_server {
+    //! A minimal OpenID provider for local development and tests.
+    //!
+    //! `/authorize` signs in immediately as the `login_hint` user (or
+    //! `MOCK_IDP_USER`, default `tester`), so a full login round trip works
+    //! without any external account.
+
+    use axum::{
+        extract::{Form, Query, State},
+        http::{HeaderMap, StatusCode},
+        response::{IntoResponse, Redirect, Response},
+        routing::{get, post},
+        Json, Router,
+    };
+    use dashmap::DashMap;
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    use crate::auth::oauth::pkce_challenge;
+
+    #[derive(Debug, Clone, Serialize, PartialEq)]
+    pub struct MockUser {
+        pub sub: String,
+        pub email: String,
+        pub email_verified: bool,
+        pub preferred_username: String,
+        pub name: String,
+    }
+
+    impl MockUser {
+        /// `ana@example.com` and `ana` both sign in as the same user
+        pub fn from_hint(hint: &str) -> Self {
+            let hint = hint.trim();
+            let username = hint.split('@').next().unwrap_or(hint).to_lowercase();
+            let email = if hint.contains('@') {
+                hint.to_lowercase()
+            } else {
+                format!("{username}@example.com")
+            };
+            let mut name = username.clone();
+            if let Some(first) = name.get_mut(0..1) {
+                first.make_ascii_uppercase();
+            }
+            MockUser { sub: format!("mock|{username}"), email, email_verified: true, preferred_username: username, name }
+        }
+    }
+
+    struct PendingCode {
+        user: MockUser,
+        redirect_uri: String,
+        code_challenge: Option<String>,
+    }
+
+    #[derive(Clone)]
+    pub struct MockIdp {
+        issuer: String,
+        codes: Arc<DashMap<String, PendingCode>>,
+        tokens: Arc<DashMap<String, MockUser>>,
+    }
+
+    impl MockIdp {
+        pub fn new(issuer: impl Into<String>) -> Self {
+            Self {
+                issuer: issuer.into().trim_end_matches('/').to_string(),
+                codes: Arc::new(DashMap::new()),
+                tokens: Arc::new(DashMap::new()),
+            }
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct AuthorizeParams {
+        pub redirect_uri: String,
+        pub state: String,
+        pub code_challenge: Option<String>,
+        pub login_hint: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct TokenParams {
+        pub code: String,
+        pub redirect_uri: Option<String>,
+        pub code_verifier: Option<String>,
+    }
+
+    /// Routes to nest under the issuer's path, e.g. `/mock-idp`
+    pub fn mock_idp_router<S: Clone + Send + Sync + 'static>(issuer: impl Into<String>) -> Router<S> {
+        Router::new()
+            .route("/.well-known/openid-configuration", get(discovery))
+            .route("/authorize", get(authorize))
+            .route("/token", post(token))
+            .route("/userinfo", get(userinfo))
+            .with_state(MockIdp::new(issuer))
+    }
+
+    async fn discovery(State(idp): State<MockIdp>) -> Json<serde_json::Value> {
+        let issuer = &idp.issuer;
+        Json(json!({
+            "issuer": issuer,
+            "authorization_endpoint": format!("{issuer}/authorize"),
+            "token_endpoint": format!("{issuer}/token"),
+            "userinfo_endpoint": format!("{issuer}/userinfo"),
+            "response_types_supported": ["code"],
+            "code_challenge_methods_supported": ["S256"],
+            "scopes_supported": ["openid", "email", "profile"],
+        }))
+    }
+
+    async fn authorize(State(idp): State<MockIdp>, Query(params): Query<AuthorizeParams>) -> Response {
+        let hint = params.login_hint
+            .or_else(|| std::env::var("MOCK_IDP_USER").ok())
+            .filter(|h| !h.trim().is_empty())
+            .unwrap_or_else(|| "tester".to_string());
+
+        let code = uuid::Uuid::new_v4().to_string();
+        idp.codes.insert(code.clone(), PendingCode {
+            user: MockUser::from_hint(&hint),
+            redirect_uri: params.redirect_uri.clone(),
+            code_challenge: params.code_challenge,
+        });
+
+        let separator = if params.redirect_uri.contains('?') { '&' } else { '?' };
+        Redirect::to(&format!(
+            "{}{separator}code={}&state={}",
+            params.redirect_uri,
+            urlencoding::encode(&code),
+            urlencoding::encode(&params.state)
+        ))
+        .into_response()
+    }
+
+    async fn token(State(idp): State<MockIdp>, Form(params): Form<TokenParams>) -> Response {
+        let invalid = |description: &str| {
+            (StatusCode::BAD_REQUEST, Json(json!({ "error": "invalid_grant", "error_description": description })))
+                .into_response()
+        };
+
+        let Some((_, pending)) = idp.codes.remove(&params.code) else {
+            return invalid("unknown or used code");
+        };
+        if params.redirect_uri.is_some_and(|uri| uri != pending.redirect_uri) {
+            return invalid("redirect_uri does not match");
+        }
+        if let Some(challenge) = pending.code_challenge {
+            match params.code_verifier {
+                Some(verifier) if pkce_challenge(&verifier) == challenge => {}
+                _ => return invalid("code_verifier does not match"),
+            }
+        }
+
+        let access_token = uuid::Uuid::new_v4().to_string();
+        idp.tokens.insert(access_token.clone(), pending.user);
+        Json(json!({ "access_token": access_token, "token_type": "Bearer", "expires_in": 3600 })).into_response()
+    }
+
+    async fn userinfo(State(idp): State<MockIdp>, headers: HeaderMap) -> Response {
+        let user = headers
+            .get(axum::http::header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .and_then(|token| idp.tokens.get(token).map(|u| u.clone()));
+
+        match user {
+            Some(user) => Json(user).into_response(),
+            None => (StatusCode::UNAUTHORIZED, Json(json!({ "error": "invalid_token" }))).into_response(),
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn code_from(response: &Response) -> String {
+            let location = response.headers()[axum::http::header::LOCATION].to_str().unwrap();
+            let query = location.split_once('?').unwrap().1;
+            query.split('&').find_map(|kv| kv.strip_prefix("code=")).unwrap().to_string()
+        }
+
+        #[test]
+        fn hints_map_to_stable_users() {
+            assert_eq!(MockUser::from_hint("ana"), MockUser::from_hint("Ana@example.com"));
+            let user = MockUser::from_hint("bo@corp.test");
+            assert_eq!(user.sub, "mock|bo");
+            assert_eq!(user.email, "bo@corp.test");
+            assert_eq!(user.name, "Bo");
+        }
+
+        #[tokio::test]
+        async fn full_round_trip_with_pkce() {
+            let idp = MockIdp::new("http://localhost:3000/mock-idp/");
+            let verifier = "a-verifier-long-enough-for-the-test";
+            let response = authorize(State(idp.clone()), Query(AuthorizeParams {
+                redirect_uri: "http://localhost:3000/auth/mock/callback".to_string(),
+                state: "s1".to_string(),
+                code_challenge: Some(pkce_challenge(verifier)),
+                login_hint: Some("ana".to_string()),
+            }))
+            .await;
+            let code = code_from(&response);
+
+            let response = token(State(idp.clone()), Form(TokenParams {
+                code: code.clone(),
+                redirect_uri: Some("http://localhost:3000/auth/mock/callback".to_string()),
+                code_verifier: Some(verifier.to_string()),
+            }))
+            .await;
+            assert_eq!(response.status(), StatusCode::OK);
+            let access_token = idp.tokens.iter().next().unwrap().key().clone();
+
+            let mut headers = HeaderMap::new();
+            headers.insert(axum::http::header::AUTHORIZATION, format!("Bearer {access_token}").parse().unwrap());
+            assert_eq!(userinfo(State(idp.clone()), headers).await.status(), StatusCode::OK);
+
+            // codes are single use
+            let replay = token(State(idp), Form(TokenParams { code, redirect_uri: None, code_verifier: Some(verifier.to_string()) })).await;
+            assert_eq!(replay.status(), StatusCode::BAD_REQUEST);
+        }
+
+        #[tokio::test]
+        async fn wrong_verifier_is_rejected() {
+            let idp = MockIdp::new("http://localhost:3000/mock-idp");
+            let response = authorize(State(idp.clone()), Query(AuthorizeParams {
+                redirect_uri: "http://localhost:3000/auth/mock/callback".to_string(),
+                state: "s2".to_string(),
+                code_challenge: Some(pkce_challenge("right")),
+                login_hint: None,
+            }))
+            .await;
+
+            let response = token(State(idp.clone()), Form(TokenParams {
+                code: code_from(&response),
+                redirect_uri: None,
+                code_verifier: Some("wrong".to_string()),
+            }))
+            .await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            assert!(idp.tokens.is_empty());
+        }
+
+        #[tokio::test]
+        async fn userinfo_requires_a_known_token() {
+            let idp = MockIdp::new("http://localhost:3000/mock-idp");
+            let mut headers = HeaderMap::new();
+            headers.insert(axum::http::header::AUTHORIZATION, "Bearer nope".parse().unwrap());
+            assert_eq!(userinfo(State(idp), headers).await.status(), StatusCode::UNAUTHORIZED);
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub use mock_idp_server::*;
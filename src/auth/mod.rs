@@ -1,11 +1,17 @@
+pub mod api_tokens;
 pub mod auth_components;
 pub mod context;
+pub mod identities;
+#[cfg(feature = "ssr")]
+pub mod mock_idp;
 #[cfg(feature = "ssr")]
 pub mod oauth;
+pub mod providers;
 #[cfg(feature = "ssr")]
 pub mod secure;
 #[cfg(feature = "ssr")]
 pub mod server;
+pub mod sessions;
 mod types;
 
 pub use auth_components::*;
@@ -20,6 +26,9 @@ use serde::{Deserialize, Serialize};
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
+    /// Session the access token was issued for
+    #[serde(default)]
+    pub sid: Option<String>,
     pub exp: i64,
     pub iat: i64,
 }
@@ -29,10 +38,14 @@ impl Claims {
     pub fn user_id(&self) -> Result<i32, std::num::ParseIntError> {
         self.sub.parse()
     }
+
+    pub fn session_id(&self) -> Option<uuid::Uuid> {
+        self.sid.as_deref().and_then(|sid| sid.parse().ok())
+    }
 }
 
 #[cfg(feature = "ssr")]
-pub fn create_jwt_token(user_id: i32) -> Result<String, jsonwebtoken::errors::Error> {
+pub fn create_jwt_token(user_id: i32, session_id: uuid::Uuid) -> Result<String, jsonwebtoken::errors::Error> {
     use jsonwebtoken::{encode, Header, EncodingKey};
     
     let secret = std::env::var("JWT_SECRET").expect("JWT_SECRET must be set");
@@ -40,7 +53,8 @@ pub fn create_jwt_token(user_id: i32) -> Result<String, jsonwebtoken::errors::Er
     
     let claims = Claims {
         sub: user_id.to_string(),
-        exp: (now + chrono::Duration::hours(24)).timestamp(),
+        sid: Some(session_id.to_string()),
+        exp: (now + sessions::access_token_ttl()).timestamp(),
         iat: now.timestamp(),
     };
     
@@ -82,7 +96,7 @@ pub async fn verify_token() -> Result<bool, leptos::server_fn::ServerFnError> {
         
         if let Some(cookie) = jar.get("auth_token") {
             match verify_jwt_token(cookie.value()) {
-                Ok(_) => Ok(true),
+                Ok(claims) => Ok(claims.session_id().is_some()),
                 Err(_) => Ok(false),
             }
         } else {
@@ -91,80 +105,429 @@ pub async fn verify_token() -> Result<bool, leptos::server_fn::ServerFnError> {
     }
 }
 
+/// The signed-in user and session of the current request.
+///
+/// Expired access tokens have already been refreshed by the
+/// `refresh_sessions` middleware, so only the access token is checked here,
+/// together with its session still being live.
+#[cfg(feature = "ssr")]
+pub async fn current_session() -> Result<Option<(crate::models::users::User, uuid::Uuid)>, leptos::server_fn::ServerFnError> {
+    use axum_extra::extract::cookie::CookieJar;
+    use leptos_axum::extract;
+    use diesel::prelude::*;
+    use diesel_async::RunQueryDsl;
+    use crate::state::AppState;
+    use crate::models::users::User;
+    use crate::schema::users;
+
+    let jar = extract::<CookieJar>().await
+        .map_err(|e| leptos::server_fn::ServerFnError::new(format!("Cookie jar error: {e}")))?;
+
+    let Some(claims) = jar.get(AUTH_COOKIE_NAME).and_then(|cookie| verify_jwt_token(cookie.value()).ok()) else {
+        return Ok(None);
+    };
+    let user_id: i32 = claims.user_id()
+        .map_err(|_| leptos::server_fn::ServerFnError::new("Invalid user ID in token"))?;
+    // tokens from before sessions existed carry no session id
+    let Some(session_id) = claims.session_id() else {
+        return Ok(None);
+    };
+
+    let app_state = leptos::context::use_context::<AppState>()
+        .ok_or_else(|| leptos::server_fn::ServerFnError::new("App state not found"))?;
+
+    let mut conn = app_state.pool.get().await
+        .map_err(|e| leptos::server_fn::ServerFnError::new(format!("Database connection error: {e}")))?;
+
+    match sessions::active_session(&mut conn, session_id).await {
+        Ok(session) if session.user_id == user_id => {}
+        Ok(_) => return Ok(None),
+        Err(sessions::SessionError::Database(e)) => {
+            return Err(leptos::server_fn::ServerFnError::new(format!("Database query error: {e}")));
+        }
+        Err(_) => return Ok(None),
+    }
+
+    let user = users::table
+        .find(user_id)
+        .first::<User>(&mut conn)
+        .await
+        .optional()
+        .map_err(|e| leptos::server_fn::ServerFnError::new(format!("Database query error: {e}")))?;
+
+    Ok(user.map(|u| (u, session_id)))
+}
+
 #[leptos::server(
     prefix = "/api",
     endpoint = "me",
     input = GetUrl,
 )]
 pub async fn get_current_user() -> Result<Option<crate::models::users::UserView>, leptos::server_fn::ServerFnError> {
+    #[cfg(feature = "ssr")]
+    {
+        Ok(current_session().await?.map(|(user, _)| user.into()))
+    }
+    
+    #[cfg(not(feature = "ssr"))]
+    {
+        // Client-side implementation - return None since we can't verify JWT on client
+        Ok(None)
+    }
+}
+
+#[leptos::server(
+    prefix = "/api",
+    endpoint = "login-providers",
+    input = GetUrl,
+)]
+pub async fn get_login_providers() -> Result<Vec<providers::LoginProvider>, leptos::server_fn::ServerFnError> {
+    #[cfg(feature = "ssr")]
+    {
+        Ok(providers::login_providers())
+    }
+
+    #[cfg(not(feature = "ssr"))]
+    {
+        Ok(Vec::new())
+    }
+}
+
+#[cfg(feature = "ssr")]
+fn set_cookies(cookies: Vec<axum_extra::extract::cookie::Cookie<'static>>) -> Result<(), leptos::server_fn::ServerFnError> {
+    use leptos_axum::ResponseOptions;
+    use http::{header::SET_COOKIE, HeaderValue};
+
+    let response_options = leptos::context::use_context::<ResponseOptions>()
+        .ok_or_else(|| leptos::server_fn::ServerFnError::new("Response options not found"))?;
+
+    for cookie in cookies {
+        let cookie_value = HeaderValue::from_str(&cookie.to_string())
+            .map_err(|e| leptos::server_fn::ServerFnError::new(format!("Cookie header error: {e}")))?;
+        response_options.append_header(SET_COOKIE, cookie_value);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "ssr")]
+async fn session_connection() -> Result<(crate::state::AppState, crate::database::db::DbConnection), leptos::server_fn::ServerFnError> {
+    let app_state = leptos::context::use_context::<crate::state::AppState>()
+        .ok_or_else(|| leptos::server_fn::ServerFnError::new("App state not found"))?;
+    let conn = app_state.pool.get().await
+        .map_err(|e| leptos::server_fn::ServerFnError::new(format!("Database connection error: {e}")))?;
+    Ok((app_state, conn))
+}
+
+/// Ends the current session everywhere it could still be used
+#[leptos::server(Logout, "/api")]
+pub async fn logout() -> Result<(), leptos::server_fn::ServerFnError> {
     #[cfg(feature = "ssr")]
     {
         use axum_extra::extract::cookie::CookieJar;
         use leptos_axum::extract;
-        use diesel::prelude::*;
-        use diesel_async::RunQueryDsl;
-        use crate::state::AppState;
-        use crate::models::users::User;
-        use crate::schema::users;
-        
+        use crate::models::audit::AUDIT_LOGOUT;
+        use crate::services::audit;
+
         let jar = extract::<CookieJar>().await
             .map_err(|e| leptos::server_fn::ServerFnError::new(format!("Cookie jar error: {e}")))?;
-        
-        if let Some(cookie) = jar.get("auth_token") {
-            if let Ok(claims) = verify_jwt_token(cookie.value()) {
-                let user_id: i32 = claims.sub.parse()
-                    .map_err(|_| leptos::server_fn::ServerFnError::new("Invalid user ID in token"))?;
-                
-                let app_state = leptos::context::use_context::<AppState>()
-                    .ok_or_else(|| leptos::server_fn::ServerFnError::new("App state not found"))?;
-                
-                let mut conn = app_state.pool.get().await
-                    .map_err(|e| leptos::server_fn::ServerFnError::new(format!("Database connection error: {e}")))?;
-                
-                let user = users::table
-                    .find(user_id)
-                    .first::<User>(&mut conn)
-                    .await
-                    .optional()
-                    .map_err(|e| leptos::server_fn::ServerFnError::new(format!("Database query error: {e}")))?;
-                
-                Ok(user.map(|u| u.into()))
-            } else {
-                Ok(None)
-            }
-        } else {
-            Ok(None)
+        let (app_state, mut conn) = session_connection().await?;
+
+        let from_access = jar.get(AUTH_COOKIE_NAME)
+            .and_then(|cookie| verify_jwt_token(cookie.value()).ok())
+            .and_then(|claims| Some((claims.user_id().ok()?, claims.session_id()?)));
+        let session = match from_access {
+            Some(ids) => Some(ids),
+            None => match jar.get(sessions::REFRESH_COOKIE_NAME) {
+                Some(cookie) => sessions::find_session_by_refresh_token(&mut conn, cookie.value()).await
+                    .map_err(|e| leptos::server_fn::ServerFnError::new(e.to_string()))?
+                    .map(|s| (s.user_id, s.id)),
+                None => None,
+            },
+        };
+
+        if let Some((user_id, session_id)) = session {
+            sessions::revoke_session(&mut conn, user_id, session_id).await
+                .map_err(|e| leptos::server_fn::ServerFnError::new(e.to_string()))?;
+            sessions::remember_revoked(&app_state, &[session_id]);
+            audit::record_request(&mut conn, user_id, audit::AuditEvent::new(AUDIT_LOGOUT).target("session", session_id)).await;
         }
+
+        set_cookies(sessions::cleared_session_cookies())?;
     }
     
+    Ok(())
+}
+
+#[leptos::server(
+    prefix = "/api",
+    endpoint = "sessions",
+    input = GetUrl,
+)]
+pub async fn list_sessions() -> Result<Vec<crate::models::sessions::SessionView>, leptos::server_fn::ServerFnError> {
+    #[cfg(feature = "ssr")]
+    {
+        let Some((user, current)) = current_session().await? else {
+            return Err(leptos::server_fn::ServerFnError::new("Not signed in"));
+        };
+        let (_, mut conn) = session_connection().await?;
+        let sessions = sessions::list_sessions(&mut conn, user.id).await
+            .map_err(|e| leptos::server_fn::ServerFnError::new(e.to_string()))?;
+        Ok(sessions.into_iter().map(|s| s.into_view(Some(current))).collect())
+    }
+
+    #[cfg(not(feature = "ssr"))]
+    {
+        Ok(Vec::new())
+    }
+}
+
+#[leptos::server(RevokeSession, "/api")]
+pub async fn revoke_session(session_id: uuid::Uuid) -> Result<(), leptos::server_fn::ServerFnError> {
+    #[cfg(feature = "ssr")]
+    {
+        use crate::models::audit::AUDIT_SESSION_REVOKED;
+        use crate::services::audit;
+
+        let Some((user, current)) = current_session().await? else {
+            return Err(leptos::server_fn::ServerFnError::new("Not signed in"));
+        };
+        let (app_state, mut conn) = session_connection().await?;
+        if !sessions::revoke_session(&mut conn, user.id, session_id).await
+            .map_err(|e| leptos::server_fn::ServerFnError::new(e.to_string()))?
+        {
+            return Err(leptos::server_fn::ServerFnError::new("Session not found"));
+        }
+        sessions::remember_revoked(&app_state, &[session_id]);
+        audit::record_request(&mut conn, user.id, audit::AuditEvent::new(AUDIT_SESSION_REVOKED).target("session", session_id)).await;
+        if session_id == current {
+            set_cookies(sessions::cleared_session_cookies())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Revokes every session of the signed-in user, this one included
+#[leptos::server(LogoutEverywhere, "/api")]
+pub async fn logout_everywhere() -> Result<usize, leptos::server_fn::ServerFnError> {
+    #[cfg(feature = "ssr")]
+    {
+        use crate::models::audit::AUDIT_LOGOUT_EVERYWHERE;
+        use crate::services::audit;
+
+        let Some((user, _)) = current_session().await? else {
+            return Err(leptos::server_fn::ServerFnError::new("Not signed in"));
+        };
+        let (app_state, mut conn) = session_connection().await?;
+        let revoked = sessions::revoke_all_sessions(&mut conn, user.id).await
+            .map_err(|e| leptos::server_fn::ServerFnError::new(e.to_string()))?;
+        sessions::remember_revoked(&app_state, &revoked);
+        let event = audit::AuditEvent::new(AUDIT_LOGOUT_EVERYWHERE).details(serde_json::json!({ "sessions": revoked.len() }));
+        audit::record_request(&mut conn, user.id, event).await;
+        set_cookies(sessions::cleared_session_cookies())?;
+        Ok(revoked.len())
+    }
+
+    #[cfg(not(feature = "ssr"))]
+    {
+        Ok(0)
+    }
+}
+
+#[leptos::server(
+    prefix = "/api",
+    endpoint = "api-tokens",
+    input = GetUrl,
+)]
+pub async fn list_api_tokens() -> Result<Vec<crate::models::api_tokens::ApiTokenView>, leptos::server_fn::ServerFnError> {
+    #[cfg(feature = "ssr")]
+    {
+        let Some((user, _)) = current_session().await? else {
+            return Err(leptos::server_fn::ServerFnError::new("Not signed in"));
+        };
+        let (_, mut conn) = session_connection().await?;
+        let tokens = api_tokens::list_api_tokens(&mut conn, user.id).await
+            .map_err(|e| leptos::server_fn::ServerFnError::new(e.to_string()))?;
+        Ok(tokens.into_iter().map(Into::into).collect())
+    }
+
+    #[cfg(not(feature = "ssr"))]
+    {
+        Ok(Vec::new())
+    }
+}
+
+/// Creates a personal access token; the secret is only returned here
+#[leptos::server(CreateApiToken, "/api")]
+pub async fn create_api_token(
+    name: String,
+    scopes: Vec<String>,
+    expires_in_days: Option<i64>,
+) -> Result<crate::models::api_tokens::CreatedApiToken, leptos::server_fn::ServerFnError> {
+    #[cfg(feature = "ssr")]
+    {
+        use crate::models::audit::AUDIT_API_TOKEN_CREATED;
+        use crate::services::audit;
+
+        let Some((user, _)) = current_session().await? else {
+            return Err(leptos::server_fn::ServerFnError::new("Not signed in"));
+        };
+        let (_, mut conn) = session_connection().await?;
+        let created = api_tokens::create_api_token(&mut conn, user.id, &name, &scopes, expires_in_days).await
+            .map_err(|e| leptos::server_fn::ServerFnError::new(e.to_string()))?;
+        let event = audit::AuditEvent::new(AUDIT_API_TOKEN_CREATED)
+            .target("api_token", created.details.id)
+            .details(serde_json::json!({ "name": name, "scopes": created.details.scopes }));
+        audit::record_request(&mut conn, user.id, event).await;
+        Ok(created)
+    }
+
+    #[cfg(not(feature = "ssr"))]
+    {
+        Err(leptos::server_fn::ServerFnError::new("API tokens can only be created on the server"))
+    }
+}
+
+#[leptos::server(RevokeApiToken, "/api")]
+pub async fn revoke_api_token(token_id: uuid::Uuid) -> Result<(), leptos::server_fn::ServerFnError> {
+    #[cfg(feature = "ssr")]
+    {
+        use crate::models::audit::AUDIT_API_TOKEN_REVOKED;
+        use crate::services::audit;
+
+        let Some((user, _)) = current_session().await? else {
+            return Err(leptos::server_fn::ServerFnError::new("Not signed in"));
+        };
+        let (_, mut conn) = session_connection().await?;
+        if !api_tokens::revoke_api_token(&mut conn, user.id, token_id).await
+            .map_err(|e| leptos::server_fn::ServerFnError::new(e.to_string()))?
+        {
+            return Err(leptos::server_fn::ServerFnError::new("API token not found"));
+        }
+        audit::record_request(&mut conn, user.id, audit::AuditEvent::new(AUDIT_API_TOKEN_REVOKED).target("api_token", token_id)).await;
+    }
+
+    Ok(())
+}
+
+#[leptos::server(
+    prefix = "/api",
+    endpoint = "identities",
+    input = GetUrl,
+)]
+pub async fn list_identities() -> Result<Vec<crate::models::identities::IdentityView>, leptos::server_fn::ServerFnError> {
+    #[cfg(feature = "ssr")]
+    {
+        let Some((user, _)) = current_session().await? else {
+            return Err(leptos::server_fn::ServerFnError::new("Not signed in"));
+        };
+        let (_, mut conn) = session_connection().await?;
+        let identities = identities::list_identities(&mut conn, user.id).await
+            .map_err(|e| leptos::server_fn::ServerFnError::new(e.to_string()))?;
+        Ok(identities.into_iter().map(Into::into).collect())
+    }
+
+    #[cfg(not(feature = "ssr"))]
+    {
+        Ok(Vec::new())
+    }
+}
+
+/// Returns the provider URL that links another account to the signed-in user
+#[leptos::server(StartLinkIdentity, "/api")]
+pub async fn start_link_identity(provider: String) -> Result<String, leptos::server_fn::ServerFnError> {
+    #[cfg(feature = "ssr")]
+    {
+        let Some((user, _)) = current_session().await? else {
+            return Err(leptos::server_fn::ServerFnError::new("Not signed in"));
+        };
+        let (app_state, _) = session_connection().await?;
+        let config = providers::find_provider(&provider)
+            .map_err(|e| leptos::server_fn::ServerFnError::new(e.to_string()))?;
+        let authorization = oauth::begin_authorization(&app_state, config, None, None, Some(user.id)).await
+            .map_err(|e| leptos::server_fn::ServerFnError::new(e.to_string()))?;
+        set_cookies(vec![authorization.cookie])?;
+        Ok(authorization.url)
+    }
+
+    #[cfg(not(feature = "ssr"))]
+    {
+        Err(leptos::server_fn::ServerFnError::new("Accounts can only be linked on the server"))
+    }
+}
+
+#[leptos::server(UnlinkIdentity, "/api")]
+pub async fn unlink_identity(identity_id: i32) -> Result<(), leptos::server_fn::ServerFnError> {
+    #[cfg(feature = "ssr")]
+    {
+        use crate::models::audit::AUDIT_IDENTITY_UNLINKED;
+        use crate::services::audit;
+
+        let Some((user, _)) = current_session().await? else {
+            return Err(leptos::server_fn::ServerFnError::new("Not signed in"));
+        };
+        let (_, mut conn) = session_connection().await?;
+        identities::unlink_identity(&mut conn, user.id, identity_id).await
+            .map_err(|e| leptos::server_fn::ServerFnError::new(e.to_string()))?;
+        audit::record_request(&mut conn, user.id, audit::AuditEvent::new(AUDIT_IDENTITY_UNLINKED).target("identity", identity_id)).await;
+    }
+
+    Ok(())
+}
+
+/// The account a link attempt found, offered for merging
+#[leptos::server(
+    prefix = "/api",
+    endpoint = "merge-candidate",
+    input = GetUrl,
+)]
+pub async fn get_merge_candidate(token: String) -> Result<Option<crate::models::identities::MergeCandidate>, leptos::server_fn::ServerFnError> {
+    #[cfg(feature = "ssr")]
+    {
+        let Some((user, _)) = current_session().await? else {
+            return Err(leptos::server_fn::ServerFnError::new("Not signed in"));
+        };
+        let (app_state, mut conn) = session_connection().await?;
+        let Ok(merge) = identities::pending_merge(&app_state.pending_merges, &token, user.id, chrono::Utc::now().naive_utc()) else {
+            return Ok(None);
+        };
+        match identities::merge_candidate(&mut conn, &merge).await {
+            Ok(candidate) => Ok(Some(candidate)),
+            Err(identities::IdentityError::MergeExpired) => Ok(None),
+            Err(e) => Err(leptos::server_fn::ServerFnError::new(e.to_string())),
+        }
+    }
+
     #[cfg(not(feature = "ssr"))]
     {
-        // Client-side implementation - return None since we can't verify JWT on client
         Ok(None)
     }
 }
 
-#[leptos::server(Logout, "/api")]
-pub async fn logout() -> Result<(), leptos::server_fn::ServerFnError> {
+/// Moves the other account's threads, projects, tokens and logins into the
+/// signed-in user's account and deletes it
+#[leptos::server(MergeAccounts, "/api")]
+pub async fn merge_accounts(token: String) -> Result<(), leptos::server_fn::ServerFnError> {
     #[cfg(feature = "ssr")]
     {
-        use axum_extra::extract::cookie::Cookie;
-        use leptos_axum::ResponseOptions;
-        use http::{HeaderName, HeaderValue};
-        
-        let response_options = leptos::context::use_context::<ResponseOptions>()
-            .ok_or_else(|| leptos::server_fn::ServerFnError::new("Response options not found"))?;
-        
-        let cookie = Cookie::build(("auth_token", ""))
-            .path("/")
-            .max_age(cookie::time::Duration::seconds(-1))
-            .build();
-        
-        let cookie_value = HeaderValue::from_str(&cookie.to_string())
-            .map_err(|e| leptos::server_fn::ServerFnError::new(format!("Cookie header error: {e}")))?;
-        
-        response_options.insert_header(HeaderName::from_static("set-cookie"), cookie_value);
+        use crate::models::audit::AUDIT_ACCOUNTS_MERGED;
+        use crate::services::audit;
+
+        let Some((user, _)) = current_session().await? else {
+            return Err(leptos::server_fn::ServerFnError::new("Not signed in"));
+        };
+        let (app_state, mut conn) = session_connection().await?;
+        let merge = identities::pending_merge(&app_state.pending_merges, &token, user.id, chrono::Utc::now().naive_utc())
+            .map_err(|e| leptos::server_fn::ServerFnError::new(e.to_string()))?;
+        let ended = identities::merge_users(&mut conn, merge.source_user_id, user.id).await
+            .map_err(|e| leptos::server_fn::ServerFnError::new(e.to_string()))?;
+        app_state.pending_merges.remove(&token);
+        sessions::remember_revoked(&app_state, &ended);
+        let event = audit::AuditEvent::new(AUDIT_ACCOUNTS_MERGED)
+            .target("user", merge.source_user_id)
+            .details(serde_json::json!({ "provider": merge.provider }));
+        audit::record_request(&mut conn, user.id, event).await;
     }
-    
+
     Ok(())
 }
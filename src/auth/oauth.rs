@@ -1,28 +1,140 @@
 #[cfg(feature = "ssr")]
 pub mod oauth_server {
     use axum::{
-        extract::{Query, State},
+        extract::{ConnectInfo, Path, Query, State},
         response::{IntoResponse, Redirect},
         http::HeaderMap,
     };
-    use axum_extra::extract::cookie::{Cookie, SameSite};
+    use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+    use chrono::{Duration, NaiveDateTime, Utc};
+    use dashmap::DashMap;
     use serde::{Deserialize, Serialize};
-    use diesel::prelude::*;
-    use diesel_async::RunQueryDsl;
-    use log::{debug, info, error};
+    use log::{debug, info, error, warn};
     use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
     use sha2::{Digest, Sha256};
     use rand::{thread_rng, Rng};
-    
+    use std::fmt;
+    use std::net::SocketAddr;
+
+    use crate::auth::identities::{link_identity, link_verified_emails, new_pending_merge, offer_merge, sign_in, LinkOutcome};
+    use crate::auth::providers::{find_provider, map_user_info, ProviderConfig, ProviderEndpoints, ProviderError};
+    use crate::auth::sessions::{client_ip, create_session, session_cookies, user_agent};
+    use crate::models::audit::{AUDIT_IDENTITY_LINKED, AUDIT_LOGIN};
+    use crate::services::audit::{self, AuditEvent};
     use crate::state::AppState;
-    use crate::models::users::{User, NewUser, CreateUserView};
-    use crate::schema::users;
+    use crate::models::users::{User, CreateUserView};
+
+    /// Pre-login cookie tying a pending login to the browser that started it
+    pub const OAUTH_STATE_COOKIE_NAME: &str = "oauth_binding";
+    /// A login not finished within this long has to start over
+    pub const OAUTH_STATE_TTL_MINUTES: i64 = 10;
+    /// Pending logins kept at most; the oldest make room for new ones
+    pub const MAX_PENDING_OAUTH_STATES: usize = 10_000;
+    const DEFAULT_RETURN_URL: &str = "/admin-panel";
 
     #[derive(Serialize, Deserialize, Clone)]
     pub struct OAuthState {
         pub provider: String,
         pub verifier: String,
+        /// Same-origin path to land on after signing in
         pub return_url: Option<String>,
+        /// Set when a signed-in user is linking another provider
+        #[serde(default)]
+        pub link_user_id: Option<i32>,
+        /// SHA-256 of the pre-login cookie's value
+        pub browser_binding: String,
+        pub created_at: NaiveDateTime,
+    }
+
+    /// Why a callback's `state` was not accepted
+    #[derive(Debug, PartialEq)]
+    pub enum StateRejection {
+        Unknown,
+        Expired,
+        /// The callback came from a browser that didn't start this login
+        WrongBrowser,
+    }
+
+    impl fmt::Display for StateRejection {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                StateRejection::Unknown => write!(f, "unknown OAuth state"),
+                StateRejection::Expired => write!(f, "OAuth state has expired"),
+                StateRejection::WrongBrowser => write!(f, "OAuth state belongs to another browser"),
+            }
+        }
+    }
+
+    /// A started authorization: where to send the browser and the cookie to set first
+    pub struct Authorization {
+        pub url: String,
+        pub cookie: Cookie<'static>,
+    }
+
+    /// Accepts `return_to` only as a path on this site; `//host` and `/\host`
+    /// would let a login link forward the user to another origin.
+    pub fn safe_return_to(raw: &str) -> Option<String> {
+        let raw = raw.trim();
+        let rest = raw.strip_prefix('/')?;
+        if rest.starts_with('/') || raw.contains('\\') || raw.chars().any(char::is_control) {
+            return None;
+        }
+        Some(raw.to_string())
+    }
+
+    /// Stores a pending login, dropping expired ones and the oldest beyond the bound
+    pub fn remember_state(states: &DashMap<String, OAuthState>, key: String, state: OAuthState, now: NaiveDateTime) {
+        let cutoff = now - Duration::minutes(OAUTH_STATE_TTL_MINUTES);
+        states.retain(|_, pending| pending.created_at > cutoff);
+        while states.len() >= MAX_PENDING_OAUTH_STATES {
+            let oldest = states.iter()
+                .min_by_key(|entry| entry.value().created_at)
+                .map(|entry| entry.key().clone());
+            match oldest {
+                Some(oldest) => { states.remove(&oldest); }
+                None => break,
+            }
+        }
+        states.insert(key, state);
+    }
+
+    /// Removes the pending login for `key`; it is only returned to the browser holding its cookie
+    pub fn take_state(
+        states: &DashMap<String, OAuthState>,
+        key: &str,
+        binding: Option<&str>,
+        now: NaiveDateTime,
+    ) -> Result<OAuthState, StateRejection> {
+        let (_, state) = states.remove(key).ok_or(StateRejection::Unknown)?;
+        if state.created_at <= now - Duration::minutes(OAUTH_STATE_TTL_MINUTES) {
+            return Err(StateRejection::Expired);
+        }
+        match binding {
+            Some(binding) if hash_binding(binding) == state.browser_binding => Ok(state),
+            _ => Err(StateRejection::WrongBrowser),
+        }
+    }
+
+    fn hash_binding(value: &str) -> String {
+        URL_SAFE_NO_PAD.encode(Sha256::digest(value.as_bytes()))
+    }
+
+    fn binding_cookie(value: String) -> Cookie<'static> {
+        Cookie::build((OAUTH_STATE_COOKIE_NAME, value))
+            .path("/auth")
+            .secure(true)
+            .http_only(true)
+            // Lax still sends it on the provider's top-level redirect back
+            .same_site(SameSite::Lax)
+            .max_age(cookie::time::Duration::minutes(OAUTH_STATE_TTL_MINUTES))
+            .build()
+    }
+
+    fn cleared_binding_cookie() -> Cookie<'static> {
+        Cookie::build((OAUTH_STATE_COOKIE_NAME, ""))
+            .path("/auth")
+            .max_age(cookie::time::Duration::seconds(-1))
+            .build()
     }
 
     #[derive(Deserialize)]
@@ -32,296 +144,278 @@ pub mod oauth_server {
     }
 
     #[derive(Deserialize)]
-    pub struct GoogleUserInfo {
-        pub id: String,
-        pub email: Option<String>,
-        pub name: Option<String>,
-        pub picture: Option<String>,
+    pub struct LoginParams {
+        /// Passed on to the provider to preselect an account
+        pub login_hint: Option<String>,
+        /// Path on this site to open after signing in
+        pub return_to: Option<String>,
     }
 
-    #[derive(Deserialize)]
-    pub struct DiscordUserInfo {
-        pub id: String,
-        pub username: Option<String>,
-        pub email: Option<String>,
-        pub avatar: Option<String>,
-        pub discriminator: Option<String>,
-    }
+    /// Starts a login with any configured provider: `/auth/{provider}`
+    pub async fn oauth_login(
+        State(state): State<AppState>,
+        Path(provider): Path<String>,
+        Query(params): Query<LoginParams>,
+    ) -> impl IntoResponse {
+        let config = match find_provider(&provider) {
+            Ok(config) => config,
+            Err(e) => {
+                error!("{e}");
+                return (axum::http::StatusCode::NOT_FOUND, "Unsupported provider").into_response();
+            }
+        };
 
-    pub async fn google_login(State(state): State<AppState>) -> impl IntoResponse {
-        let scopes = vec!["openid".to_string(), "email".to_string(), "profile".to_string()];
-        oauth_login("google", scopes, state).await
+        let return_to = params.return_to.as_deref().and_then(safe_return_to);
+        match begin_authorization(&state, config, params.login_hint, return_to, None).await {
+            Ok(authorization) => {
+                let jar = CookieJar::new().add(authorization.cookie);
+                (jar, Redirect::to(&authorization.url)).into_response()
+            }
+            Err(e) => {
+                error!("Could not resolve endpoints for {provider}: {e}");
+                Redirect::to(&format!("/admin?error=provider_unavailable&details={}", urlencoding::encode(&e.to_string()))).into_response()
+            }
+        }
     }
 
-    pub async fn discord_login(State(state): State<AppState>) -> impl IntoResponse {
-        let scopes = vec!["identify".to_string(), "email".to_string()];
-        oauth_login("discord", scopes, state).await
-    }
-
-    async fn oauth_login(
-        provider: &str,
-        scopes: Vec<String>,
-        state: AppState,
-    ) -> impl IntoResponse {
+    /// Remembers a new authorization request and returns the provider URL to
+    /// send the browser to, with the cookie binding the request to it.
+    ///
+    /// With `link_user_id` the callback links the provider account to that user
+    /// instead of signing in.
+    pub async fn begin_authorization(
+        state: &AppState,
+        config: &ProviderConfig,
+        login_hint: Option<String>,
+        return_url: Option<String>,
+        link_user_id: Option<i32>,
+    ) -> Result<Authorization, ProviderError> {
+        let endpoints = config.endpoints().await?;
 
         let oauth_state = uuid::Uuid::new_v4().to_string();
-        let (code_verifier, code_challenge) = generate_pkce(); 
-        
+        let (code_verifier, code_challenge) = generate_pkce();
+        let binding = random_token();
+
         let session_state = OAuthState {
-            provider: provider.to_string(),
-            verifier: code_verifier, 
-            return_url: None,
+            provider: config.id.clone(),
+            verifier: code_verifier,
+            return_url,
+            link_user_id,
+            browser_binding: hash_binding(&binding),
+            created_at: Utc::now().naive_utc(),
         };
 
-        state.oauth_states.insert(oauth_state.clone(), session_state);
-
-        let auth_url = match provider {
-            "google" => {
-                let scope = scopes.join(" ");
-                format!(
-                    "https://accounts.google.com/o/oauth2/auth?client_id={}&redirect_uri={}&scope={}&response_type=code&state={}&code_challenge={}&code_challenge_method=S256",
-                    std::env::var("GOOGLE_CLIENT_ID").expect("GOOGLE_CLIENT_ID must be set"),
-                    urlencoding::encode(&std::env::var("GOOGLE_REDIRECT_URL")
-                        .unwrap_or_else(|_| "http://localhost:3000/auth/google-callback".to_string())),
-                    urlencoding::encode(&scope),
-                    urlencoding::encode(&oauth_state),
-                    urlencoding::encode(&code_challenge)
-                )
-            }
-            "discord" => {
-                let scope = scopes.join("%20");
-                format!(
-                    "https://discord.com/api/oauth2/authorize?client_id={}&redirect_uri={}&scope={}&response_type=code&state={}",
-                    std::env::var("DISCORD_CLIENT_ID").expect("DISCORD_CLIENT_ID must be set"),
-                    urlencoding::encode(&std::env::var("DISCORD_REDIRECT_URL")
-                        .unwrap_or_else(|_| "http://localhost:3000/auth/discord/callback".to_string())),
-                    scope,
-                    urlencoding::encode(&oauth_state)
-                )
-            }
-            _ => {
-                error!("Unsupported OAuth provider: {provider}");
-                return (axum::http::StatusCode::BAD_REQUEST, "Unsupported provider").into_response();
-            }
-        };
+        remember_state(&state.oauth_states, oauth_state.clone(), session_state, Utc::now().naive_utc());
+
+        let separator = if endpoints.authorize_url.contains('?') { '&' } else { '?' };
+        let mut auth_url = format!(
+            "{}{separator}client_id={}&redirect_uri={}&scope={}&response_type=code&state={}",
+            endpoints.authorize_url,
+            urlencoding::encode(&config.client_id),
+            urlencoding::encode(&config.redirect_url),
+            urlencoding::encode(&config.scopes.join(" ")),
+            urlencoding::encode(&oauth_state)
+        );
+        if config.pkce {
+            auth_url.push_str(&format!("&code_challenge={}&code_challenge_method=S256", urlencoding::encode(&code_challenge)));
+        }
+        if let Some(hint) = login_hint.filter(|h| !h.is_empty()) {
+            auth_url.push_str(&format!("&login_hint={}", urlencoding::encode(&hint)));
+        }
 
-        Redirect::to(&auth_url).into_response()
+        Ok(Authorization { url: auth_url, cookie: binding_cookie(binding) })
+    }
+
+    /// 32 random bytes, base64url-encoded without padding
+    fn random_token() -> String {
+        let mut bytes = [0u8; 32];
+        thread_rng().fill(&mut bytes);
+        URL_SAFE_NO_PAD.encode(bytes)
     }
 
     fn generate_pkce() -> (String, String) {
-        // Generate a random 32-byte array for the verifier
-        let mut verifier_bytes = [0u8; 32];
-        thread_rng().fill(&mut verifier_bytes);
-        
-        // Base64url-encode the verifier (without padding)
-        let code_verifier = URL_SAFE_NO_PAD.encode(&verifier_bytes);
-        
-        // Create SHA256 hash of the verifier
-        let mut hasher = Sha256::new();
-        hasher.update(&code_verifier);
-        let challenge_bytes = hasher.finalize();
-        
-        // Base64url-encode the challenge (without padding)
-        let code_challenge = URL_SAFE_NO_PAD.encode(&challenge_bytes);
-        
+        let code_verifier = random_token();
+        let code_challenge = pkce_challenge(&code_verifier);
+
         (code_verifier, code_challenge)
     }
 
-    pub async fn google_callback(
+    /// S256 code challenge for a PKCE verifier
+    pub fn pkce_challenge(verifier: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(verifier);
+        URL_SAFE_NO_PAD.encode(hasher.finalize())
+    }
+
+    pub async fn oauth_callback(
         State(state): State<AppState>,
+        Path(provider): Path<String>,
         Query(params): Query<OAuthCallback>,
+        ConnectInfo(remote): ConnectInfo<SocketAddr>,
+        headers: HeaderMap,
     ) -> impl IntoResponse {
-        oauth_callback("google", state, params).await
+        handle_callback(&provider, state, params, headers, Some(remote)).await
     }
 
-    pub async fn discord_callback(
+    /// The redirect URL Google clients were registered with before providers were configurable
+    pub async fn google_callback(
         State(state): State<AppState>,
         Query(params): Query<OAuthCallback>,
+        ConnectInfo(remote): ConnectInfo<SocketAddr>,
+        headers: HeaderMap,
     ) -> impl IntoResponse {
-        oauth_callback("discord", state, params).await
+        handle_callback("google", state, params, headers, Some(remote)).await
     }
 
     async fn exchange_code_for_token(
-        provider: &str,
+        config: &ProviderConfig,
+        endpoints: &ProviderEndpoints,
         code: &str,
         verifier: &str,
     ) -> Result<String, Box<dyn std::error::Error>> {
         let client = reqwest::Client::new();
-        
-        let (token_url, client_id, client_secret, redirect_uri) = match provider {
-            "google" => (
-                "https://oauth2.googleapis.com/token",
-                std::env::var("GOOGLE_CLIENT_ID")?,
-                std::env::var("GOOGLE_CLIENT_SECRET")?,
-                std::env::var("GOOGLE_REDIRECT_URL")
-                    .unwrap_or_else(|_| "http://localhost:3000/auth/google-callback".to_string()),
-            ),
-            "discord" => (
-                "https://discord.com/api/oauth2/token",
-                std::env::var("DISCORD_CLIENT_ID")?,
-                std::env::var("DISCORD_CLIENT_SECRET")?,
-                std::env::var("DISCORD_REDIRECT_URL")
-                    .unwrap_or_else(|_| "http://localhost:3000/auth/discord/callback".to_string()),
-            ),
-            _ => return Err("Unsupported provider".into()),
-        };
-    
-        debug!("Exchanging code for token - Provider: {}, Client ID: {}", provider, &client_id[..8]);
-    
+
+        debug!("Exchanging code for token - Provider: {}, Client ID: {}", config.id, config.client_id.chars().take(8).collect::<String>());
+
         let mut params = vec![
             ("grant_type", "authorization_code"),
             ("code", code),
-            ("client_id", &client_id),
-            ("client_secret", &client_secret),
-            ("redirect_uri", &redirect_uri),
+            ("client_id", config.client_id.as_str()),
+            ("client_secret", config.client_secret.as_str()),
+            ("redirect_uri", config.redirect_url.as_str()),
         ];
-    
-        // Google uses PKCE
-        if provider == "google" {
+
+        if config.pkce {
             params.push(("code_verifier", verifier));
         }
-    
-        debug!("Token request params (excluding secrets): grant_type=authorization_code, redirect_uri={redirect_uri}");
-    
+
+        debug!("Token request params (excluding secrets): grant_type=authorization_code, redirect_uri={}", config.redirect_url);
+
+        // GitHub answers form-encoded unless JSON is asked for
         let response = client
-            .post(token_url)
+            .post(&endpoints.token_url)
+            .header(reqwest::header::ACCEPT, "application/json")
             .form(&params)
             .send()
             .await?;
-    
+
         let status = response.status();
         let response_text = response.text().await?;
-        
+
         debug!("Token response status: {status}");
-        debug!("Token response body: {response_text}");
-    
+
         if !status.is_success() {
             return Err(format!("Token exchange failed with status {status}: {response_text}").into());
         }
-    
+
         #[derive(serde::Deserialize)]
         struct TokenResponse {
-            access_token: String,
+            #[serde(default)]
+            access_token: Option<String>,
             #[serde(default)]
             error: Option<String>,
             #[serde(default)]
             error_description: Option<String>,
         }
-    
+
         let token_data: TokenResponse = serde_json::from_str(&response_text)?;
-        
+
         if let Some(error) = token_data.error {
             return Err(format!("OAuth error: {} - {}", error, token_data.error_description.unwrap_or_default()).into());
         }
-    
+
         debug!("Successfully obtained access token");
-        Ok(token_data.access_token)
+        token_data.access_token.ok_or_else(|| "Token response has no access_token".into())
     }
 
-    async fn get_user_info(provider: &str, token: &str) -> Result<CreateUserView, Box<dyn std::error::Error>> {
+    async fn get_user_info(
+        config: &ProviderConfig,
+        endpoints: &ProviderEndpoints,
+        token: &str,
+    ) -> Result<CreateUserView, Box<dyn std::error::Error>> {
         let client = reqwest::Client::new();
-        
-        debug!("Fetching user info from {provider} with token");
-        
-        match provider {
-            "google" => {
-                let response = client
-                    .get("https://www.googleapis.com/oauth2/v2/userinfo")
-                    .bearer_auth(token)
-                    .send()
-                    .await?;
-    
-                let status = response.status();
-                let response_text = response.text().await?;
-                
-                debug!("Google user info response status: {status}");
-                debug!("Google user info response: {response_text}");
-    
-                if !status.is_success() {
-                    return Err(format!("Failed to get Google user info: {response_text}").into());
-                }
-    
-                let resp: GoogleUserInfo = serde_json::from_str(&response_text)?;
-                debug!("Parsed Google user info: ID={}, Email={:?}", resp.id, resp.email);
-    
-                Ok(CreateUserView {
-                    external_id: resp.id,
-                    provider: "google".to_string(),
-                    email: resp.email,
-                    username: None,
-                    display_name: resp.name,
-                    avatar_url: resp.picture,
-                })
-            }
-            "discord" => {
-                let response = client
-                    .get("https://discord.com/api/v10/users/@me")
-                    .bearer_auth(token)
-                    .send()
-                    .await?;
-    
-                let status = response.status();
-                let response_text = response.text().await?;
-                
-                debug!("Discord user info response status: {status}");
-                debug!("Discord user info response: {response_text}");
-    
-                if !status.is_success() {
-                    return Err(format!("Failed to get Discord user info: {response_text}").into());
-                }
-    
-                let resp: DiscordUserInfo = serde_json::from_str(&response_text)?;
-                debug!("Parsed Discord user info: ID={}, Username={:?}", resp.id, resp.username);
-    
-                let avatar_url = resp.avatar.as_ref().map(|avatar| {
-                    format!("https://cdn.discordapp.com/avatars/{}/{}.png", resp.id, avatar)
-                });
-    
-                let username_for_formatting = resp.username.clone();
-                let username = username_for_formatting.clone().or_else(|| {
-                    resp.discriminator.as_ref().map(|disc| {
-                        format!("{}#{}", username_for_formatting.unwrap_or_default(), disc)
-                    })
-                });
-    
-                Ok(CreateUserView {
-                    external_id: resp.id,
-                    provider: "discord".to_string(),
-                    email: resp.email,
-                    username,
-                    display_name: resp.username,
-                    avatar_url,
-                })
-            }
-            _ => Err("Unsupported provider".into()),
+
+        debug!("Fetching user info from {} with token", config.id);
+
+        // GitHub rejects requests without a User-Agent
+        let response = client
+            .get(&endpoints.userinfo_url)
+            .bearer_auth(token)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .header(reqwest::header::USER_AGENT, "l3chat")
+            .send()
+            .await?;
+
+        let status = response.status();
+        let response_text = response.text().await?;
+
+        debug!("{} user info response status: {status}", config.id);
+
+        if !status.is_success() {
+            return Err(format!("Failed to get {} user info: {response_text}", config.id).into());
         }
+
+        let info: serde_json::Value = serde_json::from_str(&response_text)?;
+        let user = map_user_info(config, &info)?;
+        debug!("Parsed {} user info: ID={}, Email={:?}", config.id, user.external_id, user.email);
+
+        Ok(user)
     }
-    
-    async fn oauth_callback(
+
+    async fn handle_callback(
         provider: &str,
         app_state: AppState,
         params: OAuthCallback,
-    ) -> impl IntoResponse {
+        request_headers: HeaderMap,
+        remote: Option<SocketAddr>,
+    ) -> axum::response::Response {
+        // the binding is single use whatever the outcome
+        let jar = CookieJar::new().add(cleared_binding_cookie());
+        (jar, complete_login(provider, app_state, params, request_headers, remote).await).into_response()
+    }
+
+    async fn complete_login(
+        provider: &str,
+        app_state: AppState,
+        params: OAuthCallback,
+        request_headers: HeaderMap,
+        remote: Option<SocketAddr>,
+    ) -> axum::response::Response {
         debug!("OAuth callback received for provider: {provider}");
-        debug!("Callback params - code length: {}, state: {}", params.code.len(), params.state);
-    
-        let oauth_state = match app_state.oauth_states.get(&params.state) {
-            Some(state_ref) => {
-                debug!("Found OAuth state for: {}", params.state);
-                state_ref.value().clone()
+        debug!("Callback params - code length: {}", params.code.len());
+
+        let binding = CookieJar::from_headers(&request_headers)
+            .get(OAUTH_STATE_COOKIE_NAME)
+            .map(|cookie| cookie.value().to_string());
+        let oauth_state = match take_state(&app_state.oauth_states, &params.state, binding.as_deref(), Utc::now().naive_utc()) {
+            Ok(state) => state,
+            Err(rejection) => {
+                warn!("Rejected OAuth callback for {provider}: {rejection}");
+                return Redirect::to("/admin?error=invalid_state").into_response();
+            }
+        };
+
+        if oauth_state.provider != provider {
+            error!("OAuth state was issued for {}, not {provider}", oauth_state.provider);
+            return Redirect::to("/admin?error=invalid_state").into_response();
+        }
+
+        let (config, endpoints) = match find_provider(provider) {
+            Ok(config) => match config.endpoints().await {
+                Ok(endpoints) => (config, endpoints),
+                Err(e) => {
+                    error!("Could not resolve endpoints for {provider}: {e}");
+                    return Redirect::to(&format!("/admin?error=provider_unavailable&details={}", urlencoding::encode(&e.to_string()))).into_response();
+                }
             },
-            None => {
-                error!("OAuth state not found for state: {}", params.state);
-                error!("Available states: {:?}", app_state.oauth_states.iter().map(|entry| entry.key().clone()).collect::<Vec<_>>());
+            Err(e) => {
+                error!("{e}");
                 return Redirect::to("/admin?error=invalid_state").into_response();
             }
         };
     
-        app_state.oauth_states.remove(&params.state);
-        debug!("Cleaned up OAuth state");
-    
         debug!("Starting token exchange...");
-        let token = match exchange_code_for_token(provider, &params.code, &oauth_state.verifier).await {
+        let token = match exchange_code_for_token(config, &endpoints, &params.code, &oauth_state.verifier).await {
             Ok(token) => {
                 debug!("Token exchange successful");
                 token
@@ -333,7 +427,7 @@ pub mod oauth_server {
         };
     
         debug!("Fetching user info...");
-        let user_info = match get_user_info(provider, &token).await {
+        let user_info = match get_user_info(config, &endpoints, &token).await {
             Ok(info) => {
                 debug!("User info fetched successfully for external_id: {}", info.external_id);
                 info
@@ -344,10 +438,14 @@ pub mod oauth_server {
             }
         };
     
-        debug!("Upserting user in database...");
+        if let Some(user_id) = oauth_state.link_user_id {
+            return finish_link(&app_state, user_id, &user_info, client_ip(&request_headers, remote)).await;
+        }
+
+        debug!("Signing in user...");
         let user = match upsert_user(&app_state, user_info).await {
             Ok(user) => {
-                debug!("User upserted successfully with ID: {}", user.id);
+                debug!("User signed in with ID: {}", user.id);
                 user
             },
             Err(e) => {
@@ -356,33 +454,40 @@ pub mod oauth_server {
             }
         };
     
-        debug!("Creating JWT token...");
-        let jwt_token = match crate::auth::create_jwt_token(user.id) {
-            Ok(token) => {
-                debug!("JWT token created successfully");
-                token
+        debug!("Creating session...");
+        let ip_address = client_ip(&request_headers, remote);
+        let issued = match app_state.pool.get().await {
+            Ok(mut conn) => match create_session(&mut conn, user.id, user_agent(&request_headers), ip_address.clone()).await {
+                Ok(issued) => {
+                    let event = AuditEvent::new(AUDIT_LOGIN)
+                        .target("session", issued.session_id)
+                        .details(serde_json::json!({ "provider": provider }));
+                    audit::record(&mut conn, Some(user.id), event, ip_address).await;
+                    Ok(issued)
+                }
+                Err(e) => Err(e.to_string()),
             },
+            Err(e) => Err(e.to_string()),
+        };
+        let issued = match issued {
+            Ok(issued) => issued,
             Err(e) => {
-                error!("Failed to create JWT: {e:?}");
-                return Redirect::to(&format!("/admin?error=jwt_error&details={}", urlencoding::encode(&e.to_string()))).into_response();
+                error!("Failed to create session: {e}");
+                return Redirect::to(&format!("/admin?error=session_error&details={}", urlencoding::encode(&e))).into_response();
             }
         };
     
-        debug!("Setting auth cookie and redirecting to admin panel");
-        let cookie = Cookie::build(("auth_token", jwt_token))
-            .path("/")
-            .secure(true)
-            .http_only(true)
-            .same_site(SameSite::Lax)
-            .build();
-    
+        let destination = oauth_state.return_url.as_deref().unwrap_or(DEFAULT_RETURN_URL);
+        debug!("Setting session cookies and redirecting to {destination}");
         let mut headers = HeaderMap::new();
-        headers.insert(
-            axum::http::header::SET_COOKIE,
-            cookie.to_string().parse().unwrap(),
-        );
+        for cookie in session_cookies(&issued) {
+            headers.append(
+                axum::http::header::SET_COOKIE,
+                cookie.to_string().parse().unwrap(),
+            );
+        }
     
-        (headers, Redirect::to("/admin-panel")).into_response()
+        (headers, Redirect::to(destination)).into_response()
     }
 
     async fn upsert_user(
@@ -390,41 +495,111 @@ pub mod oauth_server {
         user_info: CreateUserView,
     ) -> Result<User, Box<dyn std::error::Error>> {
         let mut conn = app_state.pool.get().await?;
+        Ok(sign_in(&mut conn, user_info, link_verified_emails()).await?)
+    }
+
+    /// Ends a link round trip; the user stays signed in to the account they started from
+    async fn finish_link(
+        app_state: &AppState,
+        user_id: i32,
+        user_info: &CreateUserView,
+        ip_address: Option<String>,
+    ) -> axum::response::Response {
+        let outcome = match app_state.pool.get().await {
+            Ok(mut conn) => {
+                let outcome = link_identity(&mut conn, user_id, user_info).await.map_err(|e| e.to_string());
+                if let Ok(LinkOutcome::Linked) = outcome {
+                    let event = AuditEvent::new(AUDIT_IDENTITY_LINKED)
+                        .details(serde_json::json!({ "provider": user_info.provider }));
+                    audit::record(&mut conn, Some(user_id), event, ip_address).await;
+                }
+                outcome
+            }
+            Err(e) => Err(e.to_string()),
+        };
+        match outcome {
+            Ok(LinkOutcome::Linked) | Ok(LinkOutcome::AlreadyLinked) => {
+                info!("Linked {} account to user {user_id}", user_info.provider);
+                Redirect::to(&format!("/admin-panel?linked={}", urlencoding::encode(&user_info.provider))).into_response()
+            }
+            Ok(LinkOutcome::OwnedBy(source)) => {
+                let token = offer_merge(&app_state.pending_merges, new_pending_merge(user_id, source, &user_info.provider));
+                Redirect::to(&format!("/admin-panel?merge={token}")).into_response()
+            }
+            Err(e) => {
+                error!("Failed to link {} account: {e}", user_info.provider);
+                Redirect::to(&format!("/admin-panel?error=link_failed&details={}", urlencoding::encode(&e))).into_response()
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn pending(binding: &str, created_at: NaiveDateTime) -> OAuthState {
+            OAuthState {
+                provider: "mock".to_string(),
+                verifier: "verifier".to_string(),
+                return_url: None,
+                link_user_id: None,
+                browser_binding: hash_binding(binding),
+                created_at,
+            }
+        }
+
+        #[test]
+        fn return_to_stays_on_this_site() {
+            assert_eq!(safe_return_to("/chat/abc?tab=1").as_deref(), Some("/chat/abc?tab=1"));
+            assert_eq!(safe_return_to("//evil.example"), None);
+            assert_eq!(safe_return_to("/\\evil.example"), None);
+            assert_eq!(safe_return_to("https://evil.example/"), None);
+            assert_eq!(safe_return_to("/ok\nSet-Cookie: x"), None);
+        }
+
+        #[test]
+        fn state_is_single_use_and_bound_to_the_browser() {
+            let states = DashMap::new();
+            let now = Utc::now().naive_utc();
+            remember_state(&states, "s1".into(), pending("nonce", now), now);
+            assert!(matches!(take_state(&states, "s1", Some("other"), now), Err(StateRejection::WrongBrowser)));
+            // a failed attempt still consumes the state
+            assert!(matches!(take_state(&states, "s1", Some("nonce"), now), Err(StateRejection::Unknown)));
+
+            remember_state(&states, "s2".into(), pending("nonce", now), now);
+            assert!(matches!(take_state(&states, "s2", None, now), Err(StateRejection::WrongBrowser)));
+            remember_state(&states, "s3".into(), pending("nonce", now), now);
+            assert!(take_state(&states, "s3", Some("nonce"), now).is_ok());
+        }
+
+        #[test]
+        fn state_expires() {
+            let states = DashMap::new();
+            let then = Utc::now().naive_utc();
+            remember_state(&states, "s1".into(), pending("nonce", then), then);
+            let later = then + Duration::minutes(OAUTH_STATE_TTL_MINUTES + 1);
+            assert!(matches!(take_state(&states, "s1", Some("nonce"), later), Err(StateRejection::Expired)));
+        }
+
+        #[test]
+        fn pending_states_are_bounded() {
+            let states = DashMap::new();
+            let start = Utc::now().naive_utc();
+            for i in 0..MAX_PENDING_OAUTH_STATES {
+                states.insert(format!("s{i}"), pending("nonce", start + Duration::milliseconds(i as i64)));
+            }
+            for i in MAX_PENDING_OAUTH_STATES..MAX_PENDING_OAUTH_STATES + 5 {
+                let at = start + Duration::milliseconds(i as i64);
+                remember_state(&states, format!("s{i}"), pending("nonce", at), at);
+            }
+            assert_eq!(states.len(), MAX_PENDING_OAUTH_STATES);
+            assert!(!states.contains_key("s0"));
+            assert!(states.contains_key(&format!("s{}", MAX_PENDING_OAUTH_STATES + 4)));
 
-        let existing_user = users::table
-            .filter(users::external_id.eq(&user_info.external_id))
-            .filter(users::provider.eq(&user_info.provider))
-            .first::<User>(&mut conn)
-            .await
-            .optional()?;
-
-        if let Some(mut user) = existing_user {
-            diesel::update(users::table.find(user.id))
-                .set((
-                    users::email.eq(&user_info.email),
-                    users::username.eq(&user_info.username),
-                    users::display_name.eq(&user_info.display_name),
-                    users::avatar_url.eq(&user_info.avatar_url),
-                    users::updated_at.eq(diesel::dsl::now),
-                ))
-                .execute(&mut conn)
-                .await?;
-
-            user.email = user_info.email;
-            user.username = user_info.username;
-            user.display_name = user_info.display_name;
-            user.avatar_url = user_info.avatar_url;
-
-            Ok(user)
-        } else {
-            let new_user: NewUser = user_info.into();
-            let user = diesel::insert_into(users::table)
-                .values(&new_user)
-                .get_result::<User>(&mut conn)
-                .await?;
-
-            info!("Created new user: {:?}", user.id);
-            Ok(user)
+            // expired entries are swept on the next login
+            let later = start + Duration::minutes(OAUTH_STATE_TTL_MINUTES + 1);
+            remember_state(&states, "fresh".into(), pending("nonce", later), later);
+            assert_eq!(states.len(), 1);
         }
     }
 }
@@ -0,0 +1,508 @@
+use serde::{Deserialize, Serialize};
+
+/// A configured sign-in provider as shown on the login page
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LoginProvider {
+    pub id: String,
+    pub display_name: String,
+}
+
+#[cfg(feature = "ssr")]
+pub mod provider_config {
+    use dashmap::DashMap;
+    use log::{debug, warn};
+    use serde::Deserialize;
+    use serde_json::Value;
+    use std::fmt;
+    use std::sync::OnceLock;
+
+    use super::LoginProvider;
+    use crate::models::users::CreateUserView;
+
+    const DEFAULT_APP_URL: &str = "http://localhost:3000";
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum ProviderError {
+        Unknown(String),
+        Misconfigured(String),
+        Discovery(String),
+        MissingClaim(String),
+    }
+
+    impl fmt::Display for ProviderError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                ProviderError::Unknown(id) => write!(f, "Unknown OAuth provider: {id}"),
+                ProviderError::Misconfigured(e) => write!(f, "OAuth provider misconfigured: {e}"),
+                ProviderError::Discovery(e) => write!(f, "OIDC discovery failed: {e}"),
+                ProviderError::MissingClaim(claim) => write!(f, "User info is missing the '{claim}' claim"),
+            }
+        }
+    }
+
+    impl std::error::Error for ProviderError {}
+
+    /// Where each user field comes from in the provider's user info.
+    ///
+    /// Values are dotted paths (`profile.email`); `avatar` may also be a
+    /// template such as `https://cdn.example.com/{id}/{avatar}.png`.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct ClaimMapping {
+        pub subject: String,
+        pub email: Option<String>,
+        /// Boolean claim saying the provider verified `email`; without one it never counts as verified
+        pub email_verified: Option<String>,
+        pub username: Option<String>,
+        pub display_name: Option<String>,
+        pub avatar: Option<String>,
+    }
+
+    impl ClaimMapping {
+        fn oidc() -> Self {
+            Self {
+                subject: "sub".to_string(),
+                email: Some("email".to_string()),
+                email_verified: Some("email_verified".to_string()),
+                username: Some("preferred_username".to_string()),
+                display_name: Some("name".to_string()),
+                avatar: Some("picture".to_string()),
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct ProviderConfig {
+        pub id: String,
+        pub display_name: String,
+        pub client_id: String,
+        pub client_secret: String,
+        /// OIDC issuer; endpoints not set explicitly are discovered from it
+        pub issuer: Option<String>,
+        pub authorize_url: Option<String>,
+        pub token_url: Option<String>,
+        pub userinfo_url: Option<String>,
+        pub scopes: Vec<String>,
+        pub pkce: bool,
+        pub redirect_url: String,
+        pub claims: ClaimMapping,
+    }
+
+    /// Resolved endpoints for one login round trip
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct ProviderEndpoints {
+        pub authorize_url: String,
+        pub token_url: String,
+        pub userinfo_url: String,
+    }
+
+    #[derive(Debug, Clone, Deserialize)]
+    struct DiscoveryDocument {
+        authorization_endpoint: String,
+        token_endpoint: String,
+        userinfo_endpoint: Option<String>,
+    }
+
+    /// Built-in defaults for well known providers; anything can be overridden
+    fn preset(id: &str, app_url: &str) -> Option<ProviderConfig> {
+        let base = |display_name: &str, scopes: &[&str], claims: ClaimMapping| ProviderConfig {
+            id: id.to_string(),
+            display_name: display_name.to_string(),
+            client_id: String::new(),
+            client_secret: String::new(),
+            issuer: None,
+            authorize_url: None,
+            token_url: None,
+            userinfo_url: None,
+            scopes: scopes.iter().map(|s| s.to_string()).collect(),
+            pkce: true,
+            redirect_url: format!("{app_url}/auth/{id}/callback"),
+            claims,
+        };
+
+        let config = match id {
+            "google" => ProviderConfig {
+                authorize_url: Some("https://accounts.google.com/o/oauth2/auth".to_string()),
+                token_url: Some("https://oauth2.googleapis.com/token".to_string()),
+                // v2 keeps the `id` users were originally stored under
+                userinfo_url: Some("https://www.googleapis.com/oauth2/v2/userinfo".to_string()),
+                redirect_url: format!("{app_url}/auth/google-callback"),
+                ..base("Google", &["openid", "email", "profile"], ClaimMapping {
+                    subject: "id".to_string(),
+                    email_verified: Some("verified_email".to_string()),
+                    username: None,
+                    ..ClaimMapping::oidc()
+                })
+            },
+            "discord" => ProviderConfig {
+                authorize_url: Some("https://discord.com/api/oauth2/authorize".to_string()),
+                token_url: Some("https://discord.com/api/oauth2/token".to_string()),
+                userinfo_url: Some("https://discord.com/api/v10/users/@me".to_string()),
+                ..base("Discord", &["identify", "email"], ClaimMapping {
+                    subject: "id".to_string(),
+                    email: Some("email".to_string()),
+                    email_verified: Some("verified".to_string()),
+                    username: Some("username".to_string()),
+                    display_name: Some("username".to_string()),
+                    avatar: Some("https://cdn.discordapp.com/avatars/{id}/{avatar}.png".to_string()),
+                })
+            },
+            "github" => ProviderConfig {
+                authorize_url: Some("https://github.com/login/oauth/authorize".to_string()),
+                token_url: Some("https://github.com/login/oauth/access_token".to_string()),
+                userinfo_url: Some("https://api.github.com/user".to_string()),
+                ..base("GitHub", &["read:user", "user:email"], ClaimMapping {
+                    subject: "id".to_string(),
+                    email: Some("email".to_string()),
+                    // the public profile email is not necessarily verified
+                    email_verified: None,
+                    username: Some("login".to_string()),
+                    display_name: Some("name".to_string()),
+                    avatar: Some("avatar_url".to_string()),
+                })
+            },
+            "gitlab" => ProviderConfig {
+                issuer: Some("https://gitlab.com".to_string()),
+                ..base("GitLab", &["openid", "email", "profile"], ClaimMapping {
+                    username: Some("nickname".to_string()),
+                    ..ClaimMapping::oidc()
+                })
+            },
+            "microsoft" => ProviderConfig {
+                issuer: Some("https://login.microsoftonline.com/common/v2.0".to_string()),
+                ..base("Microsoft", &["openid", "email", "profile"], ClaimMapping::oidc())
+            },
+            "mock" => ProviderConfig {
+                issuer: Some(format!("{app_url}/mock-idp")),
+                client_id: "mock-client".to_string(),
+                client_secret: "mock-secret".to_string(),
+                ..base("Mock IdP", &["openid", "email", "profile"], ClaimMapping::oidc())
+            },
+            _ => return None,
+        };
+        Some(config)
+    }
+
+    /// Builds one provider from `OAUTH_<ID>_*` variables on top of its preset.
+    ///
+    /// Google and Discord also accept the older `GOOGLE_*` / `DISCORD_*` names.
+    pub fn provider_from_lookup(
+        id: &str,
+        lookup: &impl Fn(&str) -> Option<String>,
+    ) -> Result<ProviderConfig, ProviderError> {
+        let id = id.trim().to_lowercase();
+        let prefix = format!("OAUTH_{}_", id.to_uppercase().replace('-', "_"));
+        let legacy_prefix = format!("{}_", id.to_uppercase());
+        let has_legacy = matches!(id.as_str(), "google" | "discord");
+        let var = |name: &str| {
+            lookup(&format!("{prefix}{name}"))
+                .or_else(|| has_legacy.then(|| lookup(&format!("{legacy_prefix}{name}"))).flatten())
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+
+        let app_url = lookup("APP_URL")
+            .filter(|v| !v.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_APP_URL.to_string());
+        let app_url = app_url.trim_end_matches('/');
+
+        let mut config = preset(&id, app_url).unwrap_or_else(|| ProviderConfig {
+            id: id.clone(),
+            display_name: id.clone(),
+            client_id: String::new(),
+            client_secret: String::new(),
+            issuer: None,
+            authorize_url: None,
+            token_url: None,
+            userinfo_url: None,
+            scopes: vec!["openid".to_string(), "email".to_string(), "profile".to_string()],
+            pkce: true,
+            redirect_url: format!("{app_url}/auth/{id}/callback"),
+            claims: ClaimMapping::oidc(),
+        });
+
+        if let Some(v) = var("DISPLAY_NAME") { config.display_name = v; }
+        if let Some(v) = var("CLIENT_ID") { config.client_id = v; }
+        if let Some(v) = var("CLIENT_SECRET") { config.client_secret = v; }
+        if let Some(v) = var("ISSUER") { config.issuer = Some(v.trim_end_matches('/').to_string()); }
+        if let Some(v) = var("AUTH_URL") { config.authorize_url = Some(v); }
+        if let Some(v) = var("TOKEN_URL") { config.token_url = Some(v); }
+        if let Some(v) = var("USERINFO_URL") { config.userinfo_url = Some(v); }
+        if let Some(v) = var("REDIRECT_URL") { config.redirect_url = v; }
+        if let Some(v) = var("SCOPES") {
+            config.scopes = v.split([',', ' ']).filter(|s| !s.is_empty()).map(str::to_string).collect();
+        }
+        if let Some(v) = var("PKCE") {
+            config.pkce = !matches!(v.to_lowercase().as_str(), "0" | "false" | "no" | "off");
+        }
+        if let Some(v) = var("CLAIM_SUBJECT") { config.claims.subject = v; }
+        if let Some(v) = var("CLAIM_EMAIL") { config.claims.email = Some(v); }
+        if let Some(v) = var("CLAIM_EMAIL_VERIFIED") { config.claims.email_verified = Some(v); }
+        if let Some(v) = var("CLAIM_USERNAME") { config.claims.username = Some(v); }
+        if let Some(v) = var("CLAIM_DISPLAY_NAME") { config.claims.display_name = Some(v); }
+        if let Some(v) = var("CLAIM_AVATAR") { config.claims.avatar = Some(v); }
+
+        if config.client_id.is_empty() {
+            return Err(ProviderError::Misconfigured(format!("{prefix}CLIENT_ID is not set")));
+        }
+        let explicit = config.authorize_url.is_some() && config.token_url.is_some() && config.userinfo_url.is_some();
+        if config.issuer.is_none() && !explicit {
+            return Err(ProviderError::Misconfigured(format!(
+                "{id} needs {prefix}ISSUER or all of AUTH_URL, TOKEN_URL and USERINFO_URL"
+            )));
+        }
+
+        Ok(config)
+    }
+
+    /// Reads every provider listed in `OAUTH_PROVIDERS`.
+    ///
+    /// Without that list, Google and Discord are enabled when their client
+    /// ids are set, and the mock IdP when `MOCK_IDP_ENABLED` is on.
+    pub fn providers_from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Vec<ProviderConfig> {
+        let ids: Vec<String> = match lookup("OAUTH_PROVIDERS").filter(|v| !v.trim().is_empty()) {
+            Some(list) => list.split(',').map(|s| s.trim().to_lowercase()).filter(|s| !s.is_empty()).collect(),
+            None => {
+                let mut ids = Vec::new();
+                for id in ["google", "discord"] {
+                    let upper = id.to_uppercase();
+                    if lookup(&format!("OAUTH_{upper}_CLIENT_ID")).or_else(|| lookup(&format!("{upper}_CLIENT_ID")))
+                        .is_some_and(|v| !v.trim().is_empty())
+                    {
+                        ids.push(id.to_string());
+                    }
+                }
+                if mock_idp_enabled(&lookup) {
+                    ids.push("mock".to_string());
+                }
+                ids
+            }
+        };
+
+        let mut providers: Vec<ProviderConfig> = Vec::new();
+        for id in ids {
+            if providers.iter().any(|p| p.id == id) {
+                continue;
+            }
+            match provider_from_lookup(&id, &lookup) {
+                Ok(config) => providers.push(config),
+                Err(e) => warn!("Skipping OAuth provider {id}: {e}"),
+            }
+        }
+        providers
+    }
+
+    pub fn mock_idp_enabled(lookup: &impl Fn(&str) -> Option<String>) -> bool {
+        lookup("MOCK_IDP_ENABLED").is_some_and(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+    }
+
+    /// Providers configured in the environment, read once
+    pub fn configured_providers() -> &'static [ProviderConfig] {
+        static PROVIDERS: OnceLock<Vec<ProviderConfig>> = OnceLock::new();
+        PROVIDERS.get_or_init(|| providers_from_lookup(|name| std::env::var(name).ok()))
+    }
+
+    pub fn find_provider(id: &str) -> Result<&'static ProviderConfig, ProviderError> {
+        configured_providers()
+            .iter()
+            .find(|p| p.id == id)
+            .ok_or_else(|| ProviderError::Unknown(id.to_string()))
+    }
+
+    pub fn login_providers() -> Vec<LoginProvider> {
+        configured_providers()
+            .iter()
+            .map(|p| LoginProvider { id: p.id.clone(), display_name: p.display_name.clone() })
+            .collect()
+    }
+
+    impl ProviderConfig {
+        /// Explicit endpoints win; the rest come from the issuer's discovery document
+        pub async fn endpoints(&self) -> Result<ProviderEndpoints, ProviderError> {
+            if let (Some(authorize_url), Some(token_url), Some(userinfo_url)) =
+                (&self.authorize_url, &self.token_url, &self.userinfo_url)
+            {
+                return Ok(ProviderEndpoints {
+                    authorize_url: authorize_url.clone(),
+                    token_url: token_url.clone(),
+                    userinfo_url: userinfo_url.clone(),
+                });
+            }
+
+            let issuer = self.issuer.as_deref()
+                .ok_or_else(|| ProviderError::Misconfigured(format!("{} has no issuer", self.id)))?;
+            let discovered = discover(issuer).await?;
+
+            Ok(ProviderEndpoints {
+                authorize_url: self.authorize_url.clone().unwrap_or(discovered.authorization_endpoint),
+                token_url: self.token_url.clone().unwrap_or(discovered.token_endpoint),
+                userinfo_url: self.userinfo_url.clone()
+                    .or(discovered.userinfo_endpoint)
+                    .ok_or_else(|| ProviderError::Discovery(format!("{issuer} has no userinfo_endpoint")))?,
+            })
+        }
+    }
+
+    async fn discover(issuer: &str) -> Result<DiscoveryDocument, ProviderError> {
+        static DISCOVERED: OnceLock<DashMap<String, DiscoveryDocument>> = OnceLock::new();
+        let cache = DISCOVERED.get_or_init(DashMap::new);
+        if let Some(doc) = cache.get(issuer) {
+            return Ok(doc.clone());
+        }
+
+        let url = format!("{issuer}/.well-known/openid-configuration");
+        debug!("Fetching OIDC discovery document from {url}");
+        let response = reqwest::get(&url).await
+            .map_err(|e| ProviderError::Discovery(format!("{url}: {e}")))?;
+        if !response.status().is_success() {
+            return Err(ProviderError::Discovery(format!("{url} returned {}", response.status())));
+        }
+        let doc: DiscoveryDocument = response.json().await
+            .map_err(|e| ProviderError::Discovery(format!("{url}: {e}")))?;
+
+        cache.insert(issuer.to_string(), doc.clone());
+        Ok(doc)
+    }
+
+    /// Looks up a dotted path; numbers and booleans are returned as text
+    pub fn claim(info: &Value, path: &str) -> Option<String> {
+        let value = path.split('.').try_fold(info, |value, key| value.get(key))?;
+        match value {
+            Value::String(s) if !s.is_empty() => Some(s.clone()),
+            Value::Number(n) => Some(n.to_string()),
+            Value::Bool(b) => Some(b.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Resolves a path, or a `{path}` template where every placeholder must be present
+    fn resolve(info: &Value, spec: &str) -> Option<String> {
+        if !spec.contains('{') {
+            return claim(info, spec);
+        }
+        let mut out = String::new();
+        let mut rest = spec;
+        while let Some(start) = rest.find('{') {
+            let end = start + rest[start..].find('}')?;
+            out.push_str(&rest[..start]);
+            out.push_str(&claim(info, &rest[start + 1..end])?);
+            rest = &rest[end + 1..];
+        }
+        out.push_str(rest);
+        Some(out)
+    }
+
+    pub fn map_user_info(config: &ProviderConfig, info: &Value) -> Result<CreateUserView, ProviderError> {
+        let claims = &config.claims;
+        let field = |spec: &Option<String>| spec.as_deref().and_then(|s| resolve(info, s));
+
+        Ok(CreateUserView {
+            external_id: claim(info, &claims.subject)
+                .ok_or_else(|| ProviderError::MissingClaim(claims.subject.clone()))?,
+            provider: config.id.clone(),
+            email: field(&claims.email),
+            email_verified: field(&claims.email_verified).is_some_and(|v| v == "true"),
+            username: field(&claims.username),
+            display_name: field(&claims.display_name),
+            avatar_url: field(&claims.avatar),
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use serde_json::json;
+        use std::collections::HashMap;
+
+        fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+            let vars: HashMap<String, String> =
+                vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+            move |name| vars.get(name).cloned()
+        }
+
+        #[test]
+        fn legacy_variables_enable_google_and_discord() {
+            let providers = providers_from_lookup(env(&[
+                ("GOOGLE_CLIENT_ID", "g-id"),
+                ("GOOGLE_CLIENT_SECRET", "g-secret"),
+                ("DISCORD_CLIENT_ID", "d-id"),
+                ("DISCORD_REDIRECT_URL", "https://chat.example.com/auth/discord/callback"),
+            ]));
+            let ids: Vec<&str> = providers.iter().map(|p| p.id.as_str()).collect();
+            assert_eq!(ids, ["google", "discord"]);
+            assert_eq!(providers[0].client_secret, "g-secret");
+            assert_eq!(providers[0].redirect_url, "http://localhost:3000/auth/google-callback");
+            assert_eq!(providers[1].redirect_url, "https://chat.example.com/auth/discord/callback");
+            assert!(providers[1].pkce);
+        }
+
+        #[test]
+        fn custom_provider_needs_issuer_or_endpoints() {
+            let lookup = env(&[
+                ("OAUTH_PROVIDERS", "keycloak, broken"),
+                ("APP_URL", "https://chat.example.com/"),
+                ("OAUTH_KEYCLOAK_CLIENT_ID", "chat"),
+                ("OAUTH_KEYCLOAK_ISSUER", "https://sso.example.com/realms/corp/"),
+                ("OAUTH_KEYCLOAK_SCOPES", "openid email groups"),
+                ("OAUTH_KEYCLOAK_DISPLAY_NAME", "Corporate SSO"),
+                ("OAUTH_BROKEN_CLIENT_ID", "x"),
+            ]);
+            let providers = providers_from_lookup(&lookup);
+            assert_eq!(providers.len(), 1);
+            let keycloak = &providers[0];
+            assert_eq!(keycloak.issuer.as_deref(), Some("https://sso.example.com/realms/corp"));
+            assert_eq!(keycloak.scopes, ["openid", "email", "groups"]);
+            assert_eq!(keycloak.redirect_url, "https://chat.example.com/auth/keycloak/callback");
+            assert!(matches!(provider_from_lookup("broken", &lookup), Err(ProviderError::Misconfigured(_))));
+        }
+
+        #[test]
+        fn claims_follow_paths_and_stringify_numbers() {
+            let config = provider_from_lookup("github", &env(&[
+                ("OAUTH_GITHUB_CLIENT_ID", "gh"),
+                ("OAUTH_GITHUB_CLAIM_EMAIL", "contact.email"),
+            ]))
+            .unwrap();
+            let user = map_user_info(&config, &json!({
+                "id": 583231,
+                "login": "octocat",
+                "name": "",
+                "contact": { "email": "octo@example.com" },
+            }))
+            .unwrap();
+            assert_eq!(user.external_id, "583231");
+            assert_eq!(user.provider, "github");
+            assert_eq!(user.username.as_deref(), Some("octocat"));
+            assert_eq!(user.email.as_deref(), Some("octo@example.com"));
+            assert_eq!(user.display_name, None);
+            assert!(!user.email_verified);
+
+            assert!(matches!(
+                map_user_info(&config, &json!({ "login": "octocat" })),
+                Err(ProviderError::MissingClaim(claim)) if claim == "id"
+            ));
+        }
+
+        #[test]
+        fn avatar_template_needs_every_placeholder() {
+            let config = provider_from_lookup("discord", &env(&[("DISCORD_CLIENT_ID", "d")])).unwrap();
+            let with_avatar = map_user_info(&config, &json!({ "id": "42", "username": "ana", "avatar": "abc" })).unwrap();
+            assert_eq!(with_avatar.avatar_url.as_deref(), Some("https://cdn.discordapp.com/avatars/42/abc.png"));
+            let without = map_user_info(&config, &json!({ "id": "42", "username": "ana", "avatar": null })).unwrap();
+            assert_eq!(without.avatar_url, None);
+        }
+
+        #[test]
+        fn email_is_verified_only_when_the_provider_says_so() {
+            let google = provider_from_lookup("google", &env(&[("GOOGLE_CLIENT_ID", "g")])).unwrap();
+            let verified = map_user_info(&google, &json!({ "id": "1", "email": "a@x.test", "verified_email": true })).unwrap();
+            assert!(verified.email_verified);
+            let unverified = map_user_info(&google, &json!({ "id": "1", "email": "a@x.test", "verified_email": false })).unwrap();
+            assert!(!unverified.email_verified);
+            let missing = map_user_info(&google, &json!({ "id": "1", "email": "a@x.test" })).unwrap();
+            assert!(!missing.email_verified);
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub use provider_config::*;
@@ -1,9 +1,21 @@
 #[cfg(feature = "ssr")]
 use argon2::{
-    password_hash::{PasswordHash, PasswordVerifier},
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
     Argon2,
 };
 
+/// Argon2id hash in PHC format, base64 encoded the way `verify_password` reads it
+pub fn hash_password(password: &str) -> Result<String, String> {
+    use base64::{engine::general_purpose::STANDARD as b64, Engine as _};
+
+    let salt = SaltString::generate(&mut rand::rngs::OsRng);
+    let hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| format!("Failed to hash password: {}", e))?;
+
+    Ok(b64.encode(hash.to_string()))
+}
+
 pub fn verify_password(password: &str, hash_b64: &str) -> Result<bool, String> {
     use base64::{engine::general_purpose::STANDARD as b64, Engine as _};
 
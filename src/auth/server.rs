@@ -3,21 +3,85 @@ use cfg_if::cfg_if;
 #[cfg(feature = "ssr")]
 pub mod middleware {
     use axum::{
-        extract::{State, Request},
+        extract::{ConnectInfo, State, Request},
         middleware::Next,
         response::{Response, IntoResponse},
-        http::StatusCode,
+        http::{header::SET_COOKIE, HeaderValue, StatusCode},
     };
     use axum_extra::extract::CookieJar;
     use log::{debug, warn};
+    use std::net::SocketAddr;
     
     use crate::state::AppState;
-    use crate::auth::verify_jwt_token;
+    use crate::auth::{verify_jwt_token, AUTH_COOKIE_NAME};
+    use crate::auth::sessions::{
+        cleared_session_cookies, client_ip, is_revoked, refresh_session, replace_request_cookies,
+        session_cookies, REFRESH_COOKIE_NAME,
+    };
+
+    /// Paths that never need a fresh access token
+    fn skips_refresh(path: &str) -> bool {
+        ["/pkg/", "/auth/", "/mock-idp/", "/favicon"].iter().any(|prefix| path.starts_with(prefix))
+    }
+
+    /// Swaps the refresh token for a new access token when the access token is
+    /// missing or expired, before anything downstream looks at the cookies.
+    ///
+    /// The request's `Cookie` header is rewritten so handlers, server functions
+    /// and SSR all see the new token, and the new cookies are set on the response.
+    pub async fn refresh_sessions(
+        cookie_jar: CookieJar,
+        State(app_state): State<AppState>,
+        mut request: Request,
+        next: Next,
+    ) -> Response {
+        let access_valid = cookie_jar
+            .get(AUTH_COOKIE_NAME)
+            .and_then(|c| verify_jwt_token(c.value()).ok())
+            .and_then(|claims| claims.session_id())
+            .is_some_and(|sid| !is_revoked(&app_state, sid));
+
+        let refresh_token = match cookie_jar.get(REFRESH_COOKIE_NAME) {
+            Some(cookie) if !access_valid && !skips_refresh(request.uri().path()) => cookie.value().to_string(),
+            _ => return next.run(request).await,
+        };
+
+        let remote = request.extensions().get::<ConnectInfo<SocketAddr>>().map(|ci| ci.0);
+        let ip = client_ip(request.headers(), remote);
+
+        let refreshed = match app_state.pool.get().await {
+            Ok(mut conn) => refresh_session(&mut conn, &refresh_token, ip).await,
+            Err(e) => {
+                warn!("Session refresh skipped, no database connection: {e}");
+                return next.run(request).await;
+            }
+        };
+
+        let cookies = match refreshed {
+            Ok(issued) => {
+                debug!("Refreshed session {} for user {}", issued.session_id, issued.user_id);
+                replace_request_cookies(request.headers_mut(), &issued);
+                session_cookies(&issued)
+            }
+            Err(e) => {
+                debug!("Session refresh failed: {e}");
+                cleared_session_cookies()
+            }
+        };
+
+        let mut response = next.run(request).await;
+        for cookie in cookies {
+            if let Ok(value) = HeaderValue::from_str(&cookie.to_string()) {
+                response.headers_mut().append(SET_COOKIE, value);
+            }
+        }
+        response
+    }
 
     /// Middleware that requires authentication via JWT token stored in cookies
     pub async fn require_auth_no_db(
         cookie_jar: CookieJar,
-        State(_app_state): State<AppState>,
+        State(app_state): State<AppState>,
         request: Request,
         next: Next,
     ) -> Response {
@@ -27,13 +91,17 @@ pub mod middleware {
             request.uri()
         );
     
-        let auth_token = cookie_jar.get("auth_token").map(|c| c.value());
+        let auth_token = cookie_jar.get(AUTH_COOKIE_NAME).map(|c| c.value());
         
         debug!("Auth middleware - Found auth token: {}", auth_token.is_some());
     
         match auth_token {
             Some(token) => {
                 match verify_jwt_token(token) {
+                    Ok(claims) if claims.session_id().is_none_or(|sid| is_revoked(&app_state, sid)) => {
+                        debug!("Auth middleware - Token for user {} has no live session", claims.sub);
+                        StatusCode::UNAUTHORIZED.into_response()
+                    }
                     Ok(claims) => {
                         debug!("Auth middleware - Token verified for user: {}", claims.sub);
                         
@@ -0,0 +1,587 @@
+#[cfg(feature = "ssr")]
+pub mod session_server {
+    //! Server-side sessions behind the short-lived access token.
+    //!
+    //! The `auth_token` cookie is a JWT naming its session and expires after
+    //! `ACCESS_TOKEN_TTL_MINUTES`. The `refresh_token` cookie is an opaque
+    //! token that is swapped for a new one every time it is used, extending
+    //! the session by `SESSION_TTL_DAYS`.
+
+    use axum::http::HeaderMap;
+    use axum_extra::extract::cookie::{Cookie, SameSite};
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+    use chrono::{Duration, NaiveDateTime, Utc};
+    use diesel::prelude::*;
+    use diesel_async::{AsyncPgConnection, RunQueryDsl};
+    use log::{debug, warn};
+    use rand::{thread_rng, Rng};
+    use sha2::{Digest, Sha256};
+    use std::fmt;
+    use std::net::{IpAddr, SocketAddr};
+    use std::sync::OnceLock;
+    use uuid::Uuid;
+
+    use crate::auth::AUTH_COOKIE_NAME;
+    use crate::models::sessions::{NewSession, Session};
+    use crate::schema::sessions;
+    use crate::state::AppState;
+
+    pub const REFRESH_COOKIE_NAME: &str = "refresh_token";
+    /// How long the previous refresh token still works after a rotation, for
+    /// requests that were already in flight with it
+    pub const ROTATION_GRACE_SECONDS: i64 = 30;
+    const LAST_SEEN_RESOLUTION_MINUTES: i64 = 5;
+
+    pub fn access_token_ttl() -> Duration {
+        let minutes = std::env::var("ACCESS_TOKEN_TTL_MINUTES").ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .filter(|m| *m > 0)
+            .unwrap_or(15);
+        Duration::minutes(minutes)
+    }
+
+    pub fn session_ttl() -> Duration {
+        let days = std::env::var("SESSION_TTL_DAYS").ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .filter(|d| *d > 0)
+            .unwrap_or(30);
+        Duration::days(days)
+    }
+
+    #[derive(Debug)]
+    pub enum SessionError {
+        NotFound,
+        Expired,
+        Revoked,
+        /// An already rotated refresh token came back; the session is revoked
+        Reused,
+        Token(String),
+        Database(String),
+    }
+
+    impl fmt::Display for SessionError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                SessionError::NotFound => write!(f, "Session not found"),
+                SessionError::Expired => write!(f, "Session has expired"),
+                SessionError::Revoked => write!(f, "Session was revoked"),
+                SessionError::Reused => write!(f, "Refresh token was reused"),
+                SessionError::Token(e) => write!(f, "Token error: {e}"),
+                SessionError::Database(e) => write!(f, "Database error: {e}"),
+            }
+        }
+    }
+
+    impl std::error::Error for SessionError {}
+
+    impl From<diesel::result::Error> for SessionError {
+        fn from(e: diesel::result::Error) -> Self {
+            SessionError::Database(e.to_string())
+        }
+    }
+
+    pub fn generate_refresh_token() -> String {
+        let mut bytes = [0u8; 32];
+        thread_rng().fill(&mut bytes);
+        URL_SAFE_NO_PAD.encode(bytes)
+    }
+
+    /// Hex SHA-256; refresh tokens are only ever stored hashed
+    pub fn hash_token(token: &str) -> String {
+        Sha256::digest(token.as_bytes()).iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    /// "Firefox on Linux" style label for the session list
+    pub fn describe_user_agent(user_agent: Option<&str>) -> String {
+        let Some(ua) = user_agent.filter(|ua| !ua.trim().is_empty()) else {
+            return "Unknown device".to_string();
+        };
+
+        // order matters: Edge and Opera also claim Chrome, Chrome claims Safari
+        let browser = [
+            ("Edg/", "Edge"),
+            ("OPR/", "Opera"),
+            ("Firefox/", "Firefox"),
+            ("Chrome/", "Chrome"),
+            ("Safari/", "Safari"),
+            ("curl/", "curl"),
+        ]
+        .iter()
+        .find(|(needle, _)| ua.contains(needle))
+        .map(|(_, name)| *name);
+
+        let os = [
+            ("Android", "Android"),
+            ("iPhone", "iOS"),
+            ("iPad", "iPadOS"),
+            ("Windows", "Windows"),
+            ("Mac OS X", "macOS"),
+            ("CrOS", "ChromeOS"),
+            ("Linux", "Linux"),
+        ]
+        .iter()
+        .find(|(needle, _)| ua.contains(needle))
+        .map(|(_, name)| *name);
+
+        match (browser, os) {
+            (Some(browser), Some(os)) => format!("{browser} on {os}"),
+            (Some(name), None) | (None, Some(name)) => name.to_string(),
+            (None, None) => ua.chars().take(60).collect(),
+        }
+    }
+
+    /// Proxies whose forwarding headers are believed, from comma-separated
+    /// `TRUSTED_PROXIES` addresses; none by default
+    pub fn trusted_proxies() -> &'static [IpAddr] {
+        static TRUSTED: OnceLock<Vec<IpAddr>> = OnceLock::new();
+        TRUSTED.get_or_init(|| parse_trusted_proxies(&std::env::var("TRUSTED_PROXIES").unwrap_or_default()))
+    }
+
+    pub fn parse_trusted_proxies(value: &str) -> Vec<IpAddr> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|address| !address.is_empty())
+            .filter_map(|address| match address.parse() {
+                Ok(ip) => Some(ip),
+                Err(_) => {
+                    warn!("Ignoring invalid TRUSTED_PROXIES entry {address:?}");
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// The socket peer, or the client a trusted proxy forwarded for
+    pub fn client_ip(headers: &HeaderMap, remote: Option<SocketAddr>) -> Option<String> {
+        client_ip_behind(headers, remote, trusted_proxies())
+    }
+
+    /// Forwarding headers are only read when the peer is one of `trusted`. The
+    /// client is then the last `X-Forwarded-For` hop that isn't a trusted proxy
+    /// itself, since earlier hops are whatever the client sent; else `X-Real-IP`.
+    pub fn client_ip_behind(headers: &HeaderMap, remote: Option<SocketAddr>, trusted: &[IpAddr]) -> Option<String> {
+        let peer = remote?.ip();
+        if !trusted.contains(&peer) {
+            return Some(peer.to_string());
+        }
+        let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+        let forwarded = header("x-forwarded-for").and_then(|hops| {
+            hops.rsplit(',')
+                .map_while(|hop| hop.trim().parse::<IpAddr>().ok())
+                .find(|hop| !trusted.contains(hop))
+        });
+        let real_ip = || header("x-real-ip").and_then(|v| v.trim().parse::<IpAddr>().ok());
+        Some(forwarded.or_else(real_ip).unwrap_or(peer).to_string())
+    }
+
+    pub fn user_agent(headers: &HeaderMap) -> Option<String> {
+        headers.get(axum::http::header::USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.chars().take(512).collect())
+    }
+
+    #[derive(Debug, PartialEq)]
+    pub enum RefreshDecision {
+        /// Current token: hand out a new refresh token
+        Rotate,
+        /// Previous token within the grace period: new access token only
+        Reissue,
+        Reused,
+        Expired,
+        Revoked,
+    }
+
+    pub fn refresh_decision(session: &Session, presented_hash: &str, now: NaiveDateTime) -> RefreshDecision {
+        if session.revoked_at.is_some() {
+            return RefreshDecision::Revoked;
+        }
+        if session.expires_at <= now {
+            return RefreshDecision::Expired;
+        }
+        if session.refresh_token_hash == presented_hash {
+            return RefreshDecision::Rotate;
+        }
+        let in_grace = session.rotated_at
+            .is_some_and(|at| now - at <= Duration::seconds(ROTATION_GRACE_SECONDS));
+        if session.previous_token_hash.as_deref() == Some(presented_hash) && in_grace {
+            RefreshDecision::Reissue
+        } else {
+            RefreshDecision::Reused
+        }
+    }
+
+    /// Tokens to set after a login or refresh
+    pub struct IssuedSession {
+        pub session_id: Uuid,
+        pub user_id: i32,
+        pub access_token: String,
+        /// `None` when the refresh token in the browser stays valid
+        pub refresh_token: Option<String>,
+    }
+
+    fn access_token(user_id: i32, session_id: Uuid) -> Result<String, SessionError> {
+        crate::auth::create_jwt_token(user_id, session_id).map_err(|e| SessionError::Token(e.to_string()))
+    }
+
+    pub async fn create_session(
+        conn: &mut AsyncPgConnection,
+        user_id: i32,
+        user_agent: Option<String>,
+        ip_address: Option<String>,
+    ) -> Result<IssuedSession, SessionError> {
+        let now = Utc::now().naive_utc();
+
+        // drop this user's dead sessions while we're here
+        diesel::delete(
+            sessions::table
+                .filter(sessions::user_id.eq(user_id))
+                .filter(sessions::expires_at.lt(now).or(sessions::revoked_at.lt(now - Duration::days(30)))),
+        )
+        .execute(conn)
+        .await?;
+
+        let refresh_token = generate_refresh_token();
+        let session_id: Uuid = diesel::insert_into(sessions::table)
+            .values(&NewSession {
+                user_id,
+                refresh_token_hash: hash_token(&refresh_token),
+                user_agent,
+                ip_address,
+                expires_at: now + session_ttl(),
+            })
+            .returning(sessions::id)
+            .get_result(conn)
+            .await?;
+
+        debug!("Created session {session_id} for user {user_id}");
+        Ok(IssuedSession {
+            session_id,
+            user_id,
+            access_token: access_token(user_id, session_id)?,
+            refresh_token: Some(refresh_token),
+        })
+    }
+
+    /// Trades a refresh token for a new access token, rotating it when it is current
+    pub async fn refresh_session(
+        conn: &mut AsyncPgConnection,
+        refresh_token: &str,
+        ip_address: Option<String>,
+    ) -> Result<IssuedSession, SessionError> {
+        let presented = hash_token(refresh_token);
+        let now = Utc::now().naive_utc();
+
+        let session = sessions::table
+            .filter(sessions::refresh_token_hash.eq(&presented).or(sessions::previous_token_hash.eq(&presented)))
+            .first::<Session>(conn)
+            .await
+            .optional()?
+            .ok_or(SessionError::NotFound)?;
+
+        let mut decision = refresh_decision(&session, &presented, now);
+        let mut new_token = None;
+
+        if decision == RefreshDecision::Rotate {
+            let token = generate_refresh_token();
+            // only one of several concurrent refreshes gets to rotate
+            let rotated = diesel::update(
+                sessions::table
+                    .find(session.id)
+                    .filter(sessions::refresh_token_hash.eq(&presented)),
+            )
+            .set((
+                sessions::refresh_token_hash.eq(hash_token(&token)),
+                sessions::previous_token_hash.eq(&presented),
+                sessions::rotated_at.eq(now),
+                sessions::last_seen_at.eq(now),
+                sessions::expires_at.eq(now + session_ttl()),
+                sessions::ip_address.eq(ip_address.or(session.ip_address.clone())),
+            ))
+            .execute(conn)
+            .await?;
+
+            if rotated == 1 {
+                new_token = Some(token);
+            } else {
+                decision = RefreshDecision::Reissue;
+            }
+        }
+
+        match decision {
+            RefreshDecision::Rotate | RefreshDecision::Reissue => Ok(IssuedSession {
+                session_id: session.id,
+                user_id: session.user_id,
+                access_token: access_token(session.user_id, session.id)?,
+                refresh_token: new_token,
+            }),
+            RefreshDecision::Reused => {
+                warn!("Refresh token reuse on session {}; revoking it", session.id);
+                diesel::update(sessions::table.find(session.id))
+                    .set(sessions::revoked_at.eq(now))
+                    .execute(conn)
+                    .await?;
+                Err(SessionError::Reused)
+            }
+            RefreshDecision::Expired => Err(SessionError::Expired),
+            RefreshDecision::Revoked => Err(SessionError::Revoked),
+        }
+    }
+
+    /// The session if it is neither revoked nor expired; refreshes `last_seen_at`
+    pub async fn active_session(conn: &mut AsyncPgConnection, session_id: Uuid) -> Result<Session, SessionError> {
+        let now = Utc::now().naive_utc();
+        let session = sessions::table
+            .find(session_id)
+            .first::<Session>(conn)
+            .await
+            .optional()?
+            .ok_or(SessionError::NotFound)?;
+
+        if session.revoked_at.is_some() {
+            return Err(SessionError::Revoked);
+        }
+        if session.expires_at <= now {
+            return Err(SessionError::Expired);
+        }
+        if now - session.last_seen_at > Duration::minutes(LAST_SEEN_RESOLUTION_MINUTES) {
+            diesel::update(sessions::table.find(session.id))
+                .set(sessions::last_seen_at.eq(now))
+                .execute(conn)
+                .await?;
+        }
+        Ok(session)
+    }
+
+    pub async fn find_session_by_refresh_token(
+        conn: &mut AsyncPgConnection,
+        refresh_token: &str,
+    ) -> Result<Option<Session>, SessionError> {
+        let hash = hash_token(refresh_token);
+        Ok(sessions::table
+            .filter(sessions::refresh_token_hash.eq(&hash).or(sessions::previous_token_hash.eq(&hash)))
+            .first::<Session>(conn)
+            .await
+            .optional()?)
+    }
+
+    pub async fn list_sessions(conn: &mut AsyncPgConnection, user_id: i32) -> Result<Vec<Session>, SessionError> {
+        Ok(sessions::table
+            .filter(sessions::user_id.eq(user_id))
+            .filter(sessions::revoked_at.is_null())
+            .filter(sessions::expires_at.gt(Utc::now().naive_utc()))
+            .order(sessions::last_seen_at.desc())
+            .load::<Session>(conn)
+            .await?)
+    }
+
+    /// Revokes one of the user's sessions; false if it isn't theirs or is already gone
+    pub async fn revoke_session(
+        conn: &mut AsyncPgConnection,
+        user_id: i32,
+        session_id: Uuid,
+    ) -> Result<bool, SessionError> {
+        let revoked = diesel::update(
+            sessions::table
+                .find(session_id)
+                .filter(sessions::user_id.eq(user_id))
+                .filter(sessions::revoked_at.is_null()),
+        )
+        .set(sessions::revoked_at.eq(Utc::now().naive_utc()))
+        .execute(conn)
+        .await?;
+        Ok(revoked == 1)
+    }
+
+    /// Revokes every live session of the user and returns their ids
+    pub async fn revoke_all_sessions(conn: &mut AsyncPgConnection, user_id: i32) -> Result<Vec<Uuid>, SessionError> {
+        Ok(diesel::update(
+            sessions::table
+                .filter(sessions::user_id.eq(user_id))
+                .filter(sessions::revoked_at.is_null()),
+        )
+        .set(sessions::revoked_at.eq(Utc::now().naive_utc()))
+        .returning(sessions::id)
+        .get_results(conn)
+        .await?)
+    }
+
+    /// Lets the stateless middleware reject access tokens of sessions revoked
+    /// in this process until those tokens would have expired anyway
+    pub fn remember_revoked(state: &AppState, session_ids: &[Uuid]) {
+        let until = Utc::now().naive_utc() + access_token_ttl();
+        for id in session_ids {
+            state.revoked_sessions.insert(*id, until);
+        }
+        let now = Utc::now().naive_utc();
+        state.revoked_sessions.retain(|_, until| *until > now);
+    }
+
+    pub fn is_revoked(state: &AppState, session_id: Uuid) -> bool {
+        state.revoked_sessions.contains_key(&session_id)
+    }
+
+    pub fn session_cookies(issued: &IssuedSession) -> Vec<Cookie<'static>> {
+        let mut cookies = vec![Cookie::build((AUTH_COOKIE_NAME, issued.access_token.clone()))
+            .path("/")
+            .secure(true)
+            .http_only(true)
+            .same_site(SameSite::Lax)
+            .max_age(cookie::time::Duration::seconds(access_token_ttl().num_seconds()))
+            .build()];
+        if let Some(refresh_token) = &issued.refresh_token {
+            cookies.push(Cookie::build((REFRESH_COOKIE_NAME, refresh_token.clone()))
+                .path("/")
+                .secure(true)
+                .http_only(true)
+                .same_site(SameSite::Lax)
+                .max_age(cookie::time::Duration::seconds(session_ttl().num_seconds()))
+                .build());
+        }
+        cookies
+    }
+
+    /// Puts freshly issued tokens into the request's own `Cookie` header
+    pub fn replace_request_cookies(headers: &mut HeaderMap, issued: &IssuedSession) {
+        use axum::http::{header::COOKIE, HeaderValue};
+
+        let mut pairs: Vec<String> = headers
+            .get_all(COOKIE)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(';'))
+            .map(|pair| pair.trim().to_string())
+            .filter(|pair| {
+                let name = pair.split('=').next().unwrap_or_default();
+                !pair.is_empty()
+                    && name != AUTH_COOKIE_NAME
+                    && !(name == REFRESH_COOKIE_NAME && issued.refresh_token.is_some())
+            })
+            .collect();
+        pairs.push(format!("{AUTH_COOKIE_NAME}={}", issued.access_token));
+        if let Some(refresh_token) = &issued.refresh_token {
+            pairs.push(format!("{REFRESH_COOKIE_NAME}={refresh_token}"));
+        }
+
+        headers.remove(COOKIE);
+        if let Ok(value) = HeaderValue::from_str(&pairs.join("; ")) {
+            headers.insert(COOKIE, value);
+        }
+    }
+
+    pub fn cleared_session_cookies() -> Vec<Cookie<'static>> {
+        [AUTH_COOKIE_NAME, REFRESH_COOKIE_NAME]
+            .into_iter()
+            .map(|name| Cookie::build((name, ""))
+                .path("/")
+                .max_age(cookie::time::Duration::seconds(-1))
+                .build())
+            .collect()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn session(now: NaiveDateTime) -> Session {
+            Session {
+                id: Uuid::new_v4(),
+                user_id: 1,
+                refresh_token_hash: hash_token("current"),
+                previous_token_hash: Some(hash_token("previous")),
+                rotated_at: Some(now - Duration::seconds(5)),
+                user_agent: None,
+                ip_address: None,
+                created_at: now - Duration::days(1),
+                last_seen_at: now,
+                expires_at: now + Duration::days(29),
+                revoked_at: None,
+            }
+        }
+
+        #[test]
+        fn tokens_are_hashed_not_stored() {
+            let token = generate_refresh_token();
+            assert_eq!(token.len(), 43);
+            assert_ne!(token, generate_refresh_token());
+            assert_eq!(hash_token(&token), hash_token(&token));
+            assert_eq!(hash_token(&token).len(), 64);
+        }
+
+        #[test]
+        fn previous_token_only_works_during_grace() {
+            let now = Utc::now().naive_utc();
+            let mut s = session(now);
+            assert_eq!(refresh_decision(&s, &hash_token("current"), now), RefreshDecision::Rotate);
+            assert_eq!(refresh_decision(&s, &hash_token("previous"), now), RefreshDecision::Reissue);
+
+            s.rotated_at = Some(now - Duration::seconds(ROTATION_GRACE_SECONDS + 1));
+            assert_eq!(refresh_decision(&s, &hash_token("previous"), now), RefreshDecision::Reused);
+        }
+
+        #[test]
+        fn revoked_and_expired_sessions_never_refresh() {
+            let now = Utc::now().naive_utc();
+            let mut s = session(now);
+            s.expires_at = now;
+            assert_eq!(refresh_decision(&s, &hash_token("current"), now), RefreshDecision::Expired);
+            s.revoked_at = Some(now);
+            assert_eq!(refresh_decision(&s, &hash_token("current"), now), RefreshDecision::Revoked);
+        }
+
+        #[test]
+        fn devices_and_addresses_are_readable() {
+            let firefox = "Mozilla/5.0 (X11; Linux x86_64; rv:128.0) Gecko/20100101 Firefox/128.0";
+            let edge = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0 Safari/537.36 Edg/126.0";
+            assert_eq!(describe_user_agent(Some(firefox)), "Firefox on Linux");
+            assert_eq!(describe_user_agent(Some(edge)), "Edge on Windows");
+            assert_eq!(describe_user_agent(None), "Unknown device");
+
+            let mut headers = HeaderMap::new();
+            let remote: SocketAddr = "10.0.0.2:5555".parse().unwrap();
+            let proxies = parse_trusted_proxies("10.0.0.2, 10.0.0.1, nonsense");
+            assert_eq!(proxies.len(), 2);
+            assert_eq!(client_ip_behind(&headers, Some(remote), &proxies).as_deref(), Some("10.0.0.2"));
+            headers.insert("x-forwarded-for", "203.0.113.9, 10.0.0.1".parse().unwrap());
+            assert_eq!(client_ip_behind(&headers, Some(remote), &proxies).as_deref(), Some("203.0.113.9"));
+        }
+
+        #[test]
+        fn forwarding_headers_need_a_trusted_proxy() {
+            let mut headers = HeaderMap::new();
+            headers.insert("x-forwarded-for", "198.51.100.7".parse().unwrap());
+            headers.insert("x-real-ip", "198.51.100.8".parse().unwrap());
+            let remote: SocketAddr = "192.0.2.1:443".parse().unwrap();
+            assert_eq!(client_ip_behind(&headers, Some(remote), &[]).as_deref(), Some("192.0.2.1"));
+
+            // a spoofed first hop is skipped; the proxy appended the real client last
+            let proxy: SocketAddr = "10.0.0.2:5555".parse().unwrap();
+            let trusted = [proxy.ip()];
+            headers.insert("x-forwarded-for", "198.51.100.7, 203.0.113.9".parse().unwrap());
+            assert_eq!(client_ip_behind(&headers, Some(proxy), &trusted).as_deref(), Some("203.0.113.9"));
+            headers.remove("x-forwarded-for");
+            assert_eq!(client_ip_behind(&headers, Some(proxy), &trusted).as_deref(), Some("198.51.100.8"));
+        }
+
+        #[test]
+        fn refreshed_tokens_replace_request_cookies() {
+            let mut headers = HeaderMap::new();
+            headers.insert("cookie", "darkmode=true; auth_token=old; refresh_token=r1".parse().unwrap());
+            let mut issued = IssuedSession {
+                session_id: Uuid::new_v4(),
+                user_id: 1,
+                access_token: "new".to_string(),
+                refresh_token: None,
+            };
+            replace_request_cookies(&mut headers, &issued);
+            assert_eq!(headers["cookie"], "darkmode=true; refresh_token=r1; auth_token=new");
+
+            issued.refresh_token = Some("r2".to_string());
+            replace_request_cookies(&mut headers, &issued);
+            assert_eq!(headers["cookie"], "darkmode=true; auth_token=new; refresh_token=r2");
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub use session_server::*;
@@ -0,0 +1,131 @@
+//! Scores retrieval against a golden question set.
+//!
+//! ```text
+//! cargo run --bin rag-eval --features ssr -- eval/sample/golden.json \
+//!     [--configs configs.json] [--embedder stub|<model id>] [--out report.md] [--json report.json]
+//! ```
+//!
+//! Documents are read from the set's `documents_dir`, chunked like uploads, embedded
+//! (by default with the deterministic stub embedder, so no network or database is
+//! needed) and searched with every retrieval config. The markdown report is meant to
+//! be committed and diffed between changes.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use l3chat::services::chunking::{chunker_for_document, ChunkingConfig};
+use l3chat::services::embeddings::{embedding_provider, EmbeddingProvider, StubEmbeddings};
+use l3chat::services::evaluation::{evaluate, render_report, EvalIndex, GoldenSet, IndexedChunk, RetrievalConfig};
+use l3chat::services::extraction::{extract_document, sniff_format};
+use l3chat::services::ingestion::{embed_all, EmbeddingBatchConfig};
+use l3chat::services::tokens::default_token_counter;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+struct Args {
+    golden: PathBuf,
+    configs: Option<PathBuf>,
+    embedder: String,
+    out: Option<PathBuf>,
+    json: Option<PathBuf>,
+}
+
+fn parse_args() -> Result<Args, BoxError> {
+    let mut args = std::env::args().skip(1);
+    let mut golden = None;
+    let mut parsed = Args { golden: PathBuf::new(), configs: None, embedder: "stub".to_string(), out: None, json: None };
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+        match arg.as_str() {
+            "--configs" => parsed.configs = Some(value()?.into()),
+            "--embedder" => parsed.embedder = value()?,
+            "--out" => parsed.out = Some(value()?.into()),
+            "--json" => parsed.json = Some(value()?.into()),
+            _ if arg.starts_with("--") => return Err(format!("unknown option {arg}").into()),
+            _ => golden = Some(PathBuf::from(arg)),
+        }
+    }
+
+    parsed.golden = golden.ok_or("usage: rag-eval <golden.json> [--configs FILE] [--embedder stub|MODEL] [--out FILE] [--json FILE]")?;
+    Ok(parsed)
+}
+
+/// Files under `dir`, sorted so chunk order (and therefore ties) is reproducible
+fn list_files(dir: &Path) -> Result<Vec<PathBuf>, BoxError> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in std::fs::read_dir(&current)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), BoxError> {
+    dotenv::dotenv().ok();
+    let args = parse_args()?;
+
+    let set: GoldenSet = serde_json::from_str(&std::fs::read_to_string(&args.golden)?)?;
+    let configs: Vec<RetrievalConfig> = match &args.configs {
+        Some(path) => serde_json::from_str(&std::fs::read_to_string(path)?)?,
+        None => RetrievalConfig::default_matrix(),
+    };
+    let provider: Arc<dyn EmbeddingProvider> = match args.embedder.as_str() {
+        "stub" => Arc::new(StubEmbeddings::default()),
+        model => embedding_provider(model)?,
+    };
+    let token_counter = default_token_counter();
+    let batching = EmbeddingBatchConfig::default();
+
+    let documents_dir = args.golden.parent().unwrap_or(Path::new(".")).join(&set.documents_dir);
+    let mut chunks = Vec::new();
+    for path in list_files(&documents_dir)? {
+        let name = path.strip_prefix(&documents_dir)?.to_string_lossy().replace('\\', "/");
+        let bytes = std::fs::read(&path)?;
+        let extracted = match sniff_format(&bytes, &name).and_then(|format| extract_document(&bytes, format)) {
+            Ok(extracted) => extracted,
+            Err(e) => {
+                eprintln!("skipping {name}: {e}");
+                continue;
+            }
+        };
+        let chunker = chunker_for_document(&name, None, ChunkingConfig::default());
+        for chunk in chunker.chunk(&extracted.text) {
+            chunks.push(IndexedChunk {
+                document: name.clone(),
+                tokens: token_counter.count(&chunk.text),
+                text: chunk.text,
+                vector: Vec::new(),
+            });
+        }
+    }
+
+    let texts: Vec<String> = chunks.iter().map(|chunk| chunk.text.clone()).collect();
+    for (chunk, vector) in chunks.iter_mut().zip(embed_all(provider.as_ref(), &texts, &batching).await?) {
+        chunk.vector = vector;
+    }
+    let questions: Vec<String> = set.questions.iter().map(|q| q.question.clone()).collect();
+    let query_vectors = embed_all(provider.as_ref(), &questions, &batching).await?;
+
+    let report = evaluate(&set, &EvalIndex::new(chunks), &query_vectors, &configs, provider.model_id());
+    let rendered = render_report(&report);
+
+    match &args.out {
+        Some(path) => std::fs::write(path, &rendered)?,
+        None => print!("{rendered}"),
+    }
+    if let Some(path) = &args.json {
+        std::fs::write(path, serde_json::to_string_pretty(&report)?)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use leptos::prelude::*;
+
+use crate::models::audit::{AuditEntryView, AUDIT_ACTIONS, AUDIT_PAGE_SIZE};
+use crate::server_fn::audit::{get_audit_access, get_audit_log};
+
+const SELECT_CLASS: &str = "px-2 py-1 text-sm border border-gray-300 dark:border-teal-600 rounded-md dark:bg-teal-700 dark:text-gray-200";
+const BUTTON_CLASS: &str = "px-2 py-1 text-xs border border-gray-300 dark:border-teal-600 text-gray-600 dark:text-gray-300 rounded-md hover:bg-gray-100 dark:hover:bg-teal-700";
+
+fn entry_row(entry: AuditEntryView) -> impl IntoView {
+    let actor = match (entry.actor_name, entry.actor_id) {
+        (Some(name), Some(id)) => format!("{name} (#{id})"),
+        (None, Some(id)) => format!("User #{id}"),
+        (name, None) => name.unwrap_or_else(|| "Unknown".to_string()),
+    };
+    let target = entry.target_type.map(|target_type| match entry.target_id {
+        Some(id) => format!("{target_type} {id}"),
+        None => target_type,
+    });
+
+    view! {
+        <li class="py-2 text-sm">
+            <div class="flex justify-between">
+                <span class="font-medium text-gray-800 dark:text-gray-200">{entry.action}</span>
+                <span class="text-gray-500 dark:text-gray-400">
+                    {entry.created_at.format("%Y-%m-%d %H:%M:%S UTC").to_string()}
+                </span>
+            </div>
+            <div class="text-gray-500 dark:text-gray-400">
+                {actor}
+                " · "
+                {entry.ip_address.unwrap_or_else(|| "Unknown address".to_string())}
+                {target.map(|target| format!(" · {target}"))}
+            </div>
+            {entry.details.map(|details| view! {
+                <div class="text-xs font-mono text-gray-500 dark:text-gray-400 break-all">{details.to_string()}</div>
+            })}
+        </li>
+    }
+}
+
+/// Audit log browser, only shown to users listed in `ADMIN_USER_IDS`
+#[component]
+pub fn AuditLog() -> impl IntoView {
+    let access = Resource::new(|| (), |_| get_audit_access());
+    let (action, set_action) = signal(String::new());
+    // id of the last entry on the previous pages; `None` shows the newest
+    let (pages, set_pages) = signal(Vec::<i64>::new());
+    let entries = Resource::new(
+        move || (action.get(), pages.get().last().copied()),
+        |(action, before_id)| get_audit_log(Some(action), None, before_id, None),
+    );
+
+    view! {
+        <Suspense fallback=|| ()>
+            {move || access.get().and_then(|result| result.ok()).filter(|&admin| admin).map(|_| view! {
+                <div class="mt-6 bg-white dark:bg-teal-800 rounded-lg shadow-md p-6">
+                    <div class="flex justify-between items-center mb-4">
+                        <h3 class="text-lg font-semibold text-gray-800 dark:text-gray-200">
+                            "Audit Log"
+                        </h3>
+                        <select
+                            class=SELECT_CLASS
+                            on:change=move |ev| {
+                                set_action.set(event_target_value(&ev));
+                                set_pages.set(Vec::new());
+                            }
+                        >
+                            <option value="">"All actions"</option>
+                            {AUDIT_ACTIONS
+                                .iter()
+                                .map(|action| view! { <option value=*action>{*action}</option> })
+                                .collect_view()}
+                        </select>
+                    </div>
+                    <Transition fallback=|| view! { <div class="text-sm text-gray-500">"Loading audit log..."</div> }>
+                        {move || entries.get().map(|result| match result {
+                            Ok(list) => {
+                                let oldest = list.last().map(|entry| entry.id);
+                                let full_page = list.len() as i64 >= AUDIT_PAGE_SIZE;
+                                view! {
+                                    {list.is_empty().then(|| view! {
+                                        <p class="text-sm text-gray-500 dark:text-gray-400">"No entries."</p>
+                                    })}
+                                    <ul class="divide-y divide-gray-200 dark:divide-teal-700">
+                                        {list.into_iter().map(entry_row).collect_view()}
+                                    </ul>
+                                    <div class="flex gap-2 mt-3">
+                                        {move || (!pages.get().is_empty()).then(|| view! {
+                                            <button class=BUTTON_CLASS on:click=move |_| set_pages.update(|pages| { pages.pop(); })>
+                                                "Newer"
+                                            </button>
+                                        })}
+                                        {oldest.filter(|_| full_page).map(|oldest| view! {
+                                            <button class=BUTTON_CLASS on:click=move |_| set_pages.update(|pages| pages.push(oldest))>
+                                                "Older"
+                                            </button>
+                                        })}
+                                    </div>
+                                }.into_any()
+                            }
+                            Err(e) => view! {
+                                <p class="text-sm text-red-500">{format!("Could not load the audit log: {e}")}</p>
+                            }.into_any(),
+                        })}
+                    </Transition>
+                </div>
+            })}
+        </Suspense>
+    }.into_any()
+}
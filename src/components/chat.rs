@@ -10,24 +10,38 @@ use wasm_bindgen_futures::JsFuture;
 use web_sys::{EventSource, MessageEvent, ErrorEvent, HtmlElement};
 use chrono::Utc;
 
-use crate::{auth::get_current_user, models::conversations::{NewMessageView, PendingMessage}};
+use crate::{auth::get_current_user, models::conversations::{DocumentCitation, NewMessageView, PendingMessage}};
 use crate::components::toast::Toast;
 use crate::types::StreamResponse;
 
+/// Models offered in the chat box, as `(id, label)`
+pub const CHAT_MODELS: [(&str, &str); 7] = [
+    ("claude-3-haiku-20240307", "claude-3-haiku"),
+    ("claude-3-sonnet-20240229", "claude-3-sonnet"),
+    ("claude-3-opus-20240229", "claude-3-opus"),
+    ("claude-3-5-sonnet-20240620", "claude-3-5-sonnet"),
+    ("gpt-4o-mini", "gpt-4o-mini"),
+    ("gpt-4o", "gpt-4o"),
+    ("gpt-4-turbo", "gpt-4-turbo"),
+];
+
+/// The lab serving a model
+pub fn lab_for_model(model: &str) -> &'static str {
+    if model.contains("claude") {
+        "anthropic"
+    } else {
+        "openai"
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct RagResponse {
     pub message_type: String,
     pub content: Option<String>,
     pub citations: Option<Vec<DocumentCitation>>,
     pub status: Option<String>,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct DocumentCitation {
-    pub filename: String,
-    pub chunk_text: String,
-    pub similarity: f32,
-    pub chunk_index: i32,
+    #[serde(default)]
+    pub trace_id: Option<uuid::Uuid>,
 }
 
 cfg_if! {
@@ -43,11 +57,11 @@ cfg_if! {
         use tokio::sync::mpsc;
         use futures::stream::{Stream, StreamExt};
         use tokio_util::sync::CancellationToken;
-        use std::convert::Infallible;
         use log::debug;
 
         use crate::database::db::DbPool;
         use crate::models::conversations::Message;
+        use crate::services::completions::CompletionEvent;
         use crate::services::rag::create_rag_service;
 
         pub struct SseStream {
@@ -76,6 +90,22 @@ cfg_if! {
             model: String,
         }
 
+        /// Anthropic takes system prompts as a separate field rather than as messages
+        fn anthropic_messages(history: Vec<Message>) -> (Option<String>, Vec<Value>) {
+            let (system, chat): (Vec<Message>, Vec<Message>) = history.into_iter().partition(|msg| msg.role == "system");
+            let system = system.into_iter()
+                .filter_map(|msg| msg.content)
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            let api_messages = chat.into_iter()
+                .map(|msg| serde_json::json!({
+                    "role": msg.role,
+                    "content": msg.content.unwrap_or_default(),
+                }))
+                .collect();
+            ((!system.is_empty()).then_some(system), api_messages)
+        }
+
         impl AnthropicService {
             pub fn new(model: String) -> Self {
                 let api_key = env::var("ANTHROPIC_API_KEY").expect("ANTHROPIC_API_KEY must be set.");
@@ -88,7 +118,7 @@ cfg_if! {
                 pool: &DbPool,
                 thread_id: &str,
                 context: &str,
-                tx: mpsc::Sender<Result<Event, Infallible>>,
+                tx: mpsc::Sender<CompletionEvent>,
                 cancel_token: CancellationToken,
             ) -> Result<(), anyhow::Error> {
                 debug!("Sending message to Anthropic API with project context (cancellable)");
@@ -117,23 +147,22 @@ cfg_if! {
                     return Ok(());
                 }
         
-                let api_messages = history.into_iter()
-                    .map(|msg| serde_json::json!({
-                        "role": msg.role,
-                        "content": msg.content.unwrap_or_default(),
-                    }))
-                    .collect::<Vec<_>>();
+                let (system, api_messages) = anthropic_messages(history);
+                let mut body = serde_json::json!({
+                    "model": self.model,
+                    "messages": api_messages,
+                    "max_tokens": 1360,
+                    "stream": true,
+                });
+                if let Some(system) = system {
+                    body["system"] = Value::String(system);
+                }
         
                 let response = self.client.post("https://api.anthropic.com/v1/messages")
                     .header("x-api-key", self.api_key.to_string())
                     .header("anthropic-version", "2023-06-01")
                     .header("Content-Type", "application/json")
-                    .json(&serde_json::json!({
-                        "model": self.model,
-                        "messages": api_messages,
-                        "max_tokens": 1360,
-                        "stream": true,
-                    }))
+                    .json(&body)
                     .send()
                     .await
                     .map_err(|e| anyhow!("Failed to send message: {}", e))?;
@@ -144,7 +173,7 @@ cfg_if! {
                     // Check for cancellation in each iteration
                     if cancel_token.is_cancelled() {
                         info!("Anthropic message stream cancelled during processing");
-                        let _ = tx.send(Ok(Event::default().data("[CANCELLED]"))).await;
+                        let _ = tx.send(CompletionEvent::Cancelled).await;
                         return Ok(());
                     }
         
@@ -158,7 +187,7 @@ cfg_if! {
                             for line in event.trim().lines() {
                                 if line.trim() == "event: message_stop" {
                                     debug!("Received message_stop event");
-                                    tx.send(Ok(Event::default().data("[DONE]"))).await.ok();
+                                    tx.send(CompletionEvent::Done).await.ok();
                                     return Ok(());
                                 } else if line.trim().starts_with("data: ") {
                                     let json_str = &line.trim()[6..];
@@ -167,13 +196,13 @@ cfg_if! {
                                         if let Some(delta) = parsed["delta"].as_object() {
                                             if let Some(text) = delta.get("text").and_then(|t| t.as_str()) {
                                                 debug!("Extracted content: {}", text);
-                                                tx.send(Ok(Event::default().data(text))).await.ok();
+                                                tx.send(CompletionEvent::Delta(text.to_string())).await.ok();
                                             }
                                         }
                                     } else {
                                         if let Some(content) = extract_text_content(json_str) {
                                             debug!("Fallback extracted content: {}", content);
-                                            tx.send(Ok(Event::default().data(content))).await.ok();
+                                            tx.send(CompletionEvent::Delta(content.into())).await.ok();
                                         }
                                     }
                                 }
@@ -181,8 +210,7 @@ cfg_if! {
                         }
                         Err(e) => {
                             error!("Failed to process stream: {e}");
-                            let error_event = Event::default().data(format!("Error: Failed to process stream: {e}"));
-                            tx.send(Ok(error_event)).await.ok();
+                            tx.send(CompletionEvent::Error(format!("Failed to process stream: {e}"))).await.ok();
                             break;
                         }
                     }
@@ -196,7 +224,7 @@ cfg_if! {
                 &self,
                 pool: &DbPool,
                 thread_id: &str,
-                tx: mpsc::Sender<Result<Event, Infallible>>,
+                tx: mpsc::Sender<CompletionEvent>,
                 cancel_token: CancellationToken,
             ) -> Result<(), anyhow::Error> {
                 debug!("Sending message to Anthropic API (cancellable)");
@@ -216,23 +244,22 @@ cfg_if! {
                     return Ok(());
                 }
         
-                let api_messages = history.into_iter()
-                    .map(|msg| serde_json::json!({
-                        "role": msg.role,
-                        "content": msg.content.unwrap_or_default(),
-                    }))
-                    .collect::<Vec<_>>();
+                let (system, api_messages) = anthropic_messages(history);
+                let mut body = serde_json::json!({
+                    "model": self.model,
+                    "messages": api_messages,
+                    "max_tokens": 1360,
+                    "stream": true,
+                });
+                if let Some(system) = system {
+                    body["system"] = Value::String(system);
+                }
         
                 let response = self.client.post("https://api.anthropic.com/v1/messages")
                     .header("x-api-key", self.api_key.to_string())
                     .header("anthropic-version", "2023-06-01")
                     .header("Content-Type", "application/json")
-                    .json(&serde_json::json!({
-                        "model": self.model,
-                        "messages": api_messages,
-                        "max_tokens": 1360,
-                        "stream": true,
-                    }))
+                    .json(&body)
                     .send()
                     .await
                     .map_err(|e| anyhow!("Failed to send message: {}", e))?;
@@ -243,7 +270,7 @@ cfg_if! {
                     // Check for cancellation in each iteration
                     if cancel_token.is_cancelled() {
                         info!("Anthropic message stream cancelled during processing");
-                        let _ = tx.send(Ok(Event::default().data("[CANCELLED]"))).await;
+                        let _ = tx.send(CompletionEvent::Cancelled).await;
                         return Ok(());
                     }
         
@@ -257,7 +284,7 @@ cfg_if! {
                             for line in event.trim().lines() {
                                 if line.trim() == "event: message_stop" {
                                     debug!("Received message_stop event");
-                                    tx.send(Ok(Event::default().data("[DONE]"))).await.ok();
+                                    tx.send(CompletionEvent::Done).await.ok();
                                     return Ok(());
                                 } else if line.trim().starts_with("data: ") {
                                     let json_str = &line.trim()[6..];
@@ -266,13 +293,13 @@ cfg_if! {
                                         if let Some(delta) = parsed["delta"].as_object() {
                                             if let Some(text) = delta.get("text").and_then(|t| t.as_str()) {
                                                 debug!("Extracted content: {}", text);
-                                                tx.send(Ok(Event::default().data(text))).await.ok();
+                                                tx.send(CompletionEvent::Delta(text.to_string())).await.ok();
                                             }
                                         }
                                     } else {
                                         if let Some(content) = extract_text_content(json_str) {
                                             debug!("Fallback extracted content: {}", content);
-                                            tx.send(Ok(Event::default().data(content))).await.ok();
+                                            tx.send(CompletionEvent::Delta(content.into())).await.ok();
                                         }
                                     }
                                 }
@@ -280,8 +307,7 @@ cfg_if! {
                         }
                         Err(e) => {
                             error!("Failed to process stream: {e}");
-                            let error_event = Event::default().data(format!("Error: Failed to process stream: {e}"));
-                            tx.send(Ok(error_event)).await.ok();
+                            tx.send(CompletionEvent::Error(format!("Failed to process stream: {e}"))).await.ok();
                             break;
                         }
                     }
@@ -341,7 +367,7 @@ cfg_if! {
                 pool: &DbPool,
                 thread_id: &str,
                 context: &str,
-                tx: mpsc::Sender<Result<Event, Infallible>>,
+                tx: mpsc::Sender<CompletionEvent>,
                 cancel_token: CancellationToken,
             ) -> Result<(), anyhow::Error> {
                 debug!("Sending message to OpenAI API with project context (cancellable)");
@@ -396,7 +422,7 @@ cfg_if! {
                     // Check for cancellation in each iteration
                     if cancel_token.is_cancelled() {
                         info!("OpenAI message stream cancelled during processing");
-                        let _ = tx.send(Ok(Event::default().data("[CANCELLED]"))).await;
+                        let _ = tx.send(CompletionEvent::Cancelled).await;
                         return Ok(());
                     }
         
@@ -410,7 +436,7 @@ cfg_if! {
                             for line in event.trim().lines() {
                                 if line.trim() == "data: [DONE]" {
                                     debug!("Received [DONE] event");
-                                    tx.send(Ok(Event::default().data("[DONE]"))).await.ok();
+                                    tx.send(CompletionEvent::Done).await.ok();
                                     return Ok(());
                                 } else if line.trim().starts_with("data: ") {
                                     let json_str = &line.trim()[6..];
@@ -421,7 +447,7 @@ cfg_if! {
                                                 if let Some(delta) = first_choice["delta"].as_object() {
                                                     if let Some(content) = delta.get("content").and_then(|c| c.as_str()) {
                                                         debug!("Extracted content: {}", content);
-                                                        tx.send(Ok(Event::default().data(content))).await.ok();
+                                                        tx.send(CompletionEvent::Delta(content.into())).await.ok();
                                                     }
                                                 }
                                             }
@@ -429,7 +455,7 @@ cfg_if! {
                                     } else {
                                         if let Some(content) = extract_content_with_escaping(json_str) {
                                             debug!("Fallback extracted content: {}", content);
-                                            tx.send(Ok(Event::default().data(content))).await.ok();
+                                            tx.send(CompletionEvent::Delta(content.into())).await.ok();
                                         }
                                     }
                                 }
@@ -437,8 +463,7 @@ cfg_if! {
                         }
                         Err(e) => {
                             error!("Failed to process stream: {e}");
-                            let error_event = Event::default().data(format!("Error: Failed to process stream: {e}"));
-                            tx.send(Ok(error_event)).await.ok();
+                            tx.send(CompletionEvent::Error(format!("Failed to process stream: {e}"))).await.ok();
                             break;
                         }
                     }
@@ -452,7 +477,7 @@ cfg_if! {
                 &self,
                 pool: &DbPool,
                 thread_id: &str,
-                tx: mpsc::Sender<Result<Event, Infallible>>,
+                tx: mpsc::Sender<CompletionEvent>,
                 cancel_token: CancellationToken,
             ) -> Result<(), anyhow::Error> {
                 debug!("Sending message to OpenAI API (cancellable)");
@@ -498,7 +523,7 @@ cfg_if! {
                     // Check for cancellation in each iteration
                     if cancel_token.is_cancelled() {
                         info!("OpenAI message stream cancelled during processing");
-                        let _ = tx.send(Ok(Event::default().data("[CANCELLED]"))).await;
+                        let _ = tx.send(CompletionEvent::Cancelled).await;
                         return Ok(());
                     }
         
@@ -512,7 +537,7 @@ cfg_if! {
                             for line in event.trim().lines() {
                                 if line.trim() == "data: [DONE]" {
                                     debug!("Received [DONE] event");
-                                    tx.send(Ok(Event::default().data("[DONE]"))).await.ok();
+                                    tx.send(CompletionEvent::Done).await.ok();
                                     return Ok(());
                                 } else if line.trim().starts_with("data: ") {
                                     let json_str = &line.trim()[6..];
@@ -523,7 +548,7 @@ cfg_if! {
                                                 if let Some(delta) = first_choice["delta"].as_object() {
                                                     if let Some(content) = delta.get("content").and_then(|c| c.as_str()) {
                                                         debug!("Extracted content: {}", content);
-                                                        tx.send(Ok(Event::default().data(content))).await.ok();
+                                                        tx.send(CompletionEvent::Delta(content.into())).await.ok();
                                                     }
                                                 }
                                             }
@@ -531,7 +556,7 @@ cfg_if! {
                                     } else {
                                         if let Some(content) = extract_content_with_escaping(json_str) {
                                             debug!("Fallback extracted content: {}", content);
-                                            tx.send(Ok(Event::default().data(content))).await.ok();
+                                            tx.send(CompletionEvent::Delta(content.into())).await.ok();
                                         }
                                     }
                                 }
@@ -539,8 +564,7 @@ cfg_if! {
                         }
                         Err(e) => {
                             error!("Failed to process stream: {e}");
-                            let error_event = Event::default().data(format!("Error: Failed to process stream: {e}"));
-                            tx.send(Ok(error_event)).await.ok();
+                            tx.send(CompletionEvent::Error(format!("Failed to process stream: {e}"))).await.ok();
                             break;
                         }
                     }
@@ -556,7 +580,7 @@ cfg_if! {
                 pool: &DbPool,
                 thread_id: &str,
                 context: &str,
-                tx: mpsc::Sender<Result<Event, std::convert::Infallible>>
+                tx: mpsc::Sender<CompletionEvent>
             ) -> Result<(), anyhow::Error> {
                 debug!("Sending message to OpenAI API with project context");
                 debug!("Current thread id: {thread_id}");
@@ -605,7 +629,7 @@ cfg_if! {
                             for line in event.trim().lines() {
                                 if line.trim() == "data: [DONE]" {
                                     debug!("Received [DONE] event");
-                                    tx.send(Ok(Event::default().data("[DONE]"))).await.ok();
+                                    tx.send(CompletionEvent::Done).await.ok();
                                     return Ok(());
                                 } else if line.trim().starts_with("data: ") {
                                     let json_str = &line.trim()[6..];
@@ -616,7 +640,7 @@ cfg_if! {
                                                 if let Some(delta) = first_choice["delta"].as_object() {
                                                     if let Some(content) = delta.get("content").and_then(|c| c.as_str()) {
                                                         debug!("Extracted content: {}", content);
-                                                        tx.send(Ok(Event::default().data(content))).await.ok();
+                                                        tx.send(CompletionEvent::Delta(content.into())).await.ok();
                                                     }
                                                 }
                                             }
@@ -624,7 +648,7 @@ cfg_if! {
                                     } else {
                                         if let Some(content) = extract_content_with_escaping(json_str) {
                                             debug!("Fallback extracted content: {}", content);
-                                            tx.send(Ok(Event::default().data(content))).await.ok();
+                                            tx.send(CompletionEvent::Delta(content.into())).await.ok();
                                         }
                                     }
                                 }
@@ -632,8 +656,7 @@ cfg_if! {
                         }
                         Err(e) => {
                             error!("Failed to process stream: {e}");
-                            let error_event = Event::default().data(format!("Error: Failed to process stream: {e}"));
-                            tx.send(Ok(error_event)).await.ok();
+                            tx.send(CompletionEvent::Error(format!("Failed to process stream: {e}"))).await.ok();
                             break;
                         }
                     }
@@ -647,7 +670,7 @@ cfg_if! {
                 &self,
                 pool: &DbPool,
                 thread_id: &str,
-                tx: mpsc::Sender<Result<Event, std::convert::Infallible>>
+                tx: mpsc::Sender<CompletionEvent>
             ) -> Result<(), anyhow::Error> {
                 debug!("Sending message to OpenAI API");
                 debug!("Current thread id: {thread_id}");
@@ -686,7 +709,7 @@ cfg_if! {
                             for line in event.trim().lines() {
                                 if line.trim() == "data: [DONE]" {
                                     debug!("Received [DONE] event");
-                                    tx.send(Ok(Event::default().data("[DONE]"))).await.ok();
+                                    tx.send(CompletionEvent::Done).await.ok();
                                     return Ok(());
                                 } else if line.trim().starts_with("data: ") {
                                     let json_str = &line.trim()[6..];
@@ -698,7 +721,7 @@ cfg_if! {
                                                 if let Some(delta) = first_choice["delta"].as_object() {
                                                     if let Some(content) = delta.get("content").and_then(|c| c.as_str()) {
                                                         debug!("Extracted content: {}", content);
-                                                        tx.send(Ok(Event::default().data(content))).await.ok();
+                                                        tx.send(CompletionEvent::Delta(content.into())).await.ok();
                                                     }
                                                 }
                                             }
@@ -706,7 +729,7 @@ cfg_if! {
                                     } else {
                                         if let Some(content) = extract_content_with_escaping(json_str) {
                                             debug!("Fallback extracted content: {}", content);
-                                            tx.send(Ok(Event::default().data(content))).await.ok();
+                                            tx.send(CompletionEvent::Delta(content.into())).await.ok();
                                         }
                                     }
                                 }
@@ -714,8 +737,7 @@ cfg_if! {
                         }
                         Err(e) => {
                             error!("Failed to process stream: {e}");
-                            let error_event = Event::default().data(format!("Error: Failed to process stream: {e}"));
-                            tx.send(Ok(error_event)).await.ok();
+                            tx.send(CompletionEvent::Error(format!("Failed to process stream: {e}"))).await.ok();
                             break;
                         }
                     }
@@ -776,7 +798,7 @@ cfg_if! {
             thread_id: String,
             model: String,
             active_lab: String,
-            tx: mpsc::Sender<Result<Event, Infallible>>,
+            tx: mpsc::Sender<CompletionEvent>,
             cancel_token: CancellationToken,
         ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
             use log::{info, error};
@@ -900,6 +922,36 @@ cfg_if! {
                 _ => Err(anyhow::anyhow!("unsupported lab: {}", decoded_lab)),
             }.map_err(|e| e.into())
         }
+
+        #[derive(diesel::QueryableByName)]
+        struct MessageCount {
+            #[diesel(sql_type = diesel::sql_types::Integer)]
+            message_count: i32,
+        }
+
+        /// Counts a user message against the daily limit; false once it is exceeded
+        pub async fn check_increment_rate_limit(
+            user_id: i32,
+            conn: &mut diesel_async::AsyncPgConnection,
+        ) -> Result<bool, diesel::result::Error> {
+            use diesel_async::RunQueryDsl;
+
+            // upsert daily counter
+            let query = "INSERT INTO daily_usage (user_id, usage_date, message_count)
+                VALUES ($1, CURRENT_DATE, 1)
+                ON CONFLICT (user_id, usage_date)
+                DO UPDATE SET
+                  message_count = daily_usage.message_count + 1,
+                  updated_at = CURRENT_TIMESTAMP
+                RETURNING message_count";
+
+            let result: MessageCount = diesel::sql_query(query)
+                .bind::<diesel::sql_types::Integer, _>(user_id)
+                .get_result(conn)
+                .await?;
+
+            Ok(result.message_count <= 40)
+        }
     }
 }
 
@@ -913,6 +965,7 @@ pub fn Chat(
     let (message, set_message) = signal(String::new());
     let (is_sending, set_is_sending) = signal(false);
     let (current_stream_id, set_current_stream_id) = signal::<Option<String>>(None);
+    let typing = crate::components::collab::TypingNotifier::new(thread_id);
     
     let (model, set_model) = signal("gpt-4o-mini".to_string());
     let (lab, set_lab) = signal("openai".to_string());
@@ -932,13 +985,7 @@ pub fn Chat(
     let handle_model_change = move |ev| {
         let value = event_target_value(&ev);
         set_model(value.clone());
-    
-        let new_lab = if value.contains("claude") {
-            "anthropic"
-        } else {
-            "openai"
-        };
-        set_lab(new_lab.to_string());
+        set_lab(lab_for_model(&value).to_string());
     };
 
     let send_message = move || {
@@ -947,6 +994,7 @@ pub fn Chat(
         let selected_model = model.get_untracked();
         let active_lab = lab.get_untracked();
     
+        typing.stop();
         spawn_local(async move {
             set_is_sending(true);
     
@@ -972,9 +1020,11 @@ pub fn Chat(
                 active_model: selected_model.clone(),
                 active_lab: active_lab.clone(),
                 user_id,
+                citations: Vec::new(),
+                retrieval_trace_id: None,
             };
     
-            match create_message(user_message_view, false).await {
+            match create_message(user_message_view).await {
                 Ok(_) => {
                     set_message.set(String::new());
     
@@ -1062,7 +1112,6 @@ pub fn Chat(
     
                     // 5. Set up SSE stream to collect content using the stream_id
                     let mut accumulated_content = String::new();
-                    let mut current_citations: Vec<DocumentCitation> = Vec::new();
                     
                     let thread_id_value = thread_id.get_untracked().to_string();
                     let active_model_value = model.get_untracked().to_string();
@@ -1091,26 +1140,10 @@ pub fn Chat(
                                     set_is_sending(false);
                                     set_current_stream_id(None);
     
-                                    // 6. Save final content to DB and remove from pending
-                                    let final_content = accumulated_content.clone();
-                                    let is_llm = true;
-                                    let assistant_message_view = NewMessageView {
-                                        thread_id: thread_id.get_untracked().clone(),
-                                        content: Some(final_content),
-                                        role: "assistant".to_string(),
-                                        active_model: model.get_untracked().clone(),
-                                        active_lab: lab.get_untracked().clone(),
-                                        user_id,
-                                    };
-    
-                                    spawn_local(async move {
-                                        if let Err(e) = create_message(assistant_message_view, is_llm).await {
-                                            error!("Failed to create LLM message: {e:?}");
-                                        } 
-                                        if let Some(callback) = on_message_created_clone {
-                                            callback.run(());
-                                        }
-                                    });
+                                    // 6. The server stored the answer; load it in place of the pending one
+                                    if let Some(callback) = on_message_created_clone {
+                                        callback.run(());
+                                    }
     
                                     // Remove from pending messages
                                     if let Some(set_pending) = pending_messages {
@@ -1151,12 +1184,11 @@ pub fn Chat(
                                             }
                                             "citations" => {
                                                 if let Some(citations) = rag_response.citations {
-                                                    current_citations = citations;
                                                     // Optionally update the pending message to show citations received
                                                     if let Some(set_pending) = pending_messages {
                                                         set_pending.update(|msgs| {
                                                             if let Some(msg) = msgs.iter_mut().find(|m| m.id == pending_id_clone) {
-                                                                msg.content = format!("📄 Found {} relevant documents...", current_citations.len());
+                                                                msg.content = format!("📄 Found {} relevant documents...", citations.len());
                                                             }
                                                         });
                                                     }
@@ -1200,39 +1232,12 @@ pub fn Chat(
                                                 set_is_sending(false);
                                                 set_current_stream_id(None);
     
-                                                // Create final content with citations if available
-                                                let mut final_content = accumulated_content.clone();
-                                                if !current_citations.is_empty() {
-                                                    final_content.push_str("\n\n**Sources:**\n");
-                                                    for citation in &current_citations {
-                                                        final_content.push_str(&format!(
-                                                            "- **{}** (similarity: {:.2})\n",
-                                                            citation.filename,
-                                                            citation.similarity
-                                                        ));
-                                                    }
+                                                // The server stored the answer with its citations;
+                                                // load it in place of the pending one
+                                                if let Some(callback) = on_message_created_clone {
+                                                    callback.run(());
                                                 }
     
-                                                // Save final content to DB and remove from pending
-                                                let is_llm = true;
-                                                let assistant_message_view = NewMessageView {
-                                                    thread_id: thread_id.get_untracked().clone(),
-                                                    content: Some(final_content),
-                                                    role: "assistant".to_string(),
-                                                    active_model: model.get_untracked().clone(),
-                                                    active_lab: lab.get_untracked().clone(),
-                                                    user_id,
-                                                };
-    
-                                                spawn_local(async move {
-                                                    if let Err(e) = create_message(assistant_message_view, is_llm).await {
-                                                        error!("Failed to create LLM message: {e:?}");
-                                                    } 
-                                                    if let Some(callback) = on_message_created_clone {
-                                                        callback.run(());
-                                                    }
-                                                });
-    
                                                 // Remove from pending messages
                                                 if let Some(set_pending) = pending_messages {
                                                     set_pending.update(|msgs| {
@@ -1358,6 +1363,7 @@ pub fn Chat(
                         prop:value=message
                         on:input=move |event| {
                             set_message(event_target_value(&event));
+                            typing.typing();
                             let target = event.target().unwrap();
                             let style = target.unchecked_ref::<HtmlElement>().style();
                             style.set_property("height", "auto").unwrap();
@@ -1415,13 +1421,9 @@ pub fn Chat(
                         on:change=handle_model_change
                         prop:value=move || model.get()
                     >
-                        <option value="claude-3-haiku-20240307">"claude-3-haiku"</option>
-                        <option value="claude-3-sonnet-20240229">"claude-3-sonnet"</option>
-                        <option value="claude-3-opus-20240229">"claude-3-opus"</option>
-                        <option value="claude-3-5-sonnet-20240620">"claude-3-5-sonnet"</option>
-                        <option value="gpt-4o-mini">"gpt-4o-mini"</option>
-                        <option value="gpt-4o">"gpt-4o"</option>
-                        <option value="gpt-4-turbo">"gpt-4-turbo"</option>
+                        {CHAT_MODELS.into_iter().map(|(id, label)| view! {
+                            <option value=id>{label}</option>
+                        }).collect_view()}
                     </select>
 
                     <div class="flex-1 text-center">
@@ -1447,10 +1449,11 @@ pub fn Chat(
     endpoint = "new-message",
     input = PostUrl, 
 )]
-pub async fn create_message(new_message_view: NewMessageView, is_llm: bool) -> Result<(), ServerFnError> {
+/// Posts the signed-in user's message; assistant answers are stored by the
+/// completion run that produced them
+pub async fn create_message(new_message_view: NewMessageView) -> Result<(), ServerFnError> {
     use diesel::prelude::*;
-    use diesel_async::{AsyncPgConnection, AsyncConnection, RunQueryDsl};
-    use diesel::sql_types::Integer;
+    use diesel_async::{AsyncConnection, RunQueryDsl};
     use std::fmt;
 
     use crate::state::AppState;
@@ -1458,18 +1461,13 @@ pub async fn create_message(new_message_view: NewMessageView, is_llm: bool) -> R
     use crate::schema::{messages, threads};
     use crate::auth::get_current_user;
 
-    #[derive(QueryableByName)]
-    struct MessageCount {
-        #[diesel(sql_type = Integer)]
-        message_count: i32,
-    }
-
     #[derive(Debug)]
     enum CreateMessageError {
         PoolError(String),
         DatabaseError(diesel::result::Error),
         Unauthorized,
         RateLimitExceeded,
+        Forbidden(String),
     }
 
     impl fmt::Display for CreateMessageError {
@@ -1479,6 +1477,7 @@ pub async fn create_message(new_message_view: NewMessageView, is_llm: bool) -> R
                 CreateMessageError::DatabaseError(e) => write!(f, "Database error: {e}"),
                 CreateMessageError::Unauthorized => write!(f, "unauthorized - user not logged in"),
                 CreateMessageError::RateLimitExceeded => write!(f, "Daily message limit of 20 reached. Try again tomorrow!"),
+                CreateMessageError::Forbidden(e) => write!(f, "{e}"),
             }
         }
     }
@@ -1498,39 +1497,39 @@ pub async fn create_message(new_message_view: NewMessageView, is_llm: bool) -> R
         .map_err(|e| CreateMessageError::PoolError(e.to_string()))?;
 
     let current_user = get_current_user().await.map_err(|_| CreateMessageError::Unauthorized)?;
-    let user_id = current_user.ok_or(CreateMessageError::Unauthorized)?.id;
-
-    let new_message: NewMessage = new_message_view.clone().into();
-
-    // check if the user hit rate limit
-    async fn check_increment_rate_limit(user_id: i32, conn: &mut AsyncPgConnection) -> Result<bool, diesel::result::Error> {
-        use diesel_async::RunQueryDsl;
-
-        // upsert daily counter
-        let query = "INSERT INTO daily_usage (user_id, usage_date, message_count)
-            VALUES ($1, CURRENT_DATE, 1)
-            ON CONFLICT (user_id, usage_date)
-            DO UPDATE SET
-              message_count = daily_usage.message_count + 1,
-              updated_at = CURRENT_TIMESTAMP
-            RETURNING message_count";
-
-        let result: MessageCount = diesel::sql_query(query)
-            .bind::<diesel::sql_types::Integer, _>(user_id)
-            .get_result(conn)
-            .await?;
+    let current_user = current_user.ok_or(CreateMessageError::Unauthorized)?;
+    let user_id = current_user.id;
 
-        Ok(result.message_count <= 40)
+    if new_message_view.role != "user" {
+        return Err(CreateMessageError::Forbidden("Only user messages can be posted".to_string()).into());
     }
+    let mut new_message: NewMessage = new_message_view.clone().into();
+    // messages are always the signed-in user's, whatever the client claims
+    new_message.user_id = Some(user_id);
+    // citations and traces belong to answers and come from the retrieval that produced them
+    new_message.citations = None;
+    new_message.retrieval_trace_id = None;
 
-    if !is_llm {
-        let rate_limit_ok = check_increment_rate_limit(user_id, &mut conn)
+    // a new thread is created below; an existing one must be the user's or collaborative
+    let thread_exists = threads::table
+        .find(&new_message.thread_id)
+        .count()
+        .get_result::<i64>(&mut conn)
+        .await
+        .map_err(CreateMessageError::DatabaseError)?
+        > 0;
+    if thread_exists {
+        crate::services::workspaces::writable_thread(&mut conn, user_id, &new_message.thread_id)
             .await
-            .map_err(CreateMessageError::DatabaseError)?;
-        
-        if !rate_limit_ok {
-            return Err(CreateMessageError::RateLimitExceeded.into());
-        }
+            .map_err(|e| CreateMessageError::Forbidden(e.to_string()))?;
+    }
+
+    let rate_limit_ok = check_increment_rate_limit(user_id, &mut conn)
+        .await
+        .map_err(CreateMessageError::DatabaseError)?;
+    
+    if !rate_limit_ok {
+        return Err(CreateMessageError::RateLimitExceeded.into());
     }
 
     // Use async transaction
@@ -1538,39 +1537,38 @@ pub async fn create_message(new_message_view: NewMessageView, is_llm: bool) -> R
         Box::pin(async move {
             let mut thread_was_created = false;
             
-            if !is_llm {
-                let thread_id = &new_message.thread_id;
+            let thread_id = &new_message.thread_id;
+            
+            // Check if thread exists - explicitly use async version
+            let thread_exists = diesel_async::RunQueryDsl::first::<Thread>(
+                threads::table.find(thread_id),
+                conn
+            )
+            .await
+            .optional()?
+            .is_some();
+
+            if !thread_exists {
+                let new_thread = Thread {
+                    id: thread_id.clone(),
+                    created_at: None,
+                    updated_at: None,
+                    user_id: Some(user_id),
+                    parent_thread_id: None,
+                    branch_point_message_id: None,
+                    branch_name: None,
+                    title: None,
+                    project_id: None,
+                    visibility: crate::models::workspaces::THREAD_PRIVATE.to_string(),
+                };
                 
-                // Check if thread exists - explicitly use async version
-                let thread_exists = diesel_async::RunQueryDsl::first::<Thread>(
-                    threads::table.find(thread_id),
+                diesel_async::RunQueryDsl::execute(
+                    diesel::insert_into(threads::table).values(&new_thread),
                     conn
                 )
-                .await
-                .optional()?
-                .is_some();
-
-                if !thread_exists {
-                    let new_thread = Thread {
-                        id: thread_id.clone(),
-                        created_at: None,
-                        updated_at: None,
-                        user_id: Some(user_id),
-                        parent_thread_id: None,
-                        branch_point_message_id: None,
-                        branch_name: None,
-                        title: None,
-                        project_id: None,
-                    };
-                    
-                    diesel_async::RunQueryDsl::execute(
-                        diesel::insert_into(threads::table).values(&new_thread),
-                        conn
-                    )
-                    .await?;
-                    
-                    thread_was_created = true;
-                }
+                .await?;
+                
+                thread_was_created = true;
             }
 
             diesel_async::RunQueryDsl::execute(
@@ -1579,12 +1577,10 @@ pub async fn create_message(new_message_view: NewMessageView, is_llm: bool) -> R
             )
             .await?;
 
-            if !is_llm {
-                log::debug!("Message successfully inserted into the database: {new_message:?}");
-            }
+            log::debug!("Message successfully inserted into the database: {new_message:?}");
 
             // Calculate is_first_user_message AFTER thread creation and message insertion
-            let is_first_message = if !is_llm && new_message.role == "user" {
+            let is_first_message = if new_message.role == "user" {
                 if thread_was_created {
                     // If we just created the thread, this is definitely the first user message
                     true
@@ -1631,6 +1627,12 @@ pub async fn create_message(new_message_view: NewMessageView, is_llm: bool) -> R
     .await
     .map_err(CreateMessageError::DatabaseError)?;
 
+    app_state.thread_rooms.message_posted(&new_message_view.thread_id, &crate::models::collab::MessagePosted {
+        user_id,
+        name: current_user.display_name.or(current_user.username).unwrap_or_else(|| "Anonymous".to_string()),
+        role: new_message_view.role.clone(),
+    });
+
     if is_first_user_message {
         if let Some(content) = new_message_view.content {
             let app_state_clone = app_state.clone();
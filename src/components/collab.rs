@@ -0,0 +1,252 @@
+use cfg_if::cfg_if;
+use leptos::{prelude::*, task::spawn_local};
+
+use crate::models::collab::PresenceView;
+use crate::server_fn::collab::set_typing;
+
+/// Typing notices are re-sent at most this often while someone keeps typing
+const TYPING_RESEND_MS: f64 = 3000.0;
+/// Typing is cleared after this long without a keystroke
+const TYPING_IDLE_MS: u64 = 4000;
+
+/// Throttled typing notices from the chat box
+#[derive(Clone, Copy)]
+pub struct TypingNotifier {
+    thread_id: ReadSignal<String>,
+    last_sent: StoredValue<f64>,
+    keystrokes: StoredValue<u32>,
+}
+
+impl TypingNotifier {
+    pub fn new(thread_id: ReadSignal<String>) -> Self {
+        Self {
+            thread_id,
+            last_sent: StoredValue::new(0.0),
+            keystrokes: StoredValue::new(0),
+        }
+    }
+
+    /// Call on each keystroke
+    pub fn typing(self) {
+        self.keystrokes.update_value(|n| *n = n.wrapping_add(1));
+        let keystroke = self.keystrokes.get_value();
+        set_timeout(
+            move || {
+                if self.keystrokes.get_value() == keystroke {
+                    self.stop();
+                }
+            },
+            std::time::Duration::from_millis(TYPING_IDLE_MS),
+        );
+
+        let now = js_sys::Date::now();
+        if now - self.last_sent.get_value() < TYPING_RESEND_MS {
+            return;
+        }
+        self.last_sent.set_value(now);
+        let thread_id = self.thread_id.get_untracked();
+        // fails for threads that don't exist yet, which nobody else can be watching
+        spawn_local(async move {
+            let _ = set_typing(thread_id, true).await;
+        });
+    }
+
+    /// Call when the message is sent or the draft abandoned
+    pub fn stop(self) {
+        if self.last_sent.get_value() == 0.0 {
+            return;
+        }
+        self.last_sent.set_value(0.0);
+        let thread_id = self.thread_id.get_untracked();
+        spawn_local(async move {
+            let _ = set_typing(thread_id, false).await;
+        });
+    }
+}
+
+fn presence_text(people: &[PresenceView]) -> String {
+    let names: Vec<String> = people
+        .iter()
+        .map(|person| if person.typing { format!("{} (typing…)", person.name) } else { person.name.clone() })
+        .collect();
+    format!("Also here: {}", names.join(", "))
+}
+
+/// Keeps the open thread live: who else is here, their new messages, and their
+/// assistant answers streaming in. Mirrored answers show as pending messages.
+#[component]
+pub fn ThreadRoom(
+    thread_id: ReadSignal<String>,
+    /// Bumped when messages change; retries a connection that failed
+    refetch_trigger: Option<ReadSignal<i32>>,
+) -> impl IntoView {
+    let (people, set_people) = signal(Vec::<PresenceView>::new());
+    let current_user = use_context::<crate::auth::context::AuthContext>().map(|auth| auth.current_user);
+    let my_id = move || current_user.and_then(|user| user.get_untracked()).map(|user| user.id);
+
+    cfg_if! {
+        if #[cfg(feature = "hydrate")] {
+            use std::collections::HashSet;
+            use chrono::Utc;
+            use wasm_bindgen::{prelude::*, JsCast};
+            use web_sys::{EventSource, MessageEvent};
+
+            use crate::models::collab::*;
+            use crate::models::conversations::PendingMessage;
+            use crate::pages::writersroom::ThreadContext;
+
+            let thread_setters = use_context::<ThreadContext>()
+                .map(|context| (context.set_message_refetch_trigger, context.set_pending_messages));
+            let source = StoredValue::new_local(None::<(String, EventSource)>);
+            // streams mirrored from others, by stream id
+            let mirrored = StoredValue::new_local(HashSet::<String>::new());
+
+            let listen = |source: &EventSource, name: &str, handler: Box<dyn FnMut(MessageEvent)>| {
+                let callback = Closure::wrap(handler);
+                let _ = source.add_event_listener_with_callback(name, callback.as_ref().unchecked_ref());
+                callback.forget();
+            };
+
+            Effect::new(move |_| {
+                let thread_id = thread_id.get();
+                if let Some(trigger) = refetch_trigger {
+                    trigger.track();
+                }
+                // a thread that didn't exist yet is retried once it has messages
+                let connected = source.with_value(|current| {
+                    current.as_ref().is_some_and(|(id, es)| *id == thread_id && es.ready_state() != EventSource::CLOSED)
+                });
+                if connected {
+                    return;
+                }
+                if let Some((_, old)) = source.get_value() {
+                    old.close();
+                }
+                set_people.set(Vec::new());
+                mirrored.update_value(HashSet::clear);
+
+                let Ok(es) = EventSource::new(&format!("/api/threads/{}/events", urlencoding::encode(&thread_id))) else {
+                    return;
+                };
+
+                listen(&es, EVENT_PRESENCE, Box::new(move |event: MessageEvent| {
+                    if let Some(list) = event.data().as_string().and_then(|data| serde_json::from_str::<Vec<PresenceView>>(&data).ok()) {
+                        set_people.set(list);
+                    }
+                }));
+
+                listen(&es, EVENT_MESSAGE_POSTED, Box::new(move |event: MessageEvent| {
+                    let Some(posted) = event.data().as_string().and_then(|data| serde_json::from_str::<MessagePosted>(&data).ok()) else {
+                        return;
+                    };
+                    if Some(posted.user_id) == my_id() {
+                        return;
+                    }
+                    if let Some((set_refetch, set_pending)) = thread_setters {
+                        if posted.role == "assistant" {
+                            // the saved answer replaces the finished mirror
+                            let finished: HashSet<String> = mirrored.get_value();
+                            set_pending.update(|msgs| msgs.retain(|m| m.is_streaming || !finished.contains(&m.id)));
+                        }
+                        set_refetch.update(|n| *n += 1);
+                    }
+                }));
+
+                let stream_thread_id = thread_id.clone();
+                listen(&es, EVENT_STREAM_STARTED, Box::new(move |event: MessageEvent| {
+                    let Some(started) = event.data().as_string().and_then(|data| serde_json::from_str::<StreamStarted>(&data).ok()) else {
+                        return;
+                    };
+                    if Some(started.user_id) == my_id() {
+                        return;
+                    }
+                    mirrored.update_value(|ids| { ids.insert(started.stream_id.clone()); });
+                    if let Some((_, set_pending)) = thread_setters {
+                        set_pending.update(|msgs| msgs.push(PendingMessage {
+                            id: started.stream_id,
+                            thread_id: stream_thread_id.clone(),
+                            content: format!("{} asked; answering…", started.name),
+                            role: "assistant".to_string(),
+                            active_model: started.model,
+                            active_lab: started.lab,
+                            is_streaming: true,
+                            created_at: Utc::now(),
+                        }));
+                    }
+                }));
+
+                let mut texts = std::collections::HashMap::<String, String>::new();
+                listen(&es, EVENT_STREAM, Box::new(move |event: MessageEvent| {
+                    let stream_id = event.last_event_id();
+                    let Some(data) = event.data().as_string() else {
+                        return;
+                    };
+                    if !mirrored.with_value(|ids| ids.contains(&stream_id)) {
+                        return;
+                    }
+                    let Some((_, set_pending)) = thread_setters else {
+                        return;
+                    };
+                    let update = |change: &dyn Fn(&mut PendingMessage)| {
+                        set_pending.update(|msgs| {
+                            if let Some(msg) = msgs.iter_mut().find(|m| m.id == stream_id) {
+                                change(msg);
+                            }
+                        });
+                    };
+                    match parse_stream_chunk(&data) {
+                        StreamChunk::Text(text) => {
+                            let content = texts.entry(stream_id.clone()).or_default();
+                            content.push_str(&text);
+                            let content = content.clone();
+                            update(&|msg| msg.content = content.clone());
+                        }
+                        StreamChunk::Status(status) => update(&|msg| msg.content = format!("🔍 {status}")),
+                        StreamChunk::Finished => {
+                            texts.remove(&stream_id);
+                            update(&|msg| msg.is_streaming = false);
+                        }
+                        StreamChunk::Failed(error) => {
+                            texts.remove(&stream_id);
+                            mirrored.update_value(|ids| { ids.remove(&stream_id); });
+                            set_pending.update(|msgs| msgs.retain(|m| m.id != stream_id));
+                            log::error!("Mirrored answer failed: {error}");
+                        }
+                        StreamChunk::Ignored => {}
+                    }
+                }));
+
+                let on_error = Closure::wrap(Box::new({
+                    let es = es.clone();
+                    // e.g. the thread isn't saved yet; the next message retries
+                    move |_: web_sys::Event| es.close()
+                }) as Box<dyn FnMut(_)>);
+                es.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+                on_error.forget();
+
+                source.set_value(Some((thread_id, es)));
+            });
+
+            on_cleanup(move || {
+                if let Some((_, es)) = source.get_value() {
+                    es.close();
+                }
+            });
+        } else {
+            let _ = (thread_id, refetch_trigger, set_people);
+        }
+    }
+
+    view! {
+        {move || {
+            let others: Vec<PresenceView> = people
+                .get()
+                .into_iter()
+                .filter(|person| Some(person.user_id) != my_id())
+                .collect();
+            (!others.is_empty()).then(|| view! {
+                <div class="text-xs text-themed-secondary mt-2">{presence_text(&others)}</div>
+            })
+        }}
+    }
+}
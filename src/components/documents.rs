@@ -0,0 +1,262 @@
+use leptos::prelude::*;
+use leptos_icons::Icon;
+use uuid::Uuid;
+
+use crate::models::projects::*;
+use crate::server_fn::projects::*;
+use crate::components::ui::{Button, IconButton, ButtonVariant, ButtonSize};
+
+/// Splits `text` around a character range; chunk offsets count chars, not bytes
+fn split_at_chars(text: &str, start: usize, end: usize) -> (String, String, String) {
+    let byte = |chars: usize| text.char_indices().nth(chars).map(|(i, _)| i).unwrap_or(text.len());
+    let (start, end) = (byte(start), byte(end.max(start)));
+    (text[..start].to_string(), text[start..end].to_string(), text[end..].to_string())
+}
+
+/// Full text of a document, optionally at an earlier version, with a cited chunk
+/// highlighted and scrolled into view
+#[component]
+pub fn DocumentViewer(
+    document_id: Uuid,
+    #[prop(default = None)] version: Option<i32>,
+    /// Character range of the chunk to highlight, in the given version
+    #[prop(default = None)] highlight: Option<(usize, usize)>,
+    /// Slide in from the right next to the chat instead of covering the page
+    #[prop(optional)] panel: bool,
+    #[prop(into)] on_close: Callback<()>,
+) -> impl IntoView {
+    let (backdrop_class, card_class) = if panel {
+        ("fixed inset-y-0 right-0 w-full max-w-xl z-50 shadow-xl", "card-themed p-6 h-full flex flex-col rounded-none")
+    } else {
+        ("fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center z-50", "card-themed p-6 w-full max-w-4xl max-h-[90vh] flex flex-col")
+    };
+
+    let (selected_version, set_selected_version) = signal(version);
+
+    let content_resource = Resource::new(
+        move || (document_id, selected_version.get()),
+        |(document_id, version)| async move {
+            get_document_content(document_id, version).await.map_err(|e| e.to_string())
+        }
+    );
+    let versions_resource = Resource::new(
+        move || document_id,
+        |document_id| async move {
+            get_document_versions(document_id).await.map_err(|e| e.to_string())
+        }
+    );
+
+    let mark_ref = NodeRef::<leptos::html::Mark>::new();
+    Effect::new(move |_| {
+        if let Some(mark) = mark_ref.get() {
+            mark.scroll_into_view();
+        }
+    });
+
+    view! {
+        <div class=backdrop_class>
+            <div class=card_class>
+                <div class="flex justify-between items-center mb-4 gap-4">
+                    <h3 class="text-lg font-semibold text-themed-primary truncate">
+                        {move || {
+                            content_resource.get().and_then(Result::ok).map(|doc| doc.filename).unwrap_or_default()
+                        }}
+                    </h3>
+                    <div class="flex items-center gap-2">
+                        <Transition fallback=|| ()>
+                            {move || {
+                                versions_resource.get().and_then(Result::ok).filter(|versions| versions.len() > 1).map(|versions| view! {
+                                    <select
+                                        class="input-themed text-sm"
+                                        on:change=move |ev| {
+                                            set_selected_version.set(event_target_value(&ev).parse().ok());
+                                        }
+                                    >
+                                        {versions.into_iter().enumerate().map(|(index, v)| {
+                                            let label = if index == 0 { format!("v{} (latest)", v.version) } else { format!("v{}", v.version) };
+                                            let value = if index == 0 { String::new() } else { v.version.to_string() };
+                                            let selected = move || match selected_version.get() {
+                                                Some(selected) => selected == v.version,
+                                                None => index == 0,
+                                            };
+                                            view! { <option value=value selected=selected>{label}</option> }
+                                        }).collect_view()}
+                                    </select>
+                                })
+                            }}
+                        </Transition>
+                        <IconButton
+                            variant=ButtonVariant::Ghost
+                            size=ButtonSize::Small
+                            class="text-themed-secondary hover:text-themed-primary"
+                            on_click=Callback::new(move |_| on_close.run(()))
+                        >
+                            "✕"
+                        </IconButton>
+                    </div>
+                </div>
+
+                <div class="overflow-y-auto surface-secondary rounded p-4">
+                    <Transition fallback=|| view! { <div class="loading-themed">"Loading document..."</div> }>
+                        {move || match content_resource.get() {
+                            Some(Ok(doc)) => {
+                                let older = (doc.version != doc.latest_version).then(|| view! {
+                                    <p class="text-xs text-themed-secondary mb-2">
+                                        {format!("Showing version {} of {}", doc.version, doc.latest_version)}
+                                    </p>
+                                });
+                                // offsets belong to the version the citation was made against
+                                let range = highlight.filter(|_| selected_version.get_untracked() == version);
+                                let body = match range {
+                                    Some((start, end)) => {
+                                        let (before, cited, after) = split_at_chars(&doc.content, start, end);
+                                        view! {
+                                            <pre class="whitespace-pre-wrap text-sm text-themed-primary font-mono">
+                                                {before}
+                                                <mark node_ref=mark_ref class="bg-yellow-200 dark:bg-yellow-700 rounded">{cited}</mark>
+                                                {after}
+                                            </pre>
+                                        }.into_any()
+                                    }
+                                    None => view! {
+                                        <pre class="whitespace-pre-wrap text-sm text-themed-primary font-mono">{doc.content}</pre>
+                                    }.into_any(),
+                                };
+                                view! { {older} {body} }.into_any()
+                            }
+                            Some(Err(e)) => view! {
+                                <div class="error-themed text-center py-4">"Error loading document: " {e}</div>
+                            }.into_any(),
+                            None => view! { <div></div> }.into_any(),
+                        }}
+                    </Transition>
+                </div>
+            </div>
+        </div>
+    }
+}
+
+/// View, rename / move and delete buttons for one document in the project list
+#[component]
+pub fn DocumentActions(
+    document: ProjectDocumentView,
+    #[prop(into)] on_view: Callback<()>,
+    /// Called after the document was renamed or deleted
+    #[prop(into)] on_changed: Callback<()>,
+) -> impl IntoView {
+    let document_id = document.id;
+    let filename = document.filename.clone();
+    let (renaming, set_renaming) = signal(false);
+    let (new_path, set_new_path) = signal(document.filename.clone());
+    let (show_confirm, set_show_confirm) = signal(false);
+
+    let rename_action = Action::new(move |path: &String| {
+        let path = path.clone();
+        async move {
+            rename_document(document_id, path)
+                .await
+                .map(|_| {
+                    set_renaming.set(false);
+                    on_changed.run(());
+                })
+                .map_err(|e| format!("Failed to rename document: {}", e))
+        }
+    });
+
+    let delete_action = Action::new(move |_: &()| async move {
+        delete_document(document_id)
+            .await
+            .map(|_| on_changed.run(()))
+            .map_err(|e| format!("Failed to delete document: {}", e))
+    });
+
+    view! {
+        <div class="flex flex-col items-end">
+        <div class="flex items-center space-x-1">
+            <IconButton
+                variant=ButtonVariant::Ghost
+                size=ButtonSize::Small
+                class="text-themed-secondary hover:text-themed-primary"
+                on_click=Callback::new(move |_| on_view.run(()))
+            >
+                <Icon icon=icondata_bs::BsEye width="14" height="14"/>
+            </IconButton>
+            <IconButton
+                variant=ButtonVariant::Ghost
+                size=ButtonSize::Small
+                class="text-themed-secondary hover:text-themed-primary"
+                on_click=Callback::new(move |_| set_renaming.update(|open| *open = !*open))
+            >
+                <Icon icon=icondata_bs::BsPencil width="14" height="14"/>
+            </IconButton>
+            <IconButton
+                variant=ButtonVariant::Ghost
+                size=ButtonSize::Small
+                class="text-danger-500 hover:text-danger-600"
+                on_click=Callback::new(move |_| set_show_confirm.set(true))
+            >
+                <Icon icon=icondata_bs::BsTrash3 width="14" height="14"/>
+            </IconButton>
+        </div>
+
+        {move || renaming.get().then(|| view! {
+            <div class="w-full flex items-center gap-2 mt-2">
+                <input
+                    type="text"
+                    class="input-themed flex-1 text-sm font-mono"
+                    prop:value=new_path
+                    on:input=move |ev| set_new_path.set(event_target_value(&ev))
+                />
+                <Button
+                    variant=ButtonVariant::Primary
+                    size=ButtonSize::Small
+                    disabled=rename_action.pending().get()
+                    on_click=Callback::new(move |_| {
+                        rename_action.dispatch(new_path.get());
+                    })
+                >
+                    "Save"
+                </Button>
+            </div>
+        })}
+
+        {move || {
+            rename_action.value().get().and_then(Result::err)
+                .or_else(|| delete_action.value().get().and_then(Result::err))
+                .map(|error| view! { <p class="text-xs error-themed mt-1">{error}</p> })
+        }}
+
+        {move || {
+            let filename = filename.clone();
+            show_confirm.get().then(|| view! {
+                <div class="fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center z-50">
+                    <div class="card-themed p-6 w-full max-w-md">
+                        <h3 class="text-lg font-semibold text-themed-primary mb-4">"Delete Document"</h3>
+                        <p class="text-themed-primary mb-2">"Delete this document and all its versions?"</p>
+                        <p class="font-medium text-themed-primary surface-secondary p-2 rounded font-mono text-sm">
+                            {filename}
+                        </p>
+                        <div class="flex justify-end space-x-3 mt-6">
+                            <Button
+                                variant=ButtonVariant::Ghost
+                                on_click=Callback::new(move |_| set_show_confirm.set(false))
+                            >
+                                "Cancel"
+                            </Button>
+                            <Button
+                                variant=ButtonVariant::Danger
+                                on_click=Callback::new(move |_| {
+                                    delete_action.dispatch(());
+                                    set_show_confirm.set(false);
+                                })
+                            >
+                                "Delete Document"
+                            </Button>
+                        </div>
+                    </div>
+                </div>
+            })
+        }}
+        </div>
+    }
+}
@@ -6,8 +6,10 @@ use std::fmt::Write;
 pub fn MarkdownRenderer(
     #[prop(into)] content: String,
     #[prop(optional)] class: &'static str,
+    /// Number of citations; `[1]`..`[n]` in the text become clickable markers
+    #[prop(optional)] citations: usize,
 ) -> impl IntoView {
-    let rendered_html = Memo::new(move |_| markdown_to_html(&content));
+    let rendered_html = Memo::new(move |_| markdown_to_html_with_citations(&content, citations));
 
     view! {
         <div
@@ -18,6 +20,12 @@ pub fn MarkdownRenderer(
 }
 
 pub fn markdown_to_html(markdown: &str) -> String {
+    markdown_to_html_with_citations(markdown, 0)
+}
+
+/// Like `markdown_to_html`, but turns `[n]` for `1 <= n <= citations` into a
+/// `data-citation` marker outside of code
+pub fn markdown_to_html_with_citations(markdown: &str, citations: usize) -> String {
     let mut options = Options::empty();
     options.insert(Options::ENABLE_STRIKETHROUGH);
     options.insert(Options::ENABLE_TABLES);
@@ -28,8 +36,14 @@ pub fn markdown_to_html(markdown: &str) -> String {
     let parser = Parser::new_ext(markdown, options);
     let mut html_output = String::new();
     let mut in_code_block = false;
+    // the parser splits unmatched brackets into separate text events, so collect them first
+    let mut text = String::new();
 
     for event in parser {
+        if !matches!(event, Event::Text(_)) && !text.is_empty() {
+            push_text_with_citations(&mut html_output, &text, citations);
+            text.clear();
+        }
         match event {
             Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(lang))) => {
                 in_code_block = true;
@@ -139,12 +153,12 @@ pub fn markdown_to_html(markdown: &str) -> String {
                     html_escape(&text)
                 ).unwrap();
             }
-            Event::Text(text) => {
+            Event::Text(t) => {
                 if in_code_block {
                     // Preserve formatting and indentation in code blocks
-                    html_output.push_str(&html_escape(&text));
+                    html_output.push_str(&html_escape(&t));
                 } else {
-                    html_output.push_str(&html_escape(&text));
+                    text.push_str(&t);
                 }
             }
             Event::SoftBreak => {
@@ -196,10 +210,44 @@ pub fn markdown_to_html(markdown: &str) -> String {
             _ => {}
         }
     }
+    if !text.is_empty() {
+        push_text_with_citations(&mut html_output, &text, citations);
+    }
 
     html_output
 }
 
+fn push_text_with_citations(html_output: &mut String, text: &str, citations: usize) {
+    let mut rest = text;
+    while let Some(open) = rest.find('[') {
+        let marker = rest[open + 1..]
+            .find(']')
+            .map(|close| &rest[open + 1..open + 1 + close])
+            .and_then(|inner| {
+                inner.parse::<usize>().ok()
+                    .filter(|_| inner.bytes().all(|b| b.is_ascii_digit()))
+                    .map(|n| (n, inner.len()))
+            })
+            .filter(|(n, _)| (1..=citations).contains(n));
+
+        match marker {
+            Some((n, len)) => {
+                html_output.push_str(&html_escape(&rest[..open]));
+                write!(
+                    html_output,
+                    r#"<button type="button" data-citation="{n}" class="citation-marker align-super text-xs font-medium text-seafoam-600 dark:text-seafoam-400 hover:underline">[{n}]</button>"#
+                ).unwrap();
+                rest = &rest[open + len + 2..];
+            }
+            None => {
+                html_output.push_str(&html_escape(&rest[..=open]));
+                rest = &rest[open + 1..];
+            }
+        }
+    }
+    html_output.push_str(&html_escape(rest));
+}
+
 fn html_escape(text: &str) -> String {
     text.replace('&', "&amp;")
         .replace('<', "&lt;")
@@ -237,4 +285,27 @@ mod tests {
         assert!(html.contains("    print"));
         assert!(html.contains("        print"));
     }
+
+    #[test]
+    fn test_citation_markers() {
+        let html = markdown_to_html_with_citations("Config is loaded once [1][2], see [3] and [x].", 2);
+        assert!(html.contains(r#"data-citation="1""#));
+        assert!(html.contains(r#"data-citation="2""#));
+        assert!(!html.contains(r#"data-citation="3""#));
+        assert!(html.contains("see [3] and [x]."));
+    }
+
+    #[test]
+    fn test_citation_markers_skip_code() {
+        let html = markdown_to_html_with_citations("`arr[1]`\n\n```rust\nlet x = arr[1];\n```", 1);
+        assert!(!html.contains("data-citation"));
+        assert_eq!(markdown_to_html("Plain [1]"), markdown_to_html_with_citations("Plain [1]", 0));
+    }
+
+    #[test]
+    fn test_zero_padded_citation_markers() {
+        let html = markdown_to_html_with_citations("As noted [01], done.", 1);
+        assert!(html.contains(r#"data-citation="1""#));
+        assert!(html.contains("</button>, done."));
+    }
 }
@@ -8,8 +8,12 @@ use uuid::Uuid;
 use wasm_bindgen::JsCast;
 
 use crate::auth::get_current_user;
-use crate::models::conversations::{MessageView, DisplayMessage, PendingMessage, BranchInfo};
+use crate::models::conversations::{MessageView, DisplayMessage, DocumentCitation, PendingMessage, BranchInfo};
+use crate::components::collab::ThreadRoom;
+use crate::components::documents::DocumentViewer;
 use crate::components::markdown::MarkdownRenderer;
+use crate::components::shares::ShareThread;
+use crate::components::traces::RetrievalTrace;
 use crate::components::ui::{Button, IconButton, ButtonVariant, ButtonSize};
 
 async fn get_messages_query(thread_id: String) -> Result<Vec<MessageView>, String> {
@@ -117,6 +121,9 @@ pub fn MessageList(
 
     let client: QueryClient = expect_context();
     
+    // Citation whose document is open in the side panel
+    let (open_citation, set_open_citation) = signal(None::<DocumentCitation>);
+
     // Search navigation state
     let (current_match_index, set_current_match_index) = signal(0);
     let (total_matches, set_total_matches) = signal(0);
@@ -484,6 +491,9 @@ pub fn MessageList(
                     }}
 
                 </Transition>
+
+                <ThreadRoom thread_id=current_thread_id refetch_trigger/>
+                <ShareThread thread_id=current_thread_id/>
             </div>
 
             // Messages Container
@@ -542,6 +552,10 @@ pub fn MessageList(
                                                             let message_for_streaming = message.clone();
                                                             let message_for_active_lab = message.clone();
                                                             let message_for_active_model = message.clone();
+                                                            let citations = message.citations().to_vec();
+                                                            let citation_count = citations.len();
+                                                            let citations_for_click = citations.clone();
+                                                            let retrieval_trace_id = message.retrieval_trace_id();
                                                             view! {
                                                                 <div
                                                                     id=format!("message-{}", message_id)
@@ -682,7 +696,19 @@ pub fn MessageList(
                                                                     </div>
 
                                                                     // Message Content
-                                                                    <div class="message-container">
+                                                                    <div
+                                                                        class="message-container"
+                                                                        on:click=move |ev| {
+                                                                            let marker = ev.target()
+                                                                                .and_then(|target| target.dyn_into::<web_sys::Element>().ok())
+                                                                                .and_then(|element| element.closest("[data-citation]").ok().flatten())
+                                                                                .and_then(|element| element.get_attribute("data-citation"))
+                                                                                .and_then(|n| n.parse::<usize>().ok());
+                                                                            if let Some(citation) = marker.and_then(|n| citations_for_click.get(n - 1)) {
+                                                                                set_open_citation.set(Some(citation.clone()));
+                                                                            }
+                                                                        }
+                                                                    >
                                                                         {move || {
                                                                             if !search_highlight_term.is_empty() && has_match {
                                                                                 view! {
@@ -699,6 +725,7 @@ pub fn MessageList(
                                                                                     <MarkdownRenderer
                                                                                         content=message_for_content.content()
                                                                                         class="text-left w-full max-w-full"
+                                                                                        citations=citation_count
                                                                                     />
                                                                                 }
                                                                                     .into_any()
@@ -707,6 +734,13 @@ pub fn MessageList(
 
                                                                     </div>
 
+                                                                    <CitationSources
+                                                                        citations=citations
+                                                                        on_open=Callback::new(move |citation| set_open_citation.set(Some(citation)))
+                                                                    />
+
+                                                                    {retrieval_trace_id.map(|trace_id| view! { <RetrievalTrace trace_id=trace_id/> })}
+
                                                                     // Streaming indicator
                                                                     {move || {
                                                                         if message_for_streaming.is_streaming() {
@@ -748,6 +782,66 @@ pub fn MessageList(
 
                 </Transition>
             </div>
+
+            {move || {
+                open_citation.get().and_then(|citation| {
+                    let version = (citation.document_version > 0).then_some(citation.document_version);
+                    citation.document_id.map(|document_id| view! {
+                        <DocumentViewer
+                            document_id=document_id
+                            version=version
+                            highlight=citation.highlight()
+                            panel=true
+                            on_close=Callback::new(move |_| set_open_citation.set(None))
+                        />
+                    })
+                })
+            }}
+        </div>
+    }.into_any()
+}
+
+/// Numbered list of the sources under an answer, matching its inline `[n]` markers
+#[component]
+fn CitationSources(
+    citations: Vec<DocumentCitation>,
+    #[prop(into)] on_open: Callback<DocumentCitation>,
+) -> impl IntoView {
+    if citations.is_empty() {
+        return view! { <div></div> }.into_any();
+    }
+
+    view! {
+        <div class="mt-3 pt-2 border-t border-gray-200 dark:border-teal-700">
+            <p class="text-xs font-medium text-themed-secondary mb-1">"Sources"</p>
+            <ol class="space-y-1">
+                {citations.into_iter().enumerate().map(|(index, citation)| {
+                    let label = match &citation.location {
+                        Some(location) => format!("{}, {}", citation.filename, location),
+                        None => citation.filename.clone(),
+                    };
+                    let version = (citation.document_version > 1).then(|| format!(" v{}", citation.document_version));
+                    let preview = citation.chunk_text.clone();
+                    let similarity = format!(" ({:.2})", citation.similarity);
+                    let openable = citation.document_id.is_some();
+                    view! {
+                        <li>
+                            <button
+                                type="button"
+                                class="text-left text-xs text-themed-secondary hover:text-themed-primary disabled:cursor-default"
+                                title=preview
+                                disabled=!openable
+                                on:click=move |_| on_open.run(citation.clone())
+                            >
+                                <span class="font-medium text-seafoam-600 dark:text-seafoam-400">{format!("[{}] ", index + 1)}</span>
+                                <span class="font-mono">{label}</span>
+                                {version}
+                                {similarity}
+                            </button>
+                        </li>
+                    }
+                }).collect_view()}
+            </ol>
         </div>
     }.into_any()
 }
@@ -766,6 +860,7 @@ pub async fn get_messages_for_thread(_thread_id: String) -> Result<Vec<MessageVi
     use crate::models::conversations::Message;
     use crate::schema::messages::dsl::*;
     use crate::auth::get_current_user;
+    use crate::services::workspaces::{readable_thread, AccessError};
 
     #[derive(Debug)]
     enum MessageError {
@@ -806,8 +901,15 @@ pub async fn get_messages_for_thread(_thread_id: String) -> Result<Vec<MessageVi
         .map_err(|e| MessageError::Pool(e.to_string()))
         .map_err(to_server_error)?;
 
+    // the author's own threads, or ones shared with a workspace they're both in;
+    // a thread that doesn't exist yet simply has no messages
+    match readable_thread(&mut conn, current_user_id, &_thread_id).await {
+        Ok(_) => {}
+        Err(AccessError::NotFound(_)) => return Ok(Vec::new()),
+        Err(e) => return Err(ServerFnError::new(e)),
+    }
+
     let result = messages
-        .filter(user_id.eq(current_user_id))
         .filter(thread_id.eq(_thread_id))
         .order(id.asc())
         .load::<Message>(&mut conn)
@@ -955,6 +1057,7 @@ pub async fn create_branch(
                 branch_name: Some(branch_name),
                 title: None,
                 project_id: source_thread.project_id,
+                visibility: crate::models::workspaces::THREAD_PRIVATE.to_string(),
             };
     
             diesel::insert_into(threads::table)
@@ -971,6 +1074,8 @@ pub async fn create_branch(
                     active_model: message.active_model,
                     active_lab: message.active_lab,
                     user_id: Some(user_id),
+                    citations: message.citations,
+                    retrieval_trace_id: message.retrieval_trace_id,
                 };
     
                 diesel::insert_into(messages::table)
@@ -1,10 +1,16 @@
+pub mod audit;
 pub mod auth_nav;
 pub mod chat;
+pub mod collab;
 pub mod dark_mode_toggle;
+pub mod documents;
 pub mod footer;
 pub mod markdown;
 pub mod messagelist;
 pub mod projects;
+pub mod shares;
 pub mod threadlist;
 pub mod toast;
+pub mod traces;
 pub mod ui;
+pub mod workspaces;
@@ -5,10 +5,14 @@ use uuid::Uuid;
 use web_sys::Event;
 use wasm_bindgen::JsCast;
 
+use crate::models::imports::ImportView;
 use crate::models::projects::*;
 use crate::server_fn::projects::*;
+use crate::components::documents::{DocumentActions, DocumentViewer};
 use crate::components::ui::{Button, IconButton, ButtonVariant, ButtonSize};
 use crate::components::threadlist::get_threads_query;
+use crate::components::workspaces::{get_workspaces_query, ProjectSharing};
+use crate::models::workspaces::Permission;
 use crate::pages::writersroom::ThreadContext;
 
 pub async fn get_user_projects_query() -> Result<Vec<ProjectView>, String> {
@@ -374,8 +378,19 @@ pub fn DeleteProjectButton(
 
 #[component]
 fn ProjectDetails(project_id: Uuid) -> impl IntoView {
-    let _client: QueryClient = expect_context();
+    let client: QueryClient = expect_context();
     let (show_upload, set_show_upload) = signal(false);
+    let (show_import, set_show_import) = signal(false);
+    let (show_edit, set_show_edit) = signal(false);
+    let (viewing, set_viewing) = signal(None::<Uuid>);
+
+    let projects_resource = client.resource(get_user_projects_query, || ());
+    let project = move || {
+        projects_resource
+            .get()
+            .and_then(Result::ok)
+            .and_then(|projects| projects.into_iter().find(|project| project.id == project_id))
+    };
 
     let documents_resource = Resource::new(
         move || project_id,
@@ -384,11 +399,55 @@ fn ProjectDetails(project_id: Uuid) -> impl IntoView {
         }
     );
 
+    let imports_resource = Resource::new(
+        move || project_id,
+        |project_id| async move {
+            get_project_imports(project_id).await.map_err(|e| e.to_string())
+        }
+    );
+
+    // imports store their files in the background; follow them until they finish
+    Effect::new(move |_| {
+        if let Some(Ok(imports)) = imports_resource.get() {
+            if imports.iter().any(|import| import.status == "running") {
+                set_timeout(
+                    move || {
+                        imports_resource.refetch();
+                        documents_resource.refetch();
+                    },
+                    std::time::Duration::from_secs(2),
+                );
+            }
+        }
+    });
+
+    // poll while any document is still waiting for or going through indexing
+    Effect::new(move |_| {
+        if let Some(Ok(documents)) = documents_resource.get() {
+            let indexing = documents
+                .iter()
+                .any(|doc| doc.status == "queued" || doc.status == "processing");
+            if indexing {
+                set_timeout(
+                    move || documents_resource.refetch(),
+                    std::time::Duration::from_secs(3),
+                );
+            }
+        }
+    });
+
     view! {
         <div class="card-themed p-6">
             <div class="flex justify-between items-center mb-4">
                 <h2 class="text-xl font-semibold text-themed-primary">"Project Details"</h2>
                 <div class="flex space-x-2">
+                    <Button
+                        variant=ButtonVariant::Ghost
+                        size=ButtonSize::Small
+                        on_click=Callback::new(move |_| set_show_edit.set(true))
+                    >
+                        "Edit"
+                    </Button>
                     <Button
                         variant=ButtonVariant::Primary
                         size=ButtonSize::Small
@@ -396,10 +455,33 @@ fn ProjectDetails(project_id: Uuid) -> impl IntoView {
                     >
                         "Upload Document"
                     </Button>
+                    <Button
+                        variant=ButtonVariant::Secondary
+                        size=ButtonSize::Small
+                        on_click=Callback::new(move |_| set_show_import.set(true))
+                    >
+                        "Import"
+                    </Button>
                     <StartChatButton project_id=project_id/>
                 </div>
             </div>
 
+            <EmbeddingModelSettings project_id=project_id/>
+
+            {move || project().map(|project| view! {
+                <ProjectSharing project_id=project_id workspace_id=project.workspace_id/>
+            })}
+
+            <Transition fallback=|| ()>
+                {move || {
+                    imports_resource.get().and_then(Result::ok).filter(|imports| !imports.is_empty()).map(|imports| view! {
+                        <div class="space-y-2 mb-4">
+                            {imports.into_iter().map(|import| view! { <ImportProgress import=import/> }).collect_view()}
+                        </div>
+                    })
+                }}
+            </Transition>
+
             <Transition fallback=|| {
                 view! { <div class="loading-themed">"Loading documents..."</div> }.into_any()
             }>
@@ -420,17 +502,40 @@ fn ProjectDetails(project_id: Uuid) -> impl IntoView {
                                         <For
                                             each=move || documents.clone()
                                             key=|doc| doc.id
-                                            children=|doc| {
+                                            children=move |doc| {
+                                                let document_id = doc.id;
+                                                let actions = view! {
+                                                    <DocumentActions
+                                                        document=doc.clone()
+                                                        on_view=Callback::new(move |_| set_viewing.set(Some(document_id)))
+                                                        on_changed=Callback::new(move |_| documents_resource.refetch())
+                                                    />
+                                                };
                                                 view! {
                                                     <div class="surface-secondary p-3 rounded border-themed">
                                                         <div class="flex justify-between items-center">
                                                             <span class="text-themed-primary font-medium">
                                                                 {doc.filename}
+                                                                {(doc.version > 1).then(|| view! {
+                                                                    <span class="ml-2 text-xs text-themed-secondary">
+                                                                        {format!("v{}", doc.version)}
+                                                                    </span>
+                                                                })}
                                                             </span>
-                                                            <span class="text-xs text-themed-secondary">
-                                                                {format!("{} chars", doc.content.len())}
-                                                            </span>
+                                                            <div class="flex items-center space-x-2">
+                                                                <DocumentStatusBadge status=doc.status.clone()/>
+                                                                <span class="text-xs text-themed-secondary">
+                                                                    {match doc.page_count {
+                                                                        Some(pages) => format!("{} pages", pages),
+                                                                        None => format!("{} chars", doc.content.len()),
+                                                                    }}
+                                                                </span>
+                                                                {actions}
+                                                            </div>
                                                         </div>
+                                                        {doc.status_error.map(|error| view! {
+                                                            <p class="text-xs error-themed mt-1">{error}</p>
+                                                        })}
                                                     </div>
                                                 }
                                                     .into_any()
@@ -474,10 +579,178 @@ fn ProjectDetails(project_id: Uuid) -> impl IntoView {
                 }
             }}
 
+            {move || {
+                viewing.get().map(|document_id| view! {
+                    <DocumentViewer document_id=document_id on_close=Callback::new(move |_| set_viewing.set(None))/>
+                })
+            }}
+
+            {move || {
+                show_edit.get().then(project).flatten().map(|project| view! {
+                    <EditProjectModal project=project set_show=set_show_edit/>
+                })
+            }}
+
+            {move || {
+                show_import.get().then(|| view! {
+                    <ImportModal
+                        project_id=project_id
+                        set_show=set_show_import
+                        on_started=Callback::new(move |_| {
+                            imports_resource.refetch();
+                        })
+                    />
+                })
+            }}
+
         </div>
     }.into_any()
 }
 
+#[component]
+fn ImportProgress(import: ImportView) -> impl IntoView {
+    let processed = import.imported_files + import.skipped_files + import.failed_files;
+    let percent = if import.total_files > 0 { processed * 100 / import.total_files } else { 100 };
+    let (label, class) = match import.status.as_str() {
+        "running" => ("Importing…", "text-themed-secondary animate-pulse"),
+        "done" => ("Imported", "text-success-600 dark:text-success-400"),
+        _ => ("Failed", "error-themed"),
+    };
+    let running = import.status == "running";
+    let report = import.report;
+
+    view! {
+        <div class="surface-secondary p-3 rounded border-themed">
+            <div class="flex justify-between items-center">
+                <span class="text-themed-primary font-medium truncate">{import.source}</span>
+                <span class=format!("text-xs font-medium {class}")>{label}</span>
+            </div>
+            {running.then(|| view! {
+                <div class="w-full h-1.5 bg-gray-200 dark:bg-gray-700 rounded mt-2">
+                    <div class="h-1.5 bg-seafoam-600 rounded" style=format!("width: {percent}%")></div>
+                </div>
+            })}
+            <p class="text-xs text-themed-secondary mt-1">
+                {format!(
+                    "{} of {} files imported, {} skipped, {} failed",
+                    import.imported_files,
+                    import.total_files,
+                    import.skipped_files,
+                    import.failed_files,
+                )}
+            </p>
+            {import.error.map(|error| view! { <p class="text-xs error-themed mt-1">{error}</p> })}
+            {(!report.is_empty()).then(|| view! {
+                <details class="mt-1">
+                    <summary class="text-xs text-themed-secondary cursor-pointer">"Skipped and failed files"</summary>
+                    <ul class="text-xs text-themed-secondary mt-1 max-h-40 overflow-y-auto">
+                        {report.into_iter().map(|entry| view! {
+                            <li>
+                                <span class="font-mono">{entry.path}</span>
+                                ": "
+                                {entry.reason}
+                            </li>
+                        }).collect_view()}
+                    </ul>
+                </details>
+            })}
+        </div>
+    }
+}
+
+#[component]
+fn DocumentStatusBadge(status: String) -> impl IntoView {
+    let (label, class) = match status.as_str() {
+        "queued" => ("Queued", "text-themed-secondary"),
+        "processing" => ("Indexing…", "text-themed-secondary animate-pulse"),
+        "indexed" => ("Indexed", "text-success-600 dark:text-success-400"),
+        "failed" => ("Failed", "error-themed"),
+        _ => ("Unknown", "text-themed-secondary"),
+    };
+
+    view! {
+        <span class=format!("text-xs font-medium {class}")>{label}</span>
+    }
+}
+
+#[component]
+fn EmbeddingModelSettings(project_id: Uuid) -> impl IntoView {
+    let settings_resource = Resource::new(
+        move || project_id,
+        |project_id| async move {
+            get_embedding_settings(project_id).await.map_err(|e| e.to_string())
+        }
+    );
+
+    let set_model_action = Action::new(move |model: &String| {
+        let model = model.clone();
+        async move {
+            set_project_embedding_model(project_id, model).await.map_err(|e| e.to_string())
+        }
+    });
+
+    Effect::new(move |_| {
+        if let Some(Ok(())) = set_model_action.value().get() {
+            settings_resource.refetch();
+        }
+    });
+
+    let handle_model_change = move |ev| {
+        set_model_action.dispatch(event_target_value(&ev));
+    };
+
+    view! {
+        <Transition fallback=|| view! { <div></div> }.into_any()>
+            {move || {
+                match settings_resource.get() {
+                    Some(Ok(settings)) => {
+                        let current_model = settings.current_model.clone();
+                        let pending = settings.total_chunks - settings.embedded_chunks;
+                        view! {
+                            <div class="flex items-center justify-between mb-4 text-sm">
+                                <label class="text-themed-secondary">"Embedding model"</label>
+                                <div class="flex items-center space-x-2">
+                                    {(pending > 0).then(|| view! {
+                                        <span class="text-xs text-themed-secondary">
+                                            {format!("Re-embedding: {}/{} chunks", settings.embedded_chunks, settings.total_chunks)}
+                                        </span>
+                                    })}
+                                    <select
+                                        class="input-themed text-sm"
+                                        on:change=handle_model_change
+                                        prop:value=current_model.clone()
+                                        disabled=move || set_model_action.pending().get()
+                                    >
+                                        {settings.available_models.into_iter().map(|model| {
+                                            let selected = model.id == current_model;
+                                            view! {
+                                                <option value=model.id.clone() selected=selected>{model.label}</option>
+                                            }
+                                        }).collect_view()}
+                                    </select>
+                                </div>
+                            </div>
+                            {move || set_model_action.value().get().and_then(|result| result.err()).map(|e| view! {
+                                <div class="error-themed text-sm mb-4">{e}</div>
+                            })}
+                        }
+                            .into_any()
+                    }
+                    Some(Err(e)) => {
+                        view! {
+                            <div class="error-themed text-sm mb-4">
+                                "Error loading embedding settings: " {e}
+                            </div>
+                        }
+                            .into_any()
+                    }
+                    None => view! { <div></div> }.into_any(),
+                }
+            }}
+        </Transition>
+    }.into_any()
+}
+
 #[component]
 pub fn StartChatButton(project_id: Uuid) -> impl IntoView {
     let client: QueryClient = expect_context();
@@ -528,12 +801,15 @@ fn CreateProjectModal(
     let (name, set_name) = signal(String::new());
     let (description, set_description) = signal(String::new());
     let (instructions, set_instructions) = signal(String::new());
+    let (workspace_id, set_workspace_id) = signal(None::<Uuid>);
+    let workspaces = client.resource(get_workspaces_query, || ());
 
     let create_action = Action::new(move |_: &()| {
         let project_data = NewProjectView {
             name: name.get(),
             description: if description.get().is_empty() { None } else { Some(description.get()) },
             instructions: if instructions.get().is_empty() { None } else { Some(instructions.get()) },
+            workspace_id: workspace_id.get(),
         };
 
         async move {
@@ -606,6 +882,28 @@ fn CreateProjectModal(
                         ></textarea>
                     </div>
 
+                    <Transition fallback=|| ()>
+                        {move || workspaces.get().and_then(Result::ok).map(|list| {
+                            let targets: Vec<_> = list.into_iter().filter(|w| w.role.allows(Permission::Edit)).collect();
+                            (!targets.is_empty()).then(|| view! {
+                                <div>
+                                    <label class="block text-sm font-medium text-themed-primary mb-1">
+                                        "Workspace"
+                                    </label>
+                                    <select
+                                        class="input-themed w-full"
+                                        on:change=move |ev| set_workspace_id.set(Uuid::parse_str(&event_target_value(&ev)).ok())
+                                    >
+                                        <option value="">"Personal"</option>
+                                        {targets.into_iter().map(|w| view! {
+                                            <option value=w.id.to_string()>{w.name}</option>
+                                        }).collect_view()}
+                                    </select>
+                                </div>
+                            })
+                        })}
+                    </Transition>
+
                     <div class="flex justify-end space-x-3 pt-4">
                         <Button
                             variant=ButtonVariant::Ghost
@@ -642,6 +940,79 @@ fn CreateProjectModal(
     }.into_any()
 }
 
+/// Posts a multipart form to one of the upload endpoints, surfacing their JSON errors
+async fn post_form<T: serde::de::DeserializeOwned>(url: &str, form: &web_sys::FormData) -> Result<T, String> {
+    use wasm_bindgen_futures::JsFuture;
+
+    let init = web_sys::RequestInit::new();
+    init.set_method("POST");
+    init.set_body(form);
+
+    let window = web_sys::window().ok_or_else(|| "No window".to_string())?;
+    let response = JsFuture::from(window.fetch_with_str_and_init(url, &init))
+        .await
+        .map_err(|_| "Upload failed: network error".to_string())?
+        .dyn_into::<web_sys::Response>()
+        .map_err(|_| "Upload failed: invalid response".to_string())?;
+
+    let status = response.status();
+    let body = match response.json() {
+        Ok(promise) => JsFuture::from(promise).await.ok(),
+        Err(_) => None,
+    };
+
+    if response.ok() {
+        let body = body.ok_or_else(|| "Upload failed: invalid response".to_string())?;
+        serde_wasm_bindgen::from_value(body).map_err(|e| e.to_string())
+    } else {
+        let message = body
+            .and_then(|body| js_sys::Reflect::get(&body, &"error".into()).ok())
+            .and_then(|error| error.as_string())
+            .unwrap_or_else(|| match status {
+                413 => "The file is too large".to_string(),
+                _ => format!("Upload failed ({})", status),
+            });
+        Err(message)
+    }
+}
+
+/// Sends a file to the multipart upload endpoint, which sniffs and extracts it.
+/// With a `path`, the file is stored under it; an existing document there gets a new version.
+async fn upload_file(project_id: Uuid, file: web_sys::File, path: Option<String>) -> Result<ProjectDocumentView, String> {
+    let form = web_sys::FormData::new().map_err(|_| "Could not prepare the upload".to_string())?;
+    if let Some(path) = path {
+        form.append_with_str("path", &path).map_err(|_| "Could not prepare the upload".to_string())?;
+    }
+    form.append_with_blob_and_filename("file", &file, &file.name())
+        .map_err(|_| "Could not prepare the upload".to_string())?;
+
+    post_form(&format!("/api/projects/{}/documents", project_id), &form).await
+}
+
+/// Sends an archive with its include / exclude patterns to the import endpoint
+async fn import_archive(
+    project_id: Uuid,
+    file: web_sys::File,
+    include: String,
+    exclude: String,
+) -> Result<ImportView, String> {
+    let form = web_sys::FormData::new().map_err(|_| "Could not prepare the import".to_string())?;
+    form.append_with_str("include", &include)
+        .and_then(|_| form.append_with_str("exclude", &exclude))
+        .and_then(|_| form.append_with_blob_and_filename("archive", &file, &file.name()))
+        .map_err(|_| "Could not prepare the import".to_string())?;
+
+    post_form(&format!("/api/projects/{}/imports", project_id), &form).await
+}
+
+fn format_file_size(bytes: f64) -> String {
+    if bytes >= 1024.0 * 1024.0 {
+        format!("{:.1} MB", bytes / (1024.0 * 1024.0))
+    } else {
+        format!("{:.0} KB", (bytes / 1024.0).ceil())
+    }
+}
+
 #[component]
 fn UploadDocumentModal(
     project_id: Uuid,
@@ -650,7 +1021,8 @@ fn UploadDocumentModal(
     #[prop(into)] on_uploaded: Callback<()>,
 ) -> impl IntoView {
     let (filename, set_filename) = signal(String::new());
-    let (content, set_content) = signal(String::new());
+    let (selected_file, set_selected_file) = signal_local(None::<web_sys::File>);
+    let (document_path, set_document_path) = signal(String::new());
     let (is_uploading, set_is_uploading) = signal(false);
     
     // New state for switching between file upload and manual text input
@@ -664,59 +1036,49 @@ fn UploadDocumentModal(
                 if files.length() > 0 {
                     if let Some(file) = files.get(0) {
                         set_filename.set(file.name());
-                        let file_reader = web_sys::FileReader::new().unwrap();
-                        let file_reader_clone = file_reader.clone();
-                        
-                        let closure = wasm_bindgen::closure::Closure::wrap(Box::new(move |_: web_sys::Event| {
-                            if let Ok(result) = file_reader_clone.result() {
-                                if let Some(content_str) = result.as_string() {
-                                    set_content.set(content_str);
-                                }
-                            }
-                        }) as Box<dyn FnMut(_)>);
-                        
-                        file_reader.set_onload(Some(closure.as_ref().unchecked_ref()));
-                        closure.forget();
-                        let _ = file_reader.read_as_text(&file);
+                        set_selected_file.set(Some(file));
                     }
                 }
             }
         }
     };
 
-    let upload_action = Action::new(move |_: &()| {
+    // local: the file upload awaits browser fetch futures, which aren't Send
+    let upload_action = Action::new_local(move |_: &()| {
         let project_id = project_id;
-        
+        let file_mode = input_mode.get() == "file";
+        let file = selected_file.get();
+        let target_path = Some(document_path.get().trim().to_string()).filter(|path| !path.is_empty());
+
         // Determine which content to use based on input mode
-        let (final_filename, final_content) = if input_mode.get() == "file" {
-            (filename.get(), content.get())
+        let manual_fname = manual_filename.get();
+        let final_filename = if manual_fname.trim().is_empty() {
+            "untitled.txt".to_string()
+        } else if !manual_fname.contains('.') {
+            format!("{}.txt", manual_fname.trim())
         } else {
-            let manual_fname = manual_filename.get();
-            let fname = if manual_fname.trim().is_empty() {
-                "untitled.txt".to_string()
-            } else if !manual_fname.contains('.') {
-                format!("{}.txt", manual_fname.trim())
-            } else {
-                manual_fname.trim().to_string()
-            };
-            (fname, manual_content.get())
+            manual_fname.trim().to_string()
         };
-        
+        let final_content = manual_content.get();
+
         async move {
             set_is_uploading.set(true);
-            
-            match upload_document(
-                project_id,
-                final_filename,
-                final_content,
-                Some("text/plain".to_string())
-            ).await {
+
+            let result = match file {
+                Some(file) if file_mode => upload_file(project_id, file, target_path).await,
+                _ => upload_document(project_id, final_filename, final_content)
+                    .await
+                    .map_err(|e| e.to_string()),
+            };
+
+            match result {
                 Ok(_) => {
                     on_uploaded.run(());
                     set_show.set(false);
                     // Reset all form state
                     set_filename.set(String::new());
-                    set_content.set(String::new());
+                    set_selected_file.set(None);
+                    set_document_path.set(String::new());
                     set_manual_filename.set(String::new());
                     set_manual_content.set(String::new());
                     set_input_mode.set("file");
@@ -734,7 +1096,7 @@ fn UploadDocumentModal(
     // Helper to check if we have valid content to upload
     let has_valid_content = move || {
         if input_mode.get() == "file" {
-            !content.get().is_empty()
+            selected_file.with(Option::is_some)
         } else {
             !manual_content.get().trim().is_empty()
         }
@@ -752,7 +1114,7 @@ fn UploadDocumentModal(
                         on_click=Callback::new(move |_| {
                             set_show.set(false);
                             set_filename.set(String::new());
-                            set_content.set(String::new());
+                            set_selected_file.set(None);
                             set_manual_filename.set(String::new());
                             set_manual_content.set(String::new());
                             set_input_mode.set("file");
@@ -803,7 +1165,7 @@ fn UploadDocumentModal(
                             on:click=move |_| {
                                 set_input_mode.set("text");
                                 set_filename.set(String::new());
-                                set_content.set(String::new());
+                                set_selected_file.set(None);
                             }
                         >
 
@@ -823,12 +1185,28 @@ fn UploadDocumentModal(
                                         </label>
                                         <input
                                             type="file"
-                                            accept=".txt,.md,.pdf,.doc,.docx"
+                                            accept=".txt,.md,.pdf,.docx,.pptx,.odt,.html,.htm"
                                             class="input-themed w-full"
                                             on:change=handle_file_upload
                                         />
                                         <p class="text-xs text-themed-secondary mt-1">
-                                            "Supported formats: .txt, .md, .pdf, .doc, .docx"
+                                            "Supported formats: .txt, .md, .pdf, .docx, .pptx, .odt, .html"
+                                        </p>
+                                    </div>
+
+                                    <div>
+                                        <label class="block text-sm font-medium text-themed-primary mb-1">
+                                            "Path (optional)"
+                                        </label>
+                                        <input
+                                            type="text"
+                                            class="input-themed w-full font-mono"
+                                            placeholder="docs/guide.md"
+                                            prop:value=document_path
+                                            on:input=move |ev| set_document_path.set(event_target_value(&ev))
+                                        />
+                                        <p class="text-xs text-themed-secondary mt-1">
+                                            "Uploading to an existing path saves a new version of that document."
                                         </p>
                                     </div>
 
@@ -841,15 +1219,14 @@ fn UploadDocumentModal(
                                                         <span class="font-medium">{filename.get()}</span>
                                                     </p>
                                                     {move || {
-                                                        if !content.get().is_empty() {
-                                                            view! {
+                                                        match selected_file.with(|file| file.as_ref().map(|f| f.size())) {
+                                                            Some(size) => view! {
                                                                 <p class="text-xs text-themed-secondary mt-1">
-                                                                    {format!("{} characters", content.get().len())}
+                                                                    {format_file_size(size)}
                                                                 </p>
                                                             }
-                                                                .into_any()
-                                                        } else {
-                                                            view! { <div></div> }.into_any()
+                                                                .into_any(),
+                                                            None => view! { <div></div> }.into_any(),
                                                         }
                                                     }}
 
@@ -927,6 +1304,12 @@ fn UploadDocumentModal(
                         }
                     }}
 
+                    {move || {
+                        upload_action.value().get().and_then(Result::err).map(|error| view! {
+                            <p class="error-themed text-sm mt-3">{error}</p>
+                        })
+                    }}
+
                     <div class="flex justify-end space-x-3 pt-4">
                         <Button
                             variant=ButtonVariant::Ghost
@@ -934,7 +1317,7 @@ fn UploadDocumentModal(
                             on_click=Callback::new(move |_| {
                                 set_show.set(false);
                                 set_filename.set(String::new());
-                                set_content.set(String::new());
+                                set_selected_file.set(None);
                                 set_manual_filename.set(String::new());
                                 set_manual_content.set(String::new());
                                 set_input_mode.set("file");
@@ -968,3 +1351,289 @@ fn UploadDocumentModal(
         </div>
     }.into_any()
 }
+
+/// Imports a zip / tar archive, or a directory on the server for self-hosted setups
+#[component]
+fn ImportModal(
+    project_id: Uuid,
+    set_show: WriteSignal<bool>,
+    #[prop(into)] on_started: Callback<()>,
+) -> impl IntoView {
+    let (source_mode, set_source_mode) = signal("archive"); // "archive" or "directory"
+    let (selected_file, set_selected_file) = signal_local(None::<web_sys::File>);
+    let (directory, set_directory) = signal(String::new());
+    let (include, set_include) = signal(String::new());
+    let (exclude, set_exclude) = signal(String::new());
+
+    let handle_file = move |ev: Event| {
+        if let Some(input) = ev.target().and_then(|t| t.dyn_into::<web_sys::HtmlInputElement>().ok()) {
+            set_selected_file.set(input.files().and_then(|files| files.get(0)));
+        }
+    };
+
+    let import_action = Action::new_local(move |_: &()| {
+        let archive_mode = source_mode.get() == "archive";
+        let file = selected_file.get();
+        let path = directory.get().trim().to_string();
+        let include = include.get();
+        let exclude = exclude.get();
+
+        async move {
+            let lines = |text: &str| -> Vec<String> {
+                text.lines().map(str::trim).filter(|line| !line.is_empty()).map(String::from).collect()
+            };
+
+            let result = match file {
+                Some(file) if archive_mode => import_archive(project_id, file, include, exclude).await,
+                _ => import_directory(project_id, path, lines(&include), lines(&exclude))
+                    .await
+                    .map_err(|e| e.to_string()),
+            };
+
+            result.map(|_| {
+                on_started.run(());
+                set_show.set(false);
+            })
+        }
+    });
+
+    let can_start = move || {
+        !import_action.pending().get()
+            && if source_mode.get() == "archive" {
+                selected_file.with(Option::is_some)
+            } else {
+                !directory.get().trim().is_empty()
+            }
+    };
+
+    let tab_class = move |mode: &'static str| {
+        move || {
+            format!(
+                "flex-1 px-3 py-2 text-sm font-medium rounded-md transition-colors {}",
+                if source_mode.get() == mode {
+                    "bg-white dark:bg-teal-700 text-themed-primary shadow-sm"
+                } else {
+                    "text-themed-secondary hover:text-themed-primary"
+                },
+            )
+        }
+    };
+
+    view! {
+        <div class="fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center z-50">
+            <div class="card-themed p-6 w-full max-w-lg">
+                <div class="flex justify-between items-center mb-4">
+                    <h3 class="text-lg font-semibold text-themed-primary">"Import Files"</h3>
+                    <IconButton
+                        variant=ButtonVariant::Ghost
+                        size=ButtonSize::Small
+                        class="text-themed-secondary hover:text-themed-primary"
+                        on_click=Callback::new(move |_| set_show.set(false))
+                    >
+                        "✕"
+                    </IconButton>
+                </div>
+
+                <div class="space-y-4">
+                    <div class="flex bg-surface-secondary rounded-lg p-1">
+                        <button class=tab_class("archive") on:click=move |_| set_source_mode.set("archive")>
+                            <Icon icon=icondata_bs::BsFileEarmarkZip width="16" height="16"/>
+                            "Archive"
+                        </button>
+                        <button class=tab_class("directory") on:click=move |_| set_source_mode.set("directory")>
+                            <Icon icon=icondata_bs::BsFolder2Open width="16" height="16"/>
+                            "Server Directory"
+                        </button>
+                    </div>
+
+                    {move || {
+                        if source_mode.get() == "archive" {
+                            view! {
+                                <div>
+                                    <label class="block text-sm font-medium text-themed-primary mb-1">
+                                        "Choose Archive"
+                                    </label>
+                                    <input
+                                        type="file"
+                                        accept=".zip,.tar,.tar.gz,.tgz"
+                                        class="input-themed w-full"
+                                        on:change=handle_file
+                                    />
+                                    <p class="text-xs text-themed-secondary mt-1">
+                                        "A .zip, .tar or .tar.gz, such as a repository download. Binary files are skipped."
+                                    </p>
+                                </div>
+                            }
+                                .into_any()
+                        } else {
+                            view! {
+                                <div>
+                                    <label class="block text-sm font-medium text-themed-primary mb-1">
+                                        "Directory Path"
+                                    </label>
+                                    <input
+                                        type="text"
+                                        class="input-themed w-full font-mono"
+                                        placeholder="/srv/repos/my-project"
+                                        prop:value=directory
+                                        on:input=move |ev| set_directory.set(event_target_value(&ev))
+                                    />
+                                    <p class="text-xs text-themed-secondary mt-1">
+                                        "Must be inside one of the server's allowed import roots."
+                                    </p>
+                                </div>
+                            }
+                                .into_any()
+                        }
+                    }}
+
+                    <div class="grid grid-cols-2 gap-3">
+                        <div>
+                            <label class="block text-sm font-medium text-themed-primary mb-1">"Include"</label>
+                            <textarea
+                                class="input-themed w-full resize-none font-mono text-xs"
+                                rows="4"
+                                placeholder="src/**/*.rs\n*.md"
+                                prop:value=include
+                                on:input=move |ev| set_include.set(event_target_value(&ev))
+                            ></textarea>
+                        </div>
+                        <div>
+                            <label class="block text-sm font-medium text-themed-primary mb-1">"Exclude"</label>
+                            <textarea
+                                class="input-themed w-full resize-none font-mono text-xs"
+                                rows="4"
+                                placeholder="tests/\n*.snap"
+                                prop:value=exclude
+                                on:input=move |ev| set_exclude.set(event_target_value(&ev))
+                            ></textarea>
+                        </div>
+                    </div>
+                    <p class="text-xs text-themed-secondary">
+                        "One .gitignore-style pattern per line. The archive's own .gitignore files are honoured, "
+                        "and dependency and build folders are always skipped."
+                    </p>
+
+                    {move || {
+                        import_action.value().get().and_then(Result::err).map(|error| view! {
+                            <p class="error-themed text-sm">{error}</p>
+                        })
+                    }}
+
+                    <div class="flex justify-end space-x-3 pt-2">
+                        <Button variant=ButtonVariant::Ghost on_click=Callback::new(move |_| set_show.set(false))>
+                            "Cancel"
+                        </Button>
+                        <button
+                            class="px-4 py-2 bg-seafoam-600 dark:bg-seafoam-700 text-gray rounded-md
+                            hover:bg-seafoam-700 dark:hover:bg-seafoam-600 transition-colors
+                            disabled:opacity-50 disabled:cursor-not-allowed"
+                            disabled=move || !can_start()
+                            on:click=move |_| {
+                                import_action.dispatch(());
+                            }
+                        >
+                            {move || if import_action.pending().get() { "Unpacking..." } else { "Import" }}
+                        </button>
+                    </div>
+                </div>
+            </div>
+        </div>
+    }
+}
+
+#[component]
+fn EditProjectModal(project: ProjectView, set_show: WriteSignal<bool>) -> impl IntoView {
+    let client: QueryClient = expect_context();
+    let project_id = project.id;
+    let (name, set_name) = signal(project.name);
+    let (description, set_description) = signal(project.description.unwrap_or_default());
+    let (instructions, set_instructions) = signal(project.instructions.unwrap_or_default());
+
+    let save_action = Action::new(move |_: &()| {
+        let (name, description, instructions) = (name.get(), description.get(), instructions.get());
+        async move {
+            match update_project(project_id, name, Some(description), Some(instructions)).await {
+                Ok(_) => {
+                    client.invalidate_query(get_user_projects_query, ());
+                    set_show.set(false);
+                    Ok(())
+                }
+                Err(e) => Err(format!("Failed to update project: {}", e)),
+            }
+        }
+    });
+
+    view! {
+        <div class="fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center z-50">
+            <div class="card-themed p-6 w-full max-w-md">
+                <div class="flex justify-between items-center mb-4">
+                    <h3 class="text-lg font-semibold text-themed-primary">"Edit Project"</h3>
+                    <IconButton
+                        variant=ButtonVariant::Ghost
+                        size=ButtonSize::Small
+                        class="text-themed-secondary hover:text-themed-primary"
+                        on_click=Callback::new(move |_| set_show.set(false))
+                    >
+                        "✕"
+                    </IconButton>
+                </div>
+
+                <div class="space-y-4">
+                    <div>
+                        <label class="block text-sm font-medium text-themed-primary mb-1">"Project Name"</label>
+                        <input
+                            type="text"
+                            class="input-themed w-full"
+                            prop:value=name
+                            on:input=move |ev| set_name.set(event_target_value(&ev))
+                        />
+                    </div>
+
+                    <div>
+                        <label class="block text-sm font-medium text-themed-primary mb-1">"Description"</label>
+                        <textarea
+                            class="input-themed w-full resize-none"
+                            rows="3"
+                            prop:value=description
+                            on:input=move |ev| set_description.set(event_target_value(&ev))
+                        ></textarea>
+                    </div>
+
+                    <div>
+                        <label class="block text-sm font-medium text-themed-primary mb-1">"Instructions"</label>
+                        <textarea
+                            class="input-themed w-full resize-none"
+                            rows="4"
+                            prop:value=instructions
+                            on:input=move |ev| set_instructions.set(event_target_value(&ev))
+                        ></textarea>
+                    </div>
+
+                    {move || {
+                        save_action.value().get().and_then(Result::err).map(|error| view! {
+                            <p class="error-themed text-sm">{error}</p>
+                        })
+                    }}
+
+                    <div class="flex justify-end space-x-3 pt-4">
+                        <Button variant=ButtonVariant::Ghost on_click=Callback::new(move |_| set_show.set(false))>
+                            "Cancel"
+                        </Button>
+                        <button
+                            class="px-4 py-2 bg-seafoam-600 dark:bg-seafoam-700 text-white rounded-md
+                            hover:bg-seafoam-700 dark:hover:bg-seafoam-600 transition-colors
+                            disabled:opacity-50 disabled:cursor-not-allowed"
+                            disabled=move || name.get().trim().is_empty() || save_action.pending().get()
+                            on:click=move |_| {
+                                save_action.dispatch(());
+                            }
+                        >
+                            {move || if save_action.pending().get() { "Saving..." } else { "Save" }}
+                        </button>
+                    </div>
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,136 @@
+use leptos::prelude::*;
+use leptos_fetch::QueryClient;
+use uuid::Uuid;
+
+use crate::models::shares::ShareLinkView;
+use crate::server_fn::shares::*;
+
+pub async fn list_share_links_query(thread_id: String) -> Result<Vec<ShareLinkView>, String> {
+    if thread_id.is_empty() {
+        Ok(Vec::new())
+    } else {
+        list_share_links(thread_id).await.map_err(|e| e.to_string())
+    }
+}
+
+const EXPIRY_OPTIONS: [(&str, &str); 4] = [("", "Never expires"), ("1", "1 day"), ("7", "7 days"), ("30", "30 days")];
+
+/// Read-only public links to the current thread, collapsed until opened
+#[component]
+pub fn ShareThread(thread_id: ReadSignal<String>) -> impl IntoView {
+    let client: QueryClient = expect_context();
+    let create_action = ServerAction::<CreateShareLink>::new();
+    let revoke_action = ServerAction::<RevokeShareLink>::new();
+    let (open, set_open) = signal(false);
+    let (expiry, set_expiry) = signal(String::new());
+    let links = client.resource(list_share_links_query, move || thread_id.get());
+
+    Effect::new(move |_| {
+        if create_action.version().get() > 0 || revoke_action.version().get() > 0 {
+            client.invalidate_query(list_share_links_query, thread_id.get_untracked());
+        }
+    });
+
+    let error = move || {
+        create_action.value().get().and_then(Result::err)
+            .or_else(|| revoke_action.value().get().and_then(Result::err))
+            .map(|e| e.to_string())
+    };
+
+    view! {
+        <div class="card-themed p-3 mt-3">
+            <button
+                class="text-sm font-medium text-themed-primary"
+                on:click=move |_| set_open.update(|o| *o = !*o)
+            >
+                {move || if open.get() { "▾ Share" } else { "▸ Share" }}
+            </button>
+            <Show when=move || open.get()>
+                <p class="text-xs text-themed-secondary mt-2">
+                    "Anyone with a link can read a snapshot of this conversation as it is now. Later messages are not included."
+                </p>
+                <div class="flex flex-wrap items-center gap-2 mt-2">
+                    <select
+                        class="px-2 py-1 text-sm border border-gray-300 dark:border-teal-600 rounded-md dark:bg-teal-700 dark:text-gray-200"
+                        on:change=move |ev| set_expiry.set(event_target_value(&ev))
+                    >
+                        {EXPIRY_OPTIONS
+                            .into_iter()
+                            .map(|(value, label)| view! { <option value=value selected={expiry.get_untracked() == value}>{label}</option> })
+                            .collect_view()}
+                    </select>
+                    <button
+                        class="px-3 py-1 text-sm bg-seafoam-600 hover:bg-seafoam-700 text-white rounded-md disabled:opacity-50"
+                        disabled=move || create_action.pending().get()
+                        on:click=move |_| {
+                            create_action.dispatch(CreateShareLink {
+                                thread_id: thread_id.get_untracked(),
+                                expires_in_days: expiry.get_untracked().parse().ok(),
+                            });
+                        }
+                    >
+                        {move || if create_action.pending().get() { "Creating..." } else { "Create link" }}
+                    </button>
+                </div>
+                {move || error().map(|e| view! { <p class="text-xs text-red-500 mt-2">{e}</p> })}
+                <Transition fallback=|| ()>
+                    {move || links.get().map(|result| match result {
+                        Ok(links) => links
+                            .into_iter()
+                            .map(|link| view! { <ShareLinkRow link revoke_action/> })
+                            .collect_view()
+                            .into_any(),
+                        Err(e) => view! { <p class="text-xs text-red-500 mt-2">{e}</p> }.into_any(),
+                    })}
+                </Transition>
+            </Show>
+        </div>
+    }
+}
+
+#[component]
+fn ShareLinkRow(link: ShareLinkView, revoke_action: ServerAction<RevokeShareLink>) -> impl IntoView {
+    let share_id: Uuid = link.id;
+    let revoked = link.revoked;
+    let status = if link.revoked {
+        "revoked".to_string()
+    } else if let Some(expires_at) = link.expires_at {
+        format!("expires {}", expires_at.format("%Y-%m-%d"))
+    } else {
+        "no expiry".to_string()
+    };
+
+    view! {
+        <div class="flex items-center justify-between gap-2 mt-2 text-xs">
+            <div class="min-w-0">
+                {if link.revoked {
+                    view! { <span class="line-through text-themed-secondary">{link.path.clone()}</span> }.into_any()
+                } else {
+                    view! {
+                        <a href=link.path.clone() target="_blank" class="text-seafoam-600 dark:text-aqua-400 hover:underline break-all">
+                            {link.path.clone()}
+                        </a>
+                    }.into_any()
+                }}
+                <div class="text-themed-secondary">
+                    {format!(
+                        "{} messages · created {} · {}",
+                        link.message_count,
+                        link.created_at.format("%Y-%m-%d"),
+                        status,
+                    )}
+                </div>
+            </div>
+            <Show when=move || !revoked>
+                <button
+                    class="px-2 py-1 text-red-600 hover:text-red-700"
+                    on:click=move |_| {
+                        revoke_action.dispatch(RevokeShareLink { share_id });
+                    }
+                >
+                    "Revoke"
+                </button>
+            </Show>
+        </div>
+    }
+}
@@ -1041,57 +1041,35 @@ pub async fn search_threads(query: String) -> Result<Vec<ThreadView>, ServerFnEr
     Ok(threads)
 }
 
-#[server(DeleteThread, "/api")]
-pub async fn delete_thread(thread_id: String) -> Result<(), ServerFnError> {
+/// Deletes a thread with its messages and, recursively, its branches
+#[cfg(feature = "ssr")]
+pub fn delete_thread_recursive<'a>(
+    conn: &'a mut diesel_async::AsyncPgConnection,
+    thread_id: &'a str,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), diesel::result::Error>> + Send + 'a>> {
     use diesel::prelude::*;
-    use diesel_async::{RunQueryDsl, AsyncConnection};
+    use diesel_async::RunQueryDsl;
     use crate::schema::{threads, messages};
-    use std::fmt;
-    use crate::state::AppState;
-    
-    #[derive(Debug)]
-    enum ThreadError {
-        Pool(String),
-        Database(diesel::result::Error),
-    }
-    
-    impl fmt::Display for ThreadError {
-        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-            match self {
-                ThreadError::Pool(e) => write!(f, "pool error: {e}"),
-                ThreadError::Database(e)=> write!(f, "database error: {e}"),
-            }
-        }
-    }
-    
-    fn to_server_error(e: ThreadError) -> ServerFnError {
-        ServerFnError::ServerError(e.to_string())
-    }
-    
-    fn delete_thread_recursive<'a>(
-        conn: &'a mut diesel_async::AsyncPgConnection, 
-        thread_id: &'a str
-    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), diesel::result::Error>> + Send + 'a>> {
-        Box::pin(async move {
 
+    Box::pin(async move {
         let child_threads: Vec<String> = threads::table
             .filter(threads::parent_thread_id.eq(thread_id))
             .select(threads::id)
             .load(conn)
             .await?;
-        
+
         // Recursively delete child threads
         for child_thread_id in child_threads {
             delete_thread_recursive(conn, &child_thread_id).await?;
         }
-        
+
         // Get all message IDs that belong to this thread
         let message_ids: Vec<i32> = messages::table
             .filter(messages::thread_id.eq(thread_id))
             .select(messages::id)
             .load(conn)
             .await?;
-        
+
         // Update any threads that reference these messages
         if !message_ids.is_empty() {
             diesel::update(
@@ -1103,19 +1081,47 @@ pub async fn delete_thread(thread_id: String) -> Result<(), ServerFnError> {
             .execute(conn)
             .await?;
         }
-        
+
         // Delete all messages associated with this thread
         diesel::delete(messages::table.filter(messages::thread_id.eq(thread_id)))
             .execute(conn)
             .await?;
-        
+
         // Finally, delete the thread itself
         diesel::delete(threads::table.find(thread_id))
             .execute(conn)
             .await?;
-        
+
         Ok(())
-        })
+    })
+}
+
+#[server(DeleteThread, "/api")]
+pub async fn delete_thread(thread_id: String) -> Result<(), ServerFnError> {
+    use diesel_async::AsyncConnection;
+    use std::fmt;
+    use crate::auth::get_current_user;
+    use crate::models::audit::AUDIT_THREAD_DELETED;
+    use crate::services::audit::{self, AuditEvent};
+    use crate::state::AppState;
+    
+    #[derive(Debug)]
+    enum ThreadError {
+        Pool(String),
+        Database(diesel::result::Error),
+    }
+    
+    impl fmt::Display for ThreadError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                ThreadError::Pool(e) => write!(f, "pool error: {e}"),
+                ThreadError::Database(e)=> write!(f, "database error: {e}"),
+            }
+        }
+    }
+    
+    fn to_server_error(e: ThreadError) -> ServerFnError {
+        ServerFnError::ServerError(e.to_string())
     }
     
     let app_state = use_context::<AppState>()
@@ -1126,14 +1132,18 @@ pub async fn delete_thread(thread_id: String) -> Result<(), ServerFnError> {
         .map_err(|e| ThreadError::Pool(e.to_string()))
         .map_err(to_server_error)?;
     
+    let actor_id = get_current_user().await.ok().flatten().map(|user| user.id);
+    let deleted_id = thread_id.clone();
     conn.transaction(|conn| {
         Box::pin(async move {
-            delete_thread_recursive(conn, &thread_id).await
+            delete_thread_recursive(conn, &deleted_id).await
         })
     })
     .await
     .map_err(ThreadError::Database)
     .map_err(to_server_error)?;
+
+    audit::record(&mut conn, actor_id, AuditEvent::new(AUDIT_THREAD_DELETED).target("thread", thread_id), audit::request_ip()).await;
     
     Ok(())
 }
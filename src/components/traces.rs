@@ -0,0 +1,125 @@
+use leptos::prelude::*;
+use uuid::Uuid;
+
+use crate::models::traces::{RetrievalTraceView, TracedDocument};
+use crate::server_fn::projects::get_retrieval_trace;
+
+/// "Why this answer": the chunks retrieval ranked for a project answer, their
+/// scores and the strategy settings, for debugging retrieval quality
+#[component]
+pub fn RetrievalTrace(trace_id: Uuid) -> impl IntoView {
+    let (open, set_open) = signal(false);
+
+    let trace_resource = Resource::new(
+        move || open.get().then_some(trace_id),
+        |trace_id| async move {
+            match trace_id {
+                Some(trace_id) => get_retrieval_trace(trace_id).await.map(Some).map_err(|e| e.to_string()),
+                None => Ok(None),
+            }
+        }
+    );
+
+    view! {
+        <div class="mt-2">
+            <button
+                type="button"
+                class="text-xs text-themed-secondary hover:text-themed-primary underline"
+                on:click=move |_| set_open.update(|open| *open = !*open)
+            >
+                {move || if open.get() { "Hide retrieval trace" } else { "Why this answer?" }}
+            </button>
+
+            {move || open.get().then(|| view! {
+                <Transition fallback=|| view! { <div class="loading-themed text-xs">"Loading trace..."</div> }>
+                    {move || match trace_resource.get() {
+                        Some(Ok(Some(trace))) => view! { <TraceDetails trace=trace/> }.into_any(),
+                        Some(Err(e)) => view! {
+                            <p class="error-themed text-xs">"Error loading trace: " {e}</p>
+                        }.into_any(),
+                        _ => view! { <div></div> }.into_any(),
+                    }}
+                </Transition>
+            })}
+        </div>
+    }
+}
+
+#[component]
+fn TraceDetails(trace: RetrievalTraceView) -> impl IntoView {
+    let strategy: Vec<(String, String)> = trace.strategy
+        .as_object()
+        .map(|settings| settings.iter().map(|(name, value)| (name.clone(), value.to_string())).collect())
+        .unwrap_or_default();
+
+    view! {
+        <div class="mt-2 surface-secondary rounded p-3 text-xs text-themed-primary space-y-3">
+            <div>
+                <p class="text-themed-secondary">"Query"</p>
+                <p class="font-mono whitespace-pre-wrap">{trace.query}</p>
+            </div>
+
+            <div class="flex flex-wrap gap-x-4 gap-y-1">
+                <span>{format!("Context: {} tokens", trace.context_tokens)}</span>
+                <span>{format!("History: {} tokens", trace.history_tokens)}</span>
+                <span>{format!("Tokenizer: {}", trace.tokenizer)}</span>
+                <span>{format!("Reranker: {}", trace.reranker.unwrap_or_else(|| "none".to_string()))}</span>
+            </div>
+
+            {if trace.documents.is_empty() {
+                view! { <p class="text-themed-secondary">"No chunks were retrieved for this query."</p> }.into_any()
+            } else {
+                view! {
+                    <table class="w-full text-left">
+                        <thead class="text-themed-secondary">
+                            <tr>
+                                <th class="pr-2">"#"</th>
+                                <th class="pr-2">"Document"</th>
+                                <th class="pr-2">"Chunk"</th>
+                                <th class="pr-2">"Similarity"</th>
+                                <th class="pr-2">"Fused"</th>
+                                <th>"Rerank"</th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                            {trace.documents.into_iter().map(trace_rows).collect_view()}
+                        </tbody>
+                    </table>
+                }.into_any()
+            }}
+
+            <details>
+                <summary class="cursor-pointer text-themed-secondary">"Strategy"</summary>
+                <dl class="grid grid-cols-2 gap-x-4 mt-1 font-mono">
+                    {strategy.into_iter().map(|(name, value)| view! {
+                        <dt class="text-themed-secondary">{name}</dt>
+                        <dd>{value}</dd>
+                    }).collect_view()}
+                </dl>
+            </details>
+        </div>
+    }
+}
+
+fn trace_rows(document: TracedDocument) -> impl IntoView {
+    let filename = document.filename;
+    let version = (document.document_version > 1).then(|| format!(" v{}", document.document_version));
+    let summary = format!("priority {:.2}, {} tokens", document.priority_score, document.token_count);
+
+    document.chunks.into_iter().map(|chunk| {
+        let chunk_label = match chunk.location {
+            Some(location) => format!("{} ({})", chunk.chunk_index + 1, location),
+            None => (chunk.chunk_index + 1).to_string(),
+        };
+        view! {
+            <tr class="border-t border-gray-200 dark:border-teal-700">
+                <td class="pr-2">{format!("[{}]", chunk.source_number)}</td>
+                <td class="pr-2 font-mono" title=summary.clone()>{filename.clone()}{version.clone()}</td>
+                <td class="pr-2">{chunk_label}</td>
+                <td class="pr-2">{format!("{:.3}", chunk.similarity)}</td>
+                <td class="pr-2">{format!("{:.3}", chunk.fused_score)}</td>
+                <td>{chunk.rerank_score.map(|score| format!("{:.3}", score)).unwrap_or_else(|| "-".to_string())}</td>
+            </tr>
+        }
+    }).collect_view()
+}
@@ -0,0 +1,411 @@
+use leptos::prelude::*;
+use leptos_fetch::QueryClient;
+use uuid::Uuid;
+
+use crate::auth::context::AuthContext;
+use crate::components::projects::get_user_projects_query;
+use crate::models::workspaces::*;
+use crate::pages::writersroom::ThreadContext;
+use crate::server_fn::workspaces::*;
+
+pub async fn get_workspaces_query() -> Result<Vec<WorkspaceView>, String> {
+    get_workspaces().await.map_err(|e| e.to_string())
+}
+
+const SELECT_CLASS: &str = "px-2 py-1 text-sm border border-gray-300 dark:border-teal-600 rounded-md dark:bg-teal-700 dark:text-gray-200";
+
+fn visibility_label(visibility: &str) -> &'static str {
+    match visibility {
+        THREAD_WORKSPACE => "Shared",
+        THREAD_COLLABORATIVE => "Collaborative",
+        _ => "Private",
+    }
+}
+
+fn role_options(selected: WorkspaceRole) -> impl IntoView {
+    WorkspaceRole::ALL
+        .into_iter()
+        .map(|role| view! { <option value=role.as_str() selected={role == selected}>{role.as_str()}</option> })
+        .collect_view()
+}
+
+/// Workspaces the user belongs to, with a form to start a new one
+#[component]
+pub fn Workspaces() -> impl IntoView {
+    let client: QueryClient = expect_context();
+    let create_action = ServerAction::<CreateWorkspace>::new();
+    let workspaces = client.resource(get_workspaces_query, || ());
+    let (name, set_name) = signal(String::new());
+    let (open, set_open) = signal(None::<Uuid>);
+
+    Effect::new(move |_| {
+        if let Some(Ok(workspace)) = create_action.value().get() {
+            client.invalidate_query(get_workspaces_query, ());
+            set_open.set(Some(workspace.id));
+        }
+    });
+
+    view! {
+        <div class="mt-6 bg-white dark:bg-teal-800 rounded-lg shadow-md p-6">
+            <h3 class="text-lg font-semibold text-gray-800 dark:text-gray-200 mb-4">
+                "Workspaces"
+            </h3>
+            <form
+                class="flex gap-2 mb-4"
+                on:submit=move |ev| {
+                    ev.prevent_default();
+                    create_action.dispatch(CreateWorkspace { name: name.get_untracked() });
+                    set_name.set(String::new());
+                }
+            >
+                <input
+                    type="text"
+                    placeholder="Workspace name, e.g. Docs team"
+                    prop:value=name
+                    on:input=move |ev| set_name.set(event_target_value(&ev))
+                    class="flex-1 px-3 py-1 text-sm border border-gray-300 dark:border-teal-600 rounded-md dark:bg-teal-700 dark:text-gray-200"
+                />
+                <button
+                    type="submit"
+                    disabled=move || create_action.pending().get() || name.get().trim().is_empty()
+                    class="px-3 py-1 text-sm bg-seafoam-600 hover:bg-seafoam-700 text-white rounded-md transition-colors"
+                >
+                    "Create"
+                </button>
+            </form>
+            {move || create_action.value().get().and_then(|result| result.err()).map(|e| view! {
+                <p class="mb-2 text-sm text-red-500">{format!("Could not create workspace: {e}")}</p>
+            })}
+            <Transition fallback=|| view! { <div class="text-sm text-gray-500">"Loading workspaces..."</div> }>
+                {move || workspaces.get().map(|result| match result {
+                    Ok(list) if list.is_empty() => view! {
+                        <p class="text-sm text-gray-500 dark:text-gray-400">
+                            "You aren't in any workspace yet. Projects in a workspace are shared with its members."
+                        </p>
+                    }.into_any(),
+                    Ok(list) => view! {
+                        <ul class="divide-y divide-gray-200 dark:divide-teal-700">
+                            {list.into_iter().map(|workspace| {
+                                let workspace_id = workspace.id;
+                                let expanded = move || open.get() == Some(workspace_id);
+                                let name = workspace.name.clone();
+                                let plural = if workspace.member_count == 1 { "" } else { "s" };
+                                let summary = format!("{} · {} member{plural}", workspace.role, workspace.member_count);
+                                view! {
+                                    <li class="py-3 text-sm">
+                                        <button
+                                            class="w-full flex justify-between items-center text-left"
+                                            on:click=move |_| set_open.update(|open| {
+                                                *open = if *open == Some(workspace_id) { None } else { Some(workspace_id) };
+                                            })
+                                        >
+                                            <span class="font-medium text-gray-800 dark:text-gray-200">{name}</span>
+                                            <span class="text-gray-500 dark:text-gray-400">{summary}</span>
+                                        </button>
+                                        {move || expanded().then(|| view! { <WorkspaceMembers workspace=workspace.clone()/> })}
+                                    </li>
+                                }
+                            }).collect_view()}
+                        </ul>
+                    }.into_any(),
+                    Err(e) => view! {
+                        <p class="text-sm text-red-500">{format!("Could not load workspaces: {e}")}</p>
+                    }.into_any(),
+                })}
+            </Transition>
+        </div>
+    }.into_any()
+}
+
+/// Members of one workspace; owners can add, re-role and remove them
+#[component]
+fn WorkspaceMembers(workspace: WorkspaceView) -> impl IntoView {
+    let client: QueryClient = expect_context();
+    let workspace_id = workspace.id;
+    let is_owner = workspace.role == WorkspaceRole::Owner;
+
+    let add_action = ServerAction::<AddWorkspaceMember>::new();
+    let role_action = ServerAction::<UpdateWorkspaceMemberRole>::new();
+    let remove_action = ServerAction::<RemoveWorkspaceMember>::new();
+    let delete_action = ServerAction::<DeleteWorkspace>::new();
+    let members = Resource::new(
+        move || (add_action.version().get(), role_action.version().get(), remove_action.version().get()),
+        move |_| get_workspace_members(workspace_id),
+    );
+    let (email, set_email) = signal(String::new());
+    let (role, set_role) = signal(WorkspaceRole::Editor);
+
+    // member counts and the user's own role show in the workspace list; leaving
+    // or deleting also changes which projects are visible
+    Effect::new(move |_| {
+        let left = matches!(remove_action.value().get(), Some(Ok(())));
+        let deleted = matches!(delete_action.value().get(), Some(Ok(())));
+        let changed = matches!(add_action.value().get(), Some(Ok(_))) || matches!(role_action.value().get(), Some(Ok(())));
+        if left || deleted || changed {
+            client.invalidate_query(get_workspaces_query, ());
+        }
+        if left || deleted {
+            client.invalidate_query(get_user_projects_query, ());
+        }
+    });
+
+    let error = move || {
+        add_action.value().get().and_then(|r| r.err())
+            .or_else(|| role_action.value().get().and_then(|r| r.err()))
+            .or_else(|| remove_action.value().get().and_then(|r| r.err()))
+            .or_else(|| delete_action.value().get().and_then(|r| r.err()))
+    };
+
+    view! {
+        <div class="mt-3 pl-3 border-l-2 border-gray-200 dark:border-teal-700 space-y-3">
+            <Suspense fallback=|| view! { <div class="text-gray-500">"Loading members..."</div> }>
+                {move || members.get().map(|result| match result {
+                    Ok(list) => view! {
+                        <ul class="space-y-2">
+                            {list.into_iter().map(|member| {
+                                let member_id = member.user_id;
+                                view! {
+                                    <li class="flex justify-between items-center gap-2">
+                                        <div class="min-w-0">
+                                            <div class="font-medium text-gray-800 dark:text-gray-200 truncate">{member.name}</div>
+                                            <div class="text-xs text-gray-500 dark:text-gray-400 truncate">
+                                                {member.email.unwrap_or_default()}
+                                                " · joined "
+                                                {member.joined_at.format("%Y-%m-%d").to_string()}
+                                            </div>
+                                        </div>
+                                        <div class="flex items-center gap-2">
+                                            {if is_owner {
+                                                view! {
+                                                    <select
+                                                        class=SELECT_CLASS
+                                                        on:change=move |ev| {
+                                                            if let Some(role) = WorkspaceRole::parse(&event_target_value(&ev)) {
+                                                                role_action.dispatch(UpdateWorkspaceMemberRole { workspace_id, member_id, role });
+                                                            }
+                                                        }
+                                                    >
+                                                        {role_options(member.role)}
+                                                    </select>
+                                                    <button
+                                                        on:click=move |_| {
+                                                            remove_action.dispatch(RemoveWorkspaceMember { workspace_id, member_id });
+                                                        }
+                                                        class="px-2 py-1 text-xs border border-gray-300 dark:border-teal-600 text-gray-600 dark:text-gray-300 rounded-md hover:bg-gray-100 dark:hover:bg-teal-700"
+                                                    >
+                                                        "Remove"
+                                                    </button>
+                                                }.into_any()
+                                            } else {
+                                                view! { <span class="text-xs text-gray-500 dark:text-gray-400">{member.role.as_str()}</span> }.into_any()
+                                            }}
+                                        </div>
+                                    </li>
+                                }
+                            }).collect_view()}
+                        </ul>
+                    }.into_any(),
+                    Err(e) => view! {
+                        <p class="text-red-500">{format!("Could not load members: {e}")}</p>
+                    }.into_any(),
+                })}
+            </Suspense>
+
+            {is_owner.then(|| view! {
+                <form
+                    class="flex gap-2"
+                    on:submit=move |ev| {
+                        ev.prevent_default();
+                        add_action.dispatch(AddWorkspaceMember {
+                            workspace_id,
+                            email: email.get_untracked(),
+                            role: role.get_untracked(),
+                        });
+                        set_email.set(String::new());
+                    }
+                >
+                    <input
+                        type="email"
+                        placeholder="Email of someone who has signed in"
+                        prop:value=email
+                        on:input=move |ev| set_email.set(event_target_value(&ev))
+                        class="flex-1 px-3 py-1 text-sm border border-gray-300 dark:border-teal-600 rounded-md dark:bg-teal-700 dark:text-gray-200"
+                    />
+                    <select
+                        class=SELECT_CLASS
+                        on:change=move |ev| {
+                            if let Some(role) = WorkspaceRole::parse(&event_target_value(&ev)) {
+                                set_role.set(role);
+                            }
+                        }
+                    >
+                        {role_options(WorkspaceRole::Editor)}
+                    </select>
+                    <button
+                        type="submit"
+                        disabled=move || add_action.pending().get() || email.get().trim().is_empty()
+                        class="px-3 py-1 text-sm bg-seafoam-600 hover:bg-seafoam-700 text-white rounded-md transition-colors"
+                    >
+                        "Add"
+                    </button>
+                </form>
+            })}
+
+            {move || error().map(|e| view! { <p class="text-sm text-red-500">{e.to_string()}</p> })}
+
+            <div class="flex justify-end gap-2">
+                {if is_owner {
+                    view! {
+                        <button
+                            on:click=move |_| {
+                                delete_action.dispatch(DeleteWorkspace { workspace_id });
+                            }
+                            class="px-3 py-1 text-xs bg-salmon-600 hover:bg-salmon-700 text-white rounded-md transition-colors"
+                        >
+                            "Delete workspace"
+                        </button>
+                    }.into_any()
+                } else {
+                    view! {
+                        <LeaveWorkspaceButton workspace_id=workspace_id remove_action=remove_action/>
+                    }.into_any()
+                }}
+            </div>
+        </div>
+    }.into_any()
+}
+
+#[component]
+fn LeaveWorkspaceButton(workspace_id: Uuid, remove_action: ServerAction<RemoveWorkspaceMember>) -> impl IntoView {
+    let current_user = use_context::<AuthContext>().map(|auth| auth.current_user);
+    let user_id = move || current_user.and_then(|user| user.get()).map(|user| user.id);
+
+    view! {
+        <button
+            disabled=move || user_id().is_none()
+            on:click=move |_| {
+                if let Some(member_id) = user_id() {
+                    remove_action.dispatch(RemoveWorkspaceMember { workspace_id, member_id });
+                }
+            }
+            class="px-3 py-1 text-xs border border-gray-300 dark:border-teal-600 text-gray-600 dark:text-gray-300 rounded-md hover:bg-gray-100 dark:hover:bg-teal-700"
+        >
+            "Leave workspace"
+        </button>
+    }.into_any()
+}
+
+/// Which workspace a project lives in, and the threads in it that the user can open
+#[component]
+pub fn ProjectSharing(project_id: Uuid, workspace_id: Option<Uuid>) -> impl IntoView {
+    let client: QueryClient = expect_context();
+    let workspaces = client.resource(get_workspaces_query, || ());
+    let move_action = ServerAction::<MoveProjectToWorkspace>::new();
+    let visibility_action = ServerAction::<SetThreadVisibility>::new();
+    let threads = Resource::new(
+        move || (visibility_action.version().get(), move_action.version().get()),
+        move |_| get_project_threads(project_id),
+    );
+
+    Effect::new(move |_| {
+        if let Some(Ok(_)) = move_action.value().get() {
+            client.invalidate_query(get_user_projects_query, ());
+        }
+    });
+
+    let thread_setters = use_context::<ThreadContext>()
+        .map(|context| (context.set_thread_id, context.set_message_refetch_trigger));
+    let open_thread = move |thread_id: String| {
+        if let Some((set_thread_id, set_message_refetch_trigger)) = thread_setters {
+            set_thread_id.set(thread_id);
+            set_message_refetch_trigger.update(|n| *n += 1);
+        }
+    };
+
+    view! {
+        <div class="mb-4 text-sm space-y-3">
+            <div class="flex items-center justify-between">
+                <label class="text-themed-secondary">"Workspace"</label>
+                <Transition fallback=|| ()>
+                    {move || workspaces.get().and_then(Result::ok).map(|list| {
+                        // a project can only move into workspaces where the user may edit
+                        let targets: Vec<_> = list.into_iter().filter(|w| w.role.allows(Permission::Edit)).collect();
+                        view! {
+                            <select
+                                class="input-themed text-sm"
+                                disabled=move || move_action.pending().get()
+                                on:change=move |ev| {
+                                    let value = event_target_value(&ev);
+                                    move_action.dispatch(MoveProjectToWorkspace {
+                                        project_id,
+                                        workspace_id: Uuid::parse_str(&value).ok(),
+                                    });
+                                }
+                            >
+                                <option value="" selected=workspace_id.is_none()>"Personal"</option>
+                                {targets.into_iter().map(|w| view! {
+                                    <option value=w.id.to_string() selected={workspace_id == Some(w.id)}>{w.name}</option>
+                                }).collect_view()}
+                            </select>
+                        }
+                    })}
+                </Transition>
+            </div>
+            {move || move_action.value().get().and_then(|r| r.err()).map(|e| view! {
+                <div class="error-themed">{e.to_string()}</div>
+            })}
+
+            <Transition fallback=|| ()>
+                {move || threads.get().and_then(Result::ok).filter(|list| !list.is_empty()).map(|list| view! {
+                    <div class="space-y-1">
+                        <div class="text-themed-secondary">"Threads"</div>
+                        {list.into_iter().map(|thread| {
+                            let thread_id = thread.id.clone();
+                            let visibility = thread.visibility.clone();
+                            view! {
+                                <div class="flex items-center justify-between surface-secondary px-3 py-2 rounded border-themed">
+                                    <button
+                                        class="text-left text-themed-primary truncate"
+                                        on:click={
+                                            let thread_id = thread_id.clone();
+                                            move |_| open_thread(thread_id.clone())
+                                        }
+                                    >
+                                        {thread.title.clone().unwrap_or_else(|| "Untitled".to_string())}
+                                        {(!thread.own).then(|| view! {
+                                            <span class="ml-2 text-xs text-themed-secondary">{format!("by {}", thread.author)}</span>
+                                        })}
+                                    </button>
+                                    {if thread.own && workspace_id.is_some() {
+                                        view! {
+                                            <select
+                                                class="input-themed text-xs"
+                                                on:change=move |ev| {
+                                                    visibility_action.dispatch(SetThreadVisibility {
+                                                        thread_id: thread_id.clone(),
+                                                        visibility: event_target_value(&ev),
+                                                    });
+                                                }
+                                            >
+                                                {THREAD_VISIBILITIES.into_iter().map(|option| view! {
+                                                    <option value=option selected={visibility == option}>{visibility_label(option)}</option>
+                                                }).collect_view()}
+                                            </select>
+                                        }.into_any()
+                                    } else if visibility != THREAD_PRIVATE {
+                                        view! { <span class="text-xs text-themed-secondary">{visibility_label(&visibility)}</span> }.into_any()
+                                    } else {
+                                        view! { <span></span> }.into_any()
+                                    }}
+                                </div>
+                            }
+                        }).collect_view()}
+                    </div>
+                })}
+            </Transition>
+            {move || visibility_action.value().get().and_then(|r| r.err()).map(|e| view! {
+                <div class="error-themed">{e.to_string()}</div>
+            })}
+        </div>
+    }.into_any()
+}
@@ -4,9 +4,10 @@ cfg_if! {
     if #[cfg(feature = "ssr")] {
         use diesel_async::AsyncPgConnection;
         use diesel_async::pooled_connection::AsyncDieselConnectionManager;
-        use diesel_async::pooled_connection::deadpool::Pool;
+        use diesel_async::pooled_connection::deadpool::{Object, Pool};
 
         pub type DbPool = Pool<AsyncPgConnection>;
+        pub type DbConnection = Object<AsyncPgConnection>;
 
         pub fn establish_connection(database_url: &str) -> Result<DbPool, Box<dyn std::error::Error>> {
             let config = AsyncDieselConnectionManager::<AsyncPgConnection>::new(database_url);
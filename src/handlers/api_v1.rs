@@ -0,0 +1,437 @@
+//! Versioned JSON API for scripts and CI, authenticated with personal access
+//! tokens. See `docs/api.md`.
+
+use axum::{
+    extract::{ConnectInfo, Extension, Multipart, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    middleware,
+    response::{sse::{Event, Sse}, IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use futures::stream::{self, Stream};
+use log::{debug, error};
+use serde::Deserialize;
+use serde_json::json;
+use std::convert::Infallible;
+use std::fmt;
+use std::net::SocketAddr;
+use uuid::Uuid;
+
+use crate::auth::api_tokens::{
+    require_api_token, ApiPrincipal, SCOPE_PROJECTS_READ, SCOPE_PROJECTS_WRITE, SCOPE_THREADS_READ,
+    SCOPE_THREADS_WRITE,
+};
+use crate::auth::sessions::client_ip;
+use crate::auth::Claims;
+use crate::components::chat::check_increment_rate_limit;
+use crate::components::threadlist::delete_thread_recursive;
+use crate::handlers::uploads::upload_document_handler;
+use crate::models::audit::{AUDIT_DOCUMENT_DELETED, AUDIT_THREAD_DELETED};
+use crate::models::conversations::{Message, MessageView, NewMessage, NewMessageView, Thread, ThreadView};
+use crate::models::projects::{DocumentContentView, ProjectDocument, ProjectDocumentView, ProjectView};
+use crate::models::workspaces::{Permission, THREAD_PRIVATE};
+use crate::schema::{messages, project_documents, threads};
+use crate::services::audit::{self, AuditEvent};
+use crate::services::completions::{start_completion, CompletionEvent, CompletionRequest};
+use crate::services::projects::document_content;
+use crate::services::uploads::UploadLimits;
+use crate::services::workspaces::{authorize_project, own_thread, visible_projects, AccessError};
+use crate::state::AppState;
+
+const LABS: [&str; 2] = ["anthropic", "openai"];
+
+#[derive(Debug)]
+pub enum ApiError {
+    MissingScope(&'static str),
+    Forbidden(String),
+    NotFound(&'static str),
+    BadRequest(String),
+    RateLimited,
+    Internal(String),
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::MissingScope(scope) => write!(f, "Token lacks the {scope} scope"),
+            ApiError::Forbidden(e) => write!(f, "{e}"),
+            ApiError::NotFound(what) => write!(f, "{what} not found"),
+            ApiError::BadRequest(e) => write!(f, "{e}"),
+            ApiError::RateLimited => write!(f, "Daily message limit reached. Try again tomorrow!"),
+            ApiError::Internal(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl From<diesel::result::Error> for ApiError {
+    fn from(e: diesel::result::Error) -> Self {
+        ApiError::Internal(format!("Database error: {e}"))
+    }
+}
+
+impl From<AccessError> for ApiError {
+    fn from(e: AccessError) -> Self {
+        match e {
+            AccessError::NotFound(what) => ApiError::NotFound(what),
+            e @ AccessError::Forbidden(_) => ApiError::Forbidden(e.to_string()),
+            AccessError::Database(e) => e.into(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            ApiError::MissingScope(_) | ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::Internal(e) => {
+                error!("API request failed: {e}");
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        };
+        (status, Json(json!({ "error": self.to_string() }))).into_response()
+    }
+}
+
+type ApiResult<T> = Result<T, ApiError>;
+
+/// Routes under `/api/v1`, all behind `require_api_token`
+pub fn api_v1_routes(app_state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/api/v1/threads", get(list_threads).post(create_thread))
+        .route("/api/v1/threads/{thread_id}", get(get_thread).delete(delete_thread))
+        .route("/api/v1/threads/{thread_id}/messages", get(list_messages).post(send_message))
+        .route("/api/v1/projects", get(list_projects))
+        .route(
+            "/api/v1/projects/{project_id}/documents",
+            get(list_documents).post(upload_document).layer(axum::extract::DefaultBodyLimit::max(
+                UploadLimits::from_env().max_file_bytes as usize + 64 * 1024,
+            )),
+        )
+        .route("/api/v1/documents/{document_id}", get(get_document).delete(delete_document))
+        .layer(middleware::from_fn_with_state(app_state, require_api_token))
+}
+
+fn require(principal: &ApiPrincipal, scope: &'static str) -> ApiResult<()> {
+    if principal.allows(scope) {
+        Ok(())
+    } else {
+        Err(ApiError::MissingScope(scope))
+    }
+}
+
+async fn connection(state: &AppState) -> ApiResult<crate::database::db::DbConnection> {
+    state.pool.get().await.map_err(|e| ApiError::Internal(format!("Database connection error: {e}")))
+}
+
+async fn accessible_document(
+    conn: &mut AsyncPgConnection,
+    user_id: i32,
+    document_id: Uuid,
+    permission: Permission,
+) -> ApiResult<ProjectDocument> {
+    let document: ProjectDocument = project_documents::table
+        .find(document_id)
+        .first(conn)
+        .await
+        .optional()?
+        .ok_or(ApiError::NotFound("Document"))?;
+    match authorize_project(conn, user_id, document.project_id, permission).await {
+        Ok(_) => Ok(document),
+        Err(AccessError::NotFound(_)) => Err(ApiError::NotFound("Document")),
+        Err(e) => Err(e.into()),
+    }
+}
+
+async fn list_threads(
+    State(state): State<AppState>,
+    Extension(principal): Extension<ApiPrincipal>,
+) -> ApiResult<Json<Vec<ThreadView>>> {
+    require(&principal, SCOPE_THREADS_READ)?;
+    let mut conn = connection(&state).await?;
+    let threads: Vec<Thread> = threads::table
+        .filter(threads::user_id.eq(principal.user_id))
+        .order(threads::created_at.desc())
+        .load(&mut conn)
+        .await?;
+    Ok(Json(threads.into_iter().map(ThreadView::from).collect()))
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct CreateThreadBody {
+    title: Option<String>,
+    project_id: Option<Uuid>,
+}
+
+async fn create_thread(
+    State(state): State<AppState>,
+    Extension(principal): Extension<ApiPrincipal>,
+    body: Option<Json<CreateThreadBody>>,
+) -> ApiResult<(StatusCode, Json<ThreadView>)> {
+    require(&principal, SCOPE_THREADS_WRITE)?;
+    let body = body.map(|Json(body)| body).unwrap_or_default();
+    let mut conn = connection(&state).await?;
+
+    if let Some(project_id) = body.project_id {
+        require(&principal, SCOPE_PROJECTS_READ)?;
+        authorize_project(&mut conn, principal.user_id, project_id, Permission::Read).await?;
+    }
+
+    let now = Utc::now().naive_utc();
+    let thread: Thread = diesel::insert_into(threads::table)
+        .values(&Thread {
+            id: Uuid::new_v4().to_string(),
+            created_at: Some(now),
+            updated_at: Some(now),
+            user_id: Some(principal.user_id),
+            parent_thread_id: None,
+            branch_point_message_id: None,
+            branch_name: None,
+            title: body.title.map(|t| t.trim().to_string()).filter(|t| !t.is_empty()),
+            project_id: body.project_id,
+            visibility: THREAD_PRIVATE.to_string(),
+        })
+        .get_result(&mut conn)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(thread.into())))
+}
+
+async fn get_thread(
+    State(state): State<AppState>,
+    Extension(principal): Extension<ApiPrincipal>,
+    Path(thread_id): Path<String>,
+) -> ApiResult<Json<ThreadView>> {
+    require(&principal, SCOPE_THREADS_READ)?;
+    let mut conn = connection(&state).await?;
+    Ok(Json(own_thread(&mut conn, principal.user_id, &thread_id, Permission::Read).await?.into()))
+}
+
+async fn delete_thread(
+    State(state): State<AppState>,
+    Extension(principal): Extension<ApiPrincipal>,
+    Path(thread_id): Path<String>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> ApiResult<StatusCode> {
+    use diesel_async::AsyncConnection;
+
+    require(&principal, SCOPE_THREADS_WRITE)?;
+    let mut conn = connection(&state).await?;
+    own_thread(&mut conn, principal.user_id, &thread_id, Permission::Edit).await?;
+    let target_id = thread_id.clone();
+    conn.transaction(|conn| Box::pin(async move { delete_thread_recursive(conn, &thread_id).await }))
+        .await?;
+    let event = AuditEvent::new(AUDIT_THREAD_DELETED).target("thread", target_id);
+    audit::record(&mut conn, Some(principal.user_id), event, client_ip(&headers, Some(remote))).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_messages(
+    State(state): State<AppState>,
+    Extension(principal): Extension<ApiPrincipal>,
+    Path(thread_id): Path<String>,
+) -> ApiResult<Json<Vec<MessageView>>> {
+    require(&principal, SCOPE_THREADS_READ)?;
+    let mut conn = connection(&state).await?;
+    own_thread(&mut conn, principal.user_id, &thread_id, Permission::Read).await?;
+    let messages: Vec<Message> = messages::table
+        .filter(messages::thread_id.eq(&thread_id))
+        .order(messages::id.asc())
+        .load(&mut conn)
+        .await?;
+    Ok(Json(messages.into_iter().map(MessageView::from).collect()))
+}
+
+#[derive(Debug, Deserialize)]
+struct SendMessageBody {
+    content: String,
+    model: String,
+    lab: String,
+    #[serde(default)]
+    stream: bool,
+}
+
+/// Stores the user's message and answers it, either as an SSE stream or as one
+/// JSON response once the answer is complete
+async fn send_message(
+    State(state): State<AppState>,
+    Extension(principal): Extension<ApiPrincipal>,
+    Path(thread_id): Path<String>,
+    Json(body): Json<SendMessageBody>,
+) -> ApiResult<Response> {
+    require(&principal, SCOPE_THREADS_WRITE)?;
+    if body.content.trim().is_empty() {
+        return Err(ApiError::BadRequest("Message content is empty".to_string()));
+    }
+    if !LABS.contains(&body.lab.as_str()) {
+        return Err(ApiError::BadRequest(format!("Unknown lab {:?}; expected one of {LABS:?}", body.lab)));
+    }
+
+    let mut conn = connection(&state).await?;
+    own_thread(&mut conn, principal.user_id, &thread_id, Permission::Edit).await?;
+    if !check_increment_rate_limit(principal.user_id, &mut conn).await? {
+        return Err(ApiError::RateLimited);
+    }
+
+    let question: Message = diesel::insert_into(messages::table)
+        .values(NewMessage::from(NewMessageView {
+            thread_id: thread_id.clone(),
+            content: Some(body.content.clone()),
+            role: "user".to_string(),
+            active_model: body.model.clone(),
+            active_lab: body.lab.clone(),
+            user_id: Some(principal.user_id),
+            citations: Vec::new(),
+            retrieval_trace_id: None,
+        }))
+        .get_result(&mut conn)
+        .await?;
+
+    let user_messages: i64 = messages::table
+        .filter(messages::thread_id.eq(&thread_id))
+        .filter(messages::role.eq("user"))
+        .count()
+        .get_result(&mut conn)
+        .await?;
+    if user_messages == 1 {
+        tokio::spawn(crate::services::title_generation::generate_and_update_title_with_sse(
+            state.clone(),
+            principal.user_id,
+            thread_id.clone(),
+            body.content.clone(),
+        ));
+    }
+
+    debug!("API completion for thread {thread_id} with {}/{}", body.lab, body.model);
+    let mut events = start_completion(
+        state.pool.clone(),
+        CompletionRequest { user_id: principal.user_id, thread_id, model: body.model, lab: body.lab },
+    );
+
+    if body.stream {
+        return Ok(Sse::new(completion_events(events)).into_response());
+    }
+
+    while let Some(event) = events.recv().await {
+        match event {
+            CompletionEvent::Completed(answer) => {
+                let question = MessageView::from(question);
+                return Ok(Json(json!({ "message": question, "answer": answer })).into_response());
+            }
+            CompletionEvent::Error(e) => return Err(ApiError::Internal(e)),
+            CompletionEvent::Cancelled => return Err(ApiError::Internal("Completion was cancelled".to_string())),
+            _ => {}
+        }
+    }
+    Err(ApiError::Internal("Completion ended without an answer".to_string()))
+}
+
+/// Named SSE events: `status`, `citations`, `delta`, then `done` with the stored
+/// answer, or `error`
+fn completion_events(
+    events: tokio::sync::mpsc::Receiver<CompletionEvent>,
+) -> impl Stream<Item = Result<Event, Infallible>> {
+    stream::unfold(events, |mut events| async move {
+        let event = match events.recv().await? {
+            CompletionEvent::Status(status) => Event::default().event("status").data(status),
+            CompletionEvent::Citations { citations, trace_id } => Event::default()
+                .event("citations")
+                .data(json!({ "citations": citations, "trace_id": trace_id }).to_string()),
+            CompletionEvent::Delta(text) => Event::default().event("delta").data(json!({ "content": text }).to_string()),
+            CompletionEvent::Completed(answer) => {
+                Event::default().event("done").data(serde_json::to_string(&answer).unwrap_or_default())
+            }
+            CompletionEvent::Error(e) => Event::default().event("error").data(json!({ "error": e }).to_string()),
+            CompletionEvent::Done | CompletionEvent::Cancelled => {
+                Event::default().event("error").data(json!({ "error": "Completion was cancelled" }).to_string())
+            }
+        };
+        Some((Ok(event), events))
+    })
+}
+
+async fn list_projects(
+    State(state): State<AppState>,
+    Extension(principal): Extension<ApiPrincipal>,
+) -> ApiResult<Json<Vec<ProjectView>>> {
+    require(&principal, SCOPE_PROJECTS_READ)?;
+    let mut conn = connection(&state).await?;
+    let projects = visible_projects(&mut conn, principal.user_id).await?;
+    Ok(Json(projects.into_iter().map(ProjectView::from).collect()))
+}
+
+async fn list_documents(
+    State(state): State<AppState>,
+    Extension(principal): Extension<ApiPrincipal>,
+    Path(project_id): Path<Uuid>,
+) -> ApiResult<Json<Vec<ProjectDocumentView>>> {
+    require(&principal, SCOPE_PROJECTS_READ)?;
+    let mut conn = connection(&state).await?;
+    authorize_project(&mut conn, principal.user_id, project_id, Permission::Read).await?;
+    let documents: Vec<ProjectDocument> = project_documents::table
+        .filter(project_documents::project_id.eq(project_id))
+        .order(project_documents::created_at.desc())
+        .load(&mut conn)
+        .await?;
+    Ok(Json(documents.into_iter().map(ProjectDocumentView::from).collect()))
+}
+
+/// Same multipart upload as `/api/projects/{project_id}/documents`
+async fn upload_document(
+    state: State<AppState>,
+    Extension(principal): Extension<ApiPrincipal>,
+    claims: Extension<Claims>,
+    project_id: Path<Uuid>,
+    remote: ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    multipart: Multipart,
+) -> Response {
+    if let Err(e) = require(&principal, SCOPE_PROJECTS_WRITE) {
+        return e.into_response();
+    }
+    match upload_document_handler(state, claims, project_id, remote, headers, multipart).await {
+        Ok(document) => (StatusCode::CREATED, document).into_response(),
+        Err(response) => response,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DocumentQuery {
+    version: Option<i32>,
+}
+
+async fn get_document(
+    State(state): State<AppState>,
+    Extension(principal): Extension<ApiPrincipal>,
+    Path(document_id): Path<Uuid>,
+    Query(query): Query<DocumentQuery>,
+) -> ApiResult<Json<DocumentContentView>> {
+    require(&principal, SCOPE_PROJECTS_READ)?;
+    let mut conn = connection(&state).await?;
+    Ok(Json(document_content(&mut conn, principal.user_id, document_id, query.version).await?))
+}
+
+async fn delete_document(
+    State(state): State<AppState>,
+    Extension(principal): Extension<ApiPrincipal>,
+    Path(document_id): Path<Uuid>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> ApiResult<StatusCode> {
+    require(&principal, SCOPE_PROJECTS_WRITE)?;
+    let mut conn = connection(&state).await?;
+    let document = accessible_document(&mut conn, principal.user_id, document_id, Permission::Edit).await?;
+    diesel::delete(project_documents::table.find(document.id)).execute(&mut conn).await?;
+    let event = AuditEvent::new(AUDIT_DOCUMENT_DELETED)
+        .target("document", document.id)
+        .details(json!({ "filename": document.filename, "project_id": document.project_id }));
+    audit::record(&mut conn, Some(principal.user_id), event, client_ip(&headers, Some(remote))).await;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,57 @@
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    response::sse::{KeepAlive, Sse},
+};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use log::debug;
+
+use crate::{
+    auth::Claims,
+    models::users::User,
+    schema::users,
+    services::collab::RoomStream,
+    services::workspaces::{readable_thread, AccessError},
+    state::AppState,
+};
+
+pub(crate) fn access_status(error: &AccessError) -> StatusCode {
+    match error {
+        AccessError::NotFound(_) => StatusCode::NOT_FOUND,
+        AccessError::Forbidden(_) => StatusCode::FORBIDDEN,
+        AccessError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// How a user is shown to the others in a room
+pub(crate) async fn display_name(conn: &mut AsyncPgConnection, user_id: i32) -> String {
+    users::table
+        .find(user_id)
+        .first::<User>(conn)
+        .await
+        .ok()
+        .and_then(|user| user.display_name.or(user.username))
+        .unwrap_or_else(|| "Anonymous".to_string())
+}
+
+/// `GET /api/threads/{thread_id}/events`: presence, new messages and mirrored
+/// assistant streams for everyone who can read the thread
+pub async fn thread_events_handler(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(thread_id): Path<String>,
+) -> Result<Sse<RoomStream>, StatusCode> {
+    let user_id = claims.user_id()
+        .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    let mut conn = state.pool.get().await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    readable_thread(&mut conn, user_id, &thread_id).await
+        .map_err(|e| access_status(&e))?;
+    let name = display_name(&mut conn, user_id).await;
+
+    debug!("User {user_id} joined the room for thread {thread_id}");
+
+    Ok(Sse::new(state.thread_rooms.join(&thread_id, user_id, name)).keep_alive(KeepAlive::default()))
+}
@@ -1,4 +1,14 @@
 #[cfg(feature = "ssr")]
+pub mod api_v1;
+#[cfg(feature = "ssr")]
+pub mod collab;
+#[cfg(feature = "ssr")]
+pub mod openai_compat;
+#[cfg(feature = "ssr")]
 pub mod sse;
 #[cfg(feature = "ssr")]
+pub mod uploads;
+#[cfg(feature = "ssr")]
 pub use sse::*;
+#[cfg(feature = "ssr")]
+pub use uploads::*;
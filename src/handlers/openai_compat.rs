@@ -0,0 +1,510 @@
+//! OpenAI-compatible `/v1/chat/completions` and `/v1/models`, so tools that
+//! speak the OpenAI API can use l3chat with a personal access token.
+//!
+//! Every request is logged as a thread, goes through the same daily quota as
+//! the chat page and can pull in a project's documents through the `l3chat`
+//! extension field. See `docs/api.md`.
+
+use axum::{
+    extract::{Extension, State},
+    http::{HeaderName, HeaderValue, StatusCode},
+    middleware,
+    response::{sse::{Event, Sse}, IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use futures::stream::{self, Stream, StreamExt};
+use log::{debug, error};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::fmt;
+use uuid::Uuid;
+
+use crate::auth::api_tokens::{require_api_token, ApiPrincipal, SCOPE_PROJECTS_READ, SCOPE_THREADS_WRITE};
+use crate::components::chat::{check_increment_rate_limit, lab_for_model, CHAT_MODELS};
+use crate::models::conversations::{DocumentCitation, MessageView, NewMessage, NewMessageView, Thread};
+use crate::models::workspaces::{Permission, THREAD_PRIVATE};
+use crate::schema::{messages, threads};
+use crate::services::completions::{start_completion, CompletionEvent, CompletionRequest};
+use crate::services::tokens::token_counter_for_model;
+use crate::services::workspaces::{authorize_project, own_thread, AccessError};
+use crate::state::AppState;
+
+const THREAD_HEADER: &str = "x-l3chat-thread-id";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatCompletionRequest {
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    #[serde(default)]
+    pub stream: bool,
+    /// l3chat extension; ignored by other servers
+    #[serde(default)]
+    pub l3chat: CompletionOptions,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CompletionOptions {
+    /// Answer with this project's documents as context
+    pub project_id: Option<Uuid>,
+    /// Continue an existing thread: only the last message is stored, the
+    /// thread's own history is what the model sees
+    pub thread_id: Option<String>,
+    /// Overrides the lab picked from the model name
+    pub lab: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    #[serde(default)]
+    pub content: Option<MessageContent>,
+}
+
+/// A plain string, or the array-of-parts form; only text parts are kept
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ContentPart {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+impl MessageContent {
+    pub fn text(&self) -> String {
+        match self {
+            MessageContent::Text(text) => text.clone(),
+            MessageContent::Parts(parts) => parts
+                .iter()
+                .filter(|part| part.kind == "text")
+                .filter_map(|part| part.text.as_deref())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+}
+
+/// Extra fields returned with every completion
+#[derive(Debug, Clone, Serialize)]
+pub struct CompletionMetadata {
+    pub thread_id: String,
+    pub message_id: i32,
+    pub citations: Vec<DocumentCitation>,
+    pub retrieval_trace_id: Option<Uuid>,
+}
+
+#[derive(Debug)]
+pub enum CompatError {
+    InvalidRequest(String),
+    Forbidden(String),
+    NotFound(&'static str),
+    RateLimited,
+    Upstream(String),
+    Internal(String),
+}
+
+impl fmt::Display for CompatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompatError::InvalidRequest(e) | CompatError::Forbidden(e) => write!(f, "{e}"),
+            CompatError::NotFound(what) => write!(f, "{what} not found"),
+            CompatError::RateLimited => write!(f, "Daily message limit reached. Try again tomorrow!"),
+            CompatError::Upstream(e) => write!(f, "Model request failed: {e}"),
+            CompatError::Internal(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl CompatError {
+    fn kind(&self) -> (StatusCode, &'static str) {
+        match self {
+            CompatError::InvalidRequest(_) => (StatusCode::BAD_REQUEST, "invalid_request_error"),
+            CompatError::Forbidden(_) => (StatusCode::FORBIDDEN, "permission_error"),
+            CompatError::NotFound(_) => (StatusCode::NOT_FOUND, "not_found_error"),
+            CompatError::RateLimited => (StatusCode::TOO_MANY_REQUESTS, "rate_limit_error"),
+            CompatError::Upstream(_) => (StatusCode::BAD_GATEWAY, "api_error"),
+            CompatError::Internal(_) => (StatusCode::INTERNAL_SERVER_ERROR, "api_error"),
+        }
+    }
+
+    /// The OpenAI error object, `{"error": {"message", "type"}}`
+    pub fn body(&self) -> Value {
+        json!({ "error": { "message": self.to_string(), "type": self.kind().1, "code": null } })
+    }
+}
+
+impl From<diesel::result::Error> for CompatError {
+    fn from(e: diesel::result::Error) -> Self {
+        CompatError::Internal(format!("Database error: {e}"))
+    }
+}
+
+impl From<AccessError> for CompatError {
+    fn from(e: AccessError) -> Self {
+        match e {
+            AccessError::NotFound(what) => CompatError::NotFound(what),
+            e @ AccessError::Forbidden(_) => CompatError::Forbidden(e.to_string()),
+            AccessError::Database(e) => e.into(),
+        }
+    }
+}
+
+impl IntoResponse for CompatError {
+    fn into_response(self) -> Response {
+        let (status, _) = self.kind();
+        if let CompatError::Internal(e) = &self {
+            error!("Chat completion failed: {e}");
+        }
+        (status, Json(self.body())).into_response()
+    }
+}
+
+/// `lab` if set, otherwise the lab the chat page would pick for the model.
+/// Accepts `anthropic/<model>` style names too and returns the bare model.
+pub fn route_model<'a>(model: &'a str, lab: Option<&str>) -> Result<(&'a str, String), CompatError> {
+    let (lab, model) = match (lab, model.split_once('/')) {
+        (Some(lab), _) => (lab.to_string(), model),
+        (None, Some((lab, model))) => (lab.to_string(), model),
+        (None, None) => (lab_for_model(model).to_string(), model),
+    };
+    if model.trim().is_empty() {
+        return Err(CompatError::InvalidRequest("model is required".to_string()));
+    }
+    match lab.as_str() {
+        "anthropic" | "openai" => Ok((model, lab)),
+        _ => Err(CompatError::InvalidRequest(format!("Unknown lab {lab:?}"))),
+    }
+}
+
+fn lab_configured(lab: &str) -> bool {
+    let key = match lab {
+        "anthropic" => "ANTHROPIC_API_KEY",
+        _ => "OPENAI_API_KEY",
+    };
+    std::env::var(key).is_ok_and(|v| !v.trim().is_empty())
+}
+
+/// `(role, content)` pairs as stored in a thread; `developer` counts as `system`
+pub fn normalize_messages(messages: &[ChatMessage]) -> Result<Vec<(String, String)>, CompatError> {
+    let normalized = messages
+        .iter()
+        .map(|msg| {
+            let role = match msg.role.as_str() {
+                "system" | "developer" => "system",
+                "user" => "user",
+                "assistant" => "assistant",
+                other => return Err(CompatError::InvalidRequest(format!("Unsupported message role {other:?}"))),
+            };
+            Ok((role.to_string(), msg.content.as_ref().map(MessageContent::text).unwrap_or_default()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match normalized.last() {
+        Some((role, content)) if role == "user" && !content.trim().is_empty() => Ok(normalized),
+        _ => Err(CompatError::InvalidRequest("The last message must be a non-empty user message".to_string())),
+    }
+}
+
+pub fn completion_body(id: &str, created: i64, model: &str, answer: &MessageView, usage: Value, metadata: &CompletionMetadata) -> Value {
+    json!({
+        "id": id,
+        "object": "chat.completion",
+        "created": created,
+        "model": model,
+        "choices": [{
+            "index": 0,
+            "message": { "role": "assistant", "content": answer.content.clone().unwrap_or_default() },
+            "finish_reason": "stop",
+        }],
+        "usage": usage,
+        "l3chat": metadata,
+    })
+}
+
+pub fn chunk_body(id: &str, created: i64, model: &str, delta: Value, finish_reason: Option<&str>) -> Value {
+    json!({
+        "id": id,
+        "object": "chat.completion.chunk",
+        "created": created,
+        "model": model,
+        "choices": [{ "index": 0, "delta": delta, "finish_reason": finish_reason }],
+    })
+}
+
+/// `/v1/chat/completions` and `/v1/models`, behind `require_api_token`
+pub fn openai_compat_routes(app_state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/v1/chat/completions", post(chat_completions))
+        .route("/v1/models", get(list_models))
+        .layer(middleware::from_fn_with_state(app_state, require_api_token))
+}
+
+async fn list_models() -> Json<Value> {
+    let created = Utc::now().timestamp();
+    let models: Vec<Value> = CHAT_MODELS
+        .iter()
+        .map(|(id, _)| json!({ "id": id, "object": "model", "created": created, "owned_by": lab_for_model(id) }))
+        .collect();
+    Json(json!({ "object": "list", "data": models }))
+}
+
+async fn chat_completions(
+    State(state): State<AppState>,
+    Extension(principal): Extension<ApiPrincipal>,
+    Json(request): Json<ChatCompletionRequest>,
+) -> Result<Response, CompatError> {
+    if !principal.allows(SCOPE_THREADS_WRITE) {
+        return Err(CompatError::Forbidden(format!("Token lacks the {SCOPE_THREADS_WRITE} scope")));
+    }
+    let (model, lab) = route_model(&request.model, request.l3chat.lab.as_deref())?;
+    if !lab_configured(&lab) {
+        return Err(CompatError::InvalidRequest(format!("The {lab} lab is not configured on this server")));
+    }
+    let conversation = normalize_messages(&request.messages)?;
+
+    let mut conn = state.pool.get().await.map_err(|e| CompatError::Internal(format!("Database connection error: {e}")))?;
+
+    let (existing_thread, to_store) = match &request.l3chat.thread_id {
+        Some(thread_id) => {
+            let thread = own_thread(&mut conn, principal.user_id, thread_id, Permission::Edit).await?;
+            if thread.project_id.is_some() && !principal.allows(SCOPE_PROJECTS_READ) {
+                return Err(CompatError::Forbidden(format!("Token lacks the {SCOPE_PROJECTS_READ} scope")));
+            }
+            (Some(thread.id), &conversation[conversation.len() - 1..])
+        }
+        None => {
+            if let Some(project_id) = request.l3chat.project_id {
+                if !principal.allows(SCOPE_PROJECTS_READ) {
+                    return Err(CompatError::Forbidden(format!("Token lacks the {SCOPE_PROJECTS_READ} scope")));
+                }
+                authorize_project(&mut conn, principal.user_id, project_id, Permission::Read).await?;
+            }
+            (None, &conversation[..])
+        }
+    };
+
+    // a request over quota leaves nothing behind
+    if !check_increment_rate_limit(principal.user_id, &mut conn).await? {
+        return Err(CompatError::RateLimited);
+    }
+
+    let is_new = existing_thread.is_none();
+    let thread_id = existing_thread.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let new_thread = is_new.then(|| {
+        let now = Utc::now().naive_utc();
+        Thread {
+            id: thread_id.clone(),
+            created_at: Some(now),
+            updated_at: Some(now),
+            user_id: Some(principal.user_id),
+            parent_thread_id: None,
+            branch_point_message_id: None,
+            branch_name: None,
+            title: None,
+            project_id: request.l3chat.project_id,
+            visibility: THREAD_PRIVATE.to_string(),
+        }
+    });
+    let new_messages: Vec<NewMessage> = to_store
+        .iter()
+        .map(|(role, content)| {
+            NewMessage::from(NewMessageView {
+                thread_id: thread_id.clone(),
+                content: Some(content.clone()),
+                role: role.clone(),
+                active_model: model.to_string(),
+                active_lab: lab.clone(),
+                user_id: Some(principal.user_id),
+                citations: Vec::new(),
+                retrieval_trace_id: None,
+            })
+        })
+        .collect();
+    conn.transaction(|conn| {
+        Box::pin(async move {
+            if let Some(thread) = new_thread {
+                diesel::insert_into(threads::table).values(&thread).execute(conn).await?;
+            }
+            diesel::insert_into(messages::table).values(&new_messages).execute(conn).await?;
+            Ok::<_, diesel::result::Error>(())
+        })
+    })
+    .await?;
+
+    if is_new {
+        if let Some((_, first_question)) = conversation.iter().find(|(role, _)| role == "user") {
+            tokio::spawn(crate::services::title_generation::generate_and_update_title_with_sse(
+                state.clone(),
+                principal.user_id,
+                thread_id.clone(),
+                first_question.clone(),
+            ));
+        }
+    }
+
+    let counter = token_counter_for_model(model);
+    let prompt_tokens: usize = conversation.iter().map(|(_, content)| counter.count(content)).sum();
+
+    debug!("Chat completion for thread {thread_id} with {lab}/{model}");
+    let events = start_completion(
+        state.pool.clone(),
+        CompletionRequest { user_id: principal.user_id, thread_id: thread_id.clone(), model: model.to_string(), lab },
+    );
+
+    let id = format!("chatcmpl-{}", Uuid::new_v4().simple());
+    let created = Utc::now().timestamp();
+    let thread_header = HeaderValue::from_str(&thread_id).ok();
+
+    let mut response = if request.stream {
+        Sse::new(completion_chunks(events, id, created, request.model.clone())).into_response()
+    } else {
+        let answer = wait_for_answer(events).await?;
+        let completion_tokens = counter.count(answer.content.as_deref().unwrap_or_default());
+        let usage = json!({
+            "prompt_tokens": prompt_tokens,
+            "completion_tokens": completion_tokens,
+            "total_tokens": prompt_tokens + completion_tokens,
+        });
+        let metadata = CompletionMetadata {
+            thread_id,
+            message_id: answer.id,
+            citations: answer.citations.clone(),
+            retrieval_trace_id: answer.retrieval_trace_id,
+        };
+        Json(completion_body(&id, created, &request.model, &answer, usage, &metadata)).into_response()
+    };
+    if let Some(value) = thread_header {
+        response.headers_mut().insert(HeaderName::from_static(THREAD_HEADER), value);
+    }
+    Ok(response)
+}
+
+async fn wait_for_answer(mut events: tokio::sync::mpsc::Receiver<CompletionEvent>) -> Result<MessageView, CompatError> {
+    while let Some(event) = events.recv().await {
+        match event {
+            CompletionEvent::Completed(answer) => return Ok(answer),
+            CompletionEvent::Error(e) => return Err(CompatError::Upstream(e)),
+            CompletionEvent::Cancelled => return Err(CompatError::Upstream("Completion was cancelled".to_string())),
+            _ => {}
+        }
+    }
+    Err(CompatError::Upstream("Completion ended without an answer".to_string()))
+}
+
+/// `chat.completion.chunk` events ending in `[DONE]`; the last chunk carries
+/// the `l3chat` metadata of the stored answer
+fn completion_chunks(
+    events: tokio::sync::mpsc::Receiver<CompletionEvent>,
+    id: String,
+    created: i64,
+    model: String,
+) -> impl Stream<Item = Result<Event, Infallible>> {
+    let opening = chunk_body(&id, created, &model, json!({ "role": "assistant", "content": "" }), None);
+    let first = stream::once(async move { Ok(Event::default().data(opening.to_string())) });
+
+    let rest = stream::unfold(Some(events), move |events| {
+        let id = id.clone();
+        let model = model.clone();
+        async move {
+            let mut events = events?;
+            loop {
+                let Some(event) = events.recv().await else {
+                    let error = CompatError::Upstream("Completion ended without an answer".to_string());
+                    return Some((vec![error.body().to_string(), "[DONE]".to_string()], None));
+                };
+                let frames = match event {
+                    CompletionEvent::Delta(text) => {
+                        vec![chunk_body(&id, created, &model, json!({ "content": text }), None).to_string()]
+                    }
+                    CompletionEvent::Completed(answer) => {
+                        let mut last = chunk_body(&id, created, &model, json!({}), Some("stop"));
+                        last["l3chat"] = json!({
+                            "thread_id": answer.thread_id,
+                            "message_id": answer.id,
+                            "citations": answer.citations,
+                            "retrieval_trace_id": answer.retrieval_trace_id,
+                        });
+                        return Some((vec![last.to_string(), "[DONE]".to_string()], None));
+                    }
+                    CompletionEvent::Error(e) => {
+                        return Some((vec![CompatError::Upstream(e).body().to_string(), "[DONE]".to_string()], None));
+                    }
+                    CompletionEvent::Cancelled => {
+                        let error = CompatError::Upstream("Completion was cancelled".to_string());
+                        return Some((vec![error.body().to_string(), "[DONE]".to_string()], None));
+                    }
+                    CompletionEvent::Status(_) | CompletionEvent::Citations { .. } | CompletionEvent::Done => continue,
+                };
+                return Some((frames, Some(events)));
+            }
+        }
+    })
+    .flat_map(|frames| stream::iter(frames.into_iter().map(|data| Ok(Event::default().data(data)))));
+
+    first.chain(rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: &str, content: &str) -> ChatMessage {
+        ChatMessage { role: role.to_string(), content: Some(MessageContent::Text(content.to_string())) }
+    }
+
+    #[test]
+    fn models_route_to_labs() {
+        assert_eq!(route_model("gpt-4o", None).unwrap(), ("gpt-4o", "openai".to_string()));
+        assert_eq!(route_model("claude-3-haiku-20240307", None).unwrap().1, "anthropic");
+        assert_eq!(route_model("anthropic/claude-3-opus-20240229", None).unwrap(), ("claude-3-opus-20240229", "anthropic".to_string()));
+        assert_eq!(route_model("my-model", Some("anthropic")).unwrap().1, "anthropic");
+        assert!(route_model("mistral/large", None).is_err());
+    }
+
+    #[test]
+    fn request_parses_openai_shapes_and_extension() {
+        let request: ChatCompletionRequest = serde_json::from_value(json!({
+            "model": "gpt-4o",
+            "temperature": 0.2,
+            "messages": [
+                { "role": "developer", "content": "Be brief." },
+                { "role": "user", "content": [{ "type": "text", "text": "Hi" }, { "type": "image_url", "image_url": {} }] }
+            ],
+            "l3chat": { "project_id": "7f1c0a5e-2b1f-4a43-9a39-2f6e0d1b6a11" }
+        }))
+        .unwrap();
+        assert!(!request.stream);
+        assert!(request.l3chat.project_id.is_some());
+        let normalized = normalize_messages(&request.messages).unwrap();
+        assert_eq!(normalized, vec![("system".to_string(), "Be brief.".to_string()), ("user".to_string(), "Hi".to_string())]);
+    }
+
+    #[test]
+    fn conversations_must_end_with_a_question() {
+        assert!(normalize_messages(&[]).is_err());
+        assert!(normalize_messages(&[message("user", "Hi"), message("assistant", "Hello")]).is_err());
+        assert!(normalize_messages(&[message("user", "  ")]).is_err());
+        assert!(normalize_messages(&[message("tool", "{}"), message("user", "Hi")]).is_err());
+    }
+
+    #[test]
+    fn errors_use_the_openai_shape() {
+        let body = CompatError::RateLimited.body();
+        assert_eq!(body["error"]["type"], "rate_limit_error");
+        assert_eq!(CompatError::RateLimited.kind().0, StatusCode::TOO_MANY_REQUESTS);
+        let chunk = chunk_body("chatcmpl-1", 0, "gpt-4o", json!({ "content": "Hi" }), None);
+        assert_eq!(chunk["object"], "chat.completion.chunk");
+        assert_eq!(chunk["choices"][0]["delta"]["content"], "Hi");
+    }
+}
@@ -15,6 +15,10 @@ use log::debug;
 
 use crate::{
     cancellable_sse::{create_cancellable_sse_stream, CancellableSseStream},
+    handlers::collab::{access_status, display_name},
+    models::collab::{MessagePosted, StreamStarted},
+    services::completions::{chat_page_data, start_completion, CompletionEvent, CompletionRequest},
+    services::workspaces::writable_thread,
     state::AppState,
     types::StreamResponse,
     auth::Claims,
@@ -73,25 +77,75 @@ pub async fn send_message_stream_handler(
         .ok_or(StatusCode::BAD_REQUEST)?;
     
     debug!("Starting message stream for user: {user_id} - thread: {thread_id}, model: {model}, lab: {lab}");
-    
+
+    let mut conn = state.pool.get().await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    writable_thread(&mut conn, user_id, &thread_id).await
+        .map_err(|e| access_status(&e))?;
+
+    // others with the thread open watch the answer arrive as well
+    let rooms = state.thread_rooms.clone();
+    let mirror_id = uuid::Uuid::parse_str(&stream_id).ok();
+    let name = display_name(&mut conn, user_id).await;
+    if let Some(mirror_id) = mirror_id {
+        rooms.stream_started(&thread_id, &StreamStarted {
+            stream_id: mirror_id.to_string(),
+            user_id,
+            name: name.clone(),
+            model: model.clone(),
+            lab: lab.clone(),
+        });
+    }
+    drop(conn);
+
     let pool = state.pool.clone();
 
-    let sse_stream = create_cancellable_sse_stream(state.sse_state, stream_id, move |tx, token| {
-        let pool = pool.clone();
-        let thread_id = thread_id.clone();
-        let model = model.clone();
-        let lab = lab.clone();
-
-        async move {
-            crate::components::chat::send_message_stream_with_project_cancellable(
-                &pool,
-                thread_id,
-                model,
-                lab,
-                tx,
-                token
-            ).await
+    // the answer is stored by the completion runner, with the citations and
+    // trace of its own retrieval; the page only shows the stream
+    let sse_stream = create_cancellable_sse_stream(state.sse_state, stream_id, move |tx, token| async move {
+        let mut events = start_completion(pool, CompletionRequest { user_id, thread_id: thread_id.clone(), model, lab });
+        let send = |data: String| {
+            let event = Event::default().data(data);
+            if let Some(mirror_id) = mirror_id {
+                rooms.mirror_stream(&thread_id, mirror_id, event.clone());
+            }
+            let tx = tx.clone();
+            async move {
+                // keep mirroring even if the sender has gone away
+                let _ = tx.send(Ok(event)).await;
+            }
+        };
+
+        loop {
+            let event = tokio::select! {
+                _ = token.cancelled() => None,
+                event = events.recv() => event,
+            };
+            // cancelled or abandoned; dropping the receiver stops the provider request
+            let Some(event) = event else {
+                send("[CANCELLED]".to_string()).await;
+                break;
+            };
+            let finished = matches!(
+                event,
+                CompletionEvent::Completed(_) | CompletionEvent::Error(_) | CompletionEvent::Cancelled
+            );
+            if let Some(data) = chat_page_data(&event) {
+                send(data).await;
+            }
+            // after `done`, so viewers swap their finished mirror for the stored answer
+            if let CompletionEvent::Completed(answer) = &event {
+                rooms.message_posted(&thread_id, &MessagePosted {
+                    user_id,
+                    name: name.clone(),
+                    role: answer.role.clone(),
+                });
+            }
+            if finished {
+                break;
+            }
         }
+        Ok::<_, Box<dyn std::error::Error + Send + Sync>>(())
     }).await;
     
     Ok(sse_stream)
@@ -0,0 +1,209 @@
+use axum::{
+    extract::{ConnectInfo, Extension, Multipart, Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use log::debug;
+use serde_json::json;
+use std::net::SocketAddr;
+use uuid::Uuid;
+
+use crate::{
+    auth::sessions::client_ip,
+    auth::Claims,
+    models::audit::{AUDIT_DOCUMENTS_IMPORTED, AUDIT_DOCUMENT_UPLOADED},
+    models::imports::ImportView,
+    models::projects::ProjectDocumentView,
+    models::workspaces::Permission,
+    services::archive::{read_archive, sanitize_path, ArchiveError, ImportLimits, PathFilter},
+    services::audit::{self, AuditEvent},
+    services::extraction::ExtractionError,
+    services::imports::begin_import,
+    services::uploads::{project_storage_used, store_document, TempUpload, UploadError, UploadLimits},
+    services::workspaces::authorize_project,
+    state::AppState,
+};
+
+impl IntoResponse for UploadError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            UploadError::ProjectNotFound => StatusCode::NOT_FOUND,
+            UploadError::Forbidden(_) => StatusCode::FORBIDDEN,
+            UploadError::MissingFile => StatusCode::BAD_REQUEST,
+            UploadError::FileTooLarge { .. } | UploadError::QuotaExceeded { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            UploadError::Rejected(ExtractionError::Unsupported(_)) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            UploadError::Rejected(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            UploadError::Archive(ArchiveError::Unsupported(_)) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            UploadError::Archive(ArchiveError::TooLarge { .. } | ArchiveError::TooManyFiles { .. }) => {
+                StatusCode::PAYLOAD_TOO_LARGE
+            }
+            UploadError::Archive(ArchiveError::Forbidden(_)) => StatusCode::FORBIDDEN,
+            UploadError::Archive(ArchiveError::InvalidPattern(_) | ArchiveError::Malformed(_)) => {
+                StatusCode::BAD_REQUEST
+            }
+            UploadError::Archive(ArchiveError::Io(_)) => StatusCode::INTERNAL_SERVER_ERROR,
+            UploadError::Pool(_) | UploadError::Database(_) | UploadError::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(json!({ "error": self.to_string() }))).into_response()
+    }
+}
+
+/// Access and quota checks shared by uploads and imports, done before the body is read
+async fn check_project(state: &AppState, user_id: i32, project_id: Uuid, limits: UploadLimits) -> Result<(), Response> {
+    let mut conn = state.pool.get().await.map_err(|e| UploadError::Pool(e.to_string()).into_response())?;
+    authorize_project(&mut conn, user_id, project_id, Permission::Edit)
+        .await
+        .map_err(|e| UploadError::from(e).into_response())?;
+
+    let used = project_storage_used(&mut conn, project_id)
+        .await
+        .map_err(|e| UploadError::Database(e).into_response())?;
+    if used >= limits.project_quota_bytes {
+        return Err(UploadError::QuotaExceeded { used, quota: limits.project_quota_bytes }.into_response());
+    }
+    Ok(())
+}
+
+fn multipart_error(e: axum::extract::multipart::MultipartError) -> Response {
+    (e.status(), Json(json!({ "error": e.body_text() }))).into_response()
+}
+
+/// One pattern per line; blank lines and `#` comments are ignored later
+fn pattern_lines(text: &str) -> Vec<String> {
+    text.lines().map(str::trim).filter(|line| !line.is_empty()).map(String::from).collect()
+}
+
+/// `POST /api/projects/{project_id}/documents` with a multipart `file` field and an
+/// optional `path` field (e.g. `docs/guide.md`) to store it under instead of the
+/// file's own name. Uploading to an existing path adds a new version of that document.
+///
+/// The file is streamed to a temporary file, so the per-file limit is enforced while
+/// it arrives rather than after the whole body has been buffered.
+pub async fn upload_document_handler(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(project_id): Path<Uuid>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    mut multipart: Multipart,
+) -> Result<Json<ProjectDocumentView>, Response> {
+    let user_id = claims.user_id().map_err(|_| StatusCode::UNAUTHORIZED.into_response())?;
+    let limits = UploadLimits::from_env();
+
+    // reject early, before reading the body
+    check_project(&state, user_id, project_id, limits).await?;
+
+    let mut file = None;
+    let mut path = None;
+
+    while let Some(mut field) = multipart.next_field().await.map_err(multipart_error)? {
+        match field.name() {
+            Some("file") => {
+                let filename = field
+                    .file_name()
+                    .map(|name| name.rsplit(['/', '\\']).next().unwrap_or(name).to_string())
+                    .filter(|name| !name.trim().is_empty())
+                    .unwrap_or_else(|| "upload.txt".to_string());
+
+                let mut upload = TempUpload::create(limits.max_file_bytes).await.map_err(IntoResponse::into_response)?;
+                while let Some(chunk) = field.chunk().await.map_err(multipart_error)? {
+                    upload.write(&chunk).await.map_err(IntoResponse::into_response)?;
+                }
+                debug!("Received {} ({} bytes) for project {}", filename, upload.size(), project_id);
+                file = Some((filename, upload));
+            }
+            Some("path") => {
+                let text = field.text().await.map_err(multipart_error)?;
+                path = sanitize_path(text.trim()).filter(|path| path.len() <= 255);
+                if path.is_none() && !text.trim().is_empty() {
+                    return Err((StatusCode::BAD_REQUEST, Json(json!({ "error": "Invalid document path" }))).into_response());
+                }
+            }
+            _ => continue,
+        }
+    }
+
+    let (filename, upload) = file.ok_or_else(|| UploadError::MissingFile.into_response())?;
+    let bytes = upload.read().await.map_err(IntoResponse::into_response)?;
+    let (document, _) = store_document(&state.pool, project_id, path.unwrap_or(filename), bytes, limits, None)
+        .await
+        .map_err(IntoResponse::into_response)?;
+
+    let event = AuditEvent::new(AUDIT_DOCUMENT_UPLOADED)
+        .target("document", document.id)
+        .details(json!({ "filename": document.filename, "project_id": project_id, "version": document.version }));
+    audit::record_pooled(&state.pool, Some(user_id), event, client_ip(&headers, Some(remote))).await;
+
+    Ok(Json(document.into()))
+}
+
+/// `POST /api/projects/{project_id}/imports` with a multipart `archive` field (zip,
+/// tar or tar.gz) and optional `include` / `exclude` fields holding one
+/// gitignore-style pattern per line.
+///
+/// The archive is unpacked before responding so a broken one is reported straight
+/// away; its files are then stored in the background and the returned import can be
+/// polled for progress.
+pub async fn import_archive_handler(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(project_id): Path<Uuid>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    mut multipart: Multipart,
+) -> Result<Json<ImportView>, Response> {
+    let user_id = claims.user_id().map_err(|_| StatusCode::UNAUTHORIZED.into_response())?;
+    let limits = UploadLimits::from_env();
+    let import_limits = ImportLimits::from_env(limits.max_file_bytes);
+
+    check_project(&state, user_id, project_id, limits).await?;
+
+    let mut archive = None;
+    let mut include = Vec::new();
+    let mut exclude = Vec::new();
+
+    while let Some(mut field) = multipart.next_field().await.map_err(multipart_error)? {
+        match field.name() {
+            Some("archive") => {
+                let filename = field
+                    .file_name()
+                    .map(|name| name.rsplit(['/', '\\']).next().unwrap_or(name).to_string())
+                    .filter(|name| !name.trim().is_empty())
+                    .unwrap_or_else(|| "archive".to_string());
+
+                let mut upload = TempUpload::create(import_limits.max_total_bytes)
+                    .await
+                    .map_err(IntoResponse::into_response)?;
+                while let Some(chunk) = field.chunk().await.map_err(multipart_error)? {
+                    upload.write(&chunk).await.map_err(IntoResponse::into_response)?;
+                }
+                debug!("Received archive {} ({} bytes) for project {}", filename, upload.size(), project_id);
+                archive = Some((filename, upload));
+            }
+            Some("include") => include = pattern_lines(&field.text().await.map_err(multipart_error)?),
+            Some("exclude") => exclude = pattern_lines(&field.text().await.map_err(multipart_error)?),
+            _ => continue,
+        }
+    }
+
+    let (filename, upload) = archive.ok_or_else(|| UploadError::MissingFile.into_response())?;
+    let bytes = upload.read().await.map_err(IntoResponse::into_response)?;
+    let filter = PathFilter::new(&include, &exclude).map_err(|e| UploadError::Archive(e).into_response())?;
+
+    let listing = tokio::task::spawn_blocking(move || read_archive(&bytes, filter, import_limits))
+        .await
+        .map_err(|e| UploadError::Io(std::io::Error::other(e)).into_response())?
+        .map_err(|e| UploadError::Archive(e).into_response())?;
+
+    let import = begin_import(&state.pool, project_id, filename, listing, limits)
+        .await
+        .map_err(IntoResponse::into_response)?;
+
+    let event = AuditEvent::new(AUDIT_DOCUMENTS_IMPORTED)
+        .target("import", import.id)
+        .details(json!({ "source": import.source, "project_id": project_id, "files": import.total_files }));
+    audit::record_pooled(&state.pool, Some(user_id), event, client_ip(&headers, Some(remote))).await;
+
+    Ok(Json(import.into()))
+}
@@ -5,10 +5,10 @@ cfg_if! {
     if #[cfg(feature = "ssr")] {
         use axum::{
             body::Body as AxumBody,
-            extract::State,
+            extract::{DefaultBodyLimit, State},
             http::Request,
             response::IntoResponse,
-            routing::get,
+            routing::{get, post},
             middleware,
             Router,
         };
@@ -19,16 +19,28 @@ cfg_if! {
         use leptos::prelude::*;
         use leptos_axum::{generate_route_list, handle_server_fns_with_context, LeptosRoutes};
         use l3chat::app::*;
-        use l3chat::auth::server::middleware::require_auth_no_db;
-        use l3chat::auth::oauth::{google_login, discord_login, google_callback, discord_callback};
+        use l3chat::auth::server::middleware::{refresh_sessions, require_auth_no_db};
+        use l3chat::auth::mock_idp::mock_idp_router;
+        use l3chat::auth::oauth::{google_callback, oauth_callback, oauth_login};
+        use l3chat::auth::providers::{configured_providers, find_provider, mock_idp_enabled};
         use l3chat::cancellable_sse::*;
         use l3chat::database::db::establish_connection;
+        use l3chat::handlers::api_v1::api_v1_routes;
+        use l3chat::handlers::collab::thread_events_handler;
+        use l3chat::handlers::openai_compat::openai_compat_routes;
         use l3chat::handlers::sse::{
             create_stream,
             send_message_stream_handler,
             title_updates_handler,
         };
+        use l3chat::handlers::uploads::{import_archive_handler, upload_document_handler};
         use l3chat::middleware::tracing::{ColoredFields, trace_requests};
+        use l3chat::services::archive::ImportLimits;
+        use l3chat::services::audit::spawn_audit_retention;
+        use l3chat::services::collab::ThreadRooms;
+        use l3chat::services::imports::fail_interrupted_imports;
+        use l3chat::services::jobs::spawn_job_workers;
+        use l3chat::services::uploads::UploadLimits;
         use std::net::SocketAddr;
         use std::sync::Arc;
 
@@ -58,6 +70,12 @@ cfg_if! {
             let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
             let pool = establish_connection(&database_url).expect("Failed to create database pool");
 
+            // document indexing and re-embedding run from the jobs table
+            spawn_job_workers(pool.clone());
+            fail_interrupted_imports(&pool).await;
+            // audit entries past AUDIT_RETENTION_DAYS are pruned daily
+            spawn_audit_retention(pool.clone());
+
             let routes = generate_route_list(App);
 
             let app_state = AppState {
@@ -65,7 +83,10 @@ cfg_if! {
                 pool,
                 sse_state: SseState::new(),
                 oauth_states: Arc::new(dashmap::DashMap::new()),
-                title_update_senders: Arc::new(DashMap::new())
+                pending_merges: Arc::new(DashMap::new()),
+                revoked_sessions: Arc::new(DashMap::new()),
+                title_update_senders: Arc::new(DashMap::new()),
+                thread_rooms: ThreadRooms::new(),
             };
 
             async fn server_fn_handler(
@@ -81,18 +102,45 @@ cfg_if! {
                 .await
             }
 
-            // OAuth routes (public)
-            let oauth_routes = Router::new()
-                .route("/auth/google", get(google_login))
+            // OAuth routes (public); providers come from OAUTH_PROVIDERS
+            log::info!(
+                "OAuth providers: {:?}",
+                configured_providers().iter().map(|p| p.id.as_str()).collect::<Vec<_>>()
+            );
+            let mut oauth_routes = Router::new()
                 .route("/auth/google-callback", get(google_callback))
-                .route("/auth/discord", get(discord_login))
-                .route("/auth/discord/callback", get(discord_callback));
+                .route("/auth/{provider}", get(oauth_login))
+                .route("/auth/{provider}/callback", get(oauth_callback));
+
+            if mock_idp_enabled(&|name: &str| std::env::var(name).ok()) {
+                let issuer = find_provider("mock")
+                    .ok()
+                    .and_then(|p| p.issuer.clone())
+                    .unwrap_or_else(|| "http://localhost:3000/mock-idp".to_string());
+                log::warn!("Mock IdP enabled at {issuer}; anyone can sign in");
+                oauth_routes = oauth_routes.nest("/mock-idp", mock_idp_router(issuer));
+            }
 
             let protected_routes = Router::new()
                 .route("/api/create-stream", get(create_stream))
                 .route("/api/cancel-stream", get(cancel_stream))
                 .route("/api/send_message_stream", get(send_message_stream_handler))
                 .route("/api/title-updates", get(title_updates_handler))
+                .route("/api/threads/{thread_id}/events", get(thread_events_handler))
+                .route(
+                    "/api/projects/{project_id}/documents",
+                    // the handler enforces the per-file limit while streaming; this is a backstop
+                    post(upload_document_handler).layer(DefaultBodyLimit::max(
+                        UploadLimits::from_env().max_file_bytes as usize + 64 * 1024,
+                    )),
+                )
+                .route(
+                    "/api/projects/{project_id}/imports",
+                    post(import_archive_handler).layer(DefaultBodyLimit::max(
+                        ImportLimits::from_env(UploadLimits::from_env().max_file_bytes).max_total_bytes as usize
+                            + 64 * 1024,
+                    )),
+                )
                 .layer(middleware::from_fn_with_state(
                     app_state.clone(),
                     require_auth_no_db
@@ -105,6 +153,8 @@ cfg_if! {
                 )
                 .merge(oauth_routes)
                 .merge(protected_routes)
+                .merge(api_v1_routes(app_state.clone()))
+                .merge(openai_compat_routes(app_state.clone()))
                 .leptos_routes_with_handler(routes, get(|State(app_state): State<AppState>, request: Request<AxumBody>| async move {
                     let handler = leptos_axum::render_app_to_stream_with_context(
                         move || {
@@ -115,6 +165,8 @@ cfg_if! {
                     handler(request).await.into_response()
                 }))
                 .fallback(leptos_axum::file_and_error_handler::<AppState, _>(shell))
+                // renews expired access tokens before any route reads the cookies
+                .layer(middleware::from_fn_with_state(app_state.clone(), refresh_sessions))
                 .layer(middleware::from_fn(trace_requests))
                 .with_state(app_state);
 
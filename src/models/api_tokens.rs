@@ -0,0 +1,80 @@
+use cfg_if::cfg_if;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub const SCOPE_THREADS_READ: &str = "threads:read";
+pub const SCOPE_THREADS_WRITE: &str = "threads:write";
+pub const SCOPE_PROJECTS_READ: &str = "projects:read";
+pub const SCOPE_PROJECTS_WRITE: &str = "projects:write";
+/// Every scope a token can be granted; `:write` includes `:read`
+pub const SCOPES: [&str; 4] = [SCOPE_THREADS_READ, SCOPE_THREADS_WRITE, SCOPE_PROJECTS_READ, SCOPE_PROJECTS_WRITE];
+
+/// A personal access token as listed on the account page; the secret is never shown again
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ApiTokenView {
+    pub id: Uuid,
+    pub name: String,
+    /// Public part of the token, `l3c_<prefix>_…`, to tell tokens apart
+    pub prefix: String,
+    pub scopes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Returned once, when the token is created
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreatedApiToken {
+    pub token: String,
+    pub details: ApiTokenView,
+}
+
+cfg_if! { if #[cfg(feature = "ssr")] {
+    use crate::schema::*;
+    use crate::models::users::User;
+    use chrono::NaiveDateTime;
+    use diesel::prelude::*;
+
+    #[derive(Debug, Clone, Queryable, Identifiable, Associations)]
+    #[diesel(belongs_to(User, foreign_key = user_id))]
+    #[diesel(table_name = api_tokens)]
+    pub struct ApiToken {
+        pub id: Uuid,
+        pub user_id: i32,
+        pub name: String,
+        pub prefix: String,
+        pub token_hash: String,
+        pub scopes: Vec<String>,
+        pub created_at: NaiveDateTime,
+        pub last_used_at: Option<NaiveDateTime>,
+        pub expires_at: Option<NaiveDateTime>,
+        pub revoked_at: Option<NaiveDateTime>,
+    }
+
+    #[derive(Debug, Insertable)]
+    #[diesel(table_name = api_tokens)]
+    pub struct NewApiToken {
+        pub user_id: i32,
+        pub name: String,
+        pub prefix: String,
+        pub token_hash: String,
+        pub scopes: Vec<String>,
+        pub expires_at: Option<NaiveDateTime>,
+    }
+
+    impl From<ApiToken> for ApiTokenView {
+        fn from(token: ApiToken) -> Self {
+            let utc = |dt: NaiveDateTime| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc);
+            ApiTokenView {
+                id: token.id,
+                name: token.name,
+                prefix: token.prefix,
+                scopes: token.scopes,
+                created_at: utc(token.created_at),
+                last_used_at: token.last_used_at.map(utc),
+                expires_at: token.expires_at.map(utc),
+            }
+        }
+    }
+}}
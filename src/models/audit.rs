@@ -0,0 +1,174 @@
+use cfg_if::cfg_if;
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Audited actions, as stored in `audit_log.action`
+pub const AUDIT_LOGIN: &str = "login";
+pub const AUDIT_LOGOUT: &str = "logout";
+pub const AUDIT_LOGOUT_EVERYWHERE: &str = "logout_everywhere";
+pub const AUDIT_SESSION_REVOKED: &str = "session_revoked";
+pub const AUDIT_API_TOKEN_CREATED: &str = "api_token_created";
+pub const AUDIT_API_TOKEN_REVOKED: &str = "api_token_revoked";
+pub const AUDIT_IDENTITY_LINKED: &str = "identity_linked";
+pub const AUDIT_IDENTITY_UNLINKED: &str = "identity_unlinked";
+pub const AUDIT_ACCOUNTS_MERGED: &str = "accounts_merged";
+pub const AUDIT_THREAD_DELETED: &str = "thread_deleted";
+pub const AUDIT_PROJECT_DELETED: &str = "project_deleted";
+pub const AUDIT_DOCUMENT_DELETED: &str = "document_deleted";
+pub const AUDIT_DOCUMENT_UPLOADED: &str = "document_uploaded";
+pub const AUDIT_DOCUMENTS_IMPORTED: &str = "documents_imported";
+pub const AUDIT_SHARE_CREATED: &str = "share_created";
+pub const AUDIT_SHARE_REVOKED: &str = "share_revoked";
+pub const AUDIT_WORKSPACE_DELETED: &str = "workspace_deleted";
+pub const AUDIT_WORKSPACE_ROLE_CHANGED: &str = "workspace_role_changed";
+pub const AUDIT_WORKSPACE_MEMBER_REMOVED: &str = "workspace_member_removed";
+
+pub const AUDIT_ACTIONS: &[&str] = &[
+    AUDIT_LOGIN,
+    AUDIT_LOGOUT,
+    AUDIT_LOGOUT_EVERYWHERE,
+    AUDIT_SESSION_REVOKED,
+    AUDIT_API_TOKEN_CREATED,
+    AUDIT_API_TOKEN_REVOKED,
+    AUDIT_IDENTITY_LINKED,
+    AUDIT_IDENTITY_UNLINKED,
+    AUDIT_ACCOUNTS_MERGED,
+    AUDIT_THREAD_DELETED,
+    AUDIT_PROJECT_DELETED,
+    AUDIT_DOCUMENT_DELETED,
+    AUDIT_DOCUMENT_UPLOADED,
+    AUDIT_DOCUMENTS_IMPORTED,
+    AUDIT_SHARE_CREATED,
+    AUDIT_SHARE_REVOKED,
+    AUDIT_WORKSPACE_DELETED,
+    AUDIT_WORKSPACE_ROLE_CHANGED,
+    AUDIT_WORKSPACE_MEMBER_REMOVED,
+];
+
+/// Entries per page of the admin view when no limit is given, and the most allowed
+pub const AUDIT_PAGE_SIZE: i64 = 50;
+pub const AUDIT_MAX_PAGE_SIZE: i64 = 200;
+/// How long entries are kept when `AUDIT_RETENTION_DAYS` is unset
+pub const DEFAULT_AUDIT_RETENTION_DAYS: i64 = 365;
+
+/// One audit log entry as shown to admins
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AuditEntryView {
+    pub id: i64,
+    pub actor_id: Option<i32>,
+    pub actor_name: Option<String>,
+    pub action: String,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    pub details: Option<serde_json::Value>,
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// User ids allowed to read the audit log, from comma-separated `ADMIN_USER_IDS`
+pub fn admin_user_ids(lookup: &dyn Fn(&str) -> Option<String>) -> Vec<i32> {
+    lookup("ADMIN_USER_IDS")
+        .map(|ids| ids.split(',').filter_map(|id| id.trim().parse().ok()).collect())
+        .unwrap_or_default()
+}
+
+/// Days to keep entries, from `AUDIT_RETENTION_DAYS`; `0` keeps them forever
+pub fn audit_retention_days(lookup: &dyn Fn(&str) -> Option<String>) -> Option<i64> {
+    let days = lookup("AUDIT_RETENTION_DAYS")
+        .and_then(|value| value.trim().parse::<i64>().ok())
+        .filter(|&days| days >= 0)
+        .unwrap_or(DEFAULT_AUDIT_RETENTION_DAYS);
+    (days > 0).then_some(days)
+}
+
+/// Entries created before this are past retention
+pub fn retention_cutoff(now: NaiveDateTime, days: i64) -> NaiveDateTime {
+    now - Duration::days(days)
+}
+
+pub fn audit_page_size(limit: Option<i64>) -> i64 {
+    limit.unwrap_or(AUDIT_PAGE_SIZE).clamp(1, AUDIT_MAX_PAGE_SIZE)
+}
+
+cfg_if! { if #[cfg(feature = "ssr")] {
+    use crate::schema::*;
+    use diesel::prelude::*;
+
+    #[derive(Debug, Clone, Queryable, Identifiable)]
+    #[diesel(table_name = audit_log)]
+    pub struct AuditEntry {
+        pub id: i64,
+        pub actor_id: Option<i32>,
+        pub actor_name: Option<String>,
+        pub action: String,
+        pub target_type: Option<String>,
+        pub target_id: Option<String>,
+        pub details: Option<serde_json::Value>,
+        pub ip_address: Option<String>,
+        pub created_at: NaiveDateTime,
+    }
+
+    #[derive(Debug, Insertable)]
+    #[diesel(table_name = audit_log)]
+    pub struct NewAuditEntry {
+        pub actor_id: Option<i32>,
+        pub actor_name: Option<String>,
+        pub action: String,
+        pub target_type: Option<String>,
+        pub target_id: Option<String>,
+        pub details: Option<serde_json::Value>,
+        pub ip_address: Option<String>,
+    }
+
+    impl From<AuditEntry> for AuditEntryView {
+        fn from(entry: AuditEntry) -> Self {
+            AuditEntryView {
+                id: entry.id,
+                actor_id: entry.actor_id,
+                actor_name: entry.actor_name,
+                action: entry.action,
+                target_type: entry.target_type,
+                target_id: entry.target_id,
+                details: entry.details,
+                ip_address: entry.ip_address,
+                created_at: DateTime::<Utc>::from_naive_utc_and_offset(entry.created_at, Utc),
+            }
+        }
+    }
+}}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(pairs: &'static [(&'static str, &'static str)]) -> impl Fn(&str) -> Option<String> {
+        move |name| pairs.iter().find(|(key, _)| *key == name).map(|(_, value)| value.to_string())
+    }
+
+    #[test]
+    fn admin_ids_skip_garbage() {
+        assert_eq!(admin_user_ids(&env(&[("ADMIN_USER_IDS", " 1, 7,x,,3 ")])), [1, 7, 3]);
+        assert!(admin_user_ids(&env(&[])).is_empty());
+    }
+
+    #[test]
+    fn retention_defaults_to_a_year_and_zero_keeps_forever() {
+        assert_eq!(audit_retention_days(&env(&[])), Some(DEFAULT_AUDIT_RETENTION_DAYS));
+        assert_eq!(audit_retention_days(&env(&[("AUDIT_RETENTION_DAYS", "30")])), Some(30));
+        assert_eq!(audit_retention_days(&env(&[("AUDIT_RETENTION_DAYS", "0")])), None);
+        assert_eq!(audit_retention_days(&env(&[("AUDIT_RETENTION_DAYS", "-5")])), Some(DEFAULT_AUDIT_RETENTION_DAYS));
+    }
+
+    #[test]
+    fn cutoff_is_days_before_now() {
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap().naive_utc();
+        assert_eq!(now - retention_cutoff(now, 30), Duration::days(30));
+    }
+
+    #[test]
+    fn page_size_is_bounded() {
+        assert_eq!(audit_page_size(None), AUDIT_PAGE_SIZE);
+        assert_eq!(audit_page_size(Some(0)), 1);
+        assert_eq!(audit_page_size(Some(10_000)), AUDIT_MAX_PAGE_SIZE);
+    }
+}
@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+
+/// SSE event names on a thread's room stream (`/api/threads/{id}/events`)
+pub const EVENT_PRESENCE: &str = "presence";
+pub const EVENT_MESSAGE_POSTED: &str = "message_posted";
+pub const EVENT_STREAM_STARTED: &str = "stream_started";
+/// A mirrored chunk of someone's assistant stream; the SSE id is the stream id
+pub const EVENT_STREAM: &str = "stream";
+
+/// Someone with the thread open
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct PresenceView {
+    pub user_id: i32,
+    pub name: String,
+    pub typing: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct MessagePosted {
+    pub user_id: i32,
+    pub name: String,
+    pub role: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct StreamStarted {
+    pub stream_id: String,
+    pub user_id: i32,
+    pub name: String,
+    pub model: String,
+    pub lab: String,
+}
+
+/// What one chunk of an assistant stream means for a viewer mirroring it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamChunk {
+    Text(String),
+    Status(String),
+    Finished,
+    Failed(String),
+    Ignored,
+}
+
+#[derive(Deserialize)]
+struct RagChunk {
+    message_type: String,
+    content: Option<String>,
+    status: Option<String>,
+}
+
+/// Reads a chunk the way the chat box does: plain text, `[DONE]`/`[CANCELLED]`, or RAG JSON
+pub fn parse_stream_chunk(data: &str) -> StreamChunk {
+    if data == "[DONE]" || data == "[CANCELLED]" {
+        return StreamChunk::Finished;
+    }
+    match serde_json::from_str::<RagChunk>(data) {
+        Ok(chunk) => match chunk.message_type.as_str() {
+            "content" => chunk.content.map_or(StreamChunk::Ignored, StreamChunk::Text),
+            "status" => chunk.status.map_or(StreamChunk::Ignored, StreamChunk::Status),
+            "citations" => StreamChunk::Status("Found relevant documents...".to_string()),
+            "done" => StreamChunk::Finished,
+            "error" => StreamChunk::Failed(chunk.content.unwrap_or_default()),
+            _ => StreamChunk::Ignored,
+        },
+        Err(_) => StreamChunk::Text(data.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_text_chunks_are_content() {
+        assert_eq!(parse_stream_chunk("Hello"), StreamChunk::Text("Hello".into()));
+        assert_eq!(parse_stream_chunk("[DONE]"), StreamChunk::Finished);
+        assert_eq!(parse_stream_chunk("[CANCELLED]"), StreamChunk::Finished);
+    }
+
+    #[test]
+    fn rag_chunks_are_unwrapped() {
+        assert_eq!(
+            parse_stream_chunk(r#"{"message_type":"content","content":"Hi","citations":null,"status":null}"#),
+            StreamChunk::Text("Hi".into())
+        );
+        assert_eq!(
+            parse_stream_chunk(r#"{"message_type":"status","content":null,"citations":null,"status":"Searching"}"#),
+            StreamChunk::Status("Searching".into())
+        );
+        assert_eq!(parse_stream_chunk(r#"{"message_type":"done"}"#), StreamChunk::Finished);
+    }
+
+    #[test]
+    fn rag_errors_end_the_stream() {
+        assert_eq!(
+            parse_stream_chunk(r#"{"message_type":"error","content":"quota"}"#),
+            StreamChunk::Failed("quota".into())
+        );
+    }
+
+    #[test]
+    fn json_that_is_not_a_rag_chunk_is_text() {
+        assert_eq!(parse_stream_chunk("{\"a\":1}"), StreamChunk::Text("{\"a\":1}".into()));
+        assert_eq!(parse_stream_chunk(r#"{"message_type":"other"}"#), StreamChunk::Ignored);
+    }
+}
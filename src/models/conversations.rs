@@ -17,7 +17,7 @@ pub struct ThreadView {
     pub project_name: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct MessageView {
     pub id: i32,
     pub thread_id: String,
@@ -28,6 +28,12 @@ pub struct MessageView {
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
     pub user_id: Option<i32>,
+    /// Sources behind an assistant answer; `[n]` in the content refers to `citations[n - 1]`
+    #[serde(default)]
+    pub citations: Vec<DocumentCitation>,
+    /// Retrieval trace behind a project answer
+    #[serde(default)]
+    pub retrieval_trace_id: Option<Uuid>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -38,6 +44,55 @@ pub struct NewMessageView {
     pub active_model: String,
     pub active_lab: String,
     pub user_id: Option<i32>,
+    #[serde(default)]
+    pub citations: Vec<DocumentCitation>,
+    #[serde(default)]
+    pub retrieval_trace_id: Option<Uuid>,
+}
+
+/// A retrieved chunk an answer drew on, with enough to open it in its document
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DocumentCitation {
+    #[serde(default)]
+    pub document_id: Option<Uuid>,
+    /// Version of the document the chunk was indexed from
+    #[serde(default)]
+    pub document_version: i32,
+    #[serde(default)]
+    pub chunk_id: Option<Uuid>,
+    pub filename: String,
+    /// Start of the chunk text, at most `CITATION_PREVIEW_CHARS` characters
+    pub chunk_text: String,
+    pub similarity: f32,
+    pub chunk_index: i32,
+    /// Character offsets of the chunk in `document_version` of the document
+    #[serde(default)]
+    pub start_char: Option<i32>,
+    #[serde(default)]
+    pub end_char: Option<i32>,
+    /// Page or section, e.g. "page 12"
+    #[serde(default)]
+    pub location: Option<String>,
+}
+
+pub const CITATION_PREVIEW_CHARS: usize = 200;
+
+impl DocumentCitation {
+    /// Char range to highlight in the document viewer, if the chunk has offsets
+    pub fn highlight(&self) -> Option<(usize, usize)> {
+        match (self.start_char, self.end_char) {
+            (Some(start), Some(end)) if start >= 0 && end >= start => Some((start as usize, end as usize)),
+            _ => None,
+        }
+    }
+}
+
+/// Shortens chunk text for a citation preview without splitting a character
+pub fn citation_preview(text: &str) -> String {
+    match text.char_indices().nth(CITATION_PREVIEW_CHARS) {
+        Some((byte, _)) => format!("{}...", &text[..byte]),
+        None => text.to_string(),
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -117,6 +172,20 @@ impl DisplayMessage {
         }
     }
 
+    pub fn retrieval_trace_id(&self) -> Option<Uuid> {
+        match self {
+            DisplayMessage::Persisted(msg) => msg.retrieval_trace_id,
+            DisplayMessage::Pending(_) => None,
+        }
+    }
+
+    pub fn citations(&self) -> &[DocumentCitation] {
+        match self {
+            DisplayMessage::Persisted(msg) => &msg.citations,
+            DisplayMessage::Pending(_) => &[],
+        }
+    }
+
     pub fn is_streaming(&self) -> bool {
         match self {
             DisplayMessage::Persisted(_) => false,
@@ -157,6 +226,8 @@ cfg_if! { if #[cfg(feature = "ssr")] {
         pub branch_name: Option<String>,
         pub title: Option<String>,
         pub project_id: Option<Uuid>,
+        /// `private` or `workspace`, see `models::workspaces`
+        pub visibility: String,
     }
 
     impl From<Thread> for ThreadView {
@@ -191,6 +262,8 @@ cfg_if! { if #[cfg(feature = "ssr")] {
         pub created_at: Option<NaiveDateTime>,
         pub updated_at: Option<NaiveDateTime>,
         pub user_id: Option<i32>,
+        pub citations: Option<serde_json::Value>,
+        pub retrieval_trace_id: Option<Uuid>,
     }
 
     impl From<Message> for MessageView {
@@ -205,6 +278,10 @@ cfg_if! { if #[cfg(feature = "ssr")] {
                 created_at: message.created_at.map(|dt| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc)),
                 updated_at: message.updated_at.map(|dt| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc)),
                 user_id: message.user_id,
+                citations: message.citations
+                    .and_then(|value| serde_json::from_value(value).ok())
+                    .unwrap_or_default(),
+                retrieval_trace_id: message.retrieval_trace_id,
             }
         }
     }
@@ -219,6 +296,8 @@ cfg_if! { if #[cfg(feature = "ssr")] {
         pub active_model: String,
         pub active_lab: String,
         pub user_id: Option<i32>,
+        pub citations: Option<serde_json::Value>,
+        pub retrieval_trace_id: Option<Uuid>,
     }
 
     impl From<NewMessageView> for NewMessage {
@@ -230,7 +309,24 @@ cfg_if! { if #[cfg(feature = "ssr")] {
                 active_model: view.active_model,
                 active_lab: view.active_lab,
                 user_id: view.user_id,
+                citations: (!view.citations.is_empty())
+                    .then(|| serde_json::to_value(&view.citations).ok())
+                    .flatten(),
+                retrieval_trace_id: view.retrieval_trace_id,
             }
         }
     }
+
+    /// A document chunk an answer in the thread was grounded on, pinned to the
+    /// document version that was indexed at the time
+    #[derive(Debug, Insertable)]
+    #[diesel(table_name = thread_citations)]
+    pub struct NewThreadCitation {
+        pub thread_id: String,
+        pub document_id: Option<Uuid>,
+        pub document_version: i32,
+        pub filename: String,
+        pub chunk_index: i32,
+        pub location: Option<String>,
+    }
 }}
@@ -0,0 +1,73 @@
+use cfg_if::cfg_if;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A provider account linked to the signed-in user
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct IdentityView {
+    pub id: i32,
+    pub provider: String,
+    pub external_id: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub created_at: DateTime<Utc>,
+    pub last_login_at: Option<DateTime<Utc>>,
+}
+
+/// A link attempt that found the identity on another account, waiting for the
+/// user to confirm merging that account into theirs
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MergeCandidate {
+    pub provider: String,
+    pub email: Option<String>,
+    pub display_name: Option<String>,
+    pub threads: i64,
+    pub projects: i64,
+}
+
+cfg_if! { if #[cfg(feature = "ssr")] {
+    use crate::schema::*;
+    use crate::models::users::User;
+    use chrono::NaiveDateTime;
+    use diesel::prelude::*;
+
+    #[derive(Debug, Clone, Queryable, Identifiable, Associations)]
+    #[diesel(belongs_to(User, foreign_key = user_id))]
+    #[diesel(table_name = user_identities)]
+    pub struct UserIdentity {
+        pub id: i32,
+        pub user_id: i32,
+        pub provider: String,
+        pub external_id: String,
+        pub email: Option<String>,
+        pub email_verified: bool,
+        pub created_at: NaiveDateTime,
+        pub last_login_at: Option<NaiveDateTime>,
+    }
+
+    #[derive(Debug, Insertable)]
+    #[diesel(table_name = user_identities)]
+    pub struct NewUserIdentity {
+        pub user_id: i32,
+        pub provider: String,
+        pub external_id: String,
+        pub email: Option<String>,
+        pub email_verified: bool,
+        pub last_login_at: Option<NaiveDateTime>,
+    }
+
+    impl From<UserIdentity> for IdentityView {
+        fn from(identity: UserIdentity) -> Self {
+            let utc = |dt: NaiveDateTime| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc);
+            IdentityView {
+                id: identity.id,
+                provider: identity.provider,
+                external_id: identity.external_id,
+                email: identity.email,
+                email_verified: identity.email_verified,
+                created_at: utc(identity.created_at),
+                last_login_at: identity.last_login_at.map(utc),
+            }
+        }
+    }
+}}
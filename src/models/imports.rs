@@ -0,0 +1,81 @@
+use cfg_if::cfg_if;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A file an import left out, and why
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ImportReportEntry {
+    pub path: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImportView {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    /// Archive filename or directory path
+    pub source: String,
+    /// running | done | failed
+    pub status: String,
+    pub total_files: i32,
+    pub imported_files: i32,
+    pub skipped_files: i32,
+    pub failed_files: i32,
+    pub report: Vec<ImportReportEntry>,
+    pub error: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+cfg_if! { if #[cfg(feature = "ssr")] {
+    use crate::schema::*;
+    use crate::models::projects::Project;
+    use chrono::NaiveDateTime;
+    use diesel::prelude::*;
+
+    #[derive(Debug, Serialize, Deserialize, Queryable, Identifiable, Associations)]
+    #[diesel(belongs_to(Project, foreign_key = project_id))]
+    #[diesel(table_name = imports)]
+    pub struct Import {
+        pub id: Uuid,
+        pub project_id: Uuid,
+        pub source: String,
+        pub status: String,
+        pub total_files: i32,
+        pub imported_files: i32,
+        pub skipped_files: i32,
+        pub failed_files: i32,
+        pub report: serde_json::Value,
+        pub error: Option<String>,
+        pub created_at: Option<NaiveDateTime>,
+        pub finished_at: Option<NaiveDateTime>,
+    }
+
+    #[derive(Debug, Insertable)]
+    #[diesel(table_name = imports)]
+    pub struct NewImport {
+        pub project_id: Uuid,
+        pub source: String,
+        pub total_files: i32,
+    }
+
+    impl From<Import> for ImportView {
+        fn from(import: Import) -> Self {
+            ImportView {
+                id: import.id,
+                project_id: import.project_id,
+                source: import.source,
+                status: import.status,
+                total_files: import.total_files,
+                imported_files: import.imported_files,
+                skipped_files: import.skipped_files,
+                failed_files: import.failed_files,
+                report: serde_json::from_value(import.report).unwrap_or_default(),
+                error: import.error,
+                created_at: import.created_at.map(|dt| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc)),
+                finished_at: import.finished_at.map(|dt| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc)),
+            }
+        }
+    }
+}}
@@ -0,0 +1,34 @@
+use cfg_if::cfg_if;
+
+cfg_if! { if #[cfg(feature = "ssr")] {
+    use crate::schema::*;
+    use chrono::NaiveDateTime;
+    use diesel::prelude::*;
+    use serde::{Deserialize, Serialize};
+    use uuid::Uuid;
+
+    #[derive(Debug, Clone, Serialize, Deserialize, Queryable, QueryableByName, Identifiable)]
+    #[diesel(table_name = jobs)]
+    pub struct Job {
+        pub id: Uuid,
+        pub kind: String,
+        pub payload: serde_json::Value,
+        pub status: String,
+        pub attempts: i32,
+        pub max_attempts: i32,
+        pub run_at: NaiveDateTime,
+        pub locked_at: Option<NaiveDateTime>,
+        pub locked_by: Option<String>,
+        pub last_error: Option<String>,
+        pub created_at: Option<NaiveDateTime>,
+        pub updated_at: Option<NaiveDateTime>,
+    }
+
+    #[derive(Debug, Insertable)]
+    #[diesel(table_name = jobs)]
+    pub struct NewJob {
+        pub kind: String,
+        pub payload: serde_json::Value,
+        pub max_attempts: i32,
+    }
+}}
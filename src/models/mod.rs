@@ -1,3 +1,13 @@
+pub mod api_tokens;
+pub mod audit;
+pub mod collab;
 pub mod conversations;
+pub mod identities;
+pub mod imports;
+pub mod jobs;
 pub mod projects;
+pub mod sessions;
+pub mod shares;
+pub mod traces;
 pub mod users;
+pub mod workspaces;
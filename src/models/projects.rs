@@ -12,6 +12,9 @@ pub struct ProjectView {
     pub instructions: Option<String>,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
+    pub embedding_model: String,
+    /// Shared with this workspace's members; personal when `None`
+    pub workspace_id: Option<Uuid>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -24,6 +27,39 @@ pub struct ProjectDocumentView {
     pub file_size: Option<i32>,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
+    /// queued | processing | indexed | failed
+    pub status: String,
+    pub status_error: Option<String>,
+    pub indexed_at: Option<DateTime<Utc>>,
+    /// Set for paginated formats such as PDF
+    pub page_count: Option<i32>,
+    /// Bumped each time the document is re-uploaded with different content
+    pub version: i32,
+}
+
+/// Full text of one version of a document, for the document viewer
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DocumentContentView {
+    pub document_id: Uuid,
+    pub project_id: Uuid,
+    pub filename: String,
+    pub version: i32,
+    /// Current version of the document, so the viewer can flag older ones
+    pub latest_version: i32,
+    pub content: String,
+    pub content_type: Option<String>,
+    pub page_count: Option<i32>,
+}
+
+/// One stored revision of a document; the text itself is fetched separately
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DocumentVersionView {
+    pub id: Uuid,
+    pub document_id: Uuid,
+    pub version: i32,
+    pub file_size: Option<i32>,
+    pub page_count: Option<i32>,
+    pub created_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -42,7 +78,7 @@ pub struct DocumentChunkView {
 pub struct ChunkEmbeddingView {
     pub chunk_id: Uuid,
     pub embedding: Option<Vec<f32>>,
-    pub embedding_model: Option<String>,
+    pub embedding_model: String,
     pub created_at: Option<DateTime<Utc>>,
 }
 
@@ -56,11 +92,30 @@ pub struct ProjectSearchResult {
     pub chunk_index: i32,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct EmbeddingModelView {
+    pub id: String,
+    pub label: String,
+    pub dimensions: Option<usize>,
+}
+
+/// A project's embedding model and how far re-embedding has progressed
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EmbeddingSettingsView {
+    pub current_model: String,
+    pub available_models: Vec<EmbeddingModelView>,
+    pub total_chunks: i64,
+    /// Chunks that already have a vector from `current_model`
+    pub embedded_chunks: i64,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct NewProjectView {
     pub name: String,
     pub description: Option<String>,
     pub instructions: Option<String>,
+    #[serde(default)]
+    pub workspace_id: Option<Uuid>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -89,6 +144,8 @@ cfg_if! { if #[cfg(feature = "ssr")] {
         pub instructions: Option<String>,
         pub created_at: Option<NaiveDateTime>,
         pub updated_at: Option<NaiveDateTime>,
+        pub embedding_model: String,
+        pub workspace_id: Option<Uuid>,
     }
 
     #[derive(Debug, Insertable, Associations)]
@@ -99,9 +156,10 @@ cfg_if! { if #[cfg(feature = "ssr")] {
         pub name: String,
         pub description: Option<String>,
         pub instructions: Option<String>,
+        pub workspace_id: Option<Uuid>,
     }
 
-    #[derive(Debug, Serialize, Deserialize, Queryable, Identifiable, Insertable, Associations)]
+    #[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Insertable, Associations)]
     #[diesel(belongs_to(Project, foreign_key = project_id))]
     #[diesel(table_name = project_documents)]
     pub struct ProjectDocument {
@@ -113,6 +171,15 @@ cfg_if! { if #[cfg(feature = "ssr")] {
         pub file_size: Option<i32>,
         pub created_at: Option<NaiveDateTime>,
         pub updated_at: Option<NaiveDateTime>,
+        pub status: String,
+        pub status_error: Option<String>,
+        pub indexed_at: Option<NaiveDateTime>,
+        pub text_segments: Option<serde_json::Value>,
+        pub page_count: Option<i32>,
+        pub import_id: Option<Uuid>,
+        pub version: i32,
+        pub indexed_version: Option<i32>,
+        pub content_hash: Option<String>,
     }
 
     #[derive(Debug, Insertable, Associations)]
@@ -124,6 +191,10 @@ cfg_if! { if #[cfg(feature = "ssr")] {
         pub content: String,
         pub content_type: Option<String>,
         pub file_size: Option<i32>,
+        pub text_segments: Option<serde_json::Value>,
+        pub page_count: Option<i32>,
+        pub import_id: Option<Uuid>,
+        pub content_hash: Option<String>,
     }
 
     #[derive(Debug, Serialize, Deserialize, Queryable, Identifiable, Insertable, Associations)]
@@ -138,6 +209,7 @@ cfg_if! { if #[cfg(feature = "ssr")] {
         pub end_char: Option<i32>,
         pub metadata: Option<serde_json::Value>,
         pub created_at: Option<NaiveDateTime>,
+        pub content_hash: Option<String>,
     }
 
     #[derive(Debug, Insertable, Associations)]
@@ -150,15 +222,45 @@ cfg_if! { if #[cfg(feature = "ssr")] {
         pub start_char: Option<i32>,
         pub end_char: Option<i32>,
         pub metadata: Option<serde_json::Value>,
+        pub content_hash: Option<String>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Queryable, Identifiable, Associations)]
+    #[diesel(belongs_to(ProjectDocument, foreign_key = document_id))]
+    #[diesel(table_name = document_versions)]
+    pub struct DocumentVersion {
+        pub id: Uuid,
+        pub document_id: Uuid,
+        pub version: i32,
+        pub content: String,
+        pub content_type: Option<String>,
+        pub file_size: Option<i32>,
+        pub text_segments: Option<serde_json::Value>,
+        pub page_count: Option<i32>,
+        pub content_hash: Option<String>,
+        pub created_at: Option<NaiveDateTime>,
+    }
+
+    #[derive(Debug, Insertable)]
+    #[diesel(table_name = document_versions)]
+    pub struct NewDocumentVersion {
+        pub document_id: Uuid,
+        pub version: i32,
+        pub content: String,
+        pub content_type: Option<String>,
+        pub file_size: Option<i32>,
+        pub text_segments: Option<serde_json::Value>,
+        pub page_count: Option<i32>,
+        pub content_hash: Option<String>,
     }
 
     #[derive(Debug, Serialize, Deserialize, Queryable, Identifiable, Insertable, Associations)]
     #[diesel(belongs_to(DocumentChunk, foreign_key = chunk_id))]
-    #[diesel(table_name = chunk_embeddings, primary_key(chunk_id))]
+    #[diesel(table_name = chunk_embeddings, primary_key(chunk_id, embedding_model))]
     pub struct ChunkEmbedding {
         pub chunk_id: Uuid,
         pub embedding: Option<Vector>,
-        pub embedding_model: Option<String>,
+        pub embedding_model: String,
         pub created_at: Option<NaiveDateTime>,
     }
 
@@ -168,7 +270,7 @@ cfg_if! { if #[cfg(feature = "ssr")] {
     pub struct NewChunkEmbedding {
         pub chunk_id: Uuid,
         pub embedding: Option<Vector>,
-        pub embedding_model: Option<String>,
+        pub embedding_model: String,
     }
 
     impl From<Project> for ProjectView {
@@ -181,6 +283,8 @@ cfg_if! { if #[cfg(feature = "ssr")] {
                 instructions: project.instructions,
                 created_at: project.created_at.map(|dt| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc)),
                 updated_at: project.updated_at.map(|dt| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc)),
+                embedding_model: project.embedding_model,
+                workspace_id: project.workspace_id,
             }
         }
     }
@@ -192,6 +296,7 @@ cfg_if! { if #[cfg(feature = "ssr")] {
                 name: view.name,
                 description: view.description,
                 instructions: view.instructions,
+                workspace_id: view.workspace_id,
             }
         }
     }
@@ -207,6 +312,24 @@ cfg_if! { if #[cfg(feature = "ssr")] {
                 file_size: doc.file_size,
                 created_at: doc.created_at.map(|dt| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc)),
                 updated_at: doc.updated_at.map(|dt| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc)),
+                status: doc.status,
+                status_error: doc.status_error,
+                indexed_at: doc.indexed_at.map(|dt| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc)),
+                page_count: doc.page_count,
+                version: doc.version,
+            }
+        }
+    }
+
+    impl From<DocumentVersion> for DocumentVersionView {
+        fn from(version: DocumentVersion) -> Self {
+            DocumentVersionView {
+                id: version.id,
+                document_id: version.document_id,
+                version: version.version,
+                file_size: version.file_size,
+                page_count: version.page_count,
+                created_at: version.created_at.map(|dt| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc)),
             }
         }
     }
@@ -0,0 +1,67 @@
+use cfg_if::cfg_if;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A signed-in browser as listed on the account page
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SessionView {
+    pub id: Uuid,
+    /// Short label such as "Firefox on Linux"
+    pub device: String,
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// The session making the request
+    pub current: bool,
+}
+
+cfg_if! { if #[cfg(feature = "ssr")] {
+    use crate::schema::*;
+    use crate::models::users::User;
+    use chrono::NaiveDateTime;
+    use diesel::prelude::*;
+
+    #[derive(Debug, Clone, Queryable, Identifiable, Associations)]
+    #[diesel(belongs_to(User, foreign_key = user_id))]
+    #[diesel(table_name = sessions)]
+    pub struct Session {
+        pub id: Uuid,
+        pub user_id: i32,
+        pub refresh_token_hash: String,
+        pub previous_token_hash: Option<String>,
+        pub rotated_at: Option<NaiveDateTime>,
+        pub user_agent: Option<String>,
+        pub ip_address: Option<String>,
+        pub created_at: NaiveDateTime,
+        pub last_seen_at: NaiveDateTime,
+        pub expires_at: NaiveDateTime,
+        pub revoked_at: Option<NaiveDateTime>,
+    }
+
+    #[derive(Debug, Insertable)]
+    #[diesel(table_name = sessions)]
+    pub struct NewSession {
+        pub user_id: i32,
+        pub refresh_token_hash: String,
+        pub user_agent: Option<String>,
+        pub ip_address: Option<String>,
+        pub expires_at: NaiveDateTime,
+    }
+
+    impl Session {
+        pub fn into_view(self, current_session: Option<Uuid>) -> SessionView {
+            let utc = |dt: NaiveDateTime| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc);
+            SessionView {
+                id: self.id,
+                device: crate::auth::sessions::describe_user_agent(self.user_agent.as_deref()),
+                ip_address: self.ip_address,
+                created_at: utc(self.created_at),
+                last_seen_at: utc(self.last_seen_at),
+                expires_at: utc(self.expires_at),
+                current: current_session == Some(self.id),
+            }
+        }
+    }
+}}
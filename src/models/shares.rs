@@ -0,0 +1,94 @@
+use cfg_if::cfg_if;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// One message as frozen into a share snapshot
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SharedMessage {
+    pub role: String,
+    pub content: String,
+    pub model: String,
+    pub lab: String,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// A share link as listed to its creator
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ShareLinkView {
+    pub id: Uuid,
+    /// Site-relative URL of the public page, `/share/<token>`
+    pub path: String,
+    pub title: Option<String>,
+    pub message_count: usize,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked: bool,
+}
+
+/// What the public share page shows
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SharedThreadView {
+    pub title: Option<String>,
+    pub author: String,
+    pub messages: Vec<SharedMessage>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+pub fn share_path(token: &str) -> String {
+    format!("/share/{token}")
+}
+
+cfg_if! { if #[cfg(feature = "ssr")] {
+    use crate::schema::*;
+    use chrono::NaiveDateTime;
+    use diesel::prelude::*;
+
+    #[derive(Debug, Clone, Queryable, Identifiable)]
+    #[diesel(table_name = thread_shares)]
+    pub struct ThreadShare {
+        pub id: Uuid,
+        pub token: String,
+        pub user_id: i32,
+        pub thread_id: Option<String>,
+        pub title: Option<String>,
+        pub messages: serde_json::Value,
+        pub created_at: NaiveDateTime,
+        pub expires_at: Option<NaiveDateTime>,
+        pub revoked_at: Option<NaiveDateTime>,
+    }
+
+    #[derive(Debug, Insertable)]
+    #[diesel(table_name = thread_shares)]
+    pub struct NewThreadShare {
+        pub token: String,
+        pub user_id: i32,
+        pub thread_id: Option<String>,
+        pub title: Option<String>,
+        pub messages: serde_json::Value,
+        pub expires_at: Option<NaiveDateTime>,
+    }
+
+    impl ThreadShare {
+        /// The snapshot; a row that doesn't parse shows as empty rather than failing the page
+        pub fn snapshot(&self) -> Vec<SharedMessage> {
+            serde_json::from_value(self.messages.clone()).unwrap_or_default()
+        }
+    }
+
+    impl From<ThreadShare> for ShareLinkView {
+        fn from(share: ThreadShare) -> Self {
+            let utc = |dt: NaiveDateTime| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc);
+            ShareLinkView {
+                id: share.id,
+                path: share_path(&share.token),
+                message_count: share.messages.as_array().map_or(0, Vec::len),
+                title: share.title,
+                created_at: utc(share.created_at),
+                expires_at: share.expires_at.map(utc),
+                revoked: share.revoked_at.is_some(),
+            }
+        }
+    }
+}}
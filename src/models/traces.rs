@@ -0,0 +1,101 @@
+use cfg_if::cfg_if;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// One retrieved chunk as it was ranked for an answer
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TracedChunk {
+    pub chunk_id: Uuid,
+    pub chunk_index: i32,
+    /// Number of the chunk in the answer's `[n]` citations
+    pub source_number: usize,
+    pub similarity: f32,
+    pub fused_score: f32,
+    pub rerank_score: Option<f32>,
+    pub location: Option<String>,
+}
+
+/// A document that made it into the context, with the chunks that pulled it in
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TracedDocument {
+    pub document_id: Uuid,
+    pub filename: String,
+    pub document_version: i32,
+    pub priority_score: f32,
+    pub token_count: usize,
+    pub chunks: Vec<TracedChunk>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RetrievalTraceView {
+    pub id: Uuid,
+    pub thread_id: String,
+    pub project_id: Uuid,
+    pub query: String,
+    /// `ContextStrategy` settings in effect, as name/value pairs
+    pub strategy: serde_json::Value,
+    pub reranker: Option<String>,
+    pub tokenizer: String,
+    /// Tokens of the formatted context sent to the model
+    pub context_tokens: i32,
+    pub history_tokens: i32,
+    pub documents: Vec<TracedDocument>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+cfg_if! { if #[cfg(feature = "ssr")] {
+    use crate::schema::*;
+    use crate::models::projects::Project;
+    use chrono::NaiveDateTime;
+    use diesel::prelude::*;
+
+    #[derive(Debug, Serialize, Deserialize, Queryable, Identifiable, Associations)]
+    #[diesel(belongs_to(Project, foreign_key = project_id))]
+    #[diesel(table_name = retrieval_traces)]
+    pub struct RetrievalTrace {
+        pub id: Uuid,
+        pub thread_id: String,
+        pub project_id: Uuid,
+        pub query: String,
+        pub strategy: serde_json::Value,
+        pub reranker: Option<String>,
+        pub tokenizer: String,
+        pub context_tokens: i32,
+        pub history_tokens: i32,
+        pub documents: serde_json::Value,
+        pub created_at: Option<NaiveDateTime>,
+    }
+
+    #[derive(Debug, Insertable)]
+    #[diesel(table_name = retrieval_traces)]
+    pub struct NewRetrievalTrace {
+        pub thread_id: String,
+        pub project_id: Uuid,
+        pub query: String,
+        pub strategy: serde_json::Value,
+        pub reranker: Option<String>,
+        pub tokenizer: String,
+        pub context_tokens: i32,
+        pub history_tokens: i32,
+        pub documents: serde_json::Value,
+    }
+
+    impl From<RetrievalTrace> for RetrievalTraceView {
+        fn from(trace: RetrievalTrace) -> Self {
+            RetrievalTraceView {
+                id: trace.id,
+                thread_id: trace.thread_id,
+                project_id: trace.project_id,
+                query: trace.query,
+                strategy: trace.strategy,
+                reranker: trace.reranker,
+                tokenizer: trace.tokenizer,
+                context_tokens: trace.context_tokens,
+                history_tokens: trace.history_tokens,
+                documents: serde_json::from_value(trace.documents).unwrap_or_default(),
+                created_at: trace.created_at.map(|dt| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc)),
+            }
+        }
+    }
+}}
@@ -17,6 +17,9 @@ pub struct CreateUserView {
     pub external_id: String,
     pub provider: String,
     pub email: Option<String>,
+    /// The provider vouches for `email`; only then may it match another account
+    #[serde(default)]
+    pub email_verified: bool,
     pub username: Option<String>,
     pub display_name: Option<String>,
     pub avatar_url: Option<String>,
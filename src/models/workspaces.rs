@@ -0,0 +1,246 @@
+use cfg_if::cfg_if;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use uuid::Uuid;
+
+/// Visible to the thread's author only
+pub const THREAD_PRIVATE: &str = "private";
+/// Readable by every member of the project's workspace
+pub const THREAD_WORKSPACE: &str = "workspace";
+/// Readable by every member, and editors can post in it too
+pub const THREAD_COLLABORATIVE: &str = "collaborative";
+
+pub const THREAD_VISIBILITIES: [&str; 3] = [THREAD_PRIVATE, THREAD_WORKSPACE, THREAD_COLLABORATIVE];
+
+/// What a request wants to do with a project
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    /// See the project, its documents and shared threads, and chat with it
+    Read,
+    /// Change documents, imports and project settings
+    Edit,
+    /// Delete the project or move it between workspaces
+    Manage,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkspaceRole {
+    Viewer,
+    Editor,
+    Owner,
+}
+
+impl WorkspaceRole {
+    pub const ALL: [WorkspaceRole; 3] = [WorkspaceRole::Owner, WorkspaceRole::Editor, WorkspaceRole::Viewer];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WorkspaceRole::Owner => "owner",
+            WorkspaceRole::Editor => "editor",
+            WorkspaceRole::Viewer => "viewer",
+        }
+    }
+
+    pub fn parse(role: &str) -> Option<Self> {
+        match role.trim().to_lowercase().as_str() {
+            "owner" => Some(WorkspaceRole::Owner),
+            "editor" => Some(WorkspaceRole::Editor),
+            "viewer" => Some(WorkspaceRole::Viewer),
+            _ => None,
+        }
+    }
+
+    pub fn allows(&self, permission: Permission) -> bool {
+        match permission {
+            Permission::Read => true,
+            Permission::Edit => *self >= WorkspaceRole::Editor,
+            Permission::Manage => *self == WorkspaceRole::Owner,
+        }
+    }
+}
+
+impl fmt::Display for WorkspaceRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The viewer's standing on the project a thread belongs to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProjectAccess {
+    /// The thread isn't in a project
+    NoProject,
+    /// The viewer's role on the project; `None` once they can no longer open it
+    Role(Option<WorkspaceRole>),
+}
+
+/// Whether `viewer` may read a thread. Even the author loses a thread in a
+/// project they no longer have access to; others need it shared with the workspace.
+pub fn thread_readable(owner: Option<i32>, visibility: &str, viewer: i32, access: ProjectAccess) -> bool {
+    match access {
+        ProjectAccess::NoProject => owner == Some(viewer),
+        ProjectAccess::Role(None) => false,
+        ProjectAccess::Role(Some(_)) => {
+            owner == Some(viewer) || visibility == THREAD_WORKSPACE || visibility == THREAD_COLLABORATIVE
+        }
+    }
+}
+
+/// Whether `viewer` may post in a thread; see [`thread_readable`]
+pub fn thread_writable(owner: Option<i32>, visibility: &str, viewer: i32, access: ProjectAccess) -> bool {
+    match access {
+        ProjectAccess::NoProject => owner == Some(viewer),
+        ProjectAccess::Role(None) => false,
+        ProjectAccess::Role(Some(role)) => {
+            owner == Some(viewer) || (visibility == THREAD_COLLABORATIVE && role.allows(Permission::Edit))
+        }
+    }
+}
+
+/// Whether changing a member's role (or removing them, `None`) leaves the workspace an owner
+pub fn keeps_an_owner(owner_count: i64, current: WorkspaceRole, new_role: Option<WorkspaceRole>) -> bool {
+    current != WorkspaceRole::Owner || new_role == Some(WorkspaceRole::Owner) || owner_count > 1
+}
+
+/// A workspace the signed-in user belongs to
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct WorkspaceView {
+    pub id: Uuid,
+    pub name: String,
+    pub role: WorkspaceRole,
+    pub member_count: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct WorkspaceMemberView {
+    pub user_id: i32,
+    pub name: String,
+    pub email: Option<String>,
+    pub role: WorkspaceRole,
+    pub joined_at: DateTime<Utc>,
+}
+
+/// A thread in a project as listed for one member: their own, plus those shared with the workspace
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ProjectThreadView {
+    pub id: String,
+    pub title: Option<String>,
+    pub author: String,
+    pub own: bool,
+    pub visibility: String,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+cfg_if! { if #[cfg(feature = "ssr")] {
+    use crate::schema::*;
+    use chrono::NaiveDateTime;
+    use diesel::prelude::*;
+
+    #[derive(Debug, Clone, Queryable, Identifiable)]
+    #[diesel(table_name = workspaces)]
+    pub struct Workspace {
+        pub id: Uuid,
+        pub name: String,
+        pub created_at: NaiveDateTime,
+        pub updated_at: NaiveDateTime,
+    }
+
+    #[derive(Debug, Insertable)]
+    #[diesel(table_name = workspaces)]
+    pub struct NewWorkspace {
+        pub name: String,
+    }
+
+    #[derive(Debug, Clone, Queryable, Insertable)]
+    #[diesel(table_name = workspace_members)]
+    pub struct WorkspaceMember {
+        pub workspace_id: Uuid,
+        pub user_id: i32,
+        pub role: String,
+        pub created_at: NaiveDateTime,
+    }
+
+    #[derive(Debug, Insertable)]
+    #[diesel(table_name = workspace_members)]
+    pub struct NewWorkspaceMember {
+        pub workspace_id: Uuid,
+        pub user_id: i32,
+        pub role: String,
+    }
+
+    impl WorkspaceMember {
+        /// Unknown roles can't be stored (CHECK constraint); read them as the weakest
+        pub fn role(&self) -> WorkspaceRole {
+            WorkspaceRole::parse(&self.role).unwrap_or(WorkspaceRole::Viewer)
+        }
+    }
+}}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roles_grant_increasing_permissions() {
+        use Permission::*;
+        let grants = |role: WorkspaceRole| [Read, Edit, Manage].map(|p| role.allows(p));
+        assert_eq!(grants(WorkspaceRole::Viewer), [true, false, false]);
+        assert_eq!(grants(WorkspaceRole::Editor), [true, true, false]);
+        assert_eq!(grants(WorkspaceRole::Owner), [true, true, true]);
+    }
+
+    #[test]
+    fn roles_round_trip() {
+        for role in WorkspaceRole::ALL {
+            assert_eq!(WorkspaceRole::parse(role.as_str()), Some(role));
+        }
+        assert_eq!(WorkspaceRole::parse(" Editor "), Some(WorkspaceRole::Editor));
+        assert_eq!(WorkspaceRole::parse("admin"), None);
+        assert_eq!(serde_json::to_string(&WorkspaceRole::Viewer).unwrap(), "\"viewer\"");
+    }
+
+    #[test]
+    fn private_threads_stay_with_their_author() {
+        assert!(thread_readable(Some(1), THREAD_PRIVATE, 1, ProjectAccess::NoProject));
+        assert!(!thread_readable(Some(1), THREAD_PRIVATE, 2, ProjectAccess::Role(Some(WorkspaceRole::Owner))));
+    }
+
+    #[test]
+    fn removed_members_lose_their_own_threads() {
+        let member = ProjectAccess::Role(Some(WorkspaceRole::Viewer));
+        assert!(thread_readable(Some(1), THREAD_PRIVATE, 1, member));
+        assert!(thread_writable(Some(1), THREAD_PRIVATE, 1, member));
+        let removed = ProjectAccess::Role(None);
+        assert!(!thread_readable(Some(1), THREAD_PRIVATE, 1, removed));
+        assert!(!thread_writable(Some(1), THREAD_COLLABORATIVE, 1, removed));
+    }
+
+    #[test]
+    fn last_owner_cannot_step_down() {
+        assert!(!keeps_an_owner(1, WorkspaceRole::Owner, Some(WorkspaceRole::Editor)));
+        assert!(!keeps_an_owner(1, WorkspaceRole::Owner, None));
+        assert!(keeps_an_owner(2, WorkspaceRole::Owner, None));
+        assert!(keeps_an_owner(1, WorkspaceRole::Editor, None));
+    }
+
+    #[test]
+    fn shared_threads_need_membership() {
+        assert!(thread_readable(Some(1), THREAD_WORKSPACE, 2, ProjectAccess::Role(Some(WorkspaceRole::Viewer))));
+        assert!(!thread_readable(Some(1), THREAD_WORKSPACE, 2, ProjectAccess::Role(None)));
+        assert!(!thread_readable(Some(1), THREAD_WORKSPACE, 2, ProjectAccess::NoProject));
+    }
+
+    #[test]
+    fn editors_post_in_collaborative_threads() {
+        let viewer = ProjectAccess::Role(Some(WorkspaceRole::Viewer));
+        let editor = ProjectAccess::Role(Some(WorkspaceRole::Editor));
+        assert!(thread_readable(Some(1), THREAD_COLLABORATIVE, 2, viewer));
+        assert!(!thread_writable(Some(1), THREAD_COLLABORATIVE, 2, viewer));
+        assert!(thread_writable(Some(1), THREAD_COLLABORATIVE, 2, editor));
+        assert!(!thread_writable(Some(1), THREAD_WORKSPACE, 2, editor));
+        assert!(thread_writable(Some(1), THREAD_PRIVATE, 1, ProjectAccess::NoProject));
+    }
+}
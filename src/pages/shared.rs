@@ -0,0 +1,129 @@
+use leptos::prelude::*;
+use leptos_meta::Title;
+use leptos_router::hooks::{use_navigate, use_params_map};
+
+use crate::auth::context::AuthContext;
+use crate::components::markdown::MarkdownRenderer;
+use crate::models::shares::SharedMessage;
+use crate::server_fn::shares::{get_shared_thread, ContinueSharedThread};
+
+/// Public read-only page for a share link; no sign-in needed to read it
+#[component]
+pub fn SharedThread() -> impl IntoView {
+    let params = use_params_map();
+    let token = move || params.with(|p| p.get("token").unwrap_or_default());
+    let shared = Resource::new(token, get_shared_thread);
+
+    view! {
+        <div class="min-h-screen bg-gray-100 dark:bg-teal-900 py-8 px-4">
+            <div class="max-w-3xl mx-auto">
+                <Suspense fallback=|| view! { <div class="text-center text-gray-400">"Loading..."</div> }>
+                    {move || shared.get().map(|result| match result {
+                        Ok(thread) => {
+                            let title = thread.title.clone().unwrap_or_else(|| "Shared conversation".to_string());
+                            view! {
+                                <Title text=title.clone()/>
+                                <header class="mb-6">
+                                    <h1 class="text-2xl font-bold text-gray-800 dark:text-gray-200">{title}</h1>
+                                    <p class="text-sm text-gray-500 dark:text-gray-400">
+                                        {format!(
+                                            "Shared by {} on {}",
+                                            thread.author,
+                                            thread.created_at.format("%Y-%m-%d"),
+                                        )}
+                                        {thread.expires_at.map(|at| format!(" · available until {}", at.format("%Y-%m-%d")))}
+                                    </p>
+                                </header>
+                                <div class="space-y-4">
+                                    {thread.messages.into_iter().map(|message| view! { <SharedMessageCard message/> }).collect_view()}
+                                </div>
+                                <ContinueButton token=token()/>
+                            }.into_any()
+                        }
+                        Err(e) => view! {
+                            <div class="bg-white dark:bg-teal-800 rounded-lg shadow-md p-6 text-center text-gray-600 dark:text-gray-300">
+                                {e.to_string()}
+                            </div>
+                        }.into_any(),
+                    })}
+                </Suspense>
+            </div>
+        </div>
+    }
+}
+
+#[component]
+fn SharedMessageCard(message: SharedMessage) -> impl IntoView {
+    let is_user = message.role == "user";
+    let heading = if is_user {
+        "User".to_string()
+    } else {
+        format!("{} · {}", message.lab, message.model)
+    };
+
+    view! {
+        <div class=if is_user {
+            "p-4 rounded-lg bg-white dark:bg-teal-800 ml-8 text-gray-800 dark:text-gray-200"
+        } else {
+            "p-4 rounded-lg bg-gray-50 dark:bg-teal-700 mr-8 text-gray-900 dark:text-gray-100"
+        }>
+            <div class="text-xs font-medium text-gray-500 dark:text-gray-400 mb-2">
+                {heading}
+                {message.created_at.map(|at| format!(" · {}", at.format("%Y-%m-%d %H:%M")))}
+            </div>
+            <MarkdownRenderer content=message.content/>
+        </div>
+    }
+}
+
+/// Copies the conversation into the viewer's own threads; sends signed-out viewers to log in first
+#[component]
+fn ContinueButton(token: String) -> impl IntoView {
+    let auth = use_context::<AuthContext>().map(|a| a.current_user);
+    let continue_action = ServerAction::<ContinueSharedThread>::new();
+    let navigate = use_navigate();
+    let login_href = format!(
+        "/admin?return_to={}",
+        urlencoding::encode(&crate::models::shares::share_path(&token)),
+    );
+
+    Effect::new(move |_| {
+        if let Some(Ok(thread_id)) = continue_action.value().get() {
+            navigate(&format!("/?thread={thread_id}"), Default::default());
+        }
+    });
+
+    let signed_in = move || auth.is_some_and(|user| user.with(Option::is_some));
+
+    view! {
+        <div class="mt-8 text-center">
+            <Show
+                when=signed_in
+                fallback=move || view! {
+                    <a
+                        href=login_href.clone()
+                        class="inline-block px-4 py-2 text-sm bg-seafoam-600 hover:bg-seafoam-700 text-white rounded-md"
+                    >
+                        "Log in to continue this conversation"
+                    </a>
+                }
+            >
+                <button
+                    class="px-4 py-2 text-sm bg-seafoam-600 hover:bg-seafoam-700 text-white rounded-md disabled:opacity-50"
+                    disabled=move || continue_action.pending().get()
+                    on:click={
+                        let token = token.clone();
+                        move |_| {
+                            continue_action.dispatch(ContinueSharedThread { token: token.clone() });
+                        }
+                    }
+                >
+                    {move || if continue_action.pending().get() { "Copying..." } else { "Continue this conversation" }}
+                </button>
+            </Show>
+            {move || continue_action.value().get().and_then(Result::err).map(|e| view! {
+                <p class="text-sm text-red-500 mt-2">{e.to_string()}</p>
+            })}
+        </div>
+    }
+}
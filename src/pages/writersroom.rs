@@ -1,6 +1,7 @@
 use leptos::{prelude::*, task::spawn_local};
 use leptos_icons::Icon;
 use leptos_fetch::QueryClient;
+use leptos_router::hooks::use_query_map;
 use server_fn::codec::PostUrl;
 use uuid::Uuid;
 
@@ -36,7 +37,12 @@ pub fn WritersRoom() -> impl IntoView {
     
     let (show_threads, set_show_threads) = signal(false);
     let (show_projects, set_show_projects) = signal(false);
-    let (thread_id, set_thread_id) = signal(Uuid::new_v4().to_string());
+    // `?thread=` opens a specific thread, e.g. one just continued from a share link
+    let initial_thread = use_query_map()
+        .with_untracked(|q| q.get("thread"))
+        .filter(|id| Uuid::parse_str(id).is_ok())
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let (thread_id, set_thread_id) = signal(initial_thread);
     let (toast_visible, set_toast_visible) = signal(false);
     let (toast_message, set_toast_message) = signal(String::new());
     let (message_refetch_trigger, set_message_refetch_trigger) = signal(0);
@@ -542,6 +548,7 @@ pub async fn create_thread() -> Result<String, ServerFnError> {
         branch_name: None,
         title: None, 
         project_id: None,
+        visibility: crate::models::workspaces::THREAD_PRIVATE.to_string(),
     };
 
     diesel::insert_into(threads::table)
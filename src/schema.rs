@@ -4,11 +4,53 @@ diesel::table! {
     use diesel::sql_types::*;
     use pgvector::sql_types::*;
 
-    chunk_embeddings (chunk_id) {
+    api_tokens (id) {
+        id -> Uuid,
+        user_id -> Int4,
+        #[max_length = 255]
+        name -> Varchar,
+        #[max_length = 16]
+        prefix -> Varchar,
+        token_hash -> Text,
+        scopes -> Array<Text>,
+        created_at -> Timestamp,
+        last_used_at -> Nullable<Timestamp>,
+        expires_at -> Nullable<Timestamp>,
+        revoked_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use pgvector::sql_types::*;
+
+    audit_log (id) {
+        id -> Int8,
+        actor_id -> Nullable<Int4>,
+        #[max_length = 255]
+        actor_name -> Nullable<Varchar>,
+        #[max_length = 64]
+        action -> Varchar,
+        #[max_length = 32]
+        target_type -> Nullable<Varchar>,
+        #[max_length = 255]
+        target_id -> Nullable<Varchar>,
+        details -> Nullable<Jsonb>,
+        #[max_length = 64]
+        ip_address -> Nullable<Varchar>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use pgvector::sql_types::*;
+
+    chunk_embeddings (chunk_id, embedding_model) {
         chunk_id -> Uuid,
         embedding -> Nullable<Vector>,
         #[max_length = 100]
-        embedding_model -> Nullable<Varchar>,
+        embedding_model -> Varchar,
         created_at -> Nullable<Timestamp>,
     }
 }
@@ -39,6 +81,73 @@ diesel::table! {
         end_char -> Nullable<Int4>,
         metadata -> Nullable<Jsonb>,
         created_at -> Nullable<Timestamp>,
+        #[max_length = 64]
+        content_hash -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use pgvector::sql_types::*;
+
+    document_versions (id) {
+        id -> Uuid,
+        document_id -> Uuid,
+        version -> Int4,
+        content -> Text,
+        #[max_length = 100]
+        content_type -> Nullable<Varchar>,
+        file_size -> Nullable<Int4>,
+        text_segments -> Nullable<Jsonb>,
+        page_count -> Nullable<Int4>,
+        #[max_length = 64]
+        content_hash -> Nullable<Varchar>,
+        created_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use pgvector::sql_types::*;
+
+    imports (id) {
+        id -> Uuid,
+        project_id -> Uuid,
+        #[max_length = 255]
+        source -> Varchar,
+        #[max_length = 20]
+        status -> Varchar,
+        total_files -> Int4,
+        imported_files -> Int4,
+        skipped_files -> Int4,
+        failed_files -> Int4,
+        report -> Jsonb,
+        error -> Nullable<Text>,
+        created_at -> Nullable<Timestamp>,
+        finished_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use pgvector::sql_types::*;
+
+    jobs (id) {
+        id -> Uuid,
+        #[max_length = 50]
+        kind -> Varchar,
+        payload -> Jsonb,
+        #[max_length = 20]
+        status -> Varchar,
+        attempts -> Int4,
+        max_attempts -> Int4,
+        run_at -> Timestamp,
+        locked_at -> Nullable<Timestamp>,
+        #[max_length = 100]
+        locked_by -> Nullable<Varchar>,
+        last_error -> Nullable<Text>,
+        created_at -> Nullable<Timestamp>,
+        updated_at -> Nullable<Timestamp>,
     }
 }
 
@@ -57,6 +166,8 @@ diesel::table! {
         created_at -> Nullable<Timestamp>,
         updated_at -> Nullable<Timestamp>,
         user_id -> Nullable<Int4>,
+        citations -> Nullable<Jsonb>,
+        retrieval_trace_id -> Nullable<Uuid>,
     }
 }
 
@@ -75,6 +186,17 @@ diesel::table! {
         file_size -> Nullable<Int4>,
         created_at -> Nullable<Timestamp>,
         updated_at -> Nullable<Timestamp>,
+        #[max_length = 20]
+        status -> Varchar,
+        status_error -> Nullable<Text>,
+        indexed_at -> Nullable<Timestamp>,
+        text_segments -> Nullable<Jsonb>,
+        page_count -> Nullable<Int4>,
+        import_id -> Nullable<Uuid>,
+        version -> Int4,
+        indexed_version -> Nullable<Int4>,
+        #[max_length = 64]
+        content_hash -> Nullable<Varchar>,
     }
 }
 
@@ -91,6 +213,91 @@ diesel::table! {
         instructions -> Nullable<Text>,
         created_at -> Nullable<Timestamp>,
         updated_at -> Nullable<Timestamp>,
+        #[max_length = 100]
+        embedding_model -> Varchar,
+        workspace_id -> Nullable<Uuid>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use pgvector::sql_types::*;
+
+    retrieval_traces (id) {
+        id -> Uuid,
+        #[max_length = 255]
+        thread_id -> Varchar,
+        project_id -> Uuid,
+        query -> Text,
+        strategy -> Jsonb,
+        #[max_length = 255]
+        reranker -> Nullable<Varchar>,
+        #[max_length = 255]
+        tokenizer -> Varchar,
+        context_tokens -> Int4,
+        history_tokens -> Int4,
+        documents -> Jsonb,
+        created_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use pgvector::sql_types::*;
+
+    sessions (id) {
+        id -> Uuid,
+        user_id -> Int4,
+        #[max_length = 64]
+        refresh_token_hash -> Varchar,
+        #[max_length = 64]
+        previous_token_hash -> Nullable<Varchar>,
+        rotated_at -> Nullable<Timestamp>,
+        user_agent -> Nullable<Text>,
+        #[max_length = 64]
+        ip_address -> Nullable<Varchar>,
+        created_at -> Timestamp,
+        last_seen_at -> Timestamp,
+        expires_at -> Timestamp,
+        revoked_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use pgvector::sql_types::*;
+
+    thread_citations (id) {
+        id -> Uuid,
+        #[max_length = 255]
+        thread_id -> Varchar,
+        document_id -> Nullable<Uuid>,
+        document_version -> Int4,
+        #[max_length = 255]
+        filename -> Varchar,
+        chunk_index -> Int4,
+        location -> Nullable<Text>,
+        created_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use pgvector::sql_types::*;
+
+    thread_shares (id) {
+        id -> Uuid,
+        #[max_length = 64]
+        token -> Varchar,
+        user_id -> Int4,
+        #[max_length = 255]
+        thread_id -> Nullable<Varchar>,
+        #[max_length = 255]
+        title -> Nullable<Varchar>,
+        messages -> Jsonb,
+        created_at -> Timestamp,
+        expires_at -> Nullable<Timestamp>,
+        revoked_at -> Nullable<Timestamp>,
     }
 }
 
@@ -112,6 +319,24 @@ diesel::table! {
         #[max_length = 255]
         title -> Nullable<Varchar>,
         project_id -> Nullable<Uuid>,
+        #[max_length = 16]
+        visibility -> Varchar,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use pgvector::sql_types::*;
+
+    user_identities (id) {
+        id -> Int4,
+        user_id -> Int4,
+        provider -> Varchar,
+        external_id -> Varchar,
+        email -> Nullable<Varchar>,
+        email_verified -> Bool,
+        created_at -> Timestamp,
+        last_login_at -> Nullable<Timestamp>,
     }
 }
 
@@ -132,22 +357,76 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    use diesel::sql_types::*;
+    use pgvector::sql_types::*;
+
+    workspace_members (workspace_id, user_id) {
+        workspace_id -> Uuid,
+        user_id -> Int4,
+        #[max_length = 16]
+        role -> Varchar,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use pgvector::sql_types::*;
+
+    workspaces (id) {
+        id -> Uuid,
+        #[max_length = 255]
+        name -> Varchar,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::joinable!(api_tokens -> users (user_id));
 diesel::joinable!(chunk_embeddings -> document_chunks (chunk_id));
 diesel::joinable!(daily_usage -> users (user_id));
 diesel::joinable!(document_chunks -> project_documents (document_id));
+diesel::joinable!(document_versions -> project_documents (document_id));
+diesel::joinable!(imports -> projects (project_id));
+diesel::joinable!(messages -> retrieval_traces (retrieval_trace_id));
 diesel::joinable!(messages -> users (user_id));
+diesel::joinable!(project_documents -> imports (import_id));
 diesel::joinable!(project_documents -> projects (project_id));
 diesel::joinable!(projects -> users (user_id));
+diesel::joinable!(projects -> workspaces (workspace_id));
+diesel::joinable!(retrieval_traces -> projects (project_id));
+diesel::joinable!(retrieval_traces -> threads (thread_id));
+diesel::joinable!(sessions -> users (user_id));
+diesel::joinable!(thread_citations -> project_documents (document_id));
+diesel::joinable!(thread_citations -> threads (thread_id));
+diesel::joinable!(thread_shares -> threads (thread_id));
+diesel::joinable!(thread_shares -> users (user_id));
 diesel::joinable!(threads -> projects (project_id));
 diesel::joinable!(threads -> users (user_id));
+diesel::joinable!(user_identities -> users (user_id));
+diesel::joinable!(workspace_members -> users (user_id));
+diesel::joinable!(workspace_members -> workspaces (workspace_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    api_tokens,
+    audit_log,
     chunk_embeddings,
     daily_usage,
     document_chunks,
+    document_versions,
+    imports,
+    jobs,
     messages,
     project_documents,
     projects,
+    retrieval_traces,
+    sessions,
+    thread_citations,
+    thread_shares,
     threads,
+    user_identities,
     users,
+    workspace_members,
+    workspaces,
 );
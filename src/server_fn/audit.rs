@@ -0,0 +1,67 @@
+use leptos::prelude::*;
+use server_fn::codec::GetUrl;
+
+use crate::models::audit::AuditEntryView;
+
+/// The signed-in user, if they may read the audit log
+#[cfg(feature = "ssr")]
+async fn audit_admin() -> Result<i32, ServerFnError> {
+    use crate::auth::get_current_user;
+    use crate::services::audit::is_audit_admin;
+
+    let current_user = get_current_user().await?
+        .ok_or_else(|| ServerFnError::ServerError("Unauthorized".to_string()))?;
+    if is_audit_admin(current_user.id) {
+        Ok(current_user.id)
+    } else {
+        Err(ServerFnError::ServerError("Forbidden".to_string()))
+    }
+}
+
+/// Whether the signed-in user is listed in `ADMIN_USER_IDS`
+#[server(
+    prefix = "/api",
+    endpoint = "audit-access",
+    input = GetUrl,
+)]
+pub async fn get_audit_access() -> Result<bool, ServerFnError> {
+    Ok(audit_admin().await.is_ok())
+}
+
+/// Audit entries newest first, optionally for one action or actor; pass the
+/// last id seen as `before_id` for the next page
+#[server(
+    prefix = "/api",
+    endpoint = "audit-log",
+    input = GetUrl,
+)]
+pub async fn get_audit_log(
+    action: Option<String>,
+    actor_id: Option<i32>,
+    before_id: Option<i64>,
+    limit: Option<i64>,
+) -> Result<Vec<AuditEntryView>, ServerFnError> {
+    use crate::models::audit::audit_page_size;
+    use crate::services::audit::{search, AuditFilter};
+    use crate::state::AppState;
+
+    audit_admin().await?;
+
+    let app_state = use_context::<AppState>()
+        .expect("Failed to get AppState from context");
+    let mut conn = app_state.pool
+        .get()
+        .await
+        .map_err(|e| ServerFnError::ServerError(format!("Pool error: {e}")))?;
+
+    let filter = AuditFilter {
+        action: action.filter(|action| !action.is_empty()),
+        actor_id,
+        before_id,
+        limit: audit_page_size(limit),
+    };
+    let entries = search(&mut conn, &filter)
+        .await
+        .map_err(|e| ServerFnError::ServerError(format!("Database error: {e}")))?;
+    Ok(entries.into_iter().map(AuditEntryView::from).collect())
+}
@@ -0,0 +1,25 @@
+use leptos::prelude::*;
+
+/// Tells the others in a thread that the signed-in user is (or stopped) typing
+#[server(SetTyping, "/api")]
+pub async fn set_typing(thread_id: String, typing: bool) -> Result<(), ServerFnError> {
+    use crate::auth::get_current_user;
+    use crate::services::workspaces::writable_thread;
+    use crate::state::AppState;
+
+    let current_user = get_current_user().await?
+        .ok_or_else(|| ServerFnError::ServerError("Unauthorized".to_string()))?;
+
+    let app_state = use_context::<AppState>()
+        .expect("Failed to get AppState from context");
+    let mut conn = app_state.pool
+        .get()
+        .await
+        .map_err(|e| ServerFnError::ServerError(format!("Pool error: {e}")))?;
+    writable_thread(&mut conn, current_user.id, &thread_id)
+        .await
+        .map_err(|e| ServerFnError::ServerError(e.to_string()))?;
+
+    app_state.thread_rooms.set_typing(&thread_id, current_user.id, typing);
+    Ok(())
+}
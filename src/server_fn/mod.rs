@@ -1 +1,5 @@
+pub mod audit;
+pub mod collab;
 pub mod projects;
+pub mod shares;
+pub mod workspaces;
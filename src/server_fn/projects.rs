@@ -26,14 +26,17 @@ pub async fn create_project(project_data: NewProjectView) -> Result<ProjectView,
 
     use crate::state::AppState;
     use crate::models::projects::{NewProject, Project};
+    use crate::models::workspaces::Permission;
     use crate::schema::projects;
     use crate::auth::get_current_user;
+    use crate::services::workspaces::workspace_role;
 
     #[derive(Debug)]
     enum ProjectError {
         Pool(String),
         Database(diesel::result::Error),
         Unauthorized,
+        Forbidden,
     }
 
     impl fmt::Display for ProjectError {
@@ -42,6 +45,7 @@ pub async fn create_project(project_data: NewProjectView) -> Result<ProjectView,
                 ProjectError::Pool(e) => write!(f, "Pool error: {e}"),
                 ProjectError::Database(e) => write!(f, "Database error: {e}"),
                 ProjectError::Unauthorized => write!(f, "Unauthorized"),
+                ProjectError::Forbidden => write!(f, "Your workspace role can't create projects there"),
             }
         }
     }
@@ -78,6 +82,17 @@ pub async fn create_project(project_data: NewProjectView) -> Result<ProjectView,
             ProjectError::Pool(e.to_string())
         })?;
 
+    // creating a project in a workspace takes the same role as editing one there
+    if let Some(workspace_id) = project_data.workspace_id {
+        let allowed = workspace_role(&mut conn, workspace_id, user_id)
+            .await
+            .map_err(ProjectError::Database)?
+            .is_some_and(|role| role.allows(Permission::Edit));
+        if !allowed {
+            return Err(ProjectError::Forbidden.into());
+        }
+    }
+
     let mut new_project: NewProject = project_data.into();
     new_project.user_id = user_id;
 
@@ -117,14 +132,11 @@ pub async fn create_project(project_data: NewProjectView) -> Result<ProjectView,
     input = GetUrl,
 )]
 pub async fn get_user_projects() -> Result<Vec<ProjectView>, ServerFnError> {
-    use diesel::prelude::*;
-    use diesel_async::RunQueryDsl;
     use std::fmt;
 
     use crate::state::AppState;
-    use crate::models::projects::Project;
-    use crate::schema::projects;
     use crate::auth::get_current_user;
+    use crate::services::workspaces::visible_projects;
 
     #[derive(Debug)]
     enum ProjectError {
@@ -160,39 +172,73 @@ pub async fn get_user_projects() -> Result<Vec<ProjectView>, ServerFnError> {
         .await
         .map_err(|e| ProjectError::Pool(e.to_string()))?;
 
-    let user_projects: Vec<Project> = projects::table
-        .filter(projects::user_id.eq(user_id))
-        .order(projects::created_at.desc())
-        .load(&mut conn)
+    let user_projects = visible_projects(&mut conn, user_id)
         .await
         .map_err(ProjectError::Database)?;
 
     Ok(user_projects.into_iter().map(ProjectView::from).collect())
 }
 
+/// Stores text typed into the upload dialog. Files go through the multipart
+/// endpoint at `/api/projects/{project_id}/documents`.
 #[server(UploadDocument, "/api")]
 pub async fn upload_document(
     project_id: Uuid,
     filename: String,
     content: String,
-    content_type: Option<String>,
 ) -> Result<ProjectDocumentView, ServerFnError> {
+    use crate::state::AppState;
+    use crate::auth::get_current_user;
+    use crate::models::audit::AUDIT_DOCUMENT_UPLOADED;
+    use crate::models::workspaces::Permission;
+    use crate::services::audit::{self, AuditEvent};
+    use crate::services::uploads::{store_document, UploadError, UploadLimits};
+    use crate::services::workspaces::authorize_project;
+
+    let current_user = get_current_user().await.map_err(|_| ServerFnError::new("Unauthorized"))?;
+    let user_id = current_user.ok_or_else(|| ServerFnError::new("Unauthorized"))?.id;
+
+    let app_state = use_context::<AppState>()
+        .expect("Failed to get AppState from context");
+
+    {
+        let mut conn = app_state.pool
+            .get()
+            .await
+            .map_err(|e| ServerFnError::new(UploadError::Pool(e.to_string())))?;
+        authorize_project(&mut conn, user_id, project_id, Permission::Edit).await.map_err(ServerFnError::new)?;
+    }
+
+    let (document, _) = store_document(&app_state.pool, project_id, filename, content.into_bytes(), UploadLimits::from_env(), None)
+        .await
+        .map_err(ServerFnError::new)?;
+
+    let event = AuditEvent::new(AUDIT_DOCUMENT_UPLOADED)
+        .target("document", document.id)
+        .details(serde_json::json!({ "filename": document.filename, "project_id": project_id, "version": document.version }));
+    audit::record_pooled(&app_state.pool, Some(user_id), event, audit::request_ip()).await;
+
+    Ok(document.into())
+}
+
+#[server(GetProjectDocuments, "/api")]
+pub async fn get_project_documents(project_id: Uuid) -> Result<Vec<ProjectDocumentView>, ServerFnError> {
     use diesel::prelude::*;
     use diesel_async::RunQueryDsl;
     use std::fmt;
 
     use crate::state::AppState;
-    use crate::models::projects::{NewProjectDocument, ProjectDocument};
-    use crate::schema::{projects, project_documents};
+    use crate::models::projects::ProjectDocument;
+    use crate::models::workspaces::Permission;
+    use crate::schema::project_documents;
     use crate::auth::get_current_user;
-    use crate::services::projects::EnhancedProjectsService;
+    use crate::services::workspaces::authorize_project;
 
     #[derive(Debug)]
     enum DocumentError {
         Pool(String),
         Database(diesel::result::Error),
         Unauthorized,
-        ProjectNotFound,
     }
 
     impl fmt::Display for DocumentError {
@@ -201,7 +247,6 @@ pub async fn upload_document(
                 DocumentError::Pool(e) => write!(f, "Pool error: {e}"),
                 DocumentError::Database(e) => write!(f, "Database error: {e}"),
                 DocumentError::Unauthorized => write!(f, "Unauthorized"),
-                DocumentError::ProjectNotFound => write!(f, "Project not found"),
             }
         }
     }
@@ -223,87 +268,145 @@ pub async fn upload_document(
         .await
         .map_err(|e| DocumentError::Pool(e.to_string()))?;
 
-    // Verify project exists and user owns it
-    let _project: crate::models::projects::Project = projects::table
-        .find(project_id)
-        .filter(projects::user_id.eq(user_id))
-        .first(&mut conn)
+    authorize_project(&mut conn, user_id, project_id, Permission::Read)
         .await
-        .optional()
-        .map_err(DocumentError::Database)?
-        .ok_or(DocumentError::ProjectNotFound)?;
-
-    let file_size = content.len() as i32;
+        .map_err(ServerFnError::new)?;
 
-    let new_document = NewProjectDocument {
-        project_id,
-        filename: filename.clone(),
-        content: content.clone(),
-        content_type,
-        file_size: Some(file_size),
-    };
-
-    let document: ProjectDocument = diesel::insert_into(project_documents::table)
-        .values(&new_document)
-        .get_result(&mut conn)
+    let documents: Vec<ProjectDocument> = project_documents::table
+        .filter(project_documents::project_id.eq(project_id))
+        .order(project_documents::created_at.desc())
+        .load(&mut conn)
         .await
         .map_err(DocumentError::Database)?;
 
-    // Process document asynchronously (chunking and embedding)
-    let pool = app_state.pool.clone();
-    let document_id = document.id;
-    let content_for_processing = content.clone();
-    
-    tokio::spawn(async move {
-        let service = EnhancedProjectsService::new();
-        if let Err(e) = service.process_document(&pool, document_id, &content_for_processing).await {
-            log::error!("Failed to process document {}: {}", document_id, e);
-        } else {
-            log::info!("Successfully processed document: {}", document_id);
-        }
-    });
-
-    Ok(document.into())
+    Ok(documents.into_iter().map(ProjectDocumentView::from).collect())
 }
 
-#[server(GetProjectDocuments, "/api")]
-pub async fn get_project_documents(project_id: Uuid) -> Result<Vec<ProjectDocumentView>, ServerFnError> {
+/// Stored versions of a document, newest first
+#[server(GetDocumentVersions, "/api")]
+pub async fn get_document_versions(document_id: Uuid) -> Result<Vec<DocumentVersionView>, ServerFnError> {
     use diesel::prelude::*;
     use diesel_async::RunQueryDsl;
-    use std::fmt;
 
     use crate::state::AppState;
-    use crate::models::projects::ProjectDocument;
-    use crate::schema::{projects, project_documents};
     use crate::auth::get_current_user;
+    use crate::models::projects::DocumentVersion;
+    use crate::models::workspaces::Permission;
+    use crate::schema::{document_versions, project_documents};
+    use crate::services::uploads::UploadError;
+    use crate::services::workspaces::{authorize_project, AccessError};
 
-    #[derive(Debug)]
-    enum DocumentError {
-        Pool(String),
-        Database(diesel::result::Error),
-        Unauthorized,
-        ProjectNotFound,
-    }
+    let current_user = get_current_user().await.map_err(|_| ServerFnError::new("Unauthorized"))?;
+    let user_id = current_user.ok_or_else(|| ServerFnError::new("Unauthorized"))?.id;
 
-    impl fmt::Display for DocumentError {
-        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-            match self {
-                DocumentError::Pool(e) => write!(f, "Pool error: {e}"),
-                DocumentError::Database(e) => write!(f, "Database error: {e}"),
-                DocumentError::Unauthorized => write!(f, "Unauthorized"),
-                DocumentError::ProjectNotFound => write!(f, "Project not found"),
-            }
-        }
+    let app_state = use_context::<AppState>()
+        .expect("Failed to get AppState from context");
+
+    let mut conn = app_state.pool
+        .get()
+        .await
+        .map_err(|e| ServerFnError::new(UploadError::Pool(e.to_string())))?;
+
+    let project_id: Uuid = project_documents::table
+        .find(document_id)
+        .select(project_documents::project_id)
+        .first(&mut conn)
+        .await
+        .optional()
+        .map_err(|e| ServerFnError::new(UploadError::Database(e)))?
+        .ok_or_else(|| ServerFnError::new("Document not found"))?;
+    authorize_project(&mut conn, user_id, project_id, Permission::Read)
+        .await
+        .map_err(|e| match e {
+            AccessError::NotFound(_) => ServerFnError::new("Document not found"),
+            e => ServerFnError::new(e),
+        })?;
+
+    let versions: Vec<DocumentVersion> = document_versions::table
+        .filter(document_versions::document_id.eq(document_id))
+        .order(document_versions::version.desc())
+        .load(&mut conn)
+        .await
+        .map_err(|e| ServerFnError::new(UploadError::Database(e)))?;
+
+    Ok(versions.into_iter().map(DocumentVersionView::from).collect())
+}
+
+/// Imports a directory on the server, for self-hosted setups. Only admins
+/// (`ADMIN_USER_IDS`) may, and only from under `IMPORT_ALLOWED_ROOTS`; archives go
+/// through the multipart endpoint at `/api/projects/{project_id}/imports`.
+#[server(ImportDirectory, "/api")]
+pub async fn import_directory(
+    project_id: Uuid,
+    path: String,
+    include: Vec<String>,
+    exclude: Vec<String>,
+) -> Result<crate::models::imports::ImportView, ServerFnError> {
+    use crate::state::AppState;
+    use crate::auth::get_current_user;
+    use crate::services::archive::{read_directory, resolve_import_directory, ImportLimits, PathFilter};
+    use crate::services::imports::begin_import;
+    use crate::models::audit::AUDIT_DOCUMENTS_IMPORTED;
+    use crate::models::workspaces::Permission;
+    use crate::services::audit::{self, is_audit_admin, AuditEvent};
+    use crate::services::uploads::{UploadError, UploadLimits};
+    use crate::services::workspaces::authorize_project;
+
+    let current_user = get_current_user().await.map_err(|_| ServerFnError::new("Unauthorized"))?;
+    let user_id = current_user.ok_or_else(|| ServerFnError::new("Unauthorized"))?.id;
+    if !is_audit_admin(user_id) {
+        return Err(ServerFnError::new("Directory imports are limited to administrators"));
     }
 
-    impl From<DocumentError> for ServerFnError {
-        fn from(error: DocumentError) -> Self {
-            ServerFnError::ServerError(error.to_string())
-        }
+    let app_state = use_context::<AppState>()
+        .expect("Failed to get AppState from context");
+
+    {
+        let mut conn = app_state.pool
+            .get()
+            .await
+            .map_err(|e| ServerFnError::new(UploadError::Pool(e.to_string())))?;
+        authorize_project(&mut conn, user_id, project_id, Permission::Edit).await.map_err(ServerFnError::new)?;
     }
 
-    let current_user = get_current_user().await.map_err(|_| DocumentError::Unauthorized)?;
-    let user_id = current_user.ok_or(DocumentError::Unauthorized)?.id;
+    let limits = UploadLimits::from_env();
+    let import_limits = ImportLimits::from_env(limits.max_file_bytes);
+    let root = resolve_import_directory(&path).map_err(ServerFnError::new)?;
+    let filter = PathFilter::new(&include, &exclude).map_err(ServerFnError::new)?;
+
+    let listing = tokio::task::spawn_blocking(move || read_directory(&root, filter, import_limits))
+        .await
+        .map_err(|e| ServerFnError::new(format!("Import task failed: {e}")))?
+        .map_err(ServerFnError::new)?;
+
+    let import = begin_import(&app_state.pool, project_id, path, listing, limits)
+        .await
+        .map_err(ServerFnError::new)?;
+
+    let event = AuditEvent::new(AUDIT_DOCUMENTS_IMPORTED)
+        .target("import", import.id)
+        .details(serde_json::json!({ "source": import.source, "project_id": project_id, "files": import.total_files }));
+    audit::record_pooled(&app_state.pool, Some(user_id), event, audit::request_ip()).await;
+
+    Ok(import.into())
+}
+
+/// Recent imports of a project, newest first
+#[server(GetProjectImports, "/api")]
+pub async fn get_project_imports(project_id: Uuid) -> Result<Vec<crate::models::imports::ImportView>, ServerFnError> {
+    use diesel::prelude::*;
+    use diesel_async::RunQueryDsl;
+
+    use crate::state::AppState;
+    use crate::auth::get_current_user;
+    use crate::models::imports::{Import, ImportView};
+    use crate::schema::imports;
+    use crate::models::workspaces::Permission;
+    use crate::services::uploads::UploadError;
+    use crate::services::workspaces::authorize_project;
+
+    let current_user = get_current_user().await.map_err(|_| ServerFnError::new("Unauthorized"))?;
+    let user_id = current_user.ok_or_else(|| ServerFnError::new("Unauthorized"))?.id;
 
     let app_state = use_context::<AppState>()
         .expect("Failed to get AppState from context");
@@ -311,26 +414,18 @@ pub async fn get_project_documents(project_id: Uuid) -> Result<Vec<ProjectDocume
     let mut conn = app_state.pool
         .get()
         .await
-        .map_err(|e| DocumentError::Pool(e.to_string()))?;
+        .map_err(|e| ServerFnError::new(UploadError::Pool(e.to_string())))?;
+    authorize_project(&mut conn, user_id, project_id, Permission::Read).await.map_err(ServerFnError::new)?;
 
-    // Verify project exists and user owns it
-    let _project: crate::models::projects::Project = projects::table
-        .find(project_id)
-        .filter(projects::user_id.eq(user_id))
-        .first(&mut conn)
-        .await
-        .optional()
-        .map_err(DocumentError::Database)?
-        .ok_or(DocumentError::ProjectNotFound)?;
-
-    let documents: Vec<ProjectDocument> = project_documents::table
-        .filter(project_documents::project_id.eq(project_id))
-        .order(project_documents::created_at.desc())
+    let recent: Vec<Import> = imports::table
+        .filter(imports::project_id.eq(project_id))
+        .order(imports::created_at.desc())
+        .limit(10)
         .load(&mut conn)
         .await
-        .map_err(DocumentError::Database)?;
+        .map_err(|e| ServerFnError::new(UploadError::Database(e)))?;
 
-    Ok(documents.into_iter().map(ProjectDocumentView::from).collect())
+    Ok(recent.into_iter().map(ImportView::from).collect())
 }
 
 cfg_if! {
@@ -344,18 +439,15 @@ pub async fn search_project(
     use std::fmt;
 
     use crate::state::AppState;
-    use crate::schema::projects;
+    use crate::models::workspaces::Permission;
     use crate::auth::get_current_user;
     use crate::services::projects::EnhancedProjectsService;
-    use diesel::prelude::*;
-    use diesel_async::RunQueryDsl;
+    use crate::services::workspaces::authorize_project;
 
     #[derive(Debug)]
     enum SearchError {
         Pool(String),
-        Database(diesel::result::Error),
         Unauthorized,
-        ProjectNotFound,
         SearchError(String),
     }
 
@@ -363,9 +455,7 @@ pub async fn search_project(
         fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
             match self {
                 SearchError::Pool(e) => write!(f, "Pool error: {e}"),
-                SearchError::Database(e) => write!(f, "Database error: {e}"),
                 SearchError::Unauthorized => write!(f, "Unauthorized"),
-                SearchError::ProjectNotFound => write!(f, "Project not found"),
                 SearchError::SearchError(e) => write!(f, "Search error: {e}"),
             }
         }
@@ -388,15 +478,9 @@ pub async fn search_project(
         .await
         .map_err(|e| SearchError::Pool(e.to_string()))?;
 
-    // Verify project exists and user owns it
-    let _project: crate::models::projects::Project = projects::table
-        .find(project_id)
-        .filter(projects::user_id.eq(user_id))
-        .first(&mut conn)
+    authorize_project(&mut conn, user_id, project_id, Permission::Read)
         .await
-        .optional()
-        .map_err(SearchError::Database)?
-        .ok_or(SearchError::ProjectNotFound)?;
+        .map_err(ServerFnError::new)?;
 
     let service = EnhancedProjectsService::new();
     let results = service
@@ -410,22 +494,22 @@ pub async fn search_project(
 
 #[server(CreateProjectThread, "/api")]
 pub async fn create_project_thread(project_id: Uuid) -> Result<String, ServerFnError> {
-    use diesel::prelude::*;
     use diesel_async::RunQueryDsl;
     use std::fmt;
     use chrono::Utc;
 
     use crate::state::AppState;
     use crate::models::conversations::Thread;
-    use crate::schema::{projects, threads};
+    use crate::models::workspaces::{Permission, THREAD_PRIVATE};
+    use crate::schema::threads;
     use crate::auth::get_current_user;
+    use crate::services::workspaces::authorize_project;
 
     #[derive(Debug)]
     enum ThreadError {
         Pool(String),
         Database(diesel::result::Error),
         Unauthorized,
-        ProjectNotFound,
     }
 
     impl fmt::Display for ThreadError {
@@ -434,7 +518,6 @@ pub async fn create_project_thread(project_id: Uuid) -> Result<String, ServerFnE
                 ThreadError::Pool(e) => write!(f, "Pool error: {e}"),
                 ThreadError::Database(e) => write!(f, "Database error: {e}"),
                 ThreadError::Unauthorized => write!(f, "Unauthorized"),
-                ThreadError::ProjectNotFound => write!(f, "Project not found"),
             }
         }
     }
@@ -456,15 +539,10 @@ pub async fn create_project_thread(project_id: Uuid) -> Result<String, ServerFnE
         .await
         .map_err(|e| ThreadError::Pool(e.to_string()))?;
 
-    // Verify project exists and user owns it
-    let _project: crate::models::projects::Project = projects::table
-        .find(project_id)
-        .filter(projects::user_id.eq(user_id))
-        .first(&mut conn)
+    // viewers may chat with a workspace project; their threads start out private
+    authorize_project(&mut conn, user_id, project_id, Permission::Read)
         .await
-        .optional()
-        .map_err(ThreadError::Database)?
-        .ok_or(ThreadError::ProjectNotFound)?;
+        .map_err(ServerFnError::new)?;
 
     let new_thread = Thread {
         id: uuid::Uuid::new_v4().to_string(),
@@ -476,6 +554,7 @@ pub async fn create_project_thread(project_id: Uuid) -> Result<String, ServerFnE
         branch_name: None,
         title: None,
         project_id: Some(project_id),
+        visibility: THREAD_PRIVATE.to_string(),
     };
 
     diesel::insert_into(threads::table)
@@ -494,15 +573,19 @@ pub async fn delete_project(project_id: Uuid) -> Result<(), ServerFnError> {
     use std::fmt;
 
     use crate::state::AppState;
+    use crate::models::workspaces::Permission;
     use crate::schema::{projects, project_documents, document_chunks, chunk_embeddings, threads, messages};
     use crate::auth::get_current_user;
+    use crate::services::workspaces::{authorize_project, AccessError};
+    use crate::models::audit::AUDIT_PROJECT_DELETED;
+    use crate::services::audit::{self, AuditEvent};
 
     #[derive(Debug)]
     enum DeleteError {
         Pool(String),
         Database(diesel::result::Error),
         Unauthorized,
-        ProjectNotFound,
+        Access(AccessError),
     }
 
     impl fmt::Display for DeleteError {
@@ -511,7 +594,7 @@ pub async fn delete_project(project_id: Uuid) -> Result<(), ServerFnError> {
                 DeleteError::Pool(e) => write!(f, "Pool error: {e}"),
                 DeleteError::Database(e) => write!(f, "Database error: {e}"),
                 DeleteError::Unauthorized => write!(f, "Unauthorized"),
-                DeleteError::ProjectNotFound => write!(f, "Project not found or access denied"),
+                DeleteError::Access(e) => write!(f, "{e}"),
             }
         }
     }
@@ -543,19 +626,10 @@ pub async fn delete_project(project_id: Uuid) -> Result<(), ServerFnError> {
     // Use a transaction to ensure all deletions are atomic
     conn.transaction(|conn| {
         Box::pin(async move {
-            // First verify the project exists and user owns it
-            let project_exists = projects::table
-                .find(project_id)
-                .filter(projects::user_id.eq(user_id))
-                .first::<crate::models::projects::Project>(conn)
+            // Only the owner of a personal project, or a workspace owner, may delete it
+            authorize_project(conn, user_id, project_id, Permission::Manage)
                 .await
-                .optional()
-                .map_err(DeleteError::Database)?
-                .is_some();
-
-            if !project_exists {
-                return Err(DeleteError::ProjectNotFound);
-            }
+                .map_err(DeleteError::Access)?;
 
             // Get all document IDs for this project
             let document_ids: Vec<uuid::Uuid> = project_documents::table
@@ -637,5 +711,424 @@ pub async fn delete_project(project_id: Uuid) -> Result<(), ServerFnError> {
     })
     .await?;
 
+    audit::record_request(&mut conn, user_id, AuditEvent::new(AUDIT_PROJECT_DELETED).target("project", project_id)).await;
+
+    Ok(())
+}
+
+#[server(GetEmbeddingSettings, "/api")]
+pub async fn get_embedding_settings(project_id: Uuid) -> Result<EmbeddingSettingsView, ServerFnError> {
+    use diesel::prelude::*;
+    use diesel_async::RunQueryDsl;
+    use std::fmt;
+
+    use crate::state::AppState;
+    use crate::models::workspaces::Permission;
+    use crate::schema::{project_documents, document_chunks, chunk_embeddings};
+    use crate::auth::get_current_user;
+    use crate::services::embeddings::available_embedding_models;
+    use crate::services::workspaces::authorize_project;
+
+    #[derive(Debug)]
+    enum SettingsError {
+        Pool(String),
+        Database(diesel::result::Error),
+        Unauthorized,
+    }
+
+    impl fmt::Display for SettingsError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                SettingsError::Pool(e) => write!(f, "Pool error: {e}"),
+                SettingsError::Database(e) => write!(f, "Database error: {e}"),
+                SettingsError::Unauthorized => write!(f, "Unauthorized"),
+            }
+        }
+    }
+
+    impl From<SettingsError> for ServerFnError {
+        fn from(error: SettingsError) -> Self {
+            ServerFnError::ServerError(error.to_string())
+        }
+    }
+
+    let current_user = get_current_user().await.map_err(|_| SettingsError::Unauthorized)?;
+    let user_id = current_user.ok_or(SettingsError::Unauthorized)?.id;
+
+    let app_state = use_context::<AppState>()
+        .expect("Failed to get AppState from context");
+
+    let mut conn = app_state.pool
+        .get()
+        .await
+        .map_err(|e| SettingsError::Pool(e.to_string()))?;
+
+    let project = authorize_project(&mut conn, user_id, project_id, Permission::Read)
+        .await
+        .map_err(ServerFnError::new)?;
+
+    let total_chunks: i64 = document_chunks::table
+        .inner_join(project_documents::table)
+        .filter(project_documents::project_id.eq(project_id))
+        .count()
+        .get_result(&mut conn)
+        .await
+        .map_err(SettingsError::Database)?;
+
+    let embedded_chunks: i64 = chunk_embeddings::table
+        .inner_join(document_chunks::table.inner_join(project_documents::table))
+        .filter(project_documents::project_id.eq(project_id))
+        .filter(chunk_embeddings::embedding_model.eq(&project.embedding_model))
+        .count()
+        .get_result(&mut conn)
+        .await
+        .map_err(SettingsError::Database)?;
+
+    Ok(EmbeddingSettingsView {
+        current_model: project.embedding_model,
+        available_models: available_embedding_models().iter().map(EmbeddingModelView::from).collect(),
+        total_chunks,
+        embedded_chunks,
+    })
+}
+
+#[server(SetProjectEmbeddingModel, "/api")]
+pub async fn set_project_embedding_model(project_id: Uuid, model: String) -> Result<(), ServerFnError> {
+    use diesel::prelude::*;
+    use diesel_async::RunQueryDsl;
+    use std::fmt;
+    use chrono::Utc;
+
+    use crate::state::AppState;
+    use crate::models::workspaces::Permission;
+    use crate::schema::projects;
+    use crate::auth::get_current_user;
+    use crate::services::embeddings::find_embedding_model;
+    use crate::services::jobs::enqueue_reembed;
+    use crate::services::workspaces::authorize_project;
+
+    #[derive(Debug)]
+    enum SettingsError {
+        Pool(String),
+        Database(diesel::result::Error),
+        Unauthorized,
+        UnknownModel(String),
+    }
+
+    impl fmt::Display for SettingsError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                SettingsError::Pool(e) => write!(f, "Pool error: {e}"),
+                SettingsError::Database(e) => write!(f, "Database error: {e}"),
+                SettingsError::Unauthorized => write!(f, "Unauthorized"),
+                SettingsError::UnknownModel(model) => write!(f, "Unknown embedding model: {model}"),
+            }
+        }
+    }
+
+    impl From<SettingsError> for ServerFnError {
+        fn from(error: SettingsError) -> Self {
+            ServerFnError::ServerError(error.to_string())
+        }
+    }
+
+    let current_user = get_current_user().await.map_err(|_| SettingsError::Unauthorized)?;
+    let user_id = current_user.ok_or(SettingsError::Unauthorized)?.id;
+
+    if find_embedding_model(&model).is_none() {
+        return Err(SettingsError::UnknownModel(model).into());
+    }
+
+    let app_state = use_context::<AppState>()
+        .expect("Failed to get AppState from context");
+
+    let mut conn = app_state.pool
+        .get()
+        .await
+        .map_err(|e| SettingsError::Pool(e.to_string()))?;
+
+    let project = authorize_project(&mut conn, user_id, project_id, Permission::Edit)
+        .await
+        .map_err(ServerFnError::new)?;
+
+    if project.embedding_model == model {
+        return Ok(());
+    }
+
+    diesel::update(projects::table.find(project_id))
+        .set((
+            projects::embedding_model.eq(&model),
+            projects::updated_at.eq(Some(Utc::now().naive_utc())),
+        ))
+        .execute(&mut conn)
+        .await
+        .map_err(SettingsError::Database)?;
+
+    // Re-embed existing chunks with the new model in the background
+    enqueue_reembed(&mut conn, project_id)
+        .await
+        .map_err(SettingsError::Database)?;
+
     Ok(())
 }
+
+#[cfg(feature = "ssr")]
+#[derive(Debug)]
+enum ManageError {
+    Pool(String),
+    Database(diesel::result::Error),
+    Unauthorized,
+    NotFound(&'static str),
+    Forbidden(String),
+    Invalid(String),
+}
+
+#[cfg(feature = "ssr")]
+impl std::fmt::Display for ManageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ManageError::Pool(e) => write!(f, "Pool error: {e}"),
+            ManageError::Database(e) => write!(f, "Database error: {e}"),
+            ManageError::Unauthorized => write!(f, "Unauthorized"),
+            ManageError::NotFound(what) => write!(f, "{what} not found"),
+            ManageError::Forbidden(e) | ManageError::Invalid(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl From<ManageError> for ServerFnError {
+    fn from(error: ManageError) -> Self {
+        ServerFnError::ServerError(error.to_string())
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl From<diesel::result::Error> for ManageError {
+    fn from(error: diesel::result::Error) -> Self {
+        ManageError::Database(error)
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl From<crate::services::workspaces::AccessError> for ManageError {
+    fn from(error: crate::services::workspaces::AccessError) -> Self {
+        use crate::services::workspaces::AccessError;
+        match error {
+            AccessError::NotFound(what) => ManageError::NotFound(what),
+            e @ AccessError::Forbidden(_) => ManageError::Forbidden(e.to_string()),
+            AccessError::Database(e) => ManageError::Database(e),
+        }
+    }
+}
+
+/// The signed-in user and a pooled connection, for the document management functions
+#[cfg(feature = "ssr")]
+async fn user_connection() -> Result<(i32, crate::database::db::DbConnection), ManageError> {
+    use crate::auth::get_current_user;
+    use crate::state::AppState;
+
+    let current_user = get_current_user().await.map_err(|_| ManageError::Unauthorized)?;
+    let user_id = current_user.ok_or(ManageError::Unauthorized)?.id;
+
+    let app_state = use_context::<AppState>()
+        .expect("Failed to get AppState from context");
+    let conn = app_state.pool
+        .get()
+        .await
+        .map_err(|e| ManageError::Pool(e.to_string()))?;
+
+    Ok((user_id, conn))
+}
+
+/// The document, if `user_id` may act on its project with `permission`
+#[cfg(feature = "ssr")]
+async fn accessible_document(
+    conn: &mut diesel_async::AsyncPgConnection,
+    user_id: i32,
+    document_id: Uuid,
+    permission: crate::models::workspaces::Permission,
+) -> Result<ProjectDocument, ManageError> {
+    use diesel::prelude::*;
+    use diesel_async::RunQueryDsl;
+    use crate::schema::project_documents;
+    use crate::services::workspaces::{authorize_project, AccessError};
+
+    let document: ProjectDocument = project_documents::table
+        .find(document_id)
+        .first(conn)
+        .await
+        .optional()?
+        .ok_or(ManageError::NotFound("Document"))?;
+    match authorize_project(conn, user_id, document.project_id, permission).await {
+        Ok(_) => Ok(document),
+        Err(AccessError::NotFound(_)) => Err(ManageError::NotFound("Document")),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Full text of a document, or of one of its earlier versions
+#[server(GetDocumentContent, "/api")]
+pub async fn get_document_content(
+    document_id: Uuid,
+    version: Option<i32>,
+) -> Result<DocumentContentView, ServerFnError> {
+    use crate::services::projects::document_content;
+
+    let (user_id, mut conn) = user_connection().await?;
+    Ok(document_content(&mut conn, user_id, document_id, version).await.map_err(ManageError::from)?)
+}
+
+/// Deletes a document with its chunks, embeddings and stored versions
+#[server(DeleteDocument, "/api")]
+pub async fn delete_document(document_id: Uuid) -> Result<(), ServerFnError> {
+    use diesel::prelude::*;
+    use diesel_async::RunQueryDsl;
+    use crate::models::audit::AUDIT_DOCUMENT_DELETED;
+    use crate::models::workspaces::Permission;
+    use crate::schema::project_documents;
+    use crate::services::audit::{self, AuditEvent};
+
+    let (user_id, mut conn) = user_connection().await?;
+    let document = accessible_document(&mut conn, user_id, document_id, Permission::Edit).await?;
+
+    // chunks, embeddings and versions go with it (ON DELETE CASCADE); a queued
+    // indexing job finds nothing and is skipped
+    diesel::delete(project_documents::table.find(document.id))
+        .execute(&mut conn)
+        .await
+        .map_err(ManageError::Database)?;
+
+    log::info!("Deleted document {} ({}) from project {}", document.id, document.filename, document.project_id);
+    let event = AuditEvent::new(AUDIT_DOCUMENT_DELETED)
+        .target("document", document.id)
+        .details(serde_json::json!({ "filename": document.filename, "project_id": document.project_id }));
+    audit::record_request(&mut conn, user_id, event).await;
+    Ok(())
+}
+
+/// Renames or moves a document to another path within its project
+#[server(RenameDocument, "/api")]
+pub async fn rename_document(document_id: Uuid, new_path: String) -> Result<ProjectDocumentView, ServerFnError> {
+    use chrono::Utc;
+    use diesel::prelude::*;
+    use diesel_async::{AsyncConnection, RunQueryDsl};
+    use crate::schema::project_documents;
+    use crate::services::archive::sanitize_path;
+    use crate::models::workspaces::Permission;
+    use crate::services::jobs::enqueue_document;
+
+    let path = sanitize_path(new_path.trim())
+        .filter(|path| path.len() <= 255)
+        .ok_or_else(|| ManageError::Invalid(format!("Invalid document path: {new_path}")))?;
+
+    let (user_id, mut conn) = user_connection().await?;
+    let document = accessible_document(&mut conn, user_id, document_id, Permission::Edit).await?;
+    if document.filename == path {
+        return Ok(document.into());
+    }
+
+    let extension = |name: &str| name.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase());
+    let rechunk = extension(&document.filename) != extension(&path);
+
+    let renamed: ProjectDocument = conn
+        .transaction(|conn| {
+            Box::pin(async move {
+                let taken = project_documents::table
+                    .filter(project_documents::project_id.eq(document.project_id))
+                    .filter(project_documents::filename.eq(&path))
+                    .filter(project_documents::id.ne(document.id))
+                    .count()
+                    .get_result::<i64>(conn)
+                    .await?;
+                if taken > 0 {
+                    return Err(ManageError::Invalid(format!("A document named {path} already exists")));
+                }
+
+                let renamed: ProjectDocument = diesel::update(project_documents::table.find(document.id))
+                    .set((
+                        project_documents::filename.eq(&path),
+                        project_documents::updated_at.eq(Some(Utc::now().naive_utc())),
+                    ))
+                    .get_result(conn)
+                    .await?;
+
+                // the chunker is picked by extension, so a new one means new chunks
+                if rechunk {
+                    enqueue_document(conn, renamed.id).await?;
+                }
+
+                Ok::<_, ManageError>(renamed)
+            })
+        })
+        .await?;
+
+    Ok(renamed.into())
+}
+
+/// Edits a project's name, description and instructions
+#[server(UpdateProject, "/api")]
+pub async fn update_project(
+    project_id: Uuid,
+    name: String,
+    description: Option<String>,
+    instructions: Option<String>,
+) -> Result<ProjectView, ServerFnError> {
+    use chrono::Utc;
+    use diesel::prelude::*;
+    use diesel_async::RunQueryDsl;
+    use crate::models::projects::Project;
+    use crate::models::workspaces::Permission;
+    use crate::schema::projects;
+    use crate::services::workspaces::authorize_project;
+
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(ManageError::Invalid("Project name cannot be empty".to_string()).into());
+    }
+    let optional = |text: Option<String>| text.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+
+    let (user_id, mut conn) = user_connection().await?;
+    authorize_project(&mut conn, user_id, project_id, Permission::Edit)
+        .await
+        .map_err(ManageError::from)?;
+
+    let project: Project = diesel::update(projects::table.find(project_id))
+        .set((
+            projects::name.eq(name),
+            projects::description.eq(optional(description)),
+            projects::instructions.eq(optional(instructions)),
+            projects::updated_at.eq(Some(Utc::now().naive_utc())),
+        ))
+        .get_result(&mut conn)
+        .await
+        .map_err(ManageError::Database)?;
+
+    Ok(project.into())
+}
+
+/// What retrieval produced for one project answer: ranked chunks, scores and strategy
+#[server(GetRetrievalTrace, "/api")]
+pub async fn get_retrieval_trace(trace_id: Uuid) -> Result<crate::models::traces::RetrievalTraceView, ServerFnError> {
+    use diesel::prelude::*;
+    use diesel_async::RunQueryDsl;
+    use crate::models::traces::RetrievalTrace;
+    use crate::schema::retrieval_traces;
+    use crate::services::workspaces::readable_thread;
+
+    let (user_id, mut conn) = user_connection().await?;
+
+    let trace: RetrievalTrace = retrieval_traces::table
+        .find(trace_id)
+        .first(&mut conn)
+        .await
+        .optional()
+        .map_err(ManageError::Database)?
+        .ok_or(ManageError::NotFound("Retrieval trace"))?;
+    // traces follow their thread: shared threads show their retrieval to the workspace
+    readable_thread(&mut conn, user_id, &trace.thread_id)
+        .await
+        .map_err(|_| ManageError::NotFound("Retrieval trace"))?;
+
+    Ok(trace.into())
+}
@@ -0,0 +1,106 @@
+use leptos::prelude::*;
+use server_fn::codec::GetUrl;
+use uuid::Uuid;
+
+use crate::models::shares::{ShareLinkView, SharedThreadView};
+
+#[cfg(feature = "ssr")]
+fn share_error(error: crate::services::shares::ShareError) -> ServerFnError {
+    ServerFnError::ServerError(error.to_string())
+}
+
+#[cfg(feature = "ssr")]
+async fn connection() -> Result<crate::database::db::DbConnection, ServerFnError> {
+    use crate::state::AppState;
+
+    let app_state = use_context::<AppState>()
+        .expect("Failed to get AppState from context");
+    app_state.pool
+        .get()
+        .await
+        .map_err(|e| ServerFnError::ServerError(format!("Pool error: {e}")))
+}
+
+/// The signed-in user and a pooled connection
+#[cfg(feature = "ssr")]
+async fn user_connection() -> Result<(i32, crate::database::db::DbConnection), ServerFnError> {
+    use crate::auth::get_current_user;
+
+    let current_user = get_current_user().await?
+        .ok_or_else(|| ServerFnError::ServerError("Unauthorized".to_string()))?;
+    Ok((current_user.id, connection().await?))
+}
+
+/// Snapshots one of the signed-in user's threads behind a public link
+#[server(CreateShareLink, "/api")]
+pub async fn create_share_link(thread_id: String, expires_in_days: Option<i64>) -> Result<ShareLinkView, ServerFnError> {
+    use crate::models::audit::AUDIT_SHARE_CREATED;
+    use crate::services::audit::{self, AuditEvent};
+    use crate::services::shares::create_share;
+
+    let (user_id, mut conn) = user_connection().await?;
+    let share = create_share(&mut conn, user_id, &thread_id, expires_in_days)
+        .await
+        .map_err(share_error)?;
+    let event = AuditEvent::new(AUDIT_SHARE_CREATED)
+        .target("share", share.id)
+        .details(serde_json::json!({ "thread_id": thread_id, "expires_at": share.expires_at }));
+    audit::record_request(&mut conn, user_id, event).await;
+    Ok(share.into())
+}
+
+/// Links the signed-in user made for a thread, newest first
+#[server(
+    prefix = "/api",
+    endpoint = "share-links",
+    input = GetUrl,
+)]
+pub async fn list_share_links(thread_id: String) -> Result<Vec<ShareLinkView>, ServerFnError> {
+    use crate::services::shares::list_shares;
+
+    let (user_id, mut conn) = user_connection().await?;
+    let shares = list_shares(&mut conn, user_id, &thread_id)
+        .await
+        .map_err(|e| ServerFnError::ServerError(format!("Database error: {e}")))?;
+    Ok(shares.into_iter().map(ShareLinkView::from).collect())
+}
+
+#[server(RevokeShareLink, "/api")]
+pub async fn revoke_share_link(share_id: Uuid) -> Result<(), ServerFnError> {
+    use crate::models::audit::AUDIT_SHARE_REVOKED;
+    use crate::services::audit::{self, AuditEvent};
+    use crate::services::shares::revoke_share;
+
+    let (user_id, mut conn) = user_connection().await?;
+    let revoked = revoke_share(&mut conn, user_id, share_id)
+        .await
+        .map_err(|e| ServerFnError::ServerError(format!("Database error: {e}")))?;
+    if revoked {
+        audit::record_request(&mut conn, user_id, AuditEvent::new(AUDIT_SHARE_REVOKED).target("share", share_id)).await;
+        Ok(())
+    } else {
+        Err(ServerFnError::ServerError("Share link not found".to_string()))
+    }
+}
+
+/// The public page behind a share token; no sign-in needed
+#[server(
+    prefix = "/api",
+    endpoint = "shared-thread",
+    input = GetUrl,
+)]
+pub async fn get_shared_thread(token: String) -> Result<SharedThreadView, ServerFnError> {
+    use crate::services::shares::open_share;
+
+    let mut conn = connection().await?;
+    open_share(&mut conn, &token).await.map_err(share_error)
+}
+
+/// Copies a shared conversation into a new thread of the signed-in user's and returns its id
+#[server(ContinueSharedThread, "/api")]
+pub async fn continue_shared_thread(token: String) -> Result<String, ServerFnError> {
+    use crate::services::shares::continue_share;
+
+    let (user_id, mut conn) = user_connection().await?;
+    continue_share(&mut conn, &token, user_id).await.map_err(share_error)
+}
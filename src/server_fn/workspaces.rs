@@ -0,0 +1,520 @@
+use leptos::prelude::*;
+use server_fn::codec::GetUrl;
+use uuid::Uuid;
+
+use crate::models::projects::ProjectView;
+use crate::models::workspaces::*;
+
+#[cfg(feature = "ssr")]
+#[derive(Debug)]
+enum WorkspaceError {
+    Pool(String),
+    Database(diesel::result::Error),
+    Unauthorized,
+    NotFound(&'static str),
+    Forbidden(String),
+    Invalid(String),
+}
+
+#[cfg(feature = "ssr")]
+impl std::fmt::Display for WorkspaceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WorkspaceError::Pool(e) => write!(f, "Pool error: {e}"),
+            WorkspaceError::Database(e) => write!(f, "Database error: {e}"),
+            WorkspaceError::Unauthorized => write!(f, "Unauthorized"),
+            WorkspaceError::NotFound(what) => write!(f, "{what} not found"),
+            WorkspaceError::Forbidden(e) | WorkspaceError::Invalid(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl From<WorkspaceError> for ServerFnError {
+    fn from(error: WorkspaceError) -> Self {
+        ServerFnError::ServerError(error.to_string())
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl From<diesel::result::Error> for WorkspaceError {
+    fn from(error: diesel::result::Error) -> Self {
+        WorkspaceError::Database(error)
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl From<crate::services::workspaces::AccessError> for WorkspaceError {
+    fn from(error: crate::services::workspaces::AccessError) -> Self {
+        use crate::services::workspaces::AccessError;
+        match error {
+            AccessError::NotFound(what) => WorkspaceError::NotFound(what),
+            e @ AccessError::Forbidden(_) => WorkspaceError::Forbidden(e.to_string()),
+            AccessError::Database(e) => WorkspaceError::Database(e),
+        }
+    }
+}
+
+/// The signed-in user and a pooled connection
+#[cfg(feature = "ssr")]
+async fn user_connection() -> Result<(i32, crate::database::db::DbConnection), WorkspaceError> {
+    use crate::auth::get_current_user;
+    use crate::state::AppState;
+
+    let current_user = get_current_user().await.map_err(|_| WorkspaceError::Unauthorized)?;
+    let user_id = current_user.ok_or(WorkspaceError::Unauthorized)?.id;
+
+    let app_state = use_context::<AppState>()
+        .expect("Failed to get AppState from context");
+    let conn = app_state.pool
+        .get()
+        .await
+        .map_err(|e| WorkspaceError::Pool(e.to_string()))?;
+
+    Ok((user_id, conn))
+}
+
+/// The user's role in the workspace; non-members get `NotFound`
+#[cfg(feature = "ssr")]
+async fn require_role(
+    conn: &mut diesel_async::AsyncPgConnection,
+    workspace_id: Uuid,
+    user_id: i32,
+    permission: Permission,
+) -> Result<WorkspaceRole, WorkspaceError> {
+    use crate::services::workspaces::workspace_role;
+
+    let role = workspace_role(conn, workspace_id, user_id)
+        .await?
+        .ok_or(WorkspaceError::NotFound("Workspace"))?;
+    if role.allows(permission) {
+        Ok(role)
+    } else {
+        Err(WorkspaceError::Forbidden(format!("Your role in this workspace is {role}")))
+    }
+}
+
+#[cfg(feature = "ssr")]
+fn member_view(member: WorkspaceMember, user: crate::models::users::User) -> WorkspaceMemberView {
+    use chrono::{DateTime, Utc};
+
+    WorkspaceMemberView {
+        user_id: user.id,
+        name: user.display_name.or(user.username).unwrap_or_else(|| "Anonymous".to_string()),
+        email: user.email,
+        role: member.role(),
+        joined_at: DateTime::<Utc>::from_naive_utc_and_offset(member.created_at, Utc),
+    }
+}
+
+/// Workspaces the signed-in user belongs to, with their role in each
+#[server(
+    prefix = "/api",
+    endpoint = "workspaces",
+    input = GetUrl,
+)]
+pub async fn get_workspaces() -> Result<Vec<WorkspaceView>, ServerFnError> {
+    use chrono::{DateTime, Utc};
+    use diesel::prelude::*;
+    use diesel_async::RunQueryDsl;
+    use crate::schema::{workspace_members, workspaces};
+
+    let (user_id, mut conn) = user_connection().await?;
+
+    let memberships: Vec<(Workspace, WorkspaceMember)> = workspaces::table
+        .inner_join(workspace_members::table)
+        .filter(workspace_members::user_id.eq(user_id))
+        .order(workspaces::name.asc())
+        .load(&mut conn)
+        .await
+        .map_err(WorkspaceError::Database)?;
+
+    let ids: Vec<Uuid> = memberships.iter().map(|(workspace, _)| workspace.id).collect();
+    let counts: Vec<(Uuid, i64)> = workspace_members::table
+        .filter(workspace_members::workspace_id.eq_any(&ids))
+        .group_by(workspace_members::workspace_id)
+        .select((workspace_members::workspace_id, diesel::dsl::count_star()))
+        .load(&mut conn)
+        .await
+        .map_err(WorkspaceError::Database)?;
+
+    Ok(memberships
+        .into_iter()
+        .map(|(workspace, member)| WorkspaceView {
+            member_count: counts.iter().find(|(id, _)| *id == workspace.id).map_or(0, |(_, count)| *count),
+            role: member.role(),
+            id: workspace.id,
+            name: workspace.name,
+            created_at: DateTime::<Utc>::from_naive_utc_and_offset(workspace.created_at, Utc),
+        })
+        .collect())
+}
+
+/// Creates a workspace with the signed-in user as its owner
+#[server(CreateWorkspace, "/api")]
+pub async fn create_workspace(name: String) -> Result<WorkspaceView, ServerFnError> {
+    use chrono::{DateTime, Utc};
+    use diesel_async::{AsyncConnection, RunQueryDsl};
+    use crate::schema::{workspace_members, workspaces};
+
+    let name = name.trim().to_string();
+    if name.is_empty() || name.len() > 255 {
+        return Err(WorkspaceError::Invalid("Workspace name must be 1 to 255 characters".to_string()).into());
+    }
+
+    let (user_id, mut conn) = user_connection().await?;
+
+    let workspace: Workspace = conn
+        .transaction(|conn| {
+            Box::pin(async move {
+                let workspace: Workspace = diesel::insert_into(workspaces::table)
+                    .values(&NewWorkspace { name })
+                    .get_result(conn)
+                    .await?;
+                diesel::insert_into(workspace_members::table)
+                    .values(&NewWorkspaceMember {
+                        workspace_id: workspace.id,
+                        user_id,
+                        role: WorkspaceRole::Owner.as_str().to_string(),
+                    })
+                    .execute(conn)
+                    .await?;
+                Ok::<_, WorkspaceError>(workspace)
+            })
+        })
+        .await?;
+
+    Ok(WorkspaceView {
+        id: workspace.id,
+        name: workspace.name,
+        role: WorkspaceRole::Owner,
+        member_count: 1,
+        created_at: DateTime::<Utc>::from_naive_utc_and_offset(workspace.created_at, Utc),
+    })
+}
+
+/// Deletes a workspace. Its projects go back to the people who created them.
+#[server(DeleteWorkspace, "/api")]
+pub async fn delete_workspace(workspace_id: Uuid) -> Result<(), ServerFnError> {
+    use diesel::prelude::*;
+    use diesel_async::RunQueryDsl;
+    use crate::models::audit::AUDIT_WORKSPACE_DELETED;
+    use crate::schema::workspaces;
+    use crate::services::audit::{self, AuditEvent};
+
+    let (user_id, mut conn) = user_connection().await?;
+    require_role(&mut conn, workspace_id, user_id, Permission::Manage).await?;
+
+    // memberships cascade; projects.workspace_id is set to NULL
+    diesel::delete(workspaces::table.find(workspace_id))
+        .execute(&mut conn)
+        .await
+        .map_err(WorkspaceError::Database)?;
+
+    log::info!("User {} deleted workspace {}", user_id, workspace_id);
+    audit::record_request(&mut conn, user_id, AuditEvent::new(AUDIT_WORKSPACE_DELETED).target("workspace", workspace_id)).await;
+    Ok(())
+}
+
+#[server(
+    prefix = "/api",
+    endpoint = "workspace-members",
+    input = GetUrl,
+)]
+pub async fn get_workspace_members(workspace_id: Uuid) -> Result<Vec<WorkspaceMemberView>, ServerFnError> {
+    use diesel::prelude::*;
+    use diesel_async::RunQueryDsl;
+    use crate::models::users::User;
+    use crate::schema::{users, workspace_members};
+
+    let (user_id, mut conn) = user_connection().await?;
+    require_role(&mut conn, workspace_id, user_id, Permission::Read).await?;
+
+    let members: Vec<(WorkspaceMember, User)> = workspace_members::table
+        .inner_join(users::table)
+        .filter(workspace_members::workspace_id.eq(workspace_id))
+        .order(workspace_members::created_at.asc())
+        .load(&mut conn)
+        .await
+        .map_err(WorkspaceError::Database)?;
+
+    Ok(members.into_iter().map(|(member, user)| member_view(member, user)).collect())
+}
+
+/// Adds someone who has signed in before, found by email
+#[server(AddWorkspaceMember, "/api")]
+pub async fn add_workspace_member(
+    workspace_id: Uuid,
+    email: String,
+    role: WorkspaceRole,
+) -> Result<WorkspaceMemberView, ServerFnError> {
+    use diesel::prelude::*;
+    use diesel::sql_types::{Nullable, Varchar};
+    use diesel_async::RunQueryDsl;
+    use crate::models::users::User;
+    use crate::schema::{users, workspace_members};
+
+    diesel::define_sql_function!(fn lower(x: Nullable<Varchar>) -> Nullable<Varchar>);
+
+    let email = email.trim().to_lowercase();
+    if email.is_empty() {
+        return Err(WorkspaceError::Invalid("Enter the email address of the person to add".to_string()).into());
+    }
+
+    let (user_id, mut conn) = user_connection().await?;
+    require_role(&mut conn, workspace_id, user_id, Permission::Manage).await?;
+
+    let user: User = users::table
+        .filter(lower(users::email).eq(&email))
+        .order(users::id.asc())
+        .first(&mut conn)
+        .await
+        .optional()
+        .map_err(WorkspaceError::Database)?
+        .ok_or_else(|| WorkspaceError::Invalid(format!("No account uses {email}; they need to sign in once first")))?;
+
+    let member: WorkspaceMember = diesel::insert_into(workspace_members::table)
+        .values(&NewWorkspaceMember {
+            workspace_id,
+            user_id: user.id,
+            role: role.as_str().to_string(),
+        })
+        .on_conflict_do_nothing()
+        .get_result(&mut conn)
+        .await
+        .optional()
+        .map_err(WorkspaceError::Database)?
+        .ok_or_else(|| WorkspaceError::Invalid(format!("{email} is already a member")))?;
+
+    Ok(member_view(member, user))
+}
+
+#[server(UpdateWorkspaceMemberRole, "/api")]
+pub async fn update_workspace_member_role(
+    workspace_id: Uuid,
+    member_id: i32,
+    role: WorkspaceRole,
+) -> Result<(), ServerFnError> {
+    use crate::models::audit::AUDIT_WORKSPACE_ROLE_CHANGED;
+    use crate::services::audit::{self, AuditEvent};
+
+    let (user_id, mut conn) = user_connection().await?;
+    require_role(&mut conn, workspace_id, user_id, Permission::Manage).await?;
+    change_membership(&mut conn, workspace_id, member_id, Some(role)).await?;
+    let event = AuditEvent::new(AUDIT_WORKSPACE_ROLE_CHANGED)
+        .target("workspace", workspace_id)
+        .details(serde_json::json!({ "member_id": member_id, "role": role.as_str() }));
+    audit::record_request(&mut conn, user_id, event).await;
+    Ok(())
+}
+
+/// Removes a member. Owners can remove anyone; everyone else can only leave.
+#[server(RemoveWorkspaceMember, "/api")]
+pub async fn remove_workspace_member(workspace_id: Uuid, member_id: i32) -> Result<(), ServerFnError> {
+    use crate::models::audit::AUDIT_WORKSPACE_MEMBER_REMOVED;
+    use crate::services::audit::{self, AuditEvent};
+
+    let (user_id, mut conn) = user_connection().await?;
+    let permission = if member_id == user_id { Permission::Read } else { Permission::Manage };
+    require_role(&mut conn, workspace_id, user_id, permission).await?;
+    change_membership(&mut conn, workspace_id, member_id, None).await?;
+    let event = AuditEvent::new(AUDIT_WORKSPACE_MEMBER_REMOVED)
+        .target("workspace", workspace_id)
+        .details(serde_json::json!({ "member_id": member_id }));
+    audit::record_request(&mut conn, user_id, event).await;
+    Ok(())
+}
+
+/// Sets a member's role, or removes them with `None`, keeping at least one owner
+#[cfg(feature = "ssr")]
+async fn change_membership(
+    conn: &mut crate::database::db::DbConnection,
+    workspace_id: Uuid,
+    member_id: i32,
+    new_role: Option<WorkspaceRole>,
+) -> Result<(), WorkspaceError> {
+    use diesel::prelude::*;
+    use diesel_async::{AsyncConnection, RunQueryDsl};
+    use crate::schema::workspace_members;
+
+    conn.transaction(|conn| {
+        Box::pin(async move {
+            let member: WorkspaceMember = workspace_members::table
+                .find((workspace_id, member_id))
+                .for_update()
+                .first(conn)
+                .await
+                .optional()?
+                .ok_or(WorkspaceError::NotFound("Member"))?;
+
+            let owners: i64 = workspace_members::table
+                .filter(workspace_members::workspace_id.eq(workspace_id))
+                .filter(workspace_members::role.eq(WorkspaceRole::Owner.as_str()))
+                .count()
+                .get_result(conn)
+                .await?;
+            if !keeps_an_owner(owners, member.role(), new_role) {
+                return Err(WorkspaceError::Invalid("A workspace needs at least one owner".to_string()));
+            }
+
+            let membership = workspace_members::table.find((workspace_id, member_id));
+            match new_role {
+                Some(role) => diesel::update(membership)
+                    .set(workspace_members::role.eq(role.as_str()))
+                    .execute(conn)
+                    .await?,
+                None => diesel::delete(membership).execute(conn).await?,
+            };
+            Ok(())
+        })
+    })
+    .await
+}
+
+/// Moves a project into a workspace, or back to a personal project with `None`.
+/// Needs `Manage` on the project and editor rights in the target workspace.
+#[server(MoveProjectToWorkspace, "/api")]
+pub async fn move_project_to_workspace(
+    project_id: Uuid,
+    workspace_id: Option<Uuid>,
+) -> Result<ProjectView, ServerFnError> {
+    use chrono::Utc;
+    use diesel::prelude::*;
+    use diesel_async::{AsyncConnection, RunQueryDsl};
+    use crate::models::projects::Project;
+    use crate::schema::{projects, threads};
+    use crate::services::workspaces::authorize_project;
+
+    let (user_id, mut conn) = user_connection().await?;
+    let current = authorize_project(&mut conn, user_id, project_id, Permission::Manage)
+        .await
+        .map_err(WorkspaceError::from)?;
+    if let Some(workspace_id) = workspace_id {
+        require_role(&mut conn, workspace_id, user_id, Permission::Edit).await?;
+    }
+
+    // a project leaving its workspace becomes personal to whoever moved it;
+    // moving between workspaces keeps its creator
+    let owner = if workspace_id.is_none() { user_id } else { current.user_id };
+    let moved = current.workspace_id != workspace_id;
+    let project: Project = conn
+        .transaction(|conn| {
+            Box::pin(async move {
+                let project: Project = diesel::update(projects::table.find(project_id))
+                    .set((
+                        projects::workspace_id.eq(workspace_id),
+                        projects::user_id.eq(owner),
+                        projects::updated_at.eq(Some(Utc::now().naive_utc())),
+                    ))
+                    .get_result(conn)
+                    .await?;
+
+                // threads shared with the old workspace must not open up to the new one
+                if moved {
+                    diesel::update(threads::table.filter(threads::project_id.eq(project_id)))
+                        .set(threads::visibility.eq(THREAD_PRIVATE))
+                        .execute(conn)
+                        .await?;
+                }
+                Ok::<_, diesel::result::Error>(project)
+            })
+        })
+        .await
+        .map_err(WorkspaceError::Database)?;
+
+    Ok(project.into())
+}
+
+/// The user's own threads in a project plus those others shared with the workspace
+#[server(
+    prefix = "/api",
+    endpoint = "project-threads",
+    input = GetUrl,
+)]
+pub async fn get_project_threads(project_id: Uuid) -> Result<Vec<ProjectThreadView>, ServerFnError> {
+    use chrono::{DateTime, Utc};
+    use diesel::prelude::*;
+    use diesel_async::RunQueryDsl;
+    use crate::models::conversations::Thread;
+    use crate::models::users::User;
+    use crate::schema::{threads, users};
+    use crate::services::workspaces::authorize_project;
+
+    let (user_id, mut conn) = user_connection().await?;
+    authorize_project(&mut conn, user_id, project_id, Permission::Read).await.map_err(WorkspaceError::from)?;
+
+    let listed: Vec<(Thread, Option<User>)> = threads::table
+        .left_join(users::table)
+        .filter(threads::project_id.eq(project_id))
+        .filter(threads::user_id.eq(user_id).or(threads::visibility.ne(THREAD_PRIVATE)))
+        .order(threads::updated_at.desc())
+        .load(&mut conn)
+        .await
+        .map_err(WorkspaceError::Database)?;
+
+    Ok(listed
+        .into_iter()
+        .map(|(thread, author)| ProjectThreadView {
+            own: thread.user_id == Some(user_id),
+            author: author
+                .and_then(|user| user.display_name.or(user.username))
+                .unwrap_or_else(|| "Anonymous".to_string()),
+            id: thread.id,
+            title: thread.title,
+            visibility: thread.visibility,
+            updated_at: thread.updated_at.map(|dt| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc)),
+        })
+        .collect())
+}
+
+/// Shares a thread with its project's workspace, read-only (`workspace`) or open to
+/// editors' messages (`collaborative`), or makes it private again.
+/// Only the thread's author can change this.
+#[server(SetThreadVisibility, "/api")]
+pub async fn set_thread_visibility(thread_id: String, visibility: String) -> Result<(), ServerFnError> {
+    use diesel::prelude::*;
+    use diesel_async::RunQueryDsl;
+    use crate::models::conversations::Thread;
+    use crate::schema::{projects, threads};
+
+    if !THREAD_VISIBILITIES.contains(&visibility.as_str()) {
+        return Err(WorkspaceError::Invalid(format!("Unknown thread visibility: {visibility}")).into());
+    }
+
+    let (user_id, mut conn) = user_connection().await?;
+
+    let thread: Thread = threads::table
+        .find(&thread_id)
+        .filter(threads::user_id.eq(user_id))
+        .first(&mut conn)
+        .await
+        .optional()
+        .map_err(WorkspaceError::Database)?
+        .ok_or(WorkspaceError::NotFound("Thread"))?;
+
+    if visibility != THREAD_PRIVATE {
+        let in_workspace = match thread.project_id {
+            Some(project_id) => projects::table
+                .find(project_id)
+                .select(projects::workspace_id)
+                .first::<Option<Uuid>>(&mut conn)
+                .await
+                .optional()
+                .map_err(WorkspaceError::Database)?
+                .flatten()
+                .is_some(),
+            None => false,
+        };
+        if !in_workspace {
+            return Err(WorkspaceError::Invalid("Only threads in a workspace project can be shared".to_string()).into());
+        }
+    }
+
+    diesel::update(threads::table.find(&thread.id))
+        .set(threads::visibility.eq(&visibility))
+        .execute(&mut conn)
+        .await
+        .map_err(WorkspaceError::Database)?;
+
+    Ok(())
+}
@@ -0,0 +1,680 @@
+#[cfg(feature = "ssr")]
+pub mod archive_import {
+    use regex::Regex;
+    use std::env;
+    use std::fmt;
+    use std::io::Read;
+    use std::path::{Component, Path, PathBuf};
+
+    /// Never worth indexing, whatever the project's own ignore files say
+    pub const DEFAULT_EXCLUDES: &[&str] = &[
+        ".git/", ".hg/", ".svn/", "node_modules/", "target/", "dist/", "build/", ".venv/", "venv/",
+        "__pycache__/", ".idea/", ".vscode/", ".next/", ".DS_Store", "*.lock", "package-lock.json",
+        "pnpm-lock.yaml", "*.min.js", "*.min.css", "*.map",
+    ];
+
+    /// Extensions skipped without looking at the bytes
+    const BINARY_EXTENSIONS: &[&str] = &[
+        "png", "jpg", "jpeg", "gif", "bmp", "ico", "webp", "svgz", "tiff", "psd", "mp3", "mp4", "mov",
+        "avi", "wav", "flac", "ogg", "webm", "woff", "woff2", "ttf", "otf", "eot", "zip", "gz", "tgz",
+        "bz2", "xz", "7z", "rar", "jar", "war", "class", "exe", "dll", "so", "dylib", "a", "o", "obj",
+        "bin", "wasm", "pyc", "pyo", "db", "sqlite", "sqlite3", "parquet", "pkl", "npy", "pt", "onnx",
+        "safetensors", "doc", "xls", "ppt",
+    ];
+
+    /// Longest filename the documents table accepts
+    const MAX_PATH_LEN: usize = 255;
+
+    #[derive(Debug)]
+    pub enum ArchiveError {
+        Unsupported(String),
+        TooLarge { limit: u64 },
+        TooManyFiles { limit: usize },
+        InvalidPattern(String),
+        Forbidden(String),
+        Malformed(String),
+        Io(std::io::Error),
+    }
+
+    impl fmt::Display for ArchiveError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                ArchiveError::Unsupported(e) => write!(f, "Unsupported archive: {e}"),
+                ArchiveError::TooLarge { limit } => {
+                    write!(f, "Archive contents exceed the {} MB import limit", limit / (1024 * 1024))
+                }
+                ArchiveError::TooManyFiles { limit } => write!(f, "Archive has more than {limit} files"),
+                ArchiveError::InvalidPattern(e) => write!(f, "Invalid pattern: {e}"),
+                ArchiveError::Forbidden(e) => write!(f, "{e}"),
+                ArchiveError::Malformed(e) => write!(f, "Could not read archive: {e}"),
+                ArchiveError::Io(e) => write!(f, "I/O error: {e}"),
+            }
+        }
+    }
+
+    impl std::error::Error for ArchiveError {}
+
+    impl From<std::io::Error> for ArchiveError {
+        fn from(error: std::io::Error) -> Self {
+            ArchiveError::Io(error)
+        }
+    }
+
+    /// Bounds on what a single import may unpack, against zip bombs and runaway
+    /// directory walks
+    #[derive(Debug, Clone, Copy)]
+    pub struct ImportLimits {
+        pub max_file_bytes: u64,
+        pub max_total_bytes: u64,
+        pub max_files: usize,
+    }
+
+    impl ImportLimits {
+        /// `MAX_IMPORT_BYTES` (default 200 MiB) and `MAX_IMPORT_FILES` (default 5000);
+        /// files are capped at the regular upload limit
+        pub fn from_env(max_file_bytes: u64) -> Self {
+            let read = |name: &str, default: u64| {
+                env::var(name)
+                    .ok()
+                    .and_then(|value| value.trim().parse().ok())
+                    .filter(|&value: &u64| value > 0)
+                    .unwrap_or(default)
+            };
+
+            Self {
+                max_file_bytes,
+                max_total_bytes: read("MAX_IMPORT_BYTES", 200 * 1024 * 1024),
+                max_files: read("MAX_IMPORT_FILES", 5000) as usize,
+            }
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct ImportEntry {
+        /// Relative path with `/` separators, used as the document filename
+        pub path: String,
+        pub bytes: Vec<u8>,
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct SkippedEntry {
+        pub path: String,
+        pub reason: String,
+    }
+
+    #[derive(Debug, Default)]
+    pub struct ImportListing {
+        pub entries: Vec<ImportEntry>,
+        pub skipped: Vec<SkippedEntry>,
+    }
+
+    /// One line of a `.gitignore`, or of the include / exclude lists
+    #[derive(Debug, Clone)]
+    struct IgnoreRule {
+        regex: Regex,
+        negated: bool,
+        dir_only: bool,
+        source: String,
+    }
+
+    /// Converts a gitignore glob to a regex body: `**` spans directories, `*` and `?`
+    /// stay within one path component
+    fn glob_to_regex(glob: &str) -> String {
+        let mut regex = String::new();
+        let chars: Vec<char> = glob.chars().collect();
+        let mut i = 0;
+
+        while i < chars.len() {
+            match chars[i] {
+                '*' if chars.get(i + 1) == Some(&'*') => {
+                    if chars.get(i + 2) == Some(&'/') {
+                        regex.push_str("(?:.*/)?");
+                        i += 3;
+                    } else {
+                        regex.push_str(".*");
+                        i += 2;
+                    }
+                    continue;
+                }
+                '*' => regex.push_str("[^/]*"),
+                '?' => regex.push_str("[^/]"),
+                '[' => match chars[i..].iter().position(|&c| c == ']') {
+                    Some(end) if end > 1 => {
+                        let class: String = chars[i + 1..i + end].iter().collect();
+                        let class = class.strip_prefix('!').map(|rest| format!("^{rest}")).unwrap_or(class);
+                        regex.push('[');
+                        regex.push_str(&class.replace('\\', "\\\\"));
+                        regex.push(']');
+                        i += end + 1;
+                        continue;
+                    }
+                    _ => regex.push_str("\\["),
+                },
+                '\\' if i + 1 < chars.len() => {
+                    regex.push_str(&regex::escape(&chars[i + 1].to_string()));
+                    i += 2;
+                    continue;
+                }
+                c => regex.push_str(&regex::escape(&c.to_string())),
+            }
+            i += 1;
+        }
+
+        regex
+    }
+
+    impl IgnoreRule {
+        /// Parses a pattern relative to `base` (a directory path without trailing
+        /// slash, empty for the root). Returns `None` for blank lines and comments.
+        fn parse(line: &str, base: &str, source: &str) -> Result<Option<Self>, ArchiveError> {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                return Ok(None);
+            }
+
+            let (negated, pattern) = match line.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+            let (dir_only, pattern) = match pattern.strip_suffix('/') {
+                Some(rest) => (true, rest),
+                None => (false, pattern),
+            };
+            // a slash anywhere but the end anchors the pattern to its directory
+            let anchored = pattern.contains('/');
+            let pattern = pattern.trim_start_matches('/');
+            if pattern.is_empty() {
+                return Ok(None);
+            }
+
+            let prefix = if base.is_empty() { String::new() } else { format!("{}/", regex::escape(base)) };
+            let any_depth = if anchored { "" } else { "(?:.*/)?" };
+            let regex = Regex::new(&format!("^{prefix}{any_depth}{}$", glob_to_regex(pattern)))
+                .map_err(|e| ArchiveError::InvalidPattern(format!("{line}: {e}")))?;
+
+            Ok(Some(Self { regex, negated, dir_only, source: source.to_string() }))
+        }
+    }
+
+    /// Decides which files of an import are kept, using gitignore semantics: the last
+    /// matching rule wins, `!` re-includes, and nothing under an ignored directory
+    /// can be re-included.
+    #[derive(Debug, Clone, Default)]
+    pub struct PathFilter {
+        rules: Vec<IgnoreRule>,
+        include: Vec<IgnoreRule>,
+    }
+
+    impl PathFilter {
+        /// `include` patterns, when given, must match for a file to be kept; `exclude`
+        /// patterns are applied after the defaults and any `.gitignore` files
+        pub fn new(include: &[String], exclude: &[String]) -> Result<Self, ArchiveError> {
+            let mut filter = Self::default();
+
+            for pattern in DEFAULT_EXCLUDES {
+                filter.rules.extend(IgnoreRule::parse(pattern, "", "default excludes")?);
+            }
+            for pattern in include {
+                filter.include.extend(IgnoreRule::parse(pattern, "", "include patterns")?);
+            }
+            for pattern in exclude {
+                filter.rules.extend(IgnoreRule::parse(pattern, "", &format!("exclude pattern `{pattern}`"))?);
+            }
+
+            Ok(filter)
+        }
+
+        /// Adds the rules of a `.gitignore` found in `dir` ("" for the root).
+        /// Invalid lines are skipped like git does.
+        pub fn add_gitignore(&mut self, dir: &str, contents: &str) {
+            let source = if dir.is_empty() { ".gitignore".to_string() } else { format!("{dir}/.gitignore") };
+            let rules: Vec<IgnoreRule> = contents
+                .lines()
+                .filter_map(|line| IgnoreRule::parse(line, dir, &source).ok().flatten())
+                .collect();
+
+            // user excludes stay last so they still win over a project's negations
+            let user_start = self.rules.iter().position(|r| r.source.starts_with("exclude pattern")).unwrap_or(self.rules.len());
+            self.rules.splice(user_start..user_start, rules);
+        }
+
+        fn ignored_by(&self, path: &str, is_dir: bool) -> Option<&IgnoreRule> {
+            self.rules
+                .iter()
+                .rev()
+                .find(|rule| (is_dir || !rule.dir_only) && rule.regex.is_match(path))
+                .filter(|rule| !rule.negated)
+        }
+
+        /// Why a directory should be skipped, if it should
+        pub fn check_dir(&self, dir: &str) -> Option<String> {
+            self.ignored_by(dir, true).map(|rule| format!("ignored by {}", rule.source))
+        }
+
+        /// Why a file should be skipped, if it should, checking its parent directories too
+        pub fn check_file(&self, path: &str) -> Option<String> {
+            let mut dir = String::new();
+            for component in path.split('/').take(path.matches('/').count()) {
+                if !dir.is_empty() {
+                    dir.push('/');
+                }
+                dir.push_str(component);
+                if let Some(reason) = self.check_dir(&dir) {
+                    return Some(reason);
+                }
+            }
+
+            if let Some(rule) = self.ignored_by(path, false) {
+                return Some(format!("ignored by {}", rule.source));
+            }
+            if !self.include.is_empty() && !self.include.iter().any(|rule| rule.regex.is_match(path)) {
+                return Some("not matched by include patterns".to_string());
+            }
+            None
+        }
+    }
+
+    /// Normalises an archive path; `None` for anything that could escape the import
+    pub fn sanitize_path(raw: &str) -> Option<String> {
+        let normalized = raw.replace('\\', "/");
+        let mut components = Vec::new();
+
+        for component in normalized.split('/') {
+            match component {
+                "" | "." => continue,
+                ".." => return None,
+                c if c.contains(':') => return None,
+                c => components.push(c),
+            }
+        }
+
+        (!components.is_empty()).then(|| components.join("/"))
+    }
+
+    fn is_binary_extension(path: &str) -> bool {
+        path.rsplit_once('.')
+            .map(|(_, ext)| BINARY_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+            .unwrap_or(false)
+    }
+
+    /// Drops a single top-level folder shared by every entry, as in GitHub's
+    /// `repo-main/...` downloads
+    fn strip_common_root(entries: &mut [ImportEntry]) {
+        let Some(root) = entries.first().and_then(|e| e.path.split_once('/')).map(|(root, _)| format!("{root}/")) else {
+            return;
+        };
+        if entries.iter().all(|e| e.path.starts_with(&root)) {
+            for entry in entries.iter_mut() {
+                entry.path = entry.path[root.len()..].to_string();
+            }
+        }
+    }
+
+    /// Collects raw archive members, enforcing the limits
+    struct Collector {
+        limits: ImportLimits,
+        total: u64,
+        files: Vec<ImportEntry>,
+        skipped: Vec<SkippedEntry>,
+    }
+
+    impl Collector {
+        fn new(limits: ImportLimits) -> Self {
+            Self { limits, total: 0, files: Vec::new(), skipped: Vec::new() }
+        }
+
+        fn add(&mut self, raw_path: &str, declared_size: u64, reader: impl Read) -> Result<(), ArchiveError> {
+            let Some(path) = sanitize_path(raw_path) else {
+                self.skipped.push(SkippedEntry { path: raw_path.to_string(), reason: "unsafe path".to_string() });
+                return Ok(());
+            };
+
+            if self.files.len() >= self.limits.max_files {
+                return Err(ArchiveError::TooManyFiles { limit: self.limits.max_files });
+            }
+            if is_binary_extension(&path) {
+                self.skipped.push(SkippedEntry { path, reason: "binary file".to_string() });
+                return Ok(());
+            }
+            if declared_size > self.limits.max_file_bytes {
+                self.skipped.push(SkippedEntry { path, reason: "larger than the upload limit".to_string() });
+                return Ok(());
+            }
+
+            // declared sizes can lie, so never read more than the limit allows
+            let mut bytes = Vec::new();
+            reader.take(self.limits.max_file_bytes + 1).read_to_end(&mut bytes)?;
+            if bytes.len() as u64 > self.limits.max_file_bytes {
+                self.skipped.push(SkippedEntry { path, reason: "larger than the upload limit".to_string() });
+                return Ok(());
+            }
+
+            self.total += bytes.len() as u64;
+            if self.total > self.limits.max_total_bytes {
+                return Err(ArchiveError::TooLarge { limit: self.limits.max_total_bytes });
+            }
+
+            self.files.push(ImportEntry { path, bytes });
+            Ok(())
+        }
+    }
+
+    fn read_zip(bytes: &[u8], collector: &mut Collector) -> Result<(), ArchiveError> {
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes))
+            .map_err(|e| ArchiveError::Malformed(e.to_string()))?;
+
+        for index in 0..archive.len() {
+            let file = archive.by_index(index).map_err(|e| ArchiveError::Malformed(e.to_string()))?;
+            if !file.is_file() {
+                continue;
+            }
+            let name = file.name().to_string();
+            let size = file.size();
+            collector.add(&name, size, file)?;
+        }
+        Ok(())
+    }
+
+    fn read_tar(reader: impl Read, collector: &mut Collector) -> Result<(), ArchiveError> {
+        let mut archive = tar::Archive::new(reader);
+        let entries = archive.entries().map_err(|e| ArchiveError::Malformed(e.to_string()))?;
+
+        for entry in entries {
+            let entry = entry.map_err(|e| ArchiveError::Malformed(e.to_string()))?;
+            // symlinks and hard links could point outside the archive
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let path = entry.path().map_err(|e| ArchiveError::Malformed(e.to_string()))?.to_string_lossy().into_owned();
+            let size = entry.size();
+            collector.add(&path, size, entry)?;
+        }
+        Ok(())
+    }
+
+    /// Applies `.gitignore` files found among the entries plus the filter, and
+    /// drops the ignore files themselves
+    fn filter_entries(entries: Vec<ImportEntry>, mut filter: PathFilter, listing: &mut ImportListing) {
+        let mut gitignores: Vec<(String, String)> = entries
+            .iter()
+            .filter(|e| e.path == ".gitignore" || e.path.ends_with("/.gitignore"))
+            .map(|e| {
+                let dir = e.path.rsplit_once('/').map(|(dir, _)| dir.to_string()).unwrap_or_default();
+                (dir, String::from_utf8_lossy(&e.bytes).into_owned())
+            })
+            .collect();
+        // parents before children, so deeper files can override
+        gitignores.sort_by_key(|(dir, _)| dir.matches('/').count() + usize::from(!dir.is_empty()));
+        for (dir, contents) in &gitignores {
+            filter.add_gitignore(dir, contents);
+        }
+
+        for entry in entries {
+            if entry.path.rsplit('/').next() == Some(".gitignore") {
+                continue;
+            }
+            match filter.check_file(&entry.path) {
+                Some(reason) => listing.skipped.push(SkippedEntry { path: entry.path, reason }),
+                None if entry.path.len() > MAX_PATH_LEN => listing.skipped.push(SkippedEntry {
+                    path: entry.path,
+                    reason: "path too long".to_string(),
+                }),
+                None => listing.entries.push(entry),
+            }
+        }
+    }
+
+    /// Unpacks a zip, tar or tar.gz archive into the files worth importing
+    pub fn read_archive(bytes: &[u8], filter: PathFilter, limits: ImportLimits) -> Result<ImportListing, ArchiveError> {
+        let mut collector = Collector::new(limits);
+
+        if bytes.starts_with(b"PK\x03\x04") || bytes.starts_with(b"PK\x05\x06") {
+            read_zip(bytes, &mut collector)?;
+        } else if bytes.starts_with(b"\x1F\x8B") {
+            read_tar(flate2::read::GzDecoder::new(bytes), &mut collector)?;
+        } else if bytes.len() > 262 && &bytes[257..262] == b"ustar" {
+            read_tar(bytes, &mut collector)?;
+        } else {
+            return Err(ArchiveError::Unsupported("expected a .zip, .tar or .tar.gz file".to_string()));
+        }
+
+        let mut listing = ImportListing { skipped: collector.skipped, ..Default::default() };
+        let mut entries = collector.files;
+        strip_common_root(&mut entries);
+        filter_entries(entries, filter, &mut listing);
+        Ok(listing)
+    }
+
+    /// Resolves a server-side directory for import. Only allowed when the path lies
+    /// under one of the comma separated `IMPORT_ALLOWED_ROOTS`.
+    pub fn resolve_import_directory(path: &str) -> Result<PathBuf, ArchiveError> {
+        let roots: Vec<PathBuf> = env::var("IMPORT_ALLOWED_ROOTS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|root| !root.is_empty())
+            .map(PathBuf::from)
+            .collect();
+        if roots.is_empty() {
+            return Err(ArchiveError::Forbidden("Directory imports are disabled on this server".to_string()));
+        }
+        resolve_under_roots(path, &roots)
+    }
+
+    /// The path is checked against the roots before anything on disk is looked at,
+    /// and every refusal reads the same, so callers can't probe the file system.
+    /// Symlinks are resolved afterwards and must stay under a root too.
+    fn resolve_under_roots(path: &str, roots: &[PathBuf]) -> Result<PathBuf, ArchiveError> {
+        let refused = || ArchiveError::Forbidden("This directory cannot be imported".to_string());
+
+        let requested = normalize_lexically(Path::new(path)).ok_or_else(refused)?;
+        let lexical_roots: Vec<PathBuf> = roots.iter().filter_map(|root| normalize_lexically(root)).collect();
+        if !lexical_roots.iter().any(|root| requested.starts_with(root)) {
+            return Err(refused());
+        }
+
+        let resolved = requested.canonicalize().map_err(|_| refused())?;
+        let canonical_roots: Vec<PathBuf> = roots.iter().filter_map(|root| root.canonicalize().ok()).collect();
+        if !resolved.is_dir() || !canonical_roots.iter().any(|root| resolved.starts_with(root)) {
+            return Err(refused());
+        }
+
+        Ok(resolved)
+    }
+
+    /// Absolute path with `.` and `..` folded away, without touching the disk;
+    /// `None` for relative paths or `..` above the file system root
+    fn normalize_lexically(path: &Path) -> Option<PathBuf> {
+        if !path.is_absolute() {
+            return None;
+        }
+        let mut normalized = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    if !normalized.pop() {
+                        return None;
+                    }
+                }
+                other => normalized.push(other),
+            }
+        }
+        Some(normalized)
+    }
+
+    /// Walks a directory, reading `.gitignore` files on the way down so ignored
+    /// trees such as `node_modules` are never entered. Symlinks are not followed.
+    pub fn read_directory(root: &Path, mut filter: PathFilter, limits: ImportLimits) -> Result<ImportListing, ArchiveError> {
+        let mut collector = Collector::new(limits);
+        let mut pending: Vec<(PathBuf, String)> = vec![(root.to_path_buf(), String::new())];
+
+        while let Some((dir, relative)) = pending.pop() {
+            let gitignore = dir.join(".gitignore");
+            if gitignore.is_file() {
+                filter.add_gitignore(&relative, &std::fs::read_to_string(&gitignore).unwrap_or_default());
+            }
+
+            let mut children: Vec<_> = std::fs::read_dir(&dir)?.filter_map(Result::ok).collect();
+            children.sort_by_key(|entry| entry.file_name());
+
+            for child in children {
+                let name = child.file_name().to_string_lossy().into_owned();
+                let path = if relative.is_empty() { name.clone() } else { format!("{relative}/{name}") };
+                let file_type = child.file_type()?;
+
+                if file_type.is_dir() {
+                    match filter.check_dir(&path) {
+                        Some(reason) => collector.skipped.push(SkippedEntry { path: format!("{path}/"), reason }),
+                        None => pending.push((child.path(), path)),
+                    }
+                } else if file_type.is_file() && name != ".gitignore" {
+                    if let Some(reason) = filter.check_file(&path) {
+                        collector.skipped.push(SkippedEntry { path, reason });
+                        continue;
+                    }
+                    let size = child.metadata()?.len();
+                    if size > limits.max_file_bytes {
+                        collector.skipped.push(SkippedEntry { path, reason: "larger than the upload limit".to_string() });
+                        continue;
+                    }
+                    collector.add(&path, size, std::fs::File::open(child.path())?)?;
+                }
+            }
+        }
+
+        let mut listing = ImportListing { skipped: collector.skipped, ..Default::default() };
+        for entry in collector.files {
+            if entry.path.len() > MAX_PATH_LEN {
+                listing.skipped.push(SkippedEntry { path: entry.path, reason: "path too long".to_string() });
+            } else {
+                listing.entries.push(entry);
+            }
+        }
+        Ok(listing)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::collections::HashMap;
+        use std::io::Write;
+
+        fn limits() -> ImportLimits {
+            ImportLimits { max_file_bytes: 1024, max_total_bytes: 10 * 1024, max_files: 100 }
+        }
+
+        fn paths(listing: &ImportListing) -> Vec<&str> {
+            let mut paths: Vec<&str> = listing.entries.iter().map(|e| e.path.as_str()).collect();
+            paths.sort();
+            paths
+        }
+
+        #[test]
+        fn test_gitignore_semantics() {
+            let mut filter = PathFilter::new(&[], &["secrets/**".to_string()]).unwrap();
+            filter.add_gitignore("", "*.log\n!keep.log\n/out\ndocs/*.tmp\n");
+            filter.add_gitignore("web", "cache/\n");
+
+            assert!(filter.check_file("src/main.rs").is_none());
+            assert!(filter.check_file("logs/debug.log").is_some());
+            assert!(filter.check_file("logs/keep.log").is_none());
+            assert!(filter.check_file("out/bundle.js").is_some());
+            assert!(filter.check_file("src/out/kept.rs").is_none());
+            assert!(filter.check_file("docs/a.tmp").is_some());
+            assert!(filter.check_file("docs/nested/a.tmp").is_none());
+            assert!(filter.check_file("web/cache/x.js").is_some());
+            assert!(filter.check_file("cache/x.js").is_none());
+            assert!(filter.check_file("node_modules/pkg/index.js").is_some());
+            assert_eq!(filter.check_file("secrets/key.txt").as_deref(), Some("ignored by exclude pattern `secrets/**`"));
+        }
+
+        #[test]
+        fn test_include_patterns() {
+            let filter = PathFilter::new(&["src/**/*.rs".to_string(), "*.md".to_string()], &[]).unwrap();
+            assert!(filter.check_file("src/services/mod.rs").is_none());
+            assert!(filter.check_file("README.md").is_none());
+            assert!(filter.check_file("docs/guide.md").is_none());
+            assert!(filter.check_file("build.sh").is_some());
+        }
+
+        #[test]
+        fn test_sanitize_path() {
+            assert_eq!(sanitize_path("./src\\lib.rs").as_deref(), Some("src/lib.rs"));
+            assert_eq!(sanitize_path("/etc/passwd").as_deref(), Some("etc/passwd"));
+            assert_eq!(sanitize_path("a/../../b"), None);
+            assert_eq!(sanitize_path("C:/x"), None);
+        }
+
+        #[test]
+        fn test_read_zip_archive() {
+            let mut buffer = std::io::Cursor::new(Vec::new());
+            {
+                let mut writer = zip::ZipWriter::new(&mut buffer);
+                let options = zip::write::SimpleFileOptions::default();
+                for (name, content) in [
+                    ("repo-main/.gitignore", "*.log\n"),
+                    ("repo-main/src/lib.rs", "pub fn hello() {}"),
+                    ("repo-main/debug.log", "noise"),
+                    ("repo-main/logo.png", "not really a png"),
+                    ("repo-main/node_modules/x/index.js", "module.exports = 1"),
+                    ("repo-main/../escape.txt", "nope"),
+                ] {
+                    writer.start_file(name, options).unwrap();
+                    writer.write_all(content.as_bytes()).unwrap();
+                }
+                writer.start_file("repo-main/big.txt", options).unwrap();
+                writer.write_all(&vec![b'a'; 2048]).unwrap();
+                writer.finish().unwrap();
+            }
+
+            let listing = read_archive(&buffer.into_inner(), PathFilter::new(&[], &[]).unwrap(), limits()).unwrap();
+            assert_eq!(paths(&listing), vec!["src/lib.rs"]);
+
+            let reasons: HashMap<&str, &str> = listing
+                .skipped
+                .iter()
+                .map(|s| (s.path.as_str(), s.reason.as_str()))
+                .collect();
+            assert_eq!(reasons["debug.log"], "ignored by .gitignore");
+            assert_eq!(reasons["repo-main/logo.png"], "binary file");
+            assert_eq!(reasons["repo-main/big.txt"], "larger than the upload limit");
+            assert_eq!(reasons["repo-main/../escape.txt"], "unsafe path");
+            assert!(reasons["node_modules/x/index.js"].contains("default excludes"));
+        }
+
+        #[test]
+        fn test_import_directory_stays_under_roots() {
+            let base = std::env::temp_dir().join(format!("import-roots-{}", std::process::id()));
+            let root = base.join("allowed");
+            std::fs::create_dir_all(root.join("repo")).unwrap();
+            std::fs::create_dir_all(base.join("private")).unwrap();
+            let roots = vec![root.clone()];
+            let resolve = |path: PathBuf| resolve_under_roots(&path.to_string_lossy(), &roots).map_err(|e| e.to_string());
+
+            assert_eq!(resolve(root.join("repo")).unwrap(), root.join("repo").canonicalize().unwrap());
+            let refused = resolve(base.join("private")).unwrap_err();
+            // escapes, missing paths and relative paths all read the same
+            for path in [root.join("../private"), root.join("missing"), base.join("nowhere"), PathBuf::from("allowed/repo")] {
+                assert_eq!(resolve(path).unwrap_err(), refused);
+            }
+
+            #[cfg(unix)]
+            {
+                std::os::unix::fs::symlink(base.join("private"), root.join("link")).unwrap();
+                assert_eq!(resolve(root.join("link")).unwrap_err(), refused);
+            }
+            std::fs::remove_dir_all(&base).unwrap();
+        }
+
+        #[test]
+        fn test_normalize_lexically() {
+            assert_eq!(normalize_lexically(Path::new("/a/./b/../c")), Some(PathBuf::from("/a/c")));
+            assert_eq!(normalize_lexically(Path::new("/..")), None);
+            assert_eq!(normalize_lexically(Path::new("a/b")), None);
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub use archive_import::*;
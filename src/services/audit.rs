@@ -0,0 +1,173 @@
+#[cfg(feature = "ssr")]
+pub mod audit_log {
+    //! Append-only log of security-relevant and destructive actions.
+    //!
+    //! Writing an entry never fails the action being audited: a failed insert
+    //! is logged and the caller carries on. Entries past `AUDIT_RETENTION_DAYS`
+    //! are deleted by a daily background task.
+
+    use axum::extract::ConnectInfo;
+    use chrono::{NaiveDateTime, Utc};
+    use diesel::prelude::*;
+    use diesel_async::{AsyncPgConnection, RunQueryDsl};
+    use log::{error, info};
+    use std::net::SocketAddr;
+    use std::time::Duration;
+
+    use crate::auth::sessions::client_ip;
+    use crate::database::db::DbPool;
+    use crate::models::audit::*;
+    use crate::models::users::User;
+    use crate::schema::{audit_log, users};
+
+    /// How often entries past retention are pruned
+    const PRUNE_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+    /// What happened and to what; the actor and IP are added when recording
+    #[derive(Debug, Clone)]
+    pub struct AuditEvent {
+        action: &'static str,
+        target: Option<(&'static str, String)>,
+        details: Option<serde_json::Value>,
+    }
+
+    impl AuditEvent {
+        pub fn new(action: &'static str) -> Self {
+            Self { action, target: None, details: None }
+        }
+
+        pub fn target(mut self, target_type: &'static str, target_id: impl ToString) -> Self {
+            self.target = Some((target_type, target_id.to_string()));
+            self
+        }
+
+        pub fn details(mut self, details: serde_json::Value) -> Self {
+            self.details = Some(details);
+            self
+        }
+    }
+
+    /// Filters for the admin view; entries come newest first
+    #[derive(Debug, Clone, Default)]
+    pub struct AuditFilter {
+        pub action: Option<String>,
+        pub actor_id: Option<i32>,
+        /// Only entries older than this id, for paging
+        pub before_id: Option<i64>,
+        pub limit: i64,
+    }
+
+    /// Whether the user may read the audit log (`ADMIN_USER_IDS`)
+    pub fn is_audit_admin(user_id: i32) -> bool {
+        admin_user_ids(&|name| std::env::var(name).ok()).contains(&user_id)
+    }
+
+    /// Client IP of the request a server function is handling: the socket
+    /// peer, unless that is one of `TRUSTED_PROXIES`
+    pub fn request_ip() -> Option<String> {
+        let parts = leptos::prelude::use_context::<http::request::Parts>()?;
+        let remote = parts.extensions.get::<ConnectInfo<SocketAddr>>().map(|info| info.0);
+        client_ip(&parts.headers, remote)
+    }
+
+    /// Appends an entry; failures are logged rather than returned
+    pub async fn record(
+        conn: &mut AsyncPgConnection,
+        actor_id: Option<i32>,
+        event: AuditEvent,
+        ip_address: Option<String>,
+    ) {
+        let actor_name = match actor_id {
+            Some(id) => users::table
+                .find(id)
+                .first::<User>(conn)
+                .await
+                .ok()
+                .and_then(|user| user.display_name.or(user.username)),
+            None => None,
+        };
+        let (target_type, target_id) = event.target.unzip();
+        let entry = NewAuditEntry {
+            actor_id,
+            actor_name,
+            action: event.action.to_string(),
+            target_type: target_type.map(str::to_string),
+            target_id,
+            details: event.details,
+            ip_address,
+        };
+
+        if let Err(e) = diesel::insert_into(audit_log::table).values(&entry).execute(conn).await {
+            error!("Failed to write audit entry {:?}: {e}", entry);
+        }
+    }
+
+    /// Like `record`, for callers that hold the pool rather than a connection
+    pub async fn record_pooled(pool: &DbPool, actor_id: Option<i32>, event: AuditEvent, ip_address: Option<String>) {
+        match pool.get().await {
+            Ok(mut conn) => record(&mut conn, actor_id, event, ip_address).await,
+            Err(e) => error!("Failed to write audit entry {:?}: {e}", event),
+        }
+    }
+
+    /// Records an action taken from a server function, with the request's IP
+    pub async fn record_request(conn: &mut AsyncPgConnection, actor_id: i32, event: AuditEvent) {
+        record(conn, Some(actor_id), event, request_ip()).await;
+    }
+
+    pub async fn search(
+        conn: &mut AsyncPgConnection,
+        filter: &AuditFilter,
+    ) -> Result<Vec<AuditEntry>, diesel::result::Error> {
+        let mut query = audit_log::table.into_boxed();
+        if let Some(action) = &filter.action {
+            query = query.filter(audit_log::action.eq(action));
+        }
+        if let Some(actor_id) = filter.actor_id {
+            query = query.filter(audit_log::actor_id.eq(actor_id));
+        }
+        if let Some(before_id) = filter.before_id {
+            query = query.filter(audit_log::id.lt(before_id));
+        }
+        query
+            .order(audit_log::id.desc())
+            .limit(filter.limit)
+            .load::<AuditEntry>(conn)
+            .await
+    }
+
+    /// Deletes entries created before the cutoff, returning how many went
+    pub async fn prune(conn: &mut AsyncPgConnection, cutoff: NaiveDateTime) -> Result<usize, diesel::result::Error> {
+        diesel::delete(audit_log::table.filter(audit_log::created_at.lt(cutoff)))
+            .execute(conn)
+            .await
+    }
+
+    /// Prunes entries past `AUDIT_RETENTION_DAYS` (default 365) once a day
+    pub fn spawn_audit_retention(pool: DbPool) {
+        let Some(days) = audit_retention_days(&|name| std::env::var(name).ok()) else {
+            info!("Audit log retention disabled; entries are kept forever");
+            return;
+        };
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+            loop {
+                interval.tick().await;
+                let cutoff = retention_cutoff(Utc::now().naive_utc(), days);
+                let result = match pool.get().await {
+                    Ok(mut conn) => prune(&mut conn, cutoff).await.map_err(|e| e.to_string()),
+                    Err(e) => Err(e.to_string()),
+                };
+                match result {
+                    Ok(0) => {}
+                    Ok(count) => info!("Pruned {count} audit entries older than {days} days"),
+                    Err(e) => error!("Failed to prune the audit log: {e}"),
+                }
+            }
+        });
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub use audit_log::*;
@@ -0,0 +1,633 @@
+#[cfg(feature = "ssr")]
+pub mod chunking_service {
+    use regex::Regex;
+    use serde_json::{json, Map, Value};
+
+    /// A piece of a document ready to be embedded. Offsets are in characters,
+    /// matching `document_chunks.start_char` / `end_char`.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Chunk {
+        pub text: String,
+        pub start_char: usize,
+        pub end_char: usize,
+        pub metadata: Value,
+    }
+
+    #[derive(Debug, Clone, Copy)]
+    pub struct ChunkingConfig {
+        /// Upper bound for a single chunk; larger sections are split further
+        pub max_chars: usize,
+        /// Adjacent code items smaller than this are merged into one chunk
+        pub min_chars: usize,
+    }
+
+    impl Default for ChunkingConfig {
+        fn default() -> Self {
+            Self {
+                max_chars: 1500,
+                min_chars: 200,
+            }
+        }
+    }
+
+    pub trait Chunker: Send + Sync {
+        fn name(&self) -> &'static str;
+        fn chunk(&self, text: &str) -> Vec<Chunk>;
+    }
+
+    /// Picks a chunker from the file extension, falling back to the declared content type
+    pub fn chunker_for_document(filename: &str, content_type: Option<&str>, config: ChunkingConfig) -> Box<dyn Chunker> {
+        let extension = filename
+            .rsplit_once('.')
+            .map(|(_, ext)| ext.to_ascii_lowercase())
+            .unwrap_or_default();
+
+        if let Some(language) = CodeLanguage::from_extension(&extension) {
+            return Box::new(CodeChunker::new(language, config));
+        }
+
+        match extension.as_str() {
+            "md" | "markdown" | "mdx" => return Box::new(MarkdownChunker::new(config)),
+            "json" | "jsonl" | "csv" | "tsv" | "log" => return Box::new(FixedSizeChunker::new(config.max_chars, config.max_chars / 5)),
+            _ => {}
+        }
+
+        match content_type.unwrap_or_default() {
+            "text/markdown" | "text/x-markdown" => Box::new(MarkdownChunker::new(config)),
+            _ => Box::new(ProseChunker::new(config)),
+        }
+    }
+
+    /// Byte range of the source text plus whatever the chunker knows about it
+    struct Segment {
+        start: usize,
+        end: usize,
+        metadata: Map<String, Value>,
+    }
+
+    fn char_len(text: &str) -> usize {
+        text.chars().count()
+    }
+
+    /// Zero-based line containing the given char offset (offsets past the end map to the last line)
+    pub fn line_at_char(text: &str, char_offset: usize) -> usize {
+        text.chars().take(char_offset).filter(|&c| c == '\n').count()
+    }
+
+    /// Turns byte-range segments into chunks with char offsets, line numbers and word counts
+    fn finish(text: &str, segments: Vec<Segment>, chunker: &str) -> Vec<Chunk> {
+        let mut chunks = Vec::with_capacity(segments.len());
+        // segments arrive in document order, so offsets can be converted incrementally
+        let mut cursor_byte = 0;
+        let mut cursor_char = 0;
+        let mut cursor_line = 1;
+
+        let mut advance = |byte: usize| -> (usize, usize) {
+            if byte >= cursor_byte {
+                let skipped = &text[cursor_byte..byte];
+                cursor_char += char_len(skipped);
+                cursor_line += skipped.matches('\n').count();
+            } else {
+                cursor_char = char_len(&text[..byte]);
+                cursor_line = 1 + text[..byte].matches('\n').count();
+            }
+            cursor_byte = byte;
+            (cursor_char, cursor_line)
+        };
+
+        for segment in segments {
+            let raw = &text[segment.start..segment.end];
+            let leading = raw.len() - raw.trim_start().len();
+            let trimmed = raw.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+
+            let start = segment.start + leading;
+            let end = start + trimmed.len();
+            let (start_char, line_start) = advance(start);
+            let (end_char, line_end) = advance(end);
+
+            let mut metadata = segment.metadata;
+            metadata.insert("chunker".to_string(), json!(chunker));
+            metadata.insert("word_count".to_string(), json!(trimmed.split_whitespace().count()));
+            metadata.insert("line_start".to_string(), json!(line_start));
+            metadata.insert("line_end".to_string(), json!(line_end));
+
+            chunks.push(Chunk {
+                text: trimmed.to_string(),
+                start_char,
+                end_char,
+                metadata: Value::Object(metadata),
+            });
+        }
+
+        chunks
+    }
+
+    /// Byte offsets just past each occurrence of `separator` within `[start, end)`
+    fn boundaries_after(text: &str, start: usize, end: usize, separator: &str) -> Vec<usize> {
+        text[start..end]
+            .match_indices(separator)
+            .map(|(idx, sep)| start + idx + sep.len())
+            .filter(|&pos| pos < end)
+            .collect()
+    }
+
+    fn sentence_boundaries(text: &str, start: usize, end: usize) -> Vec<usize> {
+        let slice = &text[start..end];
+        let mut cuts = Vec::new();
+        let mut chars = slice.char_indices().peekable();
+        while let Some((idx, ch)) = chars.next() {
+            if matches!(ch, '.' | '!' | '?' | '。' | '！' | '？') {
+                if let Some(&(next_idx, next)) = chars.peek() {
+                    if next.is_whitespace() {
+                        cuts.push(start + next_idx);
+                    }
+                } else if idx + ch.len_utf8() < slice.len() {
+                    cuts.push(start + idx + ch.len_utf8());
+                }
+            }
+        }
+        cuts
+    }
+
+    /// Greedily packs the range into pieces of at most `max_chars`, preferring
+    /// paragraph breaks, then line breaks, then sentence ends, then hard cuts.
+    fn split_range(text: &str, start: usize, end: usize, max_chars: usize, level: usize) -> Vec<(usize, usize)> {
+        if char_len(&text[start..end]) <= max_chars {
+            return vec![(start, end)];
+        }
+
+        let cuts = match level {
+            0 => boundaries_after(text, start, end, "\n\n"),
+            1 => boundaries_after(text, start, end, "\n"),
+            2 => sentence_boundaries(text, start, end),
+            _ => {
+                let mut pieces = Vec::new();
+                let mut piece_start = start;
+                let mut count = 0;
+                for (idx, _) in text[start..end].char_indices() {
+                    if count == max_chars {
+                        pieces.push((piece_start, start + idx));
+                        piece_start = start + idx;
+                        count = 0;
+                    }
+                    count += 1;
+                }
+                pieces.push((piece_start, end));
+                return pieces;
+            }
+        };
+
+        let mut pieces = Vec::new();
+        let mut current: Option<(usize, usize)> = None;
+        let mut piece_start = start;
+
+        for piece_end in cuts.into_iter().chain(std::iter::once(end)) {
+            if piece_end <= piece_start {
+                continue;
+            }
+            let piece = (piece_start, piece_end);
+            piece_start = piece_end;
+
+            if char_len(&text[piece.0..piece.1]) > max_chars {
+                if let Some(done) = current.take() {
+                    pieces.push(done);
+                }
+                pieces.extend(split_range(text, piece.0, piece.1, max_chars, level + 1));
+                continue;
+            }
+
+            current = match current {
+                Some((cur_start, _)) if char_len(&text[cur_start..piece.1]) <= max_chars => Some((cur_start, piece.1)),
+                Some(done) => {
+                    pieces.push(done);
+                    Some(piece)
+                }
+                None => Some(piece),
+            };
+        }
+
+        if let Some(done) = current {
+            pieces.push(done);
+        }
+
+        pieces
+    }
+
+    /// Byte offset of the start of each line, plus the line text without its newline
+    fn lines_with_offsets(text: &str) -> Vec<(usize, &str)> {
+        let mut offset = 0;
+        text.split_inclusive('\n')
+            .map(|line| {
+                let start = offset;
+                offset += line.len();
+                (start, line.trim_end_matches(['\n', '\r']))
+            })
+            .collect()
+    }
+
+    /// Splits Markdown by heading hierarchy and records the heading path of each chunk
+    pub struct MarkdownChunker {
+        config: ChunkingConfig,
+        heading: Regex,
+    }
+
+    impl MarkdownChunker {
+        pub fn new(config: ChunkingConfig) -> Self {
+            Self {
+                config,
+                heading: Regex::new(r"^(#{1,6})\s+(.+?)\s*#*\s*$").expect("valid heading regex"),
+            }
+        }
+    }
+
+    impl Chunker for MarkdownChunker {
+        fn name(&self) -> &'static str {
+            "markdown"
+        }
+
+        fn chunk(&self, text: &str) -> Vec<Chunk> {
+            // (start byte, heading path) for each section
+            let mut sections: Vec<(usize, Vec<String>)> = vec![(0, Vec::new())];
+            let mut path: Vec<(usize, String)> = Vec::new();
+            let mut fence: Option<&str> = None;
+
+            for (offset, line) in lines_with_offsets(text) {
+                let trimmed = line.trim_start();
+                if let Some(marker) = fence {
+                    if trimmed.starts_with(marker) {
+                        fence = None;
+                    }
+                    continue;
+                }
+                if trimmed.starts_with("```") {
+                    fence = Some("```");
+                    continue;
+                }
+                if trimmed.starts_with("~~~") {
+                    fence = Some("~~~");
+                    continue;
+                }
+
+                if let Some(caps) = self.heading.captures(line) {
+                    let level = caps[1].len();
+                    path.retain(|(l, _)| *l < level);
+                    path.push((level, caps[2].to_string()));
+                    sections.push((offset, path.iter().map(|(_, title)| title.clone()).collect()));
+                }
+            }
+
+            let mut segments = Vec::new();
+            for (i, (start, section_path)) in sections.iter().enumerate() {
+                let end = sections.get(i + 1).map(|(next, _)| *next).unwrap_or(text.len());
+                if end <= *start {
+                    continue;
+                }
+
+                let pieces = split_range(text, *start, end, self.config.max_chars, 0);
+                let part_count = pieces.len();
+                for (part, (piece_start, piece_end)) in pieces.into_iter().enumerate() {
+                    let mut metadata = Map::new();
+                    metadata.insert("section_path".to_string(), json!(section_path));
+                    if part_count > 1 {
+                        metadata.insert("part".to_string(), json!(part + 1));
+                    }
+                    segments.push(Segment { start: piece_start, end: piece_end, metadata });
+                }
+            }
+
+            finish(text, segments, self.name())
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum CodeLanguage {
+        Rust,
+        Python,
+        JavaScript,
+        Go,
+        Jvm,
+    }
+
+    impl CodeLanguage {
+        pub fn from_extension(extension: &str) -> Option<Self> {
+            match extension {
+                "rs" => Some(Self::Rust),
+                "py" | "pyi" => Some(Self::Python),
+                "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" => Some(Self::JavaScript),
+                "go" => Some(Self::Go),
+                "java" | "kt" | "kts" | "scala" | "cs" => Some(Self::Jvm),
+                _ => None,
+            }
+        }
+
+        fn item_patterns(self) -> &'static [&'static str] {
+            match self {
+                Self::Rust => &[
+                    r#"^(?:pub(?:\([^)]*\))?\s+)?(?:(?:async|const|unsafe|extern(?:\s+"[^"]*")?)\s+)*(fn|struct|enum|trait|union|mod|type|macro_rules!)\s*([A-Za-z_][A-Za-z0-9_]*)"#,
+                    r"^(?:unsafe\s+)?(impl)\b\s*(?:<[^{]*?>)?\s*([^{;]+)",
+                ],
+                Self::Python => &[r"^(?:async\s+)?(def|class)\s+([A-Za-z_][A-Za-z0-9_]*)"],
+                Self::JavaScript => &[
+                    r"^(?:export\s+)?(?:default\s+)?(?:declare\s+)?(?:abstract\s+)?(?:async\s+)?(function\*?|class|interface|enum|type)\s+([A-Za-z_$][A-Za-z0-9_$]*)",
+                    r"^(?:export\s+)?(const|let)\s+([A-Za-z_$][A-Za-z0-9_$]*)\s*=\s*(?:async\s+)?(?:function\b|\([^)]*\)\s*=>|[A-Za-z_$][A-Za-z0-9_$]*\s*=>)",
+                ],
+                Self::Go => &[
+                    r"^(func)\s+(?:\([^)]*\)\s*)?([A-Za-z_][A-Za-z0-9_]*)",
+                    r"^(type)\s+([A-Za-z_][A-Za-z0-9_]*)",
+                ],
+                Self::Jvm => &[
+                    r"^(?:@\w+\s+)*(?:(?:public|private|protected|internal|static|final|abstract|sealed|open|data|partial)\s+)*(class|interface|enum|record|object|fun)\s+([A-Za-z_][A-Za-z0-9_]*)",
+                ],
+            }
+        }
+
+        fn name(self) -> &'static str {
+            match self {
+                Self::Rust => "rust",
+                Self::Python => "python",
+                Self::JavaScript => "javascript",
+                Self::Go => "go",
+                Self::Jvm => "jvm",
+            }
+        }
+    }
+
+    /// (kind, symbol) of a detected item, e.g. ("fn", "add") or ("impl", "impl Display for Point")
+    type ItemSymbol = (String, String);
+
+    /// Splits source files on top-level item boundaries (fn/struct/impl/class...) and records the symbol
+    pub struct CodeChunker {
+        language: CodeLanguage,
+        config: ChunkingConfig,
+        items: Vec<Regex>,
+    }
+
+    impl CodeChunker {
+        pub fn new(language: CodeLanguage, config: ChunkingConfig) -> Self {
+            let items = language
+                .item_patterns()
+                .iter()
+                .map(|pattern| Regex::new(pattern).expect("valid item regex"))
+                .collect();
+            Self { language, config, items }
+        }
+
+        /// Only top-level (column 0) items count; methods stay inside their impl or class
+        fn match_item(&self, line: &str) -> Option<ItemSymbol> {
+            self.items.iter().find_map(|re| {
+                re.captures(line).map(|caps| {
+                    let kind = caps.get(1).map(|m| m.as_str()).unwrap_or_default().to_string();
+                    let name = caps.get(2).map(|m| m.as_str().trim()).unwrap_or_default();
+                    let symbol = if kind == "impl" { format!("impl {name}") } else { name.to_string() };
+                    (kind, symbol)
+                })
+            })
+        }
+
+        /// Doc comments, attributes and decorators belong to the item that follows them
+        fn is_item_prefix(line: &str) -> bool {
+            line.starts_with("//")
+                || line.starts_with("/*")
+                || line.trim_start().starts_with('*')
+                || line.starts_with('#')
+                || line.starts_with('@')
+        }
+    }
+
+    impl Chunker for CodeChunker {
+        fn name(&self) -> &'static str {
+            "code"
+        }
+
+        fn chunk(&self, text: &str) -> Vec<Chunk> {
+            let lines = lines_with_offsets(text);
+
+            // (start byte, kind, symbol); the preamble before the first item has no symbol
+            let mut items: Vec<(usize, Option<ItemSymbol>)> = vec![(0, None)];
+            for (i, (_, line)) in lines.iter().enumerate() {
+                let Some(item) = self.match_item(line) else { continue };
+
+                let mut first = i;
+                while first > 0 && Self::is_item_prefix(lines[first - 1].1) {
+                    first -= 1;
+                }
+                let start = lines[first].0;
+                // don't let leading comments swallow the previous item entirely
+                let start = start.max(items.last().map(|(s, _)| *s).unwrap_or(0));
+                if items.last().map(|(s, _)| *s) == Some(start) {
+                    items.pop();
+                }
+                items.push((start, Some(item)));
+            }
+
+            // merge small neighbouring items so one-line consts don't become their own chunk
+            let mut merged: Vec<(usize, usize, Vec<ItemSymbol>)> = Vec::new();
+            for (i, (start, item)) in items.iter().enumerate() {
+                let end = items.get(i + 1).map(|(next, _)| *next).unwrap_or(text.len());
+                if end <= *start {
+                    continue;
+                }
+                let symbols: Vec<ItemSymbol> = item.iter().cloned().collect();
+
+                if let Some(last) = merged.last_mut() {
+                    let last_len = char_len(&text[last.0..last.1]);
+                    let combined = char_len(&text[last.0..end]);
+                    if last_len < self.config.min_chars && combined <= self.config.max_chars {
+                        last.1 = end;
+                        last.2.extend(symbols);
+                        continue;
+                    }
+                }
+                merged.push((*start, end, symbols));
+            }
+
+            let mut segments = Vec::new();
+            for (start, end, symbols) in merged {
+                let pieces = split_range(text, start, end, self.config.max_chars, 0);
+                let part_count = pieces.len();
+                for (part, (piece_start, piece_end)) in pieces.into_iter().enumerate() {
+                    let mut metadata = Map::new();
+                    metadata.insert("language".to_string(), json!(self.language.name()));
+                    if let Some((kind, symbol)) = symbols.first() {
+                        metadata.insert("symbol".to_string(), json!(symbol));
+                        metadata.insert("kind".to_string(), json!(kind));
+                    }
+                    if symbols.len() > 1 {
+                        let names: Vec<&str> = symbols.iter().map(|(_, symbol)| symbol.as_str()).collect();
+                        metadata.insert("symbols".to_string(), json!(names));
+                    }
+                    if part_count > 1 {
+                        metadata.insert("part".to_string(), json!(part + 1));
+                    }
+                    segments.push(Segment { start: piece_start, end: piece_end, metadata });
+                }
+            }
+
+            finish(text, segments, self.name())
+        }
+    }
+
+    /// Packs paragraphs into chunks, splitting long paragraphs on sentence ends
+    pub struct ProseChunker {
+        config: ChunkingConfig,
+    }
+
+    impl ProseChunker {
+        pub fn new(config: ChunkingConfig) -> Self {
+            Self { config }
+        }
+    }
+
+    impl Chunker for ProseChunker {
+        fn name(&self) -> &'static str {
+            "prose"
+        }
+
+        fn chunk(&self, text: &str) -> Vec<Chunk> {
+            let segments = split_range(text, 0, text.len(), self.config.max_chars, 0)
+                .into_iter()
+                .map(|(start, end)| Segment { start, end, metadata: Map::new() })
+                .collect();
+
+            finish(text, segments, self.name())
+        }
+    }
+
+    /// Fixed-size sliding window, for data files where structure doesn't help retrieval
+    pub struct FixedSizeChunker {
+        chunk_size: usize,
+        overlap: usize,
+    }
+
+    impl FixedSizeChunker {
+        pub fn new(chunk_size: usize, overlap: usize) -> Self {
+            Self { chunk_size, overlap }
+        }
+    }
+
+    impl Chunker for FixedSizeChunker {
+        fn name(&self) -> &'static str {
+            "fixed"
+        }
+
+        fn chunk(&self, text: &str) -> Vec<Chunk> {
+            let mut chunks = Vec::new();
+            let chars: Vec<char> = text.chars().collect();
+            let mut start = 0;
+
+            while start < chars.len() {
+                let end = std::cmp::min(start + self.chunk_size, chars.len());
+                let chunk_text: String = chars[start..end].iter().collect();
+
+                chunks.push(Chunk {
+                    metadata: json!({
+                        "chunker": self.name(),
+                        "word_count": chunk_text.split_whitespace().count(),
+                    }),
+                    text: chunk_text,
+                    start_char: start,
+                    end_char: end,
+                });
+
+                if end >= chars.len() {
+                    break;
+                }
+
+                start = if end > self.overlap { end - self.overlap } else { end };
+            }
+
+            chunks
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn slice_chars(text: &str, start: usize, end: usize) -> String {
+            text.chars().skip(start).take(end - start).collect()
+        }
+
+        #[test]
+        fn test_line_at_char_counts_chars_not_bytes() {
+            let text = "héllo\nwörld\nend";
+            assert_eq!(line_at_char(text, 0), 0);
+            assert_eq!(line_at_char(text, 6), 1);
+            assert_eq!(line_at_char(text, 12), 2);
+            assert_eq!(line_at_char(text, 1000), 2);
+        }
+
+        #[test]
+        fn test_markdown_section_paths() {
+            let text = "# Guide\n\nIntro text.\n\n## Install\n\nRun cargo.\n\n```sh\n# not a heading\n```\n\n## Usage\n\nCall it.\n";
+            let chunks = MarkdownChunker::new(ChunkingConfig::default()).chunk(text);
+
+            let paths: Vec<Value> = chunks.iter().map(|c| c.metadata["section_path"].clone()).collect();
+            assert_eq!(paths, vec![
+                json!(["Guide"]),
+                json!(["Guide", "Install"]),
+                json!(["Guide", "Usage"]),
+            ]);
+            assert!(chunks[1].text.contains("# not a heading"));
+        }
+
+        #[test]
+        fn test_code_chunks_follow_items() {
+            let text = "use std::fmt;\n\n/// Adds numbers\npub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n\npub struct Point {\n    x: i32,\n}\n\nimpl fmt::Display for Point {\n    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {\n        write!(f, \"{}\", self.x)\n    }\n}\n";
+            let config = ChunkingConfig { max_chars: 1500, min_chars: 0 };
+            let chunks = CodeChunker::new(CodeLanguage::Rust, config).chunk(text);
+
+            let symbols: Vec<Option<&str>> = chunks.iter().map(|c| c.metadata["symbol"].as_str()).collect();
+            assert_eq!(symbols, vec![None, Some("add"), Some("Point"), Some("impl fmt::Display for Point")]);
+            assert!(chunks[1].text.starts_with("/// Adds numbers"));
+            assert!(chunks[3].text.starts_with("impl fmt::Display for Point {"));
+            assert!(chunks[3].text.ends_with("    }\n}"));
+        }
+
+        #[test]
+        fn test_oversized_items_split_into_parts() {
+            let body: String = (0..20).map(|i| format!("    fn method_{i}(&self) -> usize {{\n        {i}\n    }}\n\n")).collect();
+            let text = format!("struct Small;\n\nimpl Small {{\n{body}}}\n");
+            let config = ChunkingConfig { max_chars: 200, min_chars: 0 };
+            let chunks = CodeChunker::new(CodeLanguage::Rust, config).chunk(&text);
+
+            assert_eq!(chunks[0].metadata["symbol"], json!("Small"));
+            assert!(chunks.len() > 2);
+            for (i, chunk) in chunks[1..].iter().enumerate() {
+                assert_eq!(chunk.metadata["symbol"], json!("impl Small"));
+                assert_eq!(chunk.metadata["part"], json!(i + 1));
+                assert!(chunk.text.chars().count() <= 200);
+            }
+        }
+
+        #[test]
+        fn test_prose_splits_long_paragraphs_on_sentences() {
+            let paragraph = "This sentence is filler. ".repeat(20);
+            let text = format!("Short intro.\n\n{paragraph}");
+            let config = ChunkingConfig { max_chars: 120, min_chars: 0 };
+            let chunks = ProseChunker::new(config).chunk(&text);
+
+            assert!(chunks.len() > 2);
+            for chunk in &chunks {
+                assert!(chunk.text.chars().count() <= 120);
+                assert!(chunk.text.ends_with('.'));
+            }
+        }
+
+        #[test]
+        fn test_offsets_are_char_based() {
+            let text = "Überschrift über Köln.\n\n日本語の段落です。もう一つの文です。";
+            let chunks = ProseChunker::new(ChunkingConfig { max_chars: 12, min_chars: 0 }).chunk(text);
+
+            for chunk in &chunks {
+                assert_eq!(slice_chars(text, chunk.start_char, chunk.end_char), chunk.text);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub use chunking_service::*;
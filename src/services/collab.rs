@@ -0,0 +1,262 @@
+#[cfg(feature = "ssr")]
+pub mod thread_rooms {
+    //! Live rooms for threads several people have open at once.
+    //!
+    //! Each open thread has a broadcast channel. Every connected viewer gets
+    //! presence and typing updates, a notice when someone posts, and a mirror of
+    //! any assistant answer streaming into the thread. Rooms are kept only in
+    //! memory. A room is created by the first viewer and dropped with the last.
+
+    use axum::response::sse::Event;
+    use dashmap::DashMap;
+    use futures::stream::{self, Stream};
+    use serde::Serialize;
+    use std::collections::HashMap;
+    use std::convert::Infallible;
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+    use std::time::{Duration, Instant};
+    use tokio::sync::broadcast;
+    use uuid::Uuid;
+
+    use crate::models::collab::*;
+
+    /// How long "typing" shows after the last keystroke notice
+    pub const TYPING_TTL: Duration = Duration::from_secs(6);
+    /// Events buffered per room; slower viewers skip ahead rather than hold it up
+    const ROOM_CAPACITY: usize = 256;
+
+    /// One open connection to a thread
+    #[derive(Debug, Clone)]
+    pub struct Viewer {
+        pub user_id: i32,
+        pub name: String,
+        pub typing_until: Option<Instant>,
+    }
+
+    struct Room {
+        sender: broadcast::Sender<Event>,
+        viewers: HashMap<Uuid, Viewer>,
+    }
+
+    #[derive(Clone, Default)]
+    pub struct ThreadRooms {
+        rooms: Arc<DashMap<String, Room>>,
+    }
+
+    /// Who is in a room, one entry per person however many tabs they have open
+    pub fn presence_list<'a>(viewers: impl IntoIterator<Item = &'a Viewer>, now: Instant) -> Vec<PresenceView> {
+        let mut people: Vec<PresenceView> = Vec::new();
+        for viewer in viewers {
+            let typing = viewer.typing_until.is_some_and(|until| until > now);
+            match people.iter_mut().find(|person| person.user_id == viewer.user_id) {
+                Some(person) => person.typing |= typing,
+                None => people.push(PresenceView {
+                    user_id: viewer.user_id,
+                    name: viewer.name.clone(),
+                    typing,
+                }),
+            }
+        }
+        people.sort_by(|a, b| a.name.cmp(&b.name).then(a.user_id.cmp(&b.user_id)));
+        people
+    }
+
+    fn json_event(name: &'static str, payload: &impl Serialize) -> Event {
+        Event::default()
+            .event(name)
+            .data(serde_json::to_string(payload).unwrap_or_default())
+    }
+
+    impl ThreadRooms {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Opens a connection to the thread's room; it leaves when the stream is dropped
+        pub fn join(&self, thread_id: &str, user_id: i32, name: String) -> RoomStream {
+            let connection_id = Uuid::new_v4();
+            let receiver = {
+                let mut room = self.rooms.entry(thread_id.to_string()).or_insert_with(|| Room {
+                    sender: broadcast::channel(ROOM_CAPACITY).0,
+                    viewers: HashMap::new(),
+                });
+                room.viewers.insert(connection_id, Viewer { user_id, name, typing_until: None });
+                room.sender.subscribe()
+            };
+            self.broadcast_presence(thread_id);
+
+            let events = stream::unfold(receiver, |mut receiver| async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(event) => return Some((Ok(event), receiver)),
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            log::debug!("Room viewer lagged, skipped {skipped} events");
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    }
+                }
+            });
+            RoomStream {
+                events: Box::pin(events),
+                _guard: RoomGuard {
+                    rooms: self.clone(),
+                    thread_id: thread_id.to_string(),
+                    connection_id,
+                },
+            }
+        }
+
+        fn leave(&self, thread_id: &str, connection_id: Uuid) {
+            let empty = match self.rooms.get_mut(thread_id) {
+                Some(mut room) => {
+                    room.viewers.remove(&connection_id);
+                    room.viewers.is_empty()
+                }
+                None => return,
+            };
+            if empty {
+                self.rooms.remove_if(thread_id, |_, room| room.viewers.is_empty());
+            } else {
+                self.broadcast_presence(thread_id);
+            }
+        }
+
+        /// Who has the thread open right now
+        pub fn presence(&self, thread_id: &str) -> Vec<PresenceView> {
+            self.rooms
+                .get(thread_id)
+                .map(|room| presence_list(room.viewers.values(), Instant::now()))
+                .unwrap_or_default()
+        }
+
+        /// Marks all of a user's connections to the thread as typing, or not
+        pub fn set_typing(&self, thread_id: &str, user_id: i32, typing: bool) {
+            let changed = match self.rooms.get_mut(thread_id) {
+                Some(mut room) => {
+                    let until = typing.then(|| Instant::now() + TYPING_TTL);
+                    let mut changed = false;
+                    for viewer in room.viewers.values_mut().filter(|viewer| viewer.user_id == user_id) {
+                        changed |= viewer.typing_until.is_some() != until.is_some();
+                        viewer.typing_until = until;
+                    }
+                    changed
+                }
+                None => false,
+            };
+            if changed {
+                self.broadcast_presence(thread_id);
+            }
+        }
+
+        pub fn message_posted(&self, thread_id: &str, posted: &MessagePosted) {
+            self.send(thread_id, json_event(EVENT_MESSAGE_POSTED, posted));
+        }
+
+        pub fn stream_started(&self, thread_id: &str, started: &StreamStarted) {
+            self.send(thread_id, json_event(EVENT_STREAM_STARTED, started));
+        }
+
+        /// Copies one event of an assistant stream to the room, tagged with its stream id
+        pub fn mirror_stream(&self, thread_id: &str, stream_id: Uuid, event: Event) {
+            self.send(thread_id, event.event(EVENT_STREAM).id(stream_id.to_string()));
+        }
+
+        fn send(&self, thread_id: &str, event: Event) {
+            if let Some(room) = self.rooms.get(thread_id) {
+                // an error only means nobody is listening
+                let _ = room.sender.send(event);
+            }
+        }
+
+        fn broadcast_presence(&self, thread_id: &str) {
+            if let Some(room) = self.rooms.get(thread_id) {
+                let people = presence_list(room.viewers.values(), Instant::now());
+                let _ = room.sender.send(json_event(EVENT_PRESENCE, &people));
+            }
+        }
+    }
+
+    struct RoomGuard {
+        rooms: ThreadRooms,
+        thread_id: String,
+        connection_id: Uuid,
+    }
+
+    impl Drop for RoomGuard {
+        fn drop(&mut self) {
+            self.rooms.leave(&self.thread_id, self.connection_id);
+        }
+    }
+
+    /// A viewer's SSE stream of room events
+    pub struct RoomStream {
+        events: Pin<Box<dyn Stream<Item = Result<Event, Infallible>> + Send>>,
+        _guard: RoomGuard,
+    }
+
+    impl Stream for RoomStream {
+        type Item = Result<Event, Infallible>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            self.events.as_mut().poll_next(cx)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn viewer(user_id: i32, name: &str, typing_until: Option<Instant>) -> Viewer {
+            Viewer { user_id, name: name.into(), typing_until }
+        }
+
+        #[test]
+        fn presence_lists_each_person_once() {
+            let now = Instant::now();
+            let viewers = [
+                viewer(2, "Sam", None),
+                viewer(1, "Ana", None),
+                viewer(2, "Sam", Some(now + TYPING_TTL)),
+            ];
+            let people = presence_list(&viewers, now);
+            assert_eq!(people.len(), 2);
+            assert_eq!((people[0].name.as_str(), people[0].typing), ("Ana", false));
+            assert_eq!((people[1].name.as_str(), people[1].typing), ("Sam", true));
+        }
+
+        #[test]
+        fn typing_lapses() {
+            let now = Instant::now();
+            let people = presence_list(&[viewer(1, "Ana", Some(now))], now + Duration::from_millis(1));
+            assert!(!people[0].typing);
+        }
+
+        #[test]
+        fn rooms_close_with_their_last_viewer() {
+            let rooms = ThreadRooms::new();
+            let first = rooms.join("thread", 1, "Ana".into());
+            let second = rooms.join("thread", 2, "Sam".into());
+            assert_eq!(rooms.presence("thread").len(), 2);
+
+            drop(first);
+            assert_eq!(rooms.presence("thread").iter().map(|p| p.user_id).collect::<Vec<_>>(), [2]);
+            drop(second);
+            assert!(rooms.rooms.is_empty());
+        }
+
+        #[test]
+        fn typing_covers_every_tab() {
+            let rooms = ThreadRooms::new();
+            let _tabs = [rooms.join("thread", 1, "Ana".into()), rooms.join("thread", 1, "Ana".into())];
+            rooms.set_typing("thread", 1, true);
+            assert!(rooms.presence("thread")[0].typing);
+            rooms.set_typing("thread", 1, false);
+            assert!(!rooms.presence("thread")[0].typing);
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub use thread_rooms::*;
@@ -0,0 +1,204 @@
+#[cfg(feature = "ssr")]
+pub mod chat_completions {
+    //! Runs a chat completion and stores the answer.
+    //!
+    //! Providers report typed `CompletionEvent`s over a channel; turning them into
+    //! SSE is left to each HTTP endpoint.
+
+    use diesel_async::RunQueryDsl;
+    use log::{debug, error};
+    use tokio::sync::mpsc;
+    use tokio_util::sync::CancellationToken;
+
+    use crate::components::chat::{send_message_stream_with_project_cancellable, RagResponse};
+    use crate::database::db::DbPool;
+    use crate::models::conversations::{DocumentCitation, Message, MessageView, NewMessage, NewMessageView};
+    use crate::schema::messages;
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum CompletionEvent {
+        Status(String),
+        Citations {
+            citations: Vec<DocumentCitation>,
+            trace_id: Option<uuid::Uuid>,
+        },
+        Delta(String),
+        Error(String),
+        /// The provider finished; the runner follows up with `Completed`
+        Done,
+        Cancelled,
+        /// The assistant message as stored
+        Completed(MessageView),
+    }
+
+    /// Encodes an event the way the chat page and mirrored streams read it, as
+    /// `RagResponse` JSON. `Done` gives nothing; the stored answer's `Completed`
+    /// is what the page sees as `done`.
+    pub fn chat_page_data(event: &CompletionEvent) -> Option<String> {
+        let response = |message_type: &str| RagResponse {
+            message_type: message_type.to_string(),
+            content: None,
+            citations: None,
+            status: None,
+            trace_id: None,
+        };
+        let response = match event {
+            CompletionEvent::Status(status) => RagResponse { status: Some(status.clone()), ..response("status") },
+            CompletionEvent::Citations { citations, trace_id } => RagResponse {
+                citations: Some(citations.clone()),
+                trace_id: *trace_id,
+                ..response("citations")
+            },
+            CompletionEvent::Delta(text) => RagResponse { content: Some(text.clone()), ..response("content") },
+            CompletionEvent::Error(e) => RagResponse { content: Some(e.clone()), ..response("error") },
+            CompletionEvent::Completed(_) => response("done"),
+            CompletionEvent::Cancelled => return Some("[CANCELLED]".to_string()),
+            CompletionEvent::Done => return None,
+        };
+        serde_json::to_string(&response).ok()
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct CompletionRequest {
+        pub user_id: i32,
+        pub thread_id: String,
+        pub model: String,
+        pub lab: String,
+    }
+
+    /// Starts answering the thread's latest message. Dropping the receiver cancels
+    /// the provider request.
+    pub fn start_completion(pool: DbPool, request: CompletionRequest) -> mpsc::Receiver<CompletionEvent> {
+        let (events, receiver) = mpsc::channel(100);
+        tokio::spawn(run_completion(pool, request, events));
+        receiver
+    }
+
+    async fn run_completion(pool: DbPool, request: CompletionRequest, events: mpsc::Sender<CompletionEvent>) {
+        let (tx, mut rx) = mpsc::channel::<CompletionEvent>(100);
+        let cancel = CancellationToken::new();
+
+        let provider = {
+            let pool = pool.clone();
+            let request = request.clone();
+            let cancel = cancel.clone();
+            tokio::spawn(async move {
+                let result = send_message_stream_with_project_cancellable(
+                    &pool,
+                    urlencoding::encode(&request.thread_id).into_owned(),
+                    urlencoding::encode(&request.model).into_owned(),
+                    urlencoding::encode(&request.lab).into_owned(),
+                    tx,
+                    cancel,
+                )
+                .await;
+                result.map_err(|e| e.to_string())
+            })
+        };
+
+        let mut content = String::new();
+        let mut citations = Vec::new();
+        let mut trace_id = None;
+        let mut finished = false;
+
+        while let Some(event) = rx.recv().await {
+            match &event {
+                CompletionEvent::Delta(text) => content.push_str(text),
+                CompletionEvent::Citations { citations: found, trace_id: id } => {
+                    citations = found.clone();
+                    trace_id = *id;
+                }
+                CompletionEvent::Done => {
+                    finished = true;
+                    break;
+                }
+                CompletionEvent::Error(_) | CompletionEvent::Cancelled => {
+                    let _ = events.send(event).await;
+                    cancel.cancel();
+                    return;
+                }
+                _ => {}
+            }
+            if events.send(event).await.is_err() {
+                debug!("Completion for thread {} abandoned by its client", request.thread_id);
+                cancel.cancel();
+                return;
+            }
+        }
+
+        if !finished {
+            let reason = match provider.await {
+                Ok(Err(e)) => e,
+                _ => "The model stream ended unexpectedly".to_string(),
+            };
+            let _ = events.send(CompletionEvent::Error(reason)).await;
+            return;
+        }
+
+        let answer = NewMessageView {
+            thread_id: request.thread_id.clone(),
+            content: Some(content),
+            role: "assistant".to_string(),
+            active_model: request.model,
+            active_lab: request.lab,
+            user_id: Some(request.user_id),
+            citations,
+            retrieval_trace_id: trace_id,
+        };
+        let stored = match pool.get().await {
+            Ok(mut conn) => diesel::insert_into(messages::table)
+                .values(NewMessage::from(answer))
+                .get_result::<Message>(&mut conn)
+                .await
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        let event = match stored {
+            Ok(message) => CompletionEvent::Completed(message.into()),
+            Err(e) => {
+                error!("Failed to store answer for thread {}: {e}", request.thread_id);
+                CompletionEvent::Error(format!("Failed to store answer: {e}"))
+            }
+        };
+        let _ = events.send(event).await;
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn page_data(event: &CompletionEvent) -> RagResponse {
+            serde_json::from_str(&chat_page_data(event).unwrap()).unwrap()
+        }
+
+        #[test]
+        fn chat_page_data_keeps_text_as_content() {
+            // text that looks like a marker or JSON stays content
+            for text in ["[DONE]", "[CANCELLED]", r#"{"message_type":"done"}"#] {
+                let data = page_data(&CompletionEvent::Delta(text.into()));
+                assert_eq!(data.message_type, "content");
+                assert_eq!(data.content.as_deref(), Some(text));
+            }
+        }
+
+        #[test]
+        fn chat_page_data_carries_citations_and_status() {
+            let trace = uuid::Uuid::new_v4();
+            let data = page_data(&CompletionEvent::Citations { citations: vec![], trace_id: Some(trace) });
+            assert_eq!((data.message_type.as_str(), data.trace_id), ("citations", Some(trace)));
+            let data = page_data(&CompletionEvent::Status("Searching".into()));
+            assert_eq!(data.status.as_deref(), Some("Searching"));
+            let data = page_data(&CompletionEvent::Error("boom".into()));
+            assert_eq!((data.message_type.as_str(), data.content.as_deref()), ("error", Some("boom")));
+        }
+
+        #[test]
+        fn chat_page_data_markers() {
+            assert_eq!(chat_page_data(&CompletionEvent::Cancelled).as_deref(), Some("[CANCELLED]"));
+            assert_eq!(chat_page_data(&CompletionEvent::Done), None);
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub use chat_completions::*;
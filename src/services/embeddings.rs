@@ -0,0 +1,348 @@
+#[cfg(feature = "ssr")]
+pub mod embedding_providers {
+    use dashmap::DashMap;
+    use futures::future::BoxFuture;
+    use reqwest::Client;
+    use serde::Deserialize;
+    use serde_json::json;
+    use std::env;
+    use std::sync::{Arc, OnceLock};
+
+    use crate::models::projects::EmbeddingModelView;
+
+    /// Model used for projects that never picked one; matches the column default
+    pub const DEFAULT_EMBEDDING_MODEL: &str = "text-embedding-3-small";
+
+    type EmbedResult = Result<Vec<Vec<f32>>, Box<dyn std::error::Error + Send + Sync>>;
+
+    /// Turns text into vectors. The `model_id` is what gets stored in
+    /// `chunk_embeddings.embedding_model`, so vectors from different models never mix.
+    pub trait EmbeddingProvider: Send + Sync {
+        fn model_id(&self) -> &str;
+
+        /// Vector length, when known up front
+        fn dimensions(&self) -> Option<usize>;
+
+        /// One vector per input, in input order
+        fn embed<'a>(&'a self, inputs: &'a [String]) -> BoxFuture<'a, EmbedResult>;
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum EmbeddingBackend {
+        OpenAi,
+        OpenAiCompatible,
+        Local,
+    }
+
+    /// A model that projects can choose
+    #[derive(Debug, Clone)]
+    pub struct EmbeddingModelSpec {
+        pub id: String,
+        pub backend: EmbeddingBackend,
+        /// Model name sent to the backend
+        pub model: String,
+        pub dimensions: Option<usize>,
+    }
+
+    impl From<&EmbeddingModelSpec> for EmbeddingModelView {
+        fn from(spec: &EmbeddingModelSpec) -> Self {
+            let backend = match spec.backend {
+                EmbeddingBackend::OpenAi => "OpenAI",
+                EmbeddingBackend::OpenAiCompatible => "OpenAI-compatible",
+                EmbeddingBackend::Local => "Local",
+            };
+            EmbeddingModelView {
+                id: spec.id.clone(),
+                label: format!("{} ({})", spec.model, backend),
+                dimensions: spec.dimensions,
+            }
+        }
+    }
+
+    /// OpenAI `/embeddings` API, or anything that speaks it (vLLM, LiteLLM, Together, ...)
+    pub struct OpenAiCompatibleEmbeddings {
+        client: Client,
+        base_url: String,
+        api_key: Option<String>,
+        model: String,
+        id: String,
+        dimensions: Option<usize>,
+    }
+
+    #[derive(Deserialize)]
+    struct OpenAiEmbeddingResponse {
+        data: Vec<OpenAiEmbedding>,
+    }
+
+    #[derive(Deserialize)]
+    struct OpenAiEmbedding {
+        index: usize,
+        embedding: Vec<f32>,
+    }
+
+    impl OpenAiCompatibleEmbeddings {
+        pub fn new(base_url: String, api_key: Option<String>, model: String, id: String, dimensions: Option<usize>) -> Self {
+            Self { client: Client::new(), base_url, api_key, model, id, dimensions }
+        }
+    }
+
+    impl EmbeddingProvider for OpenAiCompatibleEmbeddings {
+        fn model_id(&self) -> &str {
+            &self.id
+        }
+
+        fn dimensions(&self) -> Option<usize> {
+            self.dimensions
+        }
+
+        fn embed<'a>(&'a self, inputs: &'a [String]) -> BoxFuture<'a, EmbedResult> {
+            Box::pin(async move {
+                let mut request = self.client
+                    .post(format!("{}/embeddings", self.base_url.trim_end_matches('/')))
+                    .json(&json!({ "model": self.model, "input": inputs }));
+                if let Some(api_key) = &self.api_key {
+                    request = request.bearer_auth(api_key);
+                }
+
+                let mut response: OpenAiEmbeddingResponse = request.send().await?.error_for_status()?.json().await?;
+                response.data.sort_by_key(|e| e.index);
+                let vectors: Vec<Vec<f32>> = response.data.into_iter().map(|e| e.embedding).collect();
+
+                check_vectors(&self.id, self.dimensions, inputs.len(), vectors)
+            })
+        }
+    }
+
+    /// Local model server using the Ollama `/api/embed` shape:
+    /// `{"model", "input": [..]}` in, `{"embeddings": [[..]]}` (or a bare array) out.
+    pub struct LocalEmbeddings {
+        client: Client,
+        url: String,
+        model: String,
+        id: String,
+        dimensions: Option<usize>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum LocalEmbeddingResponse {
+        Wrapped { embeddings: Vec<Vec<f32>> },
+        Bare(Vec<Vec<f32>>),
+    }
+
+    impl LocalEmbeddings {
+        pub fn new(url: String, model: String, id: String, dimensions: Option<usize>) -> Self {
+            Self { client: Client::new(), url, model, id, dimensions }
+        }
+    }
+
+    impl EmbeddingProvider for LocalEmbeddings {
+        fn model_id(&self) -> &str {
+            &self.id
+        }
+
+        fn dimensions(&self) -> Option<usize> {
+            self.dimensions
+        }
+
+        fn embed<'a>(&'a self, inputs: &'a [String]) -> BoxFuture<'a, EmbedResult> {
+            Box::pin(async move {
+                let response: LocalEmbeddingResponse = self.client
+                    .post(&self.url)
+                    .json(&json!({ "model": self.model, "input": inputs }))
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+
+                let vectors = match response {
+                    LocalEmbeddingResponse::Wrapped { embeddings } => embeddings,
+                    LocalEmbeddingResponse::Bare(embeddings) => embeddings,
+                };
+
+                check_vectors(&self.id, self.dimensions, inputs.len(), vectors)
+            })
+        }
+    }
+
+    /// Model id of `StubEmbeddings`; never offered to projects
+    pub const STUB_EMBEDDING_MODEL: &str = "stub:hashed-terms";
+
+    /// Deterministic, offline embeddings for evaluation runs and tests: each query term
+    /// (see `query_terms`) is hashed into one of `dimensions` buckets and the counts are
+    /// L2-normalised. Texts sharing terms are close; nothing else is captured.
+    pub struct StubEmbeddings {
+        dimensions: usize,
+    }
+
+    impl StubEmbeddings {
+        pub fn new(dimensions: usize) -> Self {
+            Self { dimensions: dimensions.max(1) }
+        }
+
+        pub fn embed_one(&self, text: &str) -> Vec<f32> {
+            let mut vector = vec![0.0f32; self.dimensions];
+            for term in crate::services::retrieval::query_terms(text) {
+                // FNV-1a, so vectors are stable across platforms and Rust versions
+                let hash = term.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+                    (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+                });
+                vector[(hash % self.dimensions as u64) as usize] += 1.0;
+            }
+            let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+            if norm > 0.0 {
+                vector.iter_mut().for_each(|v| *v /= norm);
+            }
+            vector
+        }
+    }
+
+    impl Default for StubEmbeddings {
+        fn default() -> Self {
+            Self::new(256)
+        }
+    }
+
+    impl EmbeddingProvider for StubEmbeddings {
+        fn model_id(&self) -> &str {
+            STUB_EMBEDDING_MODEL
+        }
+
+        fn dimensions(&self) -> Option<usize> {
+            Some(self.dimensions)
+        }
+
+        fn embed<'a>(&'a self, inputs: &'a [String]) -> BoxFuture<'a, EmbedResult> {
+            Box::pin(async move { Ok(inputs.iter().map(|input| self.embed_one(input)).collect()) })
+        }
+    }
+
+    fn check_vectors(
+        id: &str,
+        dimensions: Option<usize>,
+        expected: usize,
+        vectors: Vec<Vec<f32>>,
+    ) -> EmbedResult {
+        if vectors.len() != expected {
+            return Err(format!("{id} returned {} embeddings for {expected} inputs", vectors.len()).into());
+        }
+        if let Some(dimensions) = dimensions {
+            if let Some(bad) = vectors.iter().find(|v| v.len() != dimensions) {
+                return Err(format!("{id} returned {} dimensions, expected {dimensions}", bad.len()).into());
+            }
+        }
+        Ok(vectors)
+    }
+
+    fn env_non_empty(name: &str) -> Option<String> {
+        env::var(name).ok().filter(|value| !value.trim().is_empty())
+    }
+
+    /// Parses `name=dims,other` lists from the environment; dims are optional
+    fn parse_model_list(value: &str) -> Vec<(String, Option<usize>)> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| match entry.rsplit_once('=') {
+                Some((name, dims)) => (name.trim().to_string(), dims.trim().parse().ok()),
+                None => (entry.to_string(), None),
+            })
+            .collect()
+    }
+
+    /// Every model a project may use.
+    ///
+    /// OpenAI models are always listed. Extra models come from
+    /// `OPENAI_COMPATIBLE_EMBEDDING_MODELS` (served at `OPENAI_COMPATIBLE_EMBEDDINGS_URL`)
+    /// and `LOCAL_EMBEDDING_MODELS` (served at `LOCAL_EMBEDDINGS_URL`), e.g.
+    /// `LOCAL_EMBEDDING_MODELS=nomic-embed-text=768,mxbai-embed-large=1024`.
+    pub fn available_embedding_models() -> Vec<EmbeddingModelSpec> {
+        let mut models: Vec<EmbeddingModelSpec> = [
+            ("text-embedding-3-small", 1536),
+            ("text-embedding-3-large", 3072),
+            ("text-embedding-ada-002", 1536),
+        ]
+        .into_iter()
+        .map(|(model, dimensions)| EmbeddingModelSpec {
+            id: model.to_string(),
+            backend: EmbeddingBackend::OpenAi,
+            model: model.to_string(),
+            dimensions: Some(dimensions),
+        })
+        .collect();
+
+        if env_non_empty("OPENAI_COMPATIBLE_EMBEDDINGS_URL").is_some() {
+            let list = env_non_empty("OPENAI_COMPATIBLE_EMBEDDING_MODELS").unwrap_or_default();
+            models.extend(parse_model_list(&list).into_iter().map(|(model, dimensions)| EmbeddingModelSpec {
+                id: format!("compatible:{model}"),
+                backend: EmbeddingBackend::OpenAiCompatible,
+                model,
+                dimensions,
+            }));
+        }
+
+        if env_non_empty("LOCAL_EMBEDDINGS_URL").is_some() {
+            let list = env_non_empty("LOCAL_EMBEDDING_MODELS").unwrap_or_default();
+            models.extend(parse_model_list(&list).into_iter().map(|(model, dimensions)| EmbeddingModelSpec {
+                id: format!("local:{model}"),
+                backend: EmbeddingBackend::Local,
+                model,
+                dimensions,
+            }));
+        }
+
+        models
+    }
+
+    pub fn find_embedding_model(model_id: &str) -> Option<EmbeddingModelSpec> {
+        available_embedding_models().into_iter().find(|spec| spec.id == model_id)
+    }
+
+    fn provider_cache() -> &'static DashMap<String, Arc<dyn EmbeddingProvider>> {
+        static PROVIDERS: OnceLock<DashMap<String, Arc<dyn EmbeddingProvider>>> = OnceLock::new();
+        PROVIDERS.get_or_init(DashMap::new)
+    }
+
+    fn build_provider(spec: &EmbeddingModelSpec) -> Result<Arc<dyn EmbeddingProvider>, Box<dyn std::error::Error + Send + Sync>> {
+        let provider: Arc<dyn EmbeddingProvider> = match spec.backend {
+            EmbeddingBackend::OpenAi => {
+                let api_key = env::var("OPENAI_API_KEY").map_err(|_| "OPENAI_API_KEY must be set")?;
+                Arc::new(OpenAiCompatibleEmbeddings::new(
+                    "https://api.openai.com/v1".to_string(),
+                    Some(api_key),
+                    spec.model.clone(),
+                    spec.id.clone(),
+                    spec.dimensions,
+                ))
+            }
+            EmbeddingBackend::OpenAiCompatible => {
+                let base_url = env_non_empty("OPENAI_COMPATIBLE_EMBEDDINGS_URL")
+                    .ok_or("OPENAI_COMPATIBLE_EMBEDDINGS_URL must be set")?;
+                let api_key = env_non_empty("OPENAI_COMPATIBLE_API_KEY");
+                Arc::new(OpenAiCompatibleEmbeddings::new(base_url, api_key, spec.model.clone(), spec.id.clone(), spec.dimensions))
+            }
+            EmbeddingBackend::Local => {
+                let url = env_non_empty("LOCAL_EMBEDDINGS_URL").ok_or("LOCAL_EMBEDDINGS_URL must be set")?;
+                Arc::new(LocalEmbeddings::new(url, spec.model.clone(), spec.id.clone(), spec.dimensions))
+            }
+        };
+        Ok(provider)
+    }
+
+    /// Shared provider for a model id; clients are built once per process
+    pub fn embedding_provider(model_id: &str) -> Result<Arc<dyn EmbeddingProvider>, Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(provider) = provider_cache().get(model_id) {
+            return Ok(provider.clone());
+        }
+
+        let spec = find_embedding_model(model_id).ok_or_else(|| format!("Unknown embedding model: {model_id}"))?;
+        let provider = build_provider(&spec)?;
+        provider_cache().insert(model_id.to_string(), provider.clone());
+        Ok(provider)
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub use embedding_providers::*;
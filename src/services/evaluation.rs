@@ -0,0 +1,420 @@
+#[cfg(feature = "ssr")]
+pub mod rag_evaluation {
+    use serde::{Deserialize, Serialize};
+    use std::collections::{HashMap, HashSet};
+    use std::fmt::Write;
+    use uuid::Uuid;
+
+    use crate::services::retrieval::{query_terms, reciprocal_rank_fusion};
+
+    /// Cut-offs reported as recall@k
+    pub const RECALL_CUTOFFS: [usize; 3] = [1, 3, 5];
+
+    /// A question with the documents a good retrieval should surface
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct GoldenQuestion {
+        pub id: String,
+        pub question: String,
+        /// Paths relative to the set's `documents_dir`
+        pub expected_documents: Vec<String>,
+        /// Reference answer, for reading the report; not scored
+        #[serde(default)]
+        pub expected_answer: Option<String>,
+    }
+
+    /// Golden questions for one project, stored next to a copy of its documents
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct GoldenSet {
+        pub name: String,
+        /// Project the questions were written against, if any
+        #[serde(default)]
+        pub project_id: Option<Uuid>,
+        /// Relative to the golden set file
+        #[serde(default = "default_documents_dir")]
+        pub documents_dir: String,
+        pub questions: Vec<GoldenQuestion>,
+    }
+
+    fn default_documents_dir() -> String {
+        "documents".to_string()
+    }
+
+    /// The retrieval knobs being compared; mirrors the fusion part of `ContextStrategy`
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct RetrievalConfig {
+        pub name: String,
+        /// Chunks handed to the context builder
+        pub limit: usize,
+        pub vector_weight: f32,
+        pub keyword_weight: f32,
+        pub rrf_k: f32,
+        /// Vector candidates below this cosine similarity are dropped (1 - `max_vector_distance`)
+        pub min_similarity: f32,
+        /// How many candidates each retriever contributes, as a multiple of `limit`
+        pub candidate_multiplier: usize,
+    }
+
+    impl RetrievalConfig {
+        /// The defaults `ContextStrategy` ships with
+        pub fn baseline() -> Self {
+            Self {
+                name: "baseline".to_string(),
+                limit: 5,
+                vector_weight: 1.0,
+                keyword_weight: 1.0,
+                rrf_k: 60.0,
+                min_similarity: 0.25,
+                candidate_multiplier: 3,
+            }
+        }
+
+        /// Baseline plus single-retriever and strict-threshold variants
+        pub fn default_matrix() -> Vec<Self> {
+            let baseline = Self::baseline();
+            vec![
+                Self { name: "vector-only".to_string(), keyword_weight: 0.0, ..baseline.clone() },
+                Self { name: "keyword-only".to_string(), vector_weight: 0.0, ..baseline.clone() },
+                Self { name: "threshold-0.72".to_string(), min_similarity: 0.72, ..baseline.clone() },
+                baseline,
+            ]
+        }
+    }
+
+    /// A chunk of a golden set document with its embedding and token cost
+    #[derive(Debug, Clone)]
+    pub struct IndexedChunk {
+        pub document: String,
+        pub text: String,
+        pub vector: Vec<f32>,
+        pub tokens: usize,
+    }
+
+    /// In-memory stand-in for the pgvector + full-text retrieval, fused the same way
+    pub struct EvalIndex {
+        chunks: Vec<IndexedChunk>,
+        term_counts: Vec<HashMap<String, usize>>,
+    }
+
+    fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+        let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+        let norm = a.iter().map(|x| x * x).sum::<f32>().sqrt() * b.iter().map(|y| y * y).sum::<f32>().sqrt();
+        if norm > 0.0 { dot / norm } else { 0.0 }
+    }
+
+    fn sort_desc(scored: &mut [(usize, f32)]) {
+        // stable, so ties keep document order and runs are reproducible
+        scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    }
+
+    impl EvalIndex {
+        pub fn new(chunks: Vec<IndexedChunk>) -> Self {
+            let term_counts = chunks
+                .iter()
+                .map(|chunk| {
+                    let mut counts = HashMap::new();
+                    for term in chunk.text.split(|c: char| !(c.is_alphanumeric() || c == '_')) {
+                        let term = term.trim_matches('_').to_lowercase();
+                        if !term.is_empty() {
+                            *counts.entry(term).or_insert(0) += 1;
+                        }
+                    }
+                    counts
+                })
+                .collect();
+            Self { chunks, term_counts }
+        }
+
+        pub fn chunks(&self) -> &[IndexedChunk] {
+            &self.chunks
+        }
+
+        /// Chunk indices, best first
+        pub fn search(&self, query: &str, query_vector: &[f32], config: &RetrievalConfig) -> Vec<usize> {
+            let candidates = config.limit.max(1) * config.candidate_multiplier.max(1);
+
+            let mut vector: Vec<(usize, f32)> = self.chunks
+                .iter()
+                .enumerate()
+                .map(|(i, chunk)| (i, cosine_similarity(query_vector, &chunk.vector)))
+                .filter(|(_, similarity)| *similarity >= config.min_similarity)
+                .collect();
+            sort_desc(&mut vector);
+
+            // roughly ts_rank: diminishing returns for repeated terms
+            let terms = query_terms(query);
+            let mut keyword: Vec<(usize, f32)> = self.term_counts
+                .iter()
+                .enumerate()
+                .map(|(i, counts)| {
+                    let score = terms
+                        .iter()
+                        .filter_map(|term| counts.get(term))
+                        .map(|&count| 1.0 + (count as f32).ln())
+                        .sum::<f32>();
+                    (i, score)
+                })
+                .filter(|(_, score)| *score > 0.0)
+                .collect();
+            sort_desc(&mut keyword);
+
+            let vector_ids: Vec<usize> = vector.into_iter().take(candidates).map(|(i, _)| i).collect();
+            let keyword_ids: Vec<usize> = keyword.into_iter().take(candidates).map(|(i, _)| i).collect();
+            let rankings: Vec<(&[usize], f32)> = [
+                (vector_ids.as_slice(), config.vector_weight),
+                (keyword_ids.as_slice(), config.keyword_weight),
+            ]
+            .into_iter()
+            .filter(|(_, weight)| *weight > 0.0)
+            .collect();
+
+            reciprocal_rank_fusion(&rankings, config.rrf_k)
+                .into_iter()
+                .take(config.limit)
+                .map(|(i, _)| i)
+                .collect()
+        }
+    }
+
+    /// Share of the expected documents found in the first `k` retrieved ones
+    pub fn recall_at_k(retrieved: &[String], expected: &[String], k: usize) -> f64 {
+        if expected.is_empty() {
+            return 1.0;
+        }
+        let top: HashSet<&String> = retrieved.iter().take(k).collect();
+        expected.iter().filter(|doc| top.contains(doc)).count() as f64 / expected.len() as f64
+    }
+
+    /// 1-based rank of the first expected document, if any was retrieved
+    pub fn first_relevant_rank(retrieved: &[String], expected: &[String]) -> Option<usize> {
+        retrieved.iter().position(|doc| expected.contains(doc)).map(|i| i + 1)
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct QuestionOutcome {
+        pub id: String,
+        /// Distinct documents in retrieval order
+        pub retrieved_documents: Vec<String>,
+        pub missing_documents: Vec<String>,
+        pub first_relevant_rank: Option<usize>,
+        /// Tokens of the retrieved chunks, i.e. what the context costs before expansion
+        pub context_tokens: usize,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ConfigReport {
+        pub config: RetrievalConfig,
+        /// (k, mean recall@k)
+        pub recall_at: Vec<(usize, f64)>,
+        pub mrr: f64,
+        pub mean_context_tokens: f64,
+        pub questions: Vec<QuestionOutcome>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct EvalReport {
+        pub set: String,
+        pub embedder: String,
+        pub configs: Vec<ConfigReport>,
+    }
+
+    /// Runs every question against every config. `query_vectors` are in question order.
+    pub fn evaluate(
+        set: &GoldenSet,
+        index: &EvalIndex,
+        query_vectors: &[Vec<f32>],
+        configs: &[RetrievalConfig],
+        embedder: &str,
+    ) -> EvalReport {
+        let configs = configs
+            .iter()
+            .map(|config| {
+                let questions: Vec<QuestionOutcome> = set.questions
+                    .iter()
+                    .zip(query_vectors)
+                    .map(|(question, vector)| {
+                        let hits = index.search(&question.question, vector, config);
+                        let mut retrieved: Vec<String> = Vec::new();
+                        for &hit in &hits {
+                            let document = &index.chunks[hit].document;
+                            if !retrieved.contains(document) {
+                                retrieved.push(document.clone());
+                            }
+                        }
+                        QuestionOutcome {
+                            id: question.id.clone(),
+                            missing_documents: question.expected_documents
+                                .iter()
+                                .filter(|doc| !retrieved.contains(doc))
+                                .cloned()
+                                .collect(),
+                            first_relevant_rank: first_relevant_rank(&retrieved, &question.expected_documents),
+                            context_tokens: hits.iter().map(|&hit| index.chunks[hit].tokens).sum(),
+                            retrieved_documents: retrieved,
+                        }
+                    })
+                    .collect();
+
+                let count = questions.len().max(1) as f64;
+                let recall_at = RECALL_CUTOFFS
+                    .iter()
+                    .map(|&k| {
+                        let total: f64 = set.questions
+                            .iter()
+                            .zip(&questions)
+                            .map(|(question, outcome)| recall_at_k(&outcome.retrieved_documents, &question.expected_documents, k))
+                            .sum();
+                        (k, total / count)
+                    })
+                    .collect();
+                let mrr = questions
+                    .iter()
+                    .map(|outcome| outcome.first_relevant_rank.map(|rank| 1.0 / rank as f64).unwrap_or(0.0))
+                    .sum::<f64>() / count;
+                let mean_context_tokens = questions.iter().map(|outcome| outcome.context_tokens as f64).sum::<f64>() / count;
+
+                ConfigReport { config: config.clone(), recall_at, mrr, mean_context_tokens, questions }
+            })
+            .collect();
+
+        EvalReport { set: set.name.clone(), embedder: embedder.to_string(), configs }
+    }
+
+    /// Markdown summary with fixed precision and ordering, so reports diff cleanly
+    pub fn render_report(report: &EvalReport) -> String {
+        let mut out = String::new();
+        writeln!(out, "# Retrieval evaluation: {}", report.set).unwrap();
+        writeln!(out).unwrap();
+        writeln!(out, "Embedder: `{}`", report.embedder).unwrap();
+        writeln!(out).unwrap();
+
+        let recall_headers: Vec<String> = RECALL_CUTOFFS.iter().map(|k| format!("recall@{k}")).collect();
+        writeln!(out, "| config | {} | MRR | context tokens |", recall_headers.join(" | ")).unwrap();
+        writeln!(out, "|---|{}---|---|", "---|".repeat(RECALL_CUTOFFS.len())).unwrap();
+        for config in &report.configs {
+            let recalls: Vec<String> = config.recall_at.iter().map(|(_, recall)| format!("{recall:.3}")).collect();
+            writeln!(
+                out,
+                "| {} | {} | {:.3} | {:.0} |",
+                config.config.name,
+                recalls.join(" | "),
+                config.mrr,
+                config.mean_context_tokens
+            ).unwrap();
+        }
+
+        for config in &report.configs {
+            let misses: Vec<&QuestionOutcome> = config.questions.iter().filter(|q| !q.missing_documents.is_empty()).collect();
+            if misses.is_empty() {
+                continue;
+            }
+            writeln!(out).unwrap();
+            writeln!(out, "## Misses: {}", config.config.name).unwrap();
+            writeln!(out).unwrap();
+            for miss in misses {
+                writeln!(
+                    out,
+                    "- `{}`: missing {}; retrieved {}",
+                    miss.id,
+                    miss.missing_documents.join(", "),
+                    if miss.retrieved_documents.is_empty() { "nothing".to_string() } else { miss.retrieved_documents.join(", ") }
+                ).unwrap();
+            }
+        }
+
+        out
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn docs(names: &[&str]) -> Vec<String> {
+            names.iter().map(|name| name.to_string()).collect()
+        }
+
+        fn chunk(document: &str, text: &str, vector: Vec<f32>) -> IndexedChunk {
+            IndexedChunk { document: document.to_string(), text: text.to_string(), vector, tokens: 10 }
+        }
+
+        #[test]
+        fn test_recall_and_rank() {
+            let retrieved = docs(&["a.md", "b.md", "c.md"]);
+            assert_eq!(recall_at_k(&retrieved, &docs(&["b.md", "z.md"]), 1), 0.0);
+            assert_eq!(recall_at_k(&retrieved, &docs(&["b.md", "z.md"]), 3), 0.5);
+            assert_eq!(first_relevant_rank(&retrieved, &docs(&["c.md"])), Some(3));
+            assert_eq!(first_relevant_rank(&retrieved, &docs(&["z.md"])), None);
+        }
+
+        #[test]
+        fn test_threshold_and_weights_change_ranking() {
+            let index = EvalIndex::new(vec![
+                chunk("vector.md", "unrelated words", vec![1.0, 0.0]),
+                chunk("keyword.md", "process_document splits text", vec![0.0, 1.0]),
+            ]);
+            let query_vector = [0.9, 0.1];
+            let baseline = RetrievalConfig::baseline();
+
+            let hits = index.search("process_document", &query_vector, &baseline);
+            assert_eq!(hits.len(), 2);
+
+            let keyword_only = RetrievalConfig { vector_weight: 0.0, ..baseline.clone() };
+            assert_eq!(index.search("process_document", &query_vector, &keyword_only), vec![1]);
+
+            let strict = RetrievalConfig { keyword_weight: 0.0, min_similarity: 0.999, ..baseline };
+            assert!(index.search("process_document", &query_vector, &strict).is_empty());
+        }
+
+        #[test]
+        fn test_evaluate_aggregates_per_config() {
+            let index = EvalIndex::new(vec![
+                chunk("auth.md", "login tokens expire", vec![1.0, 0.0]),
+                chunk("db.md", "pool connections", vec![0.0, 1.0]),
+            ]);
+            let set = GoldenSet {
+                name: "sample".to_string(),
+                project_id: None,
+                documents_dir: default_documents_dir(),
+                questions: vec![
+                    GoldenQuestion { id: "q1".into(), question: "when do tokens expire".into(), expected_documents: docs(&["auth.md"]), expected_answer: None },
+                    GoldenQuestion { id: "q2".into(), question: "pool size".into(), expected_documents: docs(&["db.md"]), expected_answer: None },
+                ],
+            };
+            let vectors = vec![vec![1.0, 0.0], vec![0.0, 1.0]];
+            let report = evaluate(&set, &index, &vectors, &[RetrievalConfig::baseline()], "stub");
+
+            let baseline = &report.configs[0];
+            assert_eq!(baseline.recall_at[0], (1, 1.0));
+            assert_eq!(baseline.mrr, 1.0);
+            assert!(baseline.mean_context_tokens > 0.0);
+        }
+
+        #[test]
+        fn test_report_is_stable_and_lists_misses() {
+            let report = EvalReport {
+                set: "sample".to_string(),
+                embedder: "stub".to_string(),
+                configs: vec![ConfigReport {
+                    config: RetrievalConfig::baseline(),
+                    recall_at: vec![(1, 0.5), (3, 0.75), (5, 1.0)],
+                    mrr: 2.0 / 3.0,
+                    mean_context_tokens: 120.4,
+                    questions: vec![QuestionOutcome {
+                        id: "q2".to_string(),
+                        retrieved_documents: docs(&["a.md"]),
+                        missing_documents: docs(&["b.md"]),
+                        first_relevant_rank: None,
+                        context_tokens: 80,
+                    }],
+                }],
+            };
+
+            let rendered = render_report(&report);
+            assert_eq!(rendered, render_report(&report));
+            assert!(rendered.contains("| baseline | 0.500 | 0.750 | 1.000 | 0.667 | 120 |"));
+            assert!(rendered.contains("- `q2`: missing b.md; retrieved a.md"));
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub use rag_evaluation::*;
@@ -0,0 +1,748 @@
+#[cfg(feature = "ssr")]
+pub mod text_extraction {
+    use quick_xml::events::{BytesStart, Event};
+    use quick_xml::Reader;
+    use scraper::{Html, Node};
+    use serde::{Deserialize, Serialize};
+    use serde_json::{json, Value};
+    use std::fmt;
+    use std::io::{Cursor, Read};
+
+    /// Formats that are converted to plain text before chunking
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum DocumentFormat {
+        PlainText,
+        Html,
+        Pdf,
+        Docx,
+        Pptx,
+        Odt,
+    }
+
+    impl DocumentFormat {
+        /// MIME type stored with the document
+        pub fn mime_type(&self, filename: &str) -> &'static str {
+            match self {
+                Self::PlainText if is_markdown(filename) => "text/markdown",
+                Self::PlainText => "text/plain",
+                Self::Html => "text/html",
+                Self::Pdf => "application/pdf",
+                Self::Docx => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+                Self::Pptx => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+                Self::Odt => "application/vnd.oasis.opendocument.text",
+            }
+        }
+    }
+
+    fn extension(filename: &str) -> String {
+        filename
+            .rsplit_once('.')
+            .map(|(_, ext)| ext.to_ascii_lowercase())
+            .unwrap_or_default()
+    }
+
+    fn is_markdown(filename: &str) -> bool {
+        matches!(extension(filename).as_str(), "md" | "markdown" | "mdx")
+    }
+
+    /// Magic numbers of common files we can't index, for a clearer rejection message
+    const KNOWN_BINARIES: &[(&[u8], &str)] = &[
+        (b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1", "legacy Office files (.doc, .xls, .ppt); save as .docx or PDF"),
+        (b"\x89PNG", "images"),
+        (b"\xFF\xD8\xFF", "images"),
+        (b"GIF8", "images"),
+        (b"\x1F\x8B", "gzip archives"),
+        (b"7z\xBC\xAF", "7z archives"),
+        (b"Rar!", "RAR archives"),
+        (b"\x7FELF", "executables"),
+        (b"MZ", "executables"),
+    ];
+
+    /// Works out what an upload is from its bytes; the client's MIME type is not
+    /// trusted. The filename only decides between text flavours (HTML vs plain).
+    pub fn sniff_format(bytes: &[u8], filename: &str) -> Result<DocumentFormat, ExtractionError> {
+        if bytes.starts_with(b"%PDF-") {
+            return Ok(DocumentFormat::Pdf);
+        }
+
+        if bytes.starts_with(b"PK\x03\x04") {
+            let mut archive = open_zip(bytes)?;
+            if archive.by_name("word/document.xml").is_ok() {
+                return Ok(DocumentFormat::Docx);
+            }
+            if archive.by_name("ppt/presentation.xml").is_ok() {
+                return Ok(DocumentFormat::Pptx);
+            }
+            if let Ok(mimetype) = read_zip_entry(&mut archive, "mimetype") {
+                if mimetype.trim() == "application/vnd.oasis.opendocument.text" {
+                    return Ok(DocumentFormat::Odt);
+                }
+            }
+            return Err(ExtractionError::Unsupported("zip archives other than .docx, .pptx and .odt".to_string()));
+        }
+
+        if let Some((_, kind)) = KNOWN_BINARIES.iter().find(|(magic, _)| bytes.starts_with(magic)) {
+            return Err(ExtractionError::Unsupported(kind.to_string()));
+        }
+
+        let text = std::str::from_utf8(bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes))
+            .map_err(|_| ExtractionError::Unsupported("binary files or text that isn't UTF-8".to_string()))?;
+        if text.contains('\0') {
+            return Err(ExtractionError::Unsupported("binary files".to_string()));
+        }
+
+        let head = text.trim_start().chars().take(256).collect::<String>().to_ascii_lowercase();
+        let looks_like_html = head.starts_with("<!doctype html") || head.starts_with("<html");
+        if looks_like_html || matches!(extension(filename).as_str(), "html" | "htm" | "xhtml") {
+            Ok(DocumentFormat::Html)
+        } else {
+            Ok(DocumentFormat::PlainText)
+        }
+    }
+
+    /// A span of extracted text that sits on one page and under one heading.
+    /// Offsets are in chars, like chunk offsets.
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct TextSegment {
+        pub start_char: usize,
+        pub end_char: usize,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub page: Option<u32>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub section_path: Vec<String>,
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct ExtractedDocument {
+        pub text: String,
+        pub segments: Vec<TextSegment>,
+        pub page_count: Option<u32>,
+    }
+
+    #[derive(Debug)]
+    pub enum ExtractionError {
+        Unsupported(String),
+        Encrypted,
+        NoText,
+        Malformed(String),
+    }
+
+    impl fmt::Display for ExtractionError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                ExtractionError::Unsupported(e) => write!(f, "Unsupported file type: {e} can't be indexed"),
+                ExtractionError::Encrypted => write!(f, "The document is password protected"),
+                ExtractionError::NoText => {
+                    write!(f, "No text could be extracted (scanned documents need OCR first)")
+                }
+                ExtractionError::Malformed(e) => write!(f, "Could not read document: {e}"),
+            }
+        }
+    }
+
+    impl std::error::Error for ExtractionError {}
+
+    /// Converts an uploaded file to plain text, keeping track of which page and
+    /// heading every part of the text came from
+    pub fn extract_document(bytes: &[u8], format: DocumentFormat) -> Result<ExtractedDocument, ExtractionError> {
+        let extracted = match format {
+            DocumentFormat::PlainText => {
+                let text = String::from_utf8_lossy(bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes)).into_owned();
+                if text.trim().is_empty() {
+                    return Err(ExtractionError::NoText);
+                }
+                return Ok(ExtractedDocument { text, segments: Vec::new(), page_count: None });
+            }
+            DocumentFormat::Html => extract_html(&String::from_utf8_lossy(bytes)),
+            DocumentFormat::Pdf => extract_pdf(bytes)?,
+            DocumentFormat::Docx => extract_docx(bytes)?,
+            DocumentFormat::Pptx => extract_pptx(bytes)?,
+            DocumentFormat::Odt => extract_odt(bytes)?,
+        };
+
+        if extracted.text.trim().is_empty() {
+            return Err(ExtractionError::NoText);
+        }
+        Ok(extracted)
+    }
+
+    /// Adds `page`, `page_end` and `section_path` to a chunk's metadata from the
+    /// segments it overlaps. Existing keys (e.g. Markdown section paths) win.
+    pub fn annotate_chunk(metadata: &mut Value, start_char: usize, end_char: usize, segments: &[TextSegment]) {
+        let overlapping: Vec<&TextSegment> = segments
+            .iter()
+            .filter(|s| s.start_char < end_char.max(start_char + 1) && s.end_char > start_char)
+            .collect();
+        let Some(first) = overlapping.first() else { return };
+        let Some(map) = metadata.as_object_mut() else { return };
+
+        let pages: Vec<u32> = overlapping.iter().filter_map(|s| s.page).collect();
+        if let (Some(&first_page), Some(&last_page)) = (pages.iter().min(), pages.iter().max()) {
+            map.entry("page").or_insert(json!(first_page));
+            if last_page != first_page {
+                map.entry("page_end").or_insert(json!(last_page));
+            }
+        }
+
+        if !first.section_path.is_empty() {
+            map.entry("section_path").or_insert(json!(first.section_path));
+        }
+    }
+
+    /// Human readable position of a chunk for citations, e.g. "page 12",
+    /// "pages 3-4" or "Setup > Install"
+    pub fn chunk_location(metadata: &Value) -> Option<String> {
+        let page = metadata.get("page").and_then(Value::as_u64);
+        let page_end = metadata.get("page_end").and_then(Value::as_u64);
+        let section = metadata
+            .get("section_path")
+            .and_then(Value::as_array)
+            .map(|path| path.iter().filter_map(Value::as_str).collect::<Vec<_>>().join(" > "))
+            .filter(|path| !path.is_empty());
+
+        let pages = match (page, page_end) {
+            (Some(start), Some(end)) if end != start => Some(format!("pages {start}-{end}")),
+            (Some(start), _) => Some(format!("page {start}")),
+            _ => None,
+        };
+
+        match (pages, section) {
+            (Some(pages), Some(section)) => Some(format!("{pages}, {section}")),
+            (pages, section) => pages.or(section),
+        }
+    }
+
+    /// Accumulates extracted text and opens a new segment whenever the page or
+    /// heading changes
+    struct TextBuilder {
+        text: String,
+        chars: usize,
+        page: Option<u32>,
+        section_path: Vec<String>,
+        segment_start: usize,
+        segments: Vec<TextSegment>,
+    }
+
+    impl TextBuilder {
+        fn new(page: Option<u32>) -> Self {
+            Self {
+                text: String::new(),
+                chars: 0,
+                page,
+                section_path: Vec::new(),
+                segment_start: 0,
+                segments: Vec::new(),
+            }
+        }
+
+        fn push(&mut self, text: &str) {
+            for c in text.chars() {
+                let c = if c.is_whitespace() && c != '\n' { ' ' } else { c };
+                // collapse runs of spaces and never start a line with one
+                if c == ' ' && (self.text.is_empty() || self.text.ends_with([' ', '\n'])) {
+                    continue;
+                }
+                self.text.push(c);
+                self.chars += 1;
+            }
+        }
+
+        fn trim_trailing_spaces(&mut self) {
+            while self.text.ends_with(' ') {
+                self.text.pop();
+                self.chars -= 1;
+            }
+        }
+
+        fn line_break(&mut self) {
+            self.trim_trailing_spaces();
+            if !self.text.is_empty() && !self.text.ends_with('\n') {
+                self.text.push('\n');
+                self.chars += 1;
+            }
+        }
+
+        /// Blank line, which the prose chunker treats as a paragraph boundary
+        fn paragraph_break(&mut self) {
+            self.trim_trailing_spaces();
+            while !self.text.is_empty() && !self.text.ends_with("\n\n") {
+                self.text.push('\n');
+                self.chars += 1;
+            }
+        }
+
+        fn close_segment(&mut self) {
+            if self.chars > self.segment_start {
+                self.segments.push(TextSegment {
+                    start_char: self.segment_start,
+                    end_char: self.chars,
+                    page: self.page,
+                    section_path: self.section_path.clone(),
+                });
+            }
+            self.segment_start = self.chars;
+        }
+
+        fn set_page(&mut self, page: u32) {
+            if self.page != Some(page) {
+                self.close_segment();
+                self.page = Some(page);
+            }
+        }
+
+        /// Starts a section for a heading of the given level (1 = top)
+        fn heading(&mut self, level: usize, title: &str) {
+            let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+            if title.is_empty() {
+                return;
+            }
+            self.paragraph_break();
+            self.close_segment();
+            self.section_path.truncate(level.max(1) - 1);
+            self.section_path.push(title.clone());
+            self.push(&title);
+            self.paragraph_break();
+        }
+
+        fn finish(mut self, page_count: Option<u32>) -> ExtractedDocument {
+            self.trim_trailing_spaces();
+            while self.text.ends_with('\n') {
+                self.text.pop();
+                self.chars -= 1;
+            }
+            self.segment_start = self.segment_start.min(self.chars);
+            self.close_segment();
+
+            // text before the first heading of a document without pages adds nothing
+            let segments = if self.segments.iter().all(|s| s.page.is_none() && s.section_path.is_empty()) {
+                Vec::new()
+            } else {
+                self.segments
+            };
+
+            ExtractedDocument { text: self.text, segments, page_count }
+        }
+    }
+
+    fn extract_pdf(bytes: &[u8]) -> Result<ExtractedDocument, ExtractionError> {
+        let document = lopdf::Document::load_mem(bytes).map_err(|e| ExtractionError::Malformed(e.to_string()))?;
+        if document.is_encrypted() {
+            return Err(ExtractionError::Encrypted);
+        }
+
+        let pages = document.get_pages();
+        let mut builder = TextBuilder::new(None);
+
+        for &number in pages.keys() {
+            builder.set_page(number);
+            // a page that fails to decode shouldn't lose the rest of the document
+            let Ok(text) = document.extract_text(&[number]) else { continue };
+            for line in text.lines() {
+                if line.trim().is_empty() {
+                    builder.paragraph_break();
+                } else {
+                    builder.push(line);
+                    builder.line_break();
+                }
+            }
+            builder.paragraph_break();
+        }
+
+        Ok(builder.finish(Some(pages.len() as u32)))
+    }
+
+    fn read_zip_entry(archive: &mut zip::ZipArchive<Cursor<&[u8]>>, name: &str) -> Result<String, ExtractionError> {
+        let mut entry = archive
+            .by_name(name)
+            .map_err(|_| ExtractionError::Malformed(format!("missing {name}")))?;
+        let mut xml = String::new();
+        entry
+            .read_to_string(&mut xml)
+            .map_err(|e| ExtractionError::Malformed(e.to_string()))?;
+        Ok(xml)
+    }
+
+    fn open_zip(bytes: &[u8]) -> Result<zip::ZipArchive<Cursor<&[u8]>>, ExtractionError> {
+        zip::ZipArchive::new(Cursor::new(bytes)).map_err(|e| ExtractionError::Malformed(e.to_string()))
+    }
+
+    fn attribute(element: &BytesStart, name: &[u8]) -> Option<String> {
+        element
+            .attributes()
+            .flatten()
+            .find(|attr| attr.key.local_name().as_ref() == name)
+            .and_then(|attr| attr.unescape_value().ok().map(|value| value.into_owned()))
+    }
+
+    /// Heading level of a Word paragraph style such as `Heading2` or `Title`
+    fn docx_heading_level(style: &str) -> Option<usize> {
+        let style = style.to_ascii_lowercase();
+        if style == "title" {
+            return Some(1);
+        }
+        style
+            .strip_prefix("heading")
+            .and_then(|level| level.trim().parse::<usize>().ok())
+            .filter(|&level| (1..=9).contains(&level))
+    }
+
+    fn extract_docx(bytes: &[u8]) -> Result<ExtractedDocument, ExtractionError> {
+        let mut archive = open_zip(bytes)?;
+        let xml = read_zip_entry(&mut archive, "word/document.xml")?;
+
+        // Word records where pages broke the last time the file was laid out; documents
+        // saved by other tools may not, in which case no page numbers are reported
+        let has_page_marks = xml.contains("lastRenderedPageBreak") || xml.contains("w:type=\"page\"");
+        let mut builder = TextBuilder::new(has_page_marks.then_some(1));
+        let mut page = 1;
+
+        let mut reader = Reader::from_str(&xml);
+        let mut paragraph = String::new();
+        let mut heading_level: Option<usize> = None;
+        let mut in_text = false;
+        let mut table_depth = 0;
+
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(e)) => match e.local_name().as_ref() {
+                    b"t" => in_text = true,
+                    b"p" => {
+                        paragraph.clear();
+                        heading_level = None;
+                    }
+                    b"tbl" => table_depth += 1,
+                    _ => {}
+                },
+                Ok(Event::Empty(e)) => match e.local_name().as_ref() {
+                    b"pStyle" => heading_level = attribute(&e, b"val").as_deref().and_then(docx_heading_level),
+                    b"tab" => paragraph.push('\t'),
+                    b"br" | b"cr" => {
+                        if attribute(&e, b"type").as_deref() == Some("page") {
+                            builder.push(&paragraph);
+                            paragraph.clear();
+                            page += 1;
+                            builder.set_page(page);
+                        } else {
+                            paragraph.push('\n');
+                        }
+                    }
+                    b"lastRenderedPageBreak" => {
+                        builder.push(&paragraph);
+                        paragraph.clear();
+                        page += 1;
+                        builder.set_page(page);
+                    }
+                    _ => {}
+                },
+                Ok(Event::Text(text)) if in_text => {
+                    paragraph.push_str(&text.unescape().map_err(|e| ExtractionError::Malformed(e.to_string()))?);
+                }
+                Ok(Event::End(e)) => match e.local_name().as_ref() {
+                    b"t" => in_text = false,
+                    b"p" => {
+                        match heading_level {
+                            Some(level) if table_depth == 0 => builder.heading(level, &paragraph),
+                            _ => {
+                                builder.push(&paragraph);
+                                if table_depth > 0 {
+                                    builder.push(" ");
+                                } else {
+                                    builder.paragraph_break();
+                                }
+                            }
+                        }
+                        paragraph.clear();
+                    }
+                    b"tc" => builder.push("| "),
+                    b"tr" => builder.line_break(),
+                    b"tbl" => {
+                        table_depth -= 1;
+                        if table_depth == 0 {
+                            builder.paragraph_break();
+                        }
+                    }
+                    _ => {}
+                },
+                Ok(Event::Eof) => break,
+                Ok(_) => {}
+                Err(e) => return Err(ExtractionError::Malformed(e.to_string())),
+            }
+        }
+
+        Ok(builder.finish(has_page_marks.then_some(page)))
+    }
+
+    fn extract_pptx(bytes: &[u8]) -> Result<ExtractedDocument, ExtractionError> {
+        let mut archive = open_zip(bytes)?;
+
+        let mut slides: Vec<(u32, String)> = archive
+            .file_names()
+            .filter_map(|name| {
+                let number = name.strip_prefix("ppt/slides/slide")?.strip_suffix(".xml")?.parse().ok()?;
+                Some((number, name.to_string()))
+            })
+            .collect();
+        slides.sort();
+
+        let mut builder = TextBuilder::new(None);
+        for (index, (_, name)) in slides.iter().enumerate() {
+            // slide numbers in file names can have gaps after deletions; use display order
+            builder.set_page(index as u32 + 1);
+            let xml = read_zip_entry(&mut archive, name)?;
+            let mut reader = Reader::from_str(&xml);
+            let mut in_text = false;
+
+            loop {
+                match reader.read_event() {
+                    Ok(Event::Start(e)) if e.local_name().as_ref() == b"t" => in_text = true,
+                    Ok(Event::End(e)) => match e.local_name().as_ref() {
+                        b"t" => in_text = false,
+                        b"p" => builder.line_break(),
+                        b"txBody" => builder.paragraph_break(),
+                        _ => {}
+                    },
+                    Ok(Event::Text(text)) if in_text => {
+                        builder.push(&text.unescape().map_err(|e| ExtractionError::Malformed(e.to_string()))?);
+                    }
+                    Ok(Event::Eof) => break,
+                    Ok(_) => {}
+                    Err(e) => return Err(ExtractionError::Malformed(e.to_string())),
+                }
+            }
+            builder.paragraph_break();
+        }
+
+        Ok(builder.finish(Some(slides.len() as u32)))
+    }
+
+    fn extract_odt(bytes: &[u8]) -> Result<ExtractedDocument, ExtractionError> {
+        let mut archive = open_zip(bytes)?;
+        let xml = read_zip_entry(&mut archive, "content.xml")?;
+
+        let has_page_marks = xml.contains("soft-page-break");
+        let mut builder = TextBuilder::new(has_page_marks.then_some(1));
+        let mut page = 1;
+
+        let mut reader = Reader::from_str(&xml);
+        let mut paragraph = String::new();
+        let mut heading_level: Option<usize> = None;
+        // paragraphs nest inside list items, frames and notes; only the outermost flushes
+        let mut depth = 0;
+
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(e)) => match e.local_name().as_ref() {
+                    b"h" | b"p" => {
+                        if depth == 0 {
+                            paragraph.clear();
+                            heading_level = (e.local_name().as_ref() == b"h").then(|| {
+                                attribute(&e, b"outline-level").and_then(|l| l.parse().ok()).unwrap_or(1)
+                            });
+                        }
+                        depth += 1;
+                    }
+                    _ => {}
+                },
+                Ok(Event::Empty(e)) => match e.local_name().as_ref() {
+                    b"s" => {
+                        let count = attribute(&e, b"c").and_then(|c| c.parse().ok()).unwrap_or(1);
+                        paragraph.push_str(&" ".repeat(count));
+                    }
+                    b"tab" => paragraph.push('\t'),
+                    b"line-break" => paragraph.push('\n'),
+                    b"soft-page-break" => {
+                        builder.push(&paragraph);
+                        paragraph.clear();
+                        page += 1;
+                        builder.set_page(page);
+                    }
+                    _ => {}
+                },
+                Ok(Event::Text(text)) if depth > 0 => {
+                    paragraph.push_str(&text.unescape().map_err(|e| ExtractionError::Malformed(e.to_string()))?);
+                }
+                Ok(Event::End(e)) => match e.local_name().as_ref() {
+                    b"h" | b"p" => {
+                        depth -= 1;
+                        if depth == 0 {
+                            match heading_level {
+                                Some(level) => builder.heading(level, &paragraph),
+                                None => {
+                                    builder.push(&paragraph);
+                                    builder.paragraph_break();
+                                }
+                            }
+                            paragraph.clear();
+                        } else {
+                            paragraph.push(' ');
+                        }
+                    }
+                    _ => {}
+                },
+                Ok(Event::Eof) => break,
+                Ok(_) => {}
+                Err(e) => return Err(ExtractionError::Malformed(e.to_string())),
+            }
+        }
+
+        Ok(builder.finish(has_page_marks.then_some(page)))
+    }
+
+    const HTML_SKIPPED: &[&str] = &["script", "style", "noscript", "template", "head", "svg", "iframe", "button", "select"];
+    const HTML_BLOCKS: &[&str] = &[
+        "p", "div", "section", "article", "main", "header", "footer", "aside", "nav", "ul", "ol", "table",
+        "blockquote", "pre", "figure", "figcaption", "dl", "form", "hr",
+    ];
+
+    fn extract_html(html: &str) -> ExtractedDocument {
+        let document = Html::parse_document(html);
+        let mut builder = TextBuilder::new(None);
+        walk_html(document.tree.root(), &mut builder, false);
+        builder.finish(None)
+    }
+
+    fn walk_html(node: ego_tree::NodeRef<Node>, builder: &mut TextBuilder, preformatted: bool) {
+        match node.value() {
+            Node::Text(text) => {
+                if preformatted {
+                    for (i, line) in text.split('\n').enumerate() {
+                        if i > 0 {
+                            builder.line_break();
+                        }
+                        builder.push(line);
+                    }
+                } else {
+                    builder.push(&text.replace('\n', " "));
+                }
+            }
+            Node::Element(element) => {
+                let name = element.name();
+                if HTML_SKIPPED.contains(&name) {
+                    return;
+                }
+
+                if let Some(level) = name.strip_prefix('h').and_then(|l| l.parse::<usize>().ok()).filter(|l| (1..=6).contains(l)) {
+                    let title: String = node
+                        .descendants()
+                        .filter_map(|d| d.value().as_text().map(|t| t.to_string()))
+                        .collect();
+                    builder.heading(level, &title);
+                    return;
+                }
+
+                match name {
+                    "br" | "li" | "tr" | "dt" | "dd" => builder.line_break(),
+                    _ if HTML_BLOCKS.contains(&name) => builder.paragraph_break(),
+                    _ => {}
+                }
+
+                let preformatted = preformatted || name == "pre";
+                for child in node.children() {
+                    walk_html(child, builder, preformatted);
+                }
+
+                match name {
+                    "td" | "th" => builder.push(" | "),
+                    "li" | "tr" | "dt" | "dd" => builder.line_break(),
+                    _ if HTML_BLOCKS.contains(&name) => builder.paragraph_break(),
+                    _ => {}
+                }
+            }
+            _ => {
+                for child in node.children() {
+                    walk_html(child, builder, preformatted);
+                }
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::io::Write;
+
+        fn zip_with(files: &[(&str, &str)]) -> Vec<u8> {
+            let mut buffer = Cursor::new(Vec::new());
+            {
+                let mut writer = zip::ZipWriter::new(&mut buffer);
+                for (name, content) in files {
+                    writer.start_file(*name, zip::write::SimpleFileOptions::default()).unwrap();
+                    writer.write_all(content.as_bytes()).unwrap();
+                }
+                writer.finish().unwrap();
+            }
+            buffer.into_inner()
+        }
+
+        fn segment_text(text: &str, segment: &TextSegment) -> String {
+            text.chars().skip(segment.start_char).take(segment.end_char - segment.start_char).collect()
+        }
+
+        #[test]
+        fn test_html_sections_and_skipped_elements() {
+            let html = "<html><head><title>x</title><style>p{}</style></head><body>\
+                <h1>Guide</h1><p>Intro &amp; overview.</p><script>alert(1)</script>\
+                <h2>Install</h2><ul><li>Run cargo</li><li>Done</li></ul></body></html>";
+            let doc = extract_document(html.as_bytes(), DocumentFormat::Html).unwrap();
+
+            assert_eq!(doc.text, "Guide\n\nIntro & overview.\n\nInstall\n\nRun cargo\nDone");
+            let install = doc.segments.iter().find(|s| s.section_path == ["Guide", "Install"]).unwrap();
+            assert!(segment_text(&doc.text, install).contains("Run cargo"));
+        }
+
+        #[test]
+        fn test_docx_headings_and_page_breaks() {
+            let xml = r#"<w:document xmlns:w="w"><w:body>
+                <w:p><w:pPr><w:pStyle w:val="Heading1"/></w:pPr><w:r><w:t>Report</w:t></w:r></w:p>
+                <w:p><w:r><w:t xml:space="preserve">First page </w:t></w:r><w:r><w:t>text.</w:t></w:r></w:p>
+                <w:p><w:r><w:br w:type="page"/><w:t>Second page.</w:t></w:r></w:p>
+            </w:body></w:document>"#;
+            let bytes = zip_with(&[("word/document.xml", xml)]);
+            let doc = extract_document(&bytes, DocumentFormat::Docx).unwrap();
+
+            assert_eq!(doc.text, "Report\n\nFirst page text.\n\nSecond page.");
+            assert_eq!(doc.page_count, Some(2));
+            let second = doc.segments.iter().find(|s| s.page == Some(2)).unwrap();
+            assert_eq!(segment_text(&doc.text, second).trim(), "Second page.");
+            assert_eq!(second.section_path, vec!["Report".to_string()]);
+        }
+
+        #[test]
+        fn test_annotate_and_locate_chunks() {
+            let segments = vec![
+                TextSegment { start_char: 0, end_char: 100, page: Some(11), section_path: vec![] },
+                TextSegment { start_char: 100, end_char: 200, page: Some(12), section_path: vec!["Results".into()] },
+            ];
+
+            let mut spanning = json!({ "chunker": "prose" });
+            annotate_chunk(&mut spanning, 90, 150, &segments);
+            assert_eq!(spanning["page"], json!(11));
+            assert_eq!(spanning["page_end"], json!(12));
+            assert_eq!(chunk_location(&spanning).as_deref(), Some("pages 11-12"));
+
+            let mut inside = json!({});
+            annotate_chunk(&mut inside, 120, 180, &segments);
+            assert_eq!(chunk_location(&inside).as_deref(), Some("page 12, Results"));
+
+            assert_eq!(chunk_location(&json!({ "section_path": ["A", "B"] })).as_deref(), Some("A > B"));
+            assert_eq!(chunk_location(&json!({ "chunker": "code" })), None);
+        }
+
+        #[test]
+        fn test_sniff_format_ignores_claimed_type() {
+            let docx = zip_with(&[("word/document.xml", "<w:document/>")]);
+            assert_eq!(sniff_format(&docx, "report.pdf").unwrap(), DocumentFormat::Docx);
+            assert_eq!(sniff_format(b"%PDF-1.7\n...", "notes.txt").unwrap(), DocumentFormat::Pdf);
+            assert_eq!(sniff_format(b"  <!DOCTYPE html><p>hi</p>", "page").unwrap(), DocumentFormat::Html);
+            assert_eq!(sniff_format("# Title\n\ntext".as_bytes(), "a.md").unwrap(), DocumentFormat::PlainText);
+
+            assert!(matches!(sniff_format(b"\x89PNG\r\n", "scan.pdf"), Err(ExtractionError::Unsupported(_))));
+            assert!(matches!(sniff_format(&[0xff, 0xfe, 0x00, 0x41], "a.txt"), Err(ExtractionError::Unsupported(_))));
+            let other_zip = zip_with(&[("data.csv", "a,b")]);
+            assert!(matches!(sniff_format(&other_zip, "data.zip"), Err(ExtractionError::Unsupported(_))));
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub use text_extraction::*;
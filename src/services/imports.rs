@@ -0,0 +1,212 @@
+#[cfg(feature = "ssr")]
+pub mod bulk_imports {
+    use chrono::Utc;
+    use diesel::prelude::*;
+    use diesel_async::{AsyncPgConnection, RunQueryDsl};
+    use log::{error, info, warn};
+    use uuid::Uuid;
+
+    use crate::database::db::DbPool;
+    use crate::models::imports::{Import, ImportReportEntry, NewImport};
+    use crate::schema::imports;
+    use crate::services::archive::{ImportListing, SkippedEntry};
+    use crate::services::extraction::ExtractionError;
+    use crate::services::uploads::{store_document, StoreOutcome, UploadError, UploadLimits};
+
+    pub const IMPORT_RUNNING: &str = "running";
+    pub const IMPORT_DONE: &str = "done";
+    pub const IMPORT_FAILED: &str = "failed";
+
+    /// Keeps the stored report readable for archives with thousands of skipped files
+    const MAX_REPORT_ENTRIES: usize = 1000;
+
+    fn report_entry(skipped: SkippedEntry) -> ImportReportEntry {
+        ImportReportEntry { path: skipped.path, reason: skipped.reason }
+    }
+
+    /// Tally of an import in progress, mirrored to its `imports` row
+    struct Progress {
+        import_id: Uuid,
+        imported: i32,
+        skipped: i32,
+        failed: i32,
+        report: Vec<ImportReportEntry>,
+    }
+
+    impl Progress {
+        fn skip(&mut self, path: String, reason: String) {
+            self.skipped += 1;
+            self.note(path, reason);
+        }
+
+        fn fail(&mut self, path: String, reason: String) {
+            self.failed += 1;
+            self.note(path, reason);
+        }
+
+        fn note(&mut self, path: String, reason: String) {
+            if self.report.len() < MAX_REPORT_ENTRIES {
+                self.report.push(ImportReportEntry { path, reason });
+            }
+        }
+
+        async fn save(&self, conn: &mut AsyncPgConnection) -> QueryResult<()> {
+            diesel::update(imports::table.find(self.import_id))
+                .set((
+                    imports::imported_files.eq(self.imported),
+                    imports::skipped_files.eq(self.skipped),
+                    imports::failed_files.eq(self.failed),
+                ))
+                .execute(conn)
+                .await?;
+            Ok(())
+        }
+
+        async fn finish(&self, conn: &mut AsyncPgConnection, error: Option<String>) -> QueryResult<()> {
+            let status = if error.is_some() { IMPORT_FAILED } else { IMPORT_DONE };
+            let report = serde_json::to_value(&self.report).unwrap_or_default();
+
+            diesel::update(imports::table.find(self.import_id))
+                .set((
+                    imports::status.eq(status),
+                    imports::imported_files.eq(self.imported),
+                    imports::skipped_files.eq(self.skipped),
+                    imports::failed_files.eq(self.failed),
+                    imports::report.eq(report),
+                    imports::error.eq(error),
+                    imports::finished_at.eq(Some(Utc::now().naive_utc())),
+                ))
+                .execute(conn)
+                .await?;
+            Ok(())
+        }
+    }
+
+    /// Records the import and stores its files in the background; the returned row
+    /// is polled for progress
+    pub async fn begin_import(
+        pool: &DbPool,
+        project_id: Uuid,
+        source: String,
+        listing: ImportListing,
+        limits: UploadLimits,
+    ) -> Result<Import, UploadError> {
+        let mut conn = pool.get().await.map_err(|e| UploadError::Pool(e.to_string()))?;
+
+        let total = (listing.entries.len() + listing.skipped.len()) as i32;
+        let import: Import = diesel::insert_into(imports::table)
+            .values(&NewImport { project_id, source: source.chars().take(255).collect(), total_files: total })
+            .get_result(&mut conn)
+            .await?;
+
+        info!(
+            "Importing {} files from {} into project {} ({} skipped up front)",
+            listing.entries.len(),
+            import.source,
+            project_id,
+            listing.skipped.len()
+        );
+
+        let pool = pool.clone();
+        let import_id = import.id;
+        tokio::spawn(async move {
+            if let Err(e) = run_import(&pool, import_id, project_id, listing, limits).await {
+                error!("Import {} failed: {}", import_id, e);
+                if let Ok(mut conn) = pool.get().await {
+                    let _ = diesel::update(imports::table.find(import_id))
+                        .set((
+                            imports::status.eq(IMPORT_FAILED),
+                            imports::error.eq(Some(e.to_string())),
+                            imports::finished_at.eq(Some(Utc::now().naive_utc())),
+                        ))
+                        .execute(&mut conn)
+                        .await;
+                }
+            }
+        });
+
+        Ok(import)
+    }
+
+    async fn run_import(
+        pool: &DbPool,
+        import_id: Uuid,
+        project_id: Uuid,
+        listing: ImportListing,
+        limits: UploadLimits,
+    ) -> Result<(), UploadError> {
+        let mut progress = Progress {
+            import_id,
+            imported: 0,
+            skipped: listing.skipped.len() as i32,
+            failed: 0,
+            report: listing.skipped.into_iter().take(MAX_REPORT_ENTRIES).map(report_entry).collect(),
+        };
+
+        let mut stop_error = None;
+        let mut entries = listing.entries.into_iter();
+
+        for entry in entries.by_ref() {
+            match store_document(pool, project_id, entry.path.clone(), entry.bytes, limits, Some(import_id)).await {
+                Ok((_, StoreOutcome::Unchanged)) => progress.skip(entry.path, "unchanged".to_string()),
+                Ok(_) => progress.imported += 1,
+                Err(UploadError::Rejected(ExtractionError::Unsupported(_))) => {
+                    progress.skip(entry.path, "binary file".to_string())
+                }
+                Err(UploadError::Rejected(e)) => progress.skip(entry.path, e.to_string()),
+                Err(e @ UploadError::QuotaExceeded { .. }) => {
+                    progress.fail(entry.path, e.to_string());
+                    stop_error = Some(e.to_string());
+                    break;
+                }
+                Err(e) => {
+                    warn!("Import {}: failed to store {}: {}", import_id, entry.path, e);
+                    progress.fail(entry.path, e.to_string());
+                }
+            }
+
+            let mut conn = pool.get().await.map_err(|e| UploadError::Pool(e.to_string()))?;
+            progress.save(&mut conn).await?;
+        }
+
+        // whatever is left after the quota ran out
+        for entry in entries {
+            progress.skip(entry.path, "not imported: storage quota exceeded".to_string());
+        }
+
+        let mut conn = pool.get().await.map_err(|e| UploadError::Pool(e.to_string()))?;
+        progress.finish(&mut conn, stop_error).await?;
+
+        info!(
+            "Import {} finished: {} imported, {} skipped, {} failed",
+            import_id, progress.imported, progress.skipped, progress.failed
+        );
+        Ok(())
+    }
+
+    /// Imports run inside the web process; any still marked running at startup were
+    /// cut off by a restart
+    pub async fn fail_interrupted_imports(pool: &DbPool) {
+        let result = match pool.get().await {
+            Ok(mut conn) => diesel::update(imports::table.filter(imports::status.eq(IMPORT_RUNNING)))
+                .set((
+                    imports::status.eq(IMPORT_FAILED),
+                    imports::error.eq(Some("Interrupted by a server restart")),
+                    imports::finished_at.eq(Some(Utc::now().naive_utc())),
+                ))
+                .execute(&mut conn)
+                .await
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+
+        match result {
+            Ok(0) => {}
+            Ok(count) => warn!("Marked {} interrupted imports as failed", count),
+            Err(e) => error!("Failed to clean up interrupted imports: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub use bulk_imports::*;
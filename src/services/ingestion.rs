@@ -0,0 +1,276 @@
+#[cfg(feature = "ssr")]
+pub mod embedding_pipeline {
+    use log::warn;
+    use rand::Rng;
+    use std::env;
+    use std::error::Error;
+    use std::fmt;
+    use std::ops::Range;
+    use std::sync::{Arc, OnceLock};
+    use std::time::Duration;
+    use tokio::sync::Semaphore;
+
+    use crate::services::embeddings::EmbeddingProvider;
+
+    type BoxError = Box<dyn Error + Send + Sync>;
+
+    /// Exponential backoff with jitter for transient embedding failures
+    #[derive(Debug, Clone)]
+    pub struct RetryPolicy {
+        /// Total tries including the first one
+        pub max_attempts: u32,
+        pub base_delay: Duration,
+        pub max_delay: Duration,
+    }
+
+    impl Default for RetryPolicy {
+        fn default() -> Self {
+            Self {
+                max_attempts: 6,
+                base_delay: Duration::from_millis(500),
+                max_delay: Duration::from_secs(30),
+            }
+        }
+    }
+
+    impl RetryPolicy {
+        /// Delay before retry number `attempt` (1 = first retry): a random point in the
+        /// upper half of `base * 2^(attempt - 1)`, capped at `max_delay`
+        pub fn delay_for(&self, attempt: u32) -> Duration {
+            let exponent = attempt.saturating_sub(1).min(16);
+            let ceiling = self.base_delay.saturating_mul(1 << exponent).min(self.max_delay);
+            let floor = ceiling / 2;
+            let jitter = rand::thread_rng().gen_range(0..=(ceiling - floor).as_millis() as u64);
+            floor + Duration::from_millis(jitter)
+        }
+    }
+
+    /// Marks a provider error as worth retrying when it isn't a `reqwest::Error`
+    #[derive(Debug)]
+    pub struct TransientError(pub String);
+
+    impl fmt::Display for TransientError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "transient embedding error: {}", self.0)
+        }
+    }
+
+    impl Error for TransientError {}
+
+    /// Rate limits, server errors, timeouts and connection failures are retried;
+    /// anything else (bad request, auth, dimension mismatch) fails immediately
+    pub fn is_retryable(error: &(dyn Error + 'static)) -> bool {
+        let mut current: Option<&(dyn Error + 'static)> = Some(error);
+        while let Some(err) = current {
+            if err.is::<TransientError>() {
+                return true;
+            }
+            if let Some(http) = err.downcast_ref::<reqwest::Error>() {
+                if let Some(status) = http.status() {
+                    return status.as_u16() == 429 || status.is_server_error();
+                }
+                return http.is_timeout() || http.is_connect() || http.is_request() || http.is_body();
+            }
+            current = err.source();
+        }
+        false
+    }
+
+    /// Caps in-flight embedding requests across every upload and re-embed job in the
+    /// process, sized by `EMBEDDING_MAX_CONCURRENCY` (default 4)
+    pub fn embedding_limiter() -> Arc<Semaphore> {
+        static LIMITER: OnceLock<Arc<Semaphore>> = OnceLock::new();
+        LIMITER
+            .get_or_init(|| {
+                let permits = env::var("EMBEDDING_MAX_CONCURRENCY")
+                    .ok()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|&permits: &usize| permits > 0)
+                    .unwrap_or(4);
+                Arc::new(Semaphore::new(permits))
+            })
+            .clone()
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct EmbeddingBatchConfig {
+        /// Inputs per request; OpenAI accepts up to 2048
+        pub max_batch_inputs: usize,
+        /// Characters per request, to stay well under the per-request token limit
+        pub max_batch_chars: usize,
+        pub retry: RetryPolicy,
+    }
+
+    impl Default for EmbeddingBatchConfig {
+        fn default() -> Self {
+            Self {
+                max_batch_inputs: 96,
+                max_batch_chars: 120_000,
+                retry: RetryPolicy::default(),
+            }
+        }
+    }
+
+    /// Groups consecutive inputs into request-sized batches. An input larger than
+    /// `max_chars` still gets a batch of its own.
+    pub fn plan_batches(inputs: &[String], max_inputs: usize, max_chars: usize) -> Vec<Range<usize>> {
+        let mut batches = Vec::new();
+        let mut start = 0;
+        let mut chars = 0;
+
+        for (i, input) in inputs.iter().enumerate() {
+            let len = input.chars().count();
+            let full = i - start >= max_inputs.max(1) || (i > start && chars + len > max_chars);
+            if full {
+                batches.push(start..i);
+                start = i;
+                chars = 0;
+            }
+            chars += len;
+        }
+
+        if start < inputs.len() {
+            batches.push(start..inputs.len());
+        }
+
+        batches
+    }
+
+    async fn embed_with_retry(
+        provider: &dyn EmbeddingProvider,
+        inputs: &[String],
+        policy: &RetryPolicy,
+    ) -> Result<Vec<Vec<f32>>, BoxError> {
+        let limiter = embedding_limiter();
+        let mut attempt = 0;
+
+        loop {
+            attempt += 1;
+            let result = {
+                let _permit = limiter.acquire().await?;
+                provider.embed(inputs).await
+            };
+
+            match result {
+                Ok(vectors) => return Ok(vectors),
+                Err(e) if attempt < policy.max_attempts && is_retryable(e.as_ref()) => {
+                    let delay = policy.delay_for(attempt);
+                    warn!(
+                        "Embedding batch of {} with {} failed (attempt {}/{}), retrying in {:?}: {}",
+                        inputs.len(), provider.model_id(), attempt, policy.max_attempts, delay, e
+                    );
+                    // the permit is released while waiting so other uploads keep moving
+                    tokio::time::sleep(delay).await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Embeds every input in batches, retrying transient failures. Returns one vector
+    /// per input in order, or the first error that survived its retries.
+    pub async fn embed_all(
+        provider: &dyn EmbeddingProvider,
+        inputs: &[String],
+        config: &EmbeddingBatchConfig,
+    ) -> Result<Vec<Vec<f32>>, BoxError> {
+        let mut vectors = Vec::with_capacity(inputs.len());
+
+        for batch in plan_batches(inputs, config.max_batch_inputs, config.max_batch_chars) {
+            vectors.extend(embed_with_retry(provider, &inputs[batch], &config.retry).await?);
+        }
+
+        Ok(vectors)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use futures::future::BoxFuture;
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        struct FlakyProvider {
+            failures_left: AtomicU32,
+            calls: AtomicU32,
+        }
+
+        impl EmbeddingProvider for FlakyProvider {
+            fn model_id(&self) -> &str {
+                "flaky"
+            }
+
+            fn dimensions(&self) -> Option<usize> {
+                Some(1)
+            }
+
+            fn embed<'a>(&'a self, inputs: &'a [String]) -> BoxFuture<'a, Result<Vec<Vec<f32>>, BoxError>> {
+                Box::pin(async move {
+                    self.calls.fetch_add(1, Ordering::SeqCst);
+                    if self.failures_left.load(Ordering::SeqCst) > 0 {
+                        self.failures_left.fetch_sub(1, Ordering::SeqCst);
+                        return Err(Box::new(TransientError("429".to_string())) as BoxError);
+                    }
+                    Ok(inputs.iter().map(|input| vec![input.len() as f32]).collect())
+                })
+            }
+        }
+
+        fn fast_config() -> EmbeddingBatchConfig {
+            EmbeddingBatchConfig {
+                max_batch_inputs: 2,
+                max_batch_chars: 1_000,
+                retry: RetryPolicy {
+                    max_attempts: 3,
+                    base_delay: Duration::from_millis(1),
+                    max_delay: Duration::from_millis(2),
+                },
+            }
+        }
+
+        #[test]
+        fn test_plan_batches_respects_limits() {
+            let inputs: Vec<String> = ["aaaa", "bb", "cccccc", "d", "e"].iter().map(|s| s.to_string()).collect();
+            assert_eq!(plan_batches(&inputs, 2, 100), vec![0..2, 2..4, 4..5]);
+            assert_eq!(plan_batches(&inputs, 10, 6), vec![0..2, 2..3, 3..5]);
+            assert!(plan_batches(&[], 10, 10).is_empty());
+        }
+
+        #[test]
+        fn test_backoff_grows_and_caps() {
+            let policy = RetryPolicy {
+                max_attempts: 10,
+                base_delay: Duration::from_millis(100),
+                max_delay: Duration::from_millis(1_000),
+            };
+            let first = policy.delay_for(1);
+            assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+            let third = policy.delay_for(3);
+            assert!(third >= Duration::from_millis(200) && third <= Duration::from_millis(400));
+            assert!(policy.delay_for(12) <= Duration::from_millis(1_000));
+        }
+
+        #[tokio::test]
+        async fn test_transient_failures_are_retried() {
+            let provider = FlakyProvider { failures_left: AtomicU32::new(2), calls: AtomicU32::new(0) };
+            let inputs: Vec<String> = vec!["one".into(), "three".into(), "five!".into()];
+
+            let vectors = embed_all(&provider, &inputs, &fast_config()).await.unwrap();
+
+            assert_eq!(vectors, vec![vec![3.0], vec![5.0], vec![5.0]]);
+            // two failed attempts, then one call per batch
+            assert_eq!(provider.calls.load(Ordering::SeqCst), 4);
+        }
+
+        #[tokio::test]
+        async fn test_gives_up_after_max_attempts() {
+            let provider = FlakyProvider { failures_left: AtomicU32::new(10), calls: AtomicU32::new(0) };
+            let inputs: Vec<String> = vec!["one".into()];
+
+            assert!(embed_all(&provider, &inputs, &fast_config()).await.is_err());
+            assert_eq!(provider.calls.load(Ordering::SeqCst), 3);
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub use embedding_pipeline::*;
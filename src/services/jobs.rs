@@ -0,0 +1,359 @@
+#[cfg(feature = "ssr")]
+pub mod job_queue {
+    use chrono::{NaiveDateTime, Utc};
+    use diesel::prelude::*;
+    use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+    use log::{debug, error, info, warn};
+    use serde_json::json;
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    use crate::database::db::DbPool;
+    use crate::models::jobs::{Job, NewJob};
+    use crate::models::projects::ProjectDocument;
+    use crate::schema::{jobs, project_documents};
+    use crate::services::projects::EnhancedProjectsService;
+
+    type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+    pub const JOB_PROCESS_DOCUMENT: &str = "process_document";
+    pub const JOB_REEMBED_PROJECT: &str = "reembed_project";
+
+    pub const JOB_QUEUED: &str = "queued";
+    pub const JOB_RUNNING: &str = "running";
+    pub const JOB_DONE: &str = "done";
+    pub const JOB_DEAD: &str = "dead";
+
+    pub const DOCUMENT_QUEUED: &str = "queued";
+    pub const DOCUMENT_PROCESSING: &str = "processing";
+    pub const DOCUMENT_INDEXED: &str = "indexed";
+    pub const DOCUMENT_FAILED: &str = "failed";
+
+    const DEFAULT_MAX_ATTEMPTS: i32 = 5;
+
+    /// What happened to a job after a failed attempt
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum FailureOutcome {
+        Retrying { run_at: NaiveDateTime },
+        Dead,
+    }
+
+    /// Delay before the next attempt: 30s, 1m, 2m, ... capped at one hour
+    pub fn retry_delay(attempts: i32) -> Duration {
+        let exponent = attempts.saturating_sub(1).clamp(0, 7) as u32;
+        Duration::from_secs(30 * 2u64.pow(exponent)).min(Duration::from_secs(3600))
+    }
+
+    pub async fn enqueue_job(
+        conn: &mut AsyncPgConnection,
+        kind: &str,
+        payload: serde_json::Value,
+    ) -> QueryResult<Job> {
+        diesel::insert_into(jobs::table)
+            .values(&NewJob {
+                kind: kind.to_string(),
+                payload,
+                max_attempts: DEFAULT_MAX_ATTEMPTS,
+            })
+            .get_result(conn)
+            .await
+    }
+
+    /// Marks the document as queued and schedules it for chunking and embedding
+    pub async fn enqueue_document(conn: &mut AsyncPgConnection, document_id: Uuid) -> QueryResult<Job> {
+        conn.transaction(|conn| {
+            Box::pin(async move {
+                diesel::update(project_documents::table.find(document_id))
+                    .set((
+                        project_documents::status.eq(DOCUMENT_QUEUED),
+                        project_documents::status_error.eq(None::<String>),
+                    ))
+                    .execute(conn)
+                    .await?;
+
+                enqueue_job(conn, JOB_PROCESS_DOCUMENT, json!({ "document_id": document_id })).await
+            })
+        })
+        .await
+    }
+
+    pub async fn enqueue_reembed(conn: &mut AsyncPgConnection, project_id: Uuid) -> QueryResult<Job> {
+        enqueue_job(conn, JOB_REEMBED_PROJECT, json!({ "project_id": project_id })).await
+    }
+
+    /// Claims the oldest runnable job. Jobs whose worker died mid-run (locked longer
+    /// than `lock_timeout`) are picked up again.
+    pub async fn claim_job(
+        conn: &mut AsyncPgConnection,
+        worker_id: &str,
+        lock_timeout: Duration,
+    ) -> QueryResult<Option<Job>> {
+        diesel::sql_query(
+            "UPDATE jobs
+            SET status = 'running', locked_at = NOW(), locked_by = $1,
+                attempts = attempts + 1, updated_at = NOW()
+            WHERE id = (
+                SELECT id FROM jobs
+                WHERE (status = 'queued' AND run_at <= NOW())
+                    OR (status = 'running' AND locked_at < NOW() - make_interval(secs => $2))
+                ORDER BY run_at
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *",
+        )
+        .bind::<diesel::sql_types::Text, _>(worker_id)
+        .bind::<diesel::sql_types::Double, _>(lock_timeout.as_secs_f64())
+        .get_result::<Job>(conn)
+        .await
+        .optional()
+    }
+
+    /// Marks the job done; `false` if the worker had lost its lock and nothing changed.
+    /// A run that outlives `lock_timeout` may have been claimed again by another worker.
+    pub async fn complete_job(conn: &mut AsyncPgConnection, job_id: Uuid, worker_id: &str) -> QueryResult<bool> {
+        let held = jobs::table
+            .find(job_id)
+            .filter(jobs::status.eq(JOB_RUNNING))
+            .filter(jobs::locked_by.eq(worker_id));
+        let updated = diesel::update(held)
+            .set((
+                jobs::status.eq(JOB_DONE),
+                jobs::locked_at.eq(None::<NaiveDateTime>),
+                jobs::locked_by.eq(None::<String>),
+                jobs::last_error.eq(None::<String>),
+                jobs::updated_at.eq(Some(Utc::now().naive_utc())),
+            ))
+            .execute(conn)
+            .await?;
+        Ok(updated > 0)
+    }
+
+    /// Reschedules the job with backoff, or moves it to the dead letter state once
+    /// it has used up its attempts. `None` if the worker had lost its lock.
+    pub async fn fail_job(
+        conn: &mut AsyncPgConnection,
+        job: &Job,
+        worker_id: &str,
+        error: &str,
+    ) -> QueryResult<Option<FailureOutcome>> {
+        let now = Utc::now().naive_utc();
+        let outcome = if job.attempts >= job.max_attempts {
+            FailureOutcome::Dead
+        } else {
+            let delay = chrono::Duration::from_std(retry_delay(job.attempts)).unwrap_or_default();
+            FailureOutcome::Retrying { run_at: now + delay }
+        };
+
+        let (status, run_at) = match &outcome {
+            FailureOutcome::Retrying { run_at } => (JOB_QUEUED, *run_at),
+            FailureOutcome::Dead => (JOB_DEAD, job.run_at),
+        };
+
+        let held = jobs::table
+            .find(job.id)
+            .filter(jobs::status.eq(JOB_RUNNING))
+            .filter(jobs::locked_by.eq(worker_id));
+        let updated = diesel::update(held)
+            .set((
+                jobs::status.eq(status),
+                jobs::run_at.eq(run_at),
+                jobs::locked_at.eq(None::<NaiveDateTime>),
+                jobs::locked_by.eq(None::<String>),
+                jobs::last_error.eq(Some(error.to_string())),
+                jobs::updated_at.eq(Some(now)),
+            ))
+            .execute(conn)
+            .await?;
+
+        Ok((updated > 0).then_some(outcome))
+    }
+
+    /// Puts a dead job back in the queue with a fresh set of attempts
+    pub async fn requeue_dead_job(conn: &mut AsyncPgConnection, job_id: Uuid) -> QueryResult<usize> {
+        diesel::update(jobs::table.find(job_id).filter(jobs::status.eq(JOB_DEAD)))
+            .set((
+                jobs::status.eq(JOB_QUEUED),
+                jobs::attempts.eq(0),
+                jobs::run_at.eq(Utc::now().naive_utc()),
+                jobs::updated_at.eq(Some(Utc::now().naive_utc())),
+            ))
+            .execute(conn)
+            .await
+    }
+
+    fn payload_uuid(job: &Job, key: &str) -> Result<Uuid, BoxError> {
+        let value = job.payload[key]
+            .as_str()
+            .ok_or_else(|| format!("job {} payload is missing {key}", job.id))?;
+        Ok(Uuid::parse_str(value)?)
+    }
+
+    async fn set_document_status(
+        pool: &DbPool,
+        document_id: Uuid,
+        status: &str,
+        status_error: Option<String>,
+    ) -> Result<(), BoxError> {
+        let mut conn = pool.get().await?;
+        let indexed_at = (status == DOCUMENT_INDEXED).then(|| Utc::now().naive_utc());
+
+        diesel::update(project_documents::table.find(document_id))
+            .set((
+                project_documents::status.eq(status),
+                project_documents::status_error.eq(status_error),
+                project_documents::indexed_at.eq(indexed_at),
+            ))
+            .execute(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    async fn run_job(pool: &DbPool, job: &Job) -> Result<(), BoxError> {
+        let service = EnhancedProjectsService::new();
+
+        match job.kind.as_str() {
+            JOB_PROCESS_DOCUMENT => {
+                let document_id = payload_uuid(job, "document_id")?;
+                let document: Option<ProjectDocument> = {
+                    let mut conn = pool.get().await?;
+                    project_documents::table
+                        .find(document_id)
+                        .first(&mut conn)
+                        .await
+                        .optional()?
+                };
+
+                // deleted while it waited in the queue
+                let Some(document) = document else {
+                    debug!("Document {} no longer exists, skipping job {}", document_id, job.id);
+                    return Ok(());
+                };
+
+                set_document_status(pool, document_id, DOCUMENT_PROCESSING, None).await?;
+                service.process_document(pool, &document).await?;
+                set_document_status(pool, document_id, DOCUMENT_INDEXED, None).await?;
+                Ok(())
+            }
+            JOB_REEMBED_PROJECT => {
+                let project_id = payload_uuid(job, "project_id")?;
+                let count = service.reembed_project(pool, project_id).await?;
+                info!("Re-embedded {} chunks of project {}", count, project_id);
+                Ok(())
+            }
+            other => Err(format!("unknown job kind: {other}").into()),
+        }
+    }
+
+    /// Keeps the document's status in step with its job after a failed attempt
+    async fn record_document_failure(pool: &DbPool, job: &Job, error: &str, outcome: &FailureOutcome) {
+        if job.kind != JOB_PROCESS_DOCUMENT {
+            return;
+        }
+        let Ok(document_id) = payload_uuid(job, "document_id") else { return };
+
+        let (status, message) = match outcome {
+            FailureOutcome::Retrying { .. } => (
+                DOCUMENT_QUEUED,
+                format!("Attempt {}/{} failed, retrying: {}", job.attempts, job.max_attempts, error),
+            ),
+            FailureOutcome::Dead => (DOCUMENT_FAILED, error.to_string()),
+        };
+
+        if let Err(e) = set_document_status(pool, document_id, status, Some(message)).await {
+            error!("Failed to record status for document {}: {}", document_id, e);
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct JobWorker {
+        pool: DbPool,
+        worker_id: String,
+        poll_interval: Duration,
+        lock_timeout: Duration,
+    }
+
+    impl JobWorker {
+        pub fn new(pool: DbPool, worker_id: String) -> Self {
+            Self {
+                pool,
+                worker_id,
+                poll_interval: Duration::from_secs(2),
+                // long enough for a large document to embed; a crashed worker's job is retried after this
+                lock_timeout: Duration::from_secs(15 * 60),
+            }
+        }
+
+        /// Claims and runs one job; returns whether there was one
+        pub async fn run_once(&self) -> Result<bool, BoxError> {
+            let job = {
+                let mut conn = self.pool.get().await?;
+                claim_job(&mut conn, &self.worker_id, self.lock_timeout).await?
+            };
+
+            let Some(job) = job else {
+                return Ok(false);
+            };
+
+            debug!("Worker {} running job {} ({}), attempt {}", self.worker_id, job.id, job.kind, job.attempts);
+
+            let result = run_job(&self.pool, &job).await;
+            let mut conn = self.pool.get().await?;
+
+            match result {
+                Ok(()) => {
+                    if !complete_job(&mut conn, job.id, &self.worker_id).await? {
+                        warn!("Worker {} lost the lock on job {} ({}), leaving it to the new holder", self.worker_id, job.id, job.kind);
+                    }
+                }
+                Err(e) => {
+                    let message = e.to_string();
+                    let Some(outcome) = fail_job(&mut conn, &job, &self.worker_id, &message).await? else {
+                        warn!("Worker {} lost the lock on job {} ({}), dropping its failure: {}", self.worker_id, job.id, job.kind, message);
+                        return Ok(true);
+                    };
+                    match &outcome {
+                        FailureOutcome::Retrying { run_at } => {
+                            warn!("Job {} ({}) failed, retrying at {}: {}", job.id, job.kind, run_at, message)
+                        }
+                        FailureOutcome::Dead => {
+                            error!("Job {} ({}) failed permanently after {} attempts: {}", job.id, job.kind, job.attempts, message)
+                        }
+                    }
+                    record_document_failure(&self.pool, &job, &message, &outcome).await;
+                }
+            }
+
+            Ok(true)
+        }
+
+        pub async fn run(self) {
+            info!("Job worker {} started", self.worker_id);
+            loop {
+                match self.run_once().await {
+                    Ok(true) => continue,
+                    Ok(false) => {}
+                    Err(e) => error!("Job worker {} error: {}", self.worker_id, e),
+                }
+                tokio::time::sleep(self.poll_interval).await;
+            }
+        }
+    }
+
+    /// Starts `JOB_WORKERS` (default 2) background workers for this process
+    pub fn spawn_job_workers(pool: DbPool) {
+        let count = std::env::var("JOB_WORKERS")
+            .ok()
+            .and_then(|value| value.parse::<usize>().ok())
+            .unwrap_or(2);
+        let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "local".to_string());
+
+        for i in 0..count {
+            let worker_id = format!("{}-{}-{}", host, std::process::id(), i);
+            tokio::spawn(JobWorker::new(pool.clone(), worker_id).run());
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub use job_queue::*;
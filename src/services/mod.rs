@@ -1,13 +1,85 @@
 #[cfg(feature = "ssr")]
+pub mod archive;
+#[cfg(feature = "ssr")]
+pub mod audit;
+#[cfg(feature = "ssr")]
+pub mod chunking;
+#[cfg(feature = "ssr")]
+pub mod collab;
+#[cfg(feature = "ssr")]
+pub mod completions;
+#[cfg(feature = "ssr")]
+pub mod embeddings;
+#[cfg(feature = "ssr")]
+pub mod evaluation;
+#[cfg(feature = "ssr")]
+pub mod extraction;
+#[cfg(feature = "ssr")]
+pub mod imports;
+#[cfg(feature = "ssr")]
+pub mod ingestion;
+#[cfg(feature = "ssr")]
+pub mod jobs;
+#[cfg(feature = "ssr")]
 pub mod projects;
 #[cfg(feature = "ssr")]
 pub mod rag;
 #[cfg(feature = "ssr")]
+pub mod rerank;
+#[cfg(feature = "ssr")]
+pub mod retrieval;
+#[cfg(feature = "ssr")]
+pub mod shares;
+#[cfg(feature = "ssr")]
 pub mod title_generation;
+#[cfg(feature = "ssr")]
+pub mod tokens;
+#[cfg(feature = "ssr")]
+pub mod uploads;
+#[cfg(feature = "ssr")]
+pub mod versioning;
+#[cfg(feature = "ssr")]
+pub mod workspaces;
 
+#[cfg(feature = "ssr")]
+pub use archive::*;
+#[cfg(feature = "ssr")]
+pub use audit::*;
+#[cfg(feature = "ssr")]
+pub use chunking::*;
+#[cfg(feature = "ssr")]
+pub use collab::*;
+#[cfg(feature = "ssr")]
+pub use completions::*;
+#[cfg(feature = "ssr")]
+pub use embeddings::*;
+#[cfg(feature = "ssr")]
+pub use evaluation::*;
+#[cfg(feature = "ssr")]
+pub use extraction::*;
+#[cfg(feature = "ssr")]
+pub use imports::*;
+#[cfg(feature = "ssr")]
+pub use ingestion::*;
+#[cfg(feature = "ssr")]
+pub use jobs::*;
 #[cfg(feature = "ssr")]
 pub use projects::*;
 #[cfg(feature = "ssr")]
 pub use rag::*;
 #[cfg(feature = "ssr")]
+pub use rerank::*;
+#[cfg(feature = "ssr")]
+pub use retrieval::*;
+#[cfg(feature = "ssr")]
+pub use shares::*;
+#[cfg(feature = "ssr")]
 pub use title_generation::*;
+#[cfg(feature = "ssr")]
+pub use tokens::*;
+#[cfg(feature = "ssr")]
+pub use uploads::*;
+#[cfg(feature = "ssr")]
+pub use versioning::*;
+#[cfg(feature = "ssr")]
+pub use workspaces::*;
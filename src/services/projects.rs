@@ -1,28 +1,40 @@
 #[cfg(feature = "ssr")]
 pub mod projects_service {
     use pgvector::{Vector, VectorExpressionMethods};
-    use async_openai::{
-        types::{CreateEmbeddingRequestArgs, EmbeddingInput},
-        Client as OpenAIClient,
-    };
     use diesel::prelude::*;
-    use diesel_async::RunQueryDsl;
+    use diesel_async::{AsyncConnection, RunQueryDsl};
     use uuid::Uuid;
     use serde::{Serialize, Deserialize};
     use std::collections::HashMap;
+    use std::sync::Arc;
+    use log::{debug, warn};
 
     use crate::database::db::DbPool;
     use crate::models::projects::*;
+    use crate::models::workspaces::Permission;
     use crate::schema::*;
+    use crate::services::chunking::{chunker_for_document, line_at_char, ChunkingConfig};
+    use crate::services::embeddings::{embedding_provider, EmbeddingProvider};
+    use crate::services::extraction::{annotate_chunk, chunk_location, TextSegment};
+    use crate::services::ingestion::{embed_all, EmbeddingBatchConfig};
+    use crate::services::rerank::{normalise_scores, reranker_from_env, RerankCandidate, Reranker};
+    use crate::services::retrieval::{keyword_tsquery, max_fused_score, reciprocal_rank_fusion, KEYWORD_SEARCH_CONFIG};
+    use crate::services::tokens::{default_token_counter, TokenCounter};
+    use crate::services::versioning::{content_hash, plan_chunk_reuse};
+    use crate::services::workspaces::{authorize_project, AccessError};
 
     #[derive(Debug, Clone, Serialize, Deserialize)]
     pub struct DocumentContext {
         pub document_id: Uuid,
         pub filename: String,
+        /// Document version the chunks were indexed from
+        #[serde(default)]
+        pub document_version: i32,
         pub content: String,
         pub relevant_chunks: Vec<ChunkMatch>,
         pub file_size: usize,
         pub priority_score: f32,
+        pub token_count: usize,
     }
 
     #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,6 +45,22 @@ pub mod projects_service {
         pub chunk_index: i32,
         pub start_char: Option<i32>,
         pub end_char: Option<i32>,
+        /// Reciprocal rank fusion score normalised to 0..1 (1 = ranked first by every retriever)
+        #[serde(default)]
+        pub fused_score: f32,
+        /// Reranker score normalised to 0..1 within the candidate pool, if a reranker ran
+        #[serde(default)]
+        pub rerank_score: Option<f32>,
+        /// Page or section the chunk came from, e.g. "page 12", for citations
+        #[serde(default)]
+        pub location: Option<String>,
+    }
+
+    impl ChunkMatch {
+        /// Best available relevance signal: the reranker's if it ran, otherwise retrieval's
+        pub fn relevance(&self) -> f32 {
+            self.rerank_score.unwrap_or(self.fused_score)
+        }
     }
 
     #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,14 +68,30 @@ pub mod projects_service {
         pub documents: Vec<DocumentContext>,
         pub total_tokens: usize,
         pub summary: Option<String>,
+        /// Which counter produced `total_tokens`, e.g. "o200k_base" or "estimate:claude"
+        pub tokenizer: String,
     }
 
-    #[derive(Debug, Clone)]
+    #[derive(Debug, Clone, Serialize, Deserialize)]
     pub struct ContextStrategy {
         pub max_total_tokens: usize,
         pub max_full_documents: usize,
         pub small_file_threshold: usize, // Lines
         pub chunk_expansion_lines: usize,
+        /// Weight of the embedding ranking in reciprocal rank fusion
+        pub vector_weight: f32,
+        /// Weight of the keyword (full-text) ranking in reciprocal rank fusion
+        pub keyword_weight: f32,
+        /// RRF damping constant; larger values flatten the difference between ranks
+        pub rrf_k: f32,
+        /// Vector candidates further than this cosine distance are dropped before fusion
+        pub max_vector_distance: f64,
+        /// Keyword candidates ranked below this `ts_rank_cd` score are dropped before fusion
+        pub min_keyword_rank: f32,
+        /// How many candidates each retriever contributes, as a multiple of the final limit
+        pub candidate_multiplier: usize,
+        /// Size of the fused candidate pool handed to the reranker (ignored without one)
+        pub rerank_candidates: usize,
     }
 
     impl Default for ContextStrategy {
@@ -57,21 +101,42 @@ pub mod projects_service {
                 max_full_documents: 5,
                 small_file_threshold: 500, // Lines
                 chunk_expansion_lines: 50,
+                vector_weight: 1.0,
+                keyword_weight: 1.0,
+                rrf_k: 60.0,
+                max_vector_distance: 0.75, // 1 - 0.25 similarity
+                min_keyword_rank: 0.0,
+                candidate_multiplier: 3,
+                rerank_candidates: 20,
             }
         }
     }
 
+    #[derive(QueryableByName)]
+    struct ChunkIdRow {
+        #[diesel(sql_type = diesel::sql_types::Uuid)]
+        chunk_id: Uuid,
+    }
+
     pub struct EnhancedProjectsService {
-        openai: OpenAIClient<async_openai::config::OpenAIConfig>,
+        /// When set, used for every project instead of the project's configured model
+        embedder: Option<Arc<dyn EmbeddingProvider>>,
         strategy: ContextStrategy,
+        token_counter: Arc<dyn TokenCounter>,
+        chunking: ChunkingConfig,
+        batching: EmbeddingBatchConfig,
+        reranker: Option<Arc<dyn Reranker>>,
     }
 
     impl Default for EnhancedProjectsService {
         fn default() -> Self {
-            let openai = OpenAIClient::new();
             Self { 
-                openai,
+                embedder: None,
                 strategy: ContextStrategy::default(),
+                token_counter: default_token_counter(),
+                chunking: ChunkingConfig::default(),
+                batching: EmbeddingBatchConfig::default(),
+                reranker: reranker_from_env(),
             }
         }
     }
@@ -86,102 +151,313 @@ pub mod projects_service {
             self
         }
 
-        pub fn chunk_text(&self, text: &str, chunk_size: usize, overlap: usize) -> Vec<(String, usize, usize)> {
-            let mut chunks = Vec::new();
-            let chars: Vec<char> = text.chars().collect();
-            let mut start = 0;
-    
-            while start < chars.len() {
-                let end = std::cmp::min(start + chunk_size, chars.len());
-                let chunk_text: String = chars[start..end].iter().collect();
-    
-                chunks.push((chunk_text, start, end));
-    
-                if end >= chars.len() {
-                    break;
-                }
-    
-                start = if end > overlap { end - overlap } else { end };
-            }
-    
-            chunks
+        /// Count context tokens with the tokenizer of the model that will receive them
+        pub fn with_token_counter(mut self, token_counter: Arc<dyn TokenCounter>) -> Self {
+            self.token_counter = token_counter;
+            self
+        }
+
+        pub fn strategy(&self) -> &ContextStrategy {
+            &self.strategy
+        }
+
+        pub fn reranker_name(&self) -> Option<&str> {
+            self.reranker.as_deref().map(|reranker| reranker.name())
+        }
+
+        pub fn count_tokens(&self, text: &str) -> usize {
+            self.token_counter.count(text)
+        }
+
+        /// Replace the reranker picked from `RERANKER`; `None` keeps the fused retrieval order
+        pub fn with_reranker(mut self, reranker: Option<Arc<dyn Reranker>>) -> Self {
+            self.reranker = reranker;
+            self
+        }
+
+        pub fn with_chunking_config(mut self, chunking: ChunkingConfig) -> Self {
+            self.chunking = chunking;
+            self
+        }
+
+        pub fn with_batch_config(mut self, batching: EmbeddingBatchConfig) -> Self {
+            self.batching = batching;
+            self
         }
 
-        pub async fn generate_embedding(&self, text: &str) -> Result<Vector, Box<dyn std::error::Error + Send + Sync>> {
-            let response = self.openai
-                .embeddings()
-                .create(CreateEmbeddingRequestArgs::default()
-                    .model("text-embedding-3-small")
-                    .input(EmbeddingInput::String(text.to_string()))
-                    .build()?)
+        /// Embed with a fixed provider regardless of project settings
+        pub fn with_embedding_provider(mut self, embedder: Arc<dyn EmbeddingProvider>) -> Self {
+            self.embedder = Some(embedder);
+            self
+        }
+
+        /// The provider for the project's configured embedding model
+        pub async fn embedding_provider_for_project(
+            &self,
+            conn: &mut diesel_async::AsyncPgConnection,
+            project_id: Uuid,
+        ) -> Result<Arc<dyn EmbeddingProvider>, Box<dyn std::error::Error + Send + Sync>> {
+            if let Some(embedder) = &self.embedder {
+                return Ok(embedder.clone());
+            }
+
+            let model: String = projects::table
+                .find(project_id)
+                .select(projects::embedding_model)
+                .first(conn)
                 .await?;
-    
-            Ok(response.data[0].embedding.clone().into())
+
+            embedding_provider(&model)
+        }
+
+        pub async fn generate_embedding(
+            &self,
+            provider: &dyn EmbeddingProvider,
+            text: &str,
+        ) -> Result<Vector, Box<dyn std::error::Error + Send + Sync>> {
+            let mut vectors = embed_all(provider, &[text.to_string()], &self.batching).await?;
+            let vector = vectors.pop().ok_or("embedding provider returned no vectors")?;
+            Ok(vector.into())
         }
     
+        /// Chunks and embeds a document, replacing any previous chunks.
+        ///
+        /// Chunks whose text is unchanged since the last indexing keep their stored
+        /// embedding, so an edited document only re-embeds what changed. All new
+        /// embeddings are computed before the database is touched, and the chunks are
+        /// swapped in one transaction, so a failure part-way leaves the previous index
+        /// intact instead of a half-indexed document.
         pub async fn process_document(
             &self,
             pool: &DbPool,
-            document_id: Uuid,
-            content: &str,
+            document: &ProjectDocument,
         ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
             let mut conn = pool.get().await?;
-    
+
+            let document_id = document.id;
+            let version = document.version;
+            let provider = self.embedding_provider_for_project(&mut conn, document.project_id).await?;
+            let model_id = provider.model_id().to_string();
+
+            // Pages / headings recorded when the text was extracted from a PDF, DOCX, ...
+            let segments: Vec<TextSegment> = match &document.text_segments {
+                Some(value) => serde_json::from_value(value.clone())?,
+                None => Vec::new(),
+            };
+
+            // Split along headings / code items / paragraphs depending on the file type
+            let chunker = chunker_for_document(&document.filename, document.content_type.as_deref(), self.chunking);
+            let chunks: Vec<_> = chunker
+                .chunk(&document.content)
+                .into_iter()
+                .filter(|chunk| chunk.text.trim().len() >= 10)
+                .map(|mut chunk| {
+                    annotate_chunk(&mut chunk.metadata, chunk.start_char, chunk.end_char, &segments);
+                    chunk
+                })
+                .collect();
+            let hashes: Vec<String> = chunks.iter().map(|chunk| content_hash(&chunk.text)).collect();
+
+            // only chunks that already have a vector from this model are worth keeping
+            let existing: Vec<(Uuid, Option<String>)> = document_chunks::table
+                .inner_join(chunk_embeddings::table)
+                .filter(document_chunks::document_id.eq(document_id))
+                .filter(chunk_embeddings::embedding_model.eq(&model_id))
+                .select((document_chunks::id, document_chunks::content_hash))
+                .order(document_chunks::chunk_index)
+                .load(&mut conn)
+                .await?;
+            let plan = plan_chunk_reuse(&existing, &hashes);
+            let to_embed = plan.to_embed();
+
+            let texts: Vec<String> = to_embed.iter().map(|&index| chunks[index].text.clone()).collect();
+            let vectors = embed_all(provider.as_ref(), &texts, &self.batching).await?;
+            debug!(
+                "Document {} v{}: {} chunks unchanged, {} to embed",
+                document_id,
+                version,
+                chunks.len() - to_embed.len(),
+                to_embed.len()
+            );
+
+            let mut reused: Vec<(Uuid, NewDocumentChunk)> = Vec::new();
+            let mut new_chunks: Vec<NewDocumentChunk> = Vec::new();
+            for (index, (chunk, hash)) in chunks.into_iter().zip(hashes).enumerate() {
+                let row = NewDocumentChunk {
+                    document_id,
+                    chunk_text: chunk.text,
+                    chunk_index: index as i32,
+                    start_char: Some(chunk.start_char as i32),
+                    end_char: Some(chunk.end_char as i32),
+                    metadata: Some(chunk.metadata),
+                    content_hash: Some(hash),
+                };
+                match plan.reuse[index] {
+                    Some(chunk_id) => reused.push((chunk_id, row)),
+                    None => new_chunks.push(row),
+                }
+            }
+            let kept: Vec<Uuid> = reused.iter().map(|(chunk_id, _)| *chunk_id).collect();
+
+            conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                Box::pin(async move {
+                    let stale = document_chunks::table
+                        .select(document_chunks::id)
+                        .filter(document_chunks::document_id.eq(document_id))
+                        .filter(diesel::dsl::not(document_chunks::id.eq_any(&kept)));
+
+                    diesel::delete(chunk_embeddings::table.filter(chunk_embeddings::chunk_id.eq_any(stale)))
+                        .execute(conn)
+                        .await?;
+
+                    diesel::delete(
+                        document_chunks::table
+                            .filter(document_chunks::document_id.eq(document_id))
+                            .filter(diesel::dsl::not(document_chunks::id.eq_any(&kept)))
+                    )
+                    .execute(conn)
+                    .await?;
+
+                    // unchanged text may still have moved, or landed on another page
+                    for (chunk_id, row) in &reused {
+                        diesel::update(document_chunks::table.find(chunk_id))
+                            .set((
+                                document_chunks::chunk_index.eq(row.chunk_index),
+                                document_chunks::start_char.eq(row.start_char),
+                                document_chunks::end_char.eq(row.end_char),
+                                document_chunks::metadata.eq(&row.metadata),
+                            ))
+                            .execute(conn)
+                            .await?;
+                    }
+
+                    if !new_chunks.is_empty() {
+                        let inserted: Vec<DocumentChunk> = diesel::insert_into(document_chunks::table)
+                            .values(&new_chunks)
+                            .get_results(conn)
+                            .await?;
+
+                        // vectors come back in the order of `to_embed`, which holds chunk indices
+                        let mut vectors: HashMap<i32, Vec<f32>> = to_embed
+                            .iter()
+                            .map(|&index| index as i32)
+                            .zip(vectors)
+                            .collect();
+                        let new_embeddings: Vec<NewChunkEmbedding> = inserted
+                            .iter()
+                            .filter_map(|chunk| {
+                                let vector = vectors.remove(&chunk.chunk_index)?;
+                                Some(NewChunkEmbedding {
+                                    chunk_id: chunk.id,
+                                    embedding: Some(vector.into()),
+                                    embedding_model: model_id.clone(),
+                                })
+                            })
+                            .collect();
+
+                        diesel::insert_into(chunk_embeddings::table)
+                            .values(&new_embeddings)
+                            .execute(conn)
+                            .await?;
+                    }
+
+                    diesel::update(project_documents::table.find(document_id))
+                        .set(project_documents::indexed_version.eq(Some(version)))
+                        .execute(conn)
+                        .await?;
+
+                    Ok(())
+                })
+            })
+            .await?;
+
+            Ok(())
+        }
+
+        /// Embeds every chunk of the project that has no vector from the project's current
+        /// model, then drops vectors from other models. Safe to re-run after a failure;
+        /// returns how many chunks were embedded.
+        pub async fn reembed_project(
+            &self,
+            pool: &DbPool,
+            project_id: Uuid,
+        ) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+            const BATCH_SIZE: i64 = 64;
+
+            let mut conn = pool.get().await?;
+            let provider = self.embedding_provider_for_project(&mut conn, project_id).await?;
+            let model_id = provider.model_id().to_string();
+            let mut embedded = 0;
+
+            loop {
+                let pending: Vec<(Uuid, String)> = document_chunks::table
+                    .inner_join(project_documents::table)
+                    .filter(project_documents::project_id.eq(project_id))
+                    .filter(diesel::dsl::not(document_chunks::id.eq_any(
+                        chunk_embeddings::table
+                            .filter(chunk_embeddings::embedding_model.eq(&model_id))
+                            .select(chunk_embeddings::chunk_id)
+                    )))
+                    .select((document_chunks::id, document_chunks::chunk_text))
+                    .order(document_chunks::id)
+                    .limit(BATCH_SIZE)
+                    .load(&mut conn)
+                    .await?;
+
+                if pending.is_empty() {
+                    break;
+                }
+
+                let texts: Vec<String> = pending.iter().map(|(_, text)| text.clone()).collect();
+                let vectors = embed_all(provider.as_ref(), &texts, &self.batching).await?;
+
+                let rows: Vec<NewChunkEmbedding> = pending
+                    .iter()
+                    .zip(vectors)
+                    .map(|((chunk_id, _), vector)| NewChunkEmbedding {
+                        chunk_id: *chunk_id,
+                        embedding: Some(vector.into()),
+                        embedding_model: model_id.clone(),
+                    })
+                    .collect();
+
+                diesel::insert_into(chunk_embeddings::table)
+                    .values(&rows)
+                    .on_conflict((chunk_embeddings::chunk_id, chunk_embeddings::embedding_model))
+                    .do_nothing()
+                    .execute(&mut conn)
+                    .await?;
+
+                embedded += rows.len();
+                debug!("Re-embedded {} chunks of project {} with {}", embedded, project_id, model_id);
+            }
+
+            // the model may have been changed again while this job ran; leave cleanup to that job
+            let current_model: String = projects::table
+                .find(project_id)
+                .select(projects::embedding_model)
+                .first(&mut conn)
+                .await?;
+
+            if self.embedder.is_none() && current_model != model_id {
+                warn!("Project {} switched to {} during re-embedding, keeping old vectors", project_id, current_model);
+                return Ok(embedded);
+            }
+
             diesel::delete(
                 chunk_embeddings::table
+                    .filter(chunk_embeddings::embedding_model.ne(&model_id))
                     .filter(chunk_embeddings::chunk_id.eq_any(
                         document_chunks::table
+                            .inner_join(project_documents::table)
+                            .filter(project_documents::project_id.eq(project_id))
                             .select(document_chunks::id)
-                            .filter(document_chunks::document_id.eq(document_id))
                     ))
             )
             .execute(&mut conn)
             .await?;
-    
-            diesel::delete(
-                document_chunks::table.filter(document_chunks::document_id.eq(document_id))
-            )
-            .execute(&mut conn)
-            .await?;
-    
-            let chunks = self.chunk_text(content, 1000, 200);
-    
-            for (index, (chunk_text, start_char, end_char)) in chunks.into_iter().enumerate() {
-                if chunk_text.trim().len() < 10 {
-                    continue;
-                }
-    
-                let new_chunk = NewDocumentChunk {
-                    document_id,
-                    chunk_text: chunk_text.clone(),
-                    chunk_index: index as i32,
-                    start_char: Some(start_char as i32),
-                    end_char: Some(end_char as i32),
-                    metadata: Some(serde_json::json!({
-                        "word_count": chunk_text.split_whitespace().count()
-                    })),
-                };
-    
-                let chunk: DocumentChunk = diesel::insert_into(document_chunks::table)
-                    .values(&new_chunk)
-                    .get_result(&mut conn)
-                    .await?;
-    
-                let embedding = self.generate_embedding(&chunk_text).await?;
-    
-                let new_embedding = NewChunkEmbedding {
-                    chunk_id: chunk.id,
-                    embedding: Some(embedding),
-                    embedding_model: Some("text-embedding-3-small".to_string()),
-                };
-    
-                diesel::insert_into(chunk_embeddings::table)
-                    .values(&new_embedding)
-                    .execute(&mut conn)
-                    .await?;
-            }
-    
-            Ok(())
+
+            Ok(embedded)
         }
 
         pub async fn search_project_with_context(
@@ -193,14 +469,45 @@ pub mod projects_service {
         ) -> Result<WorkingContext, Box<dyn std::error::Error + Send + Sync>> {
             let mut conn = pool.get().await?;
 
-            let query_embedding = self.generate_embedding(query).await?;
+            let provider = self.embedding_provider_for_project(&mut conn, project_id).await?;
+            let query_embedding = self.generate_embedding(provider.as_ref(), query).await?;
+
+            // with a reranker, retrieve a larger pool and let it pick the final `limit`
+            let limit = limit.max(0) as usize;
+            let pool_size = match self.reranker {
+                Some(_) => limit.max(self.strategy.rerank_candidates),
+                None => limit,
+            };
+            let candidate_limit = (pool_size.max(1) * self.strategy.candidate_multiplier.max(1)) as i64;
+
+            // Vector candidates
+            let vector_ids = self
+                .vector_candidates(&mut conn, project_id, provider.as_ref(), &query_embedding, candidate_limit)
+                .await?;
 
-            // Find relevant chunks
+            // Keyword candidates, so exact identifiers are found even when embeddings miss them
+            let keyword_ids = self.keyword_candidates(&mut conn, project_id, query, candidate_limit).await?;
+
+            let fused = reciprocal_rank_fusion(
+                &[
+                    (vector_ids.as_slice(), self.strategy.vector_weight),
+                    (keyword_ids.as_slice(), self.strategy.keyword_weight),
+                ],
+                self.strategy.rrf_k,
+            );
+            let max_score = max_fused_score(&[self.strategy.vector_weight, self.strategy.keyword_weight], self.strategy.rrf_k);
+            let fused_scores: HashMap<Uuid, f32> = fused
+                .into_iter()
+                .take(pool_size)
+                .map(|(id, score)| (id, if max_score > 0.0 { score / max_score } else { 0.0 }))
+                .collect();
+
+            let selected_ids: Vec<Uuid> = fused_scores.keys().cloned().collect();
             let chunk_results = document_chunks::table
                 .inner_join(chunk_embeddings::table)
                 .inner_join(project_documents::table)
-                .filter(project_documents::project_id.eq(project_id))
-                .filter(chunk_embeddings::embedding.is_not_null())
+                .filter(document_chunks::id.eq_any(&selected_ids))
+                .filter(chunk_embeddings::embedding_model.eq(provider.model_id()))
                 .select((
                     document_chunks::id,
                     document_chunks::chunk_text,
@@ -210,29 +517,43 @@ pub mod projects_service {
                     document_chunks::chunk_index,
                     document_chunks::start_char,
                     document_chunks::end_char,
+                    document_chunks::metadata,
                 ))
-                .order(chunk_embeddings::embedding.cosine_distance(&query_embedding))
-                .filter(chunk_embeddings::embedding.cosine_distance(&query_embedding).lt(0.75)) // 1 - 0.25 threshold
-                .limit(limit as i64)
-                .load::<(Uuid, String, Option<f64>, Uuid, String, i32, Option<i32>, Option<i32>)>(&mut conn)
+                .load::<(Uuid, String, Option<f64>, Uuid, String, i32, Option<i32>, Option<i32>, Option<serde_json::Value>)>(&mut conn)
                 .await?;
 
+            let mut candidates: Vec<(Uuid, String, ChunkMatch)> = chunk_results
+                .into_iter()
+                .map(|(chunk_id, chunk_text, distance, document_id, filename, chunk_index, start_char, end_char, metadata)| {
+                    let similarity = distance.map(|d| 1.0 - d as f32).unwrap_or(0.0);
+                    let fused_score = fused_scores.get(&chunk_id).copied().unwrap_or(0.0);
+
+                    (document_id, filename, ChunkMatch {
+                        chunk_id,
+                        chunk_text,
+                        similarity,
+                        chunk_index,
+                        start_char,
+                        end_char,
+                        fused_score,
+                        rerank_score: None,
+                        location: metadata.as_ref().and_then(chunk_location),
+                    })
+                })
+                .collect();
+            candidates.sort_by(|a, b| b.2.fused_score.partial_cmp(&a.2.fused_score).unwrap_or(std::cmp::Ordering::Equal));
+
+            if let Some(reranker) = &self.reranker {
+                self.rerank(reranker.as_ref(), query, &mut candidates).await;
+            }
+            candidates.truncate(limit);
+
             // Group chunks by document
             let mut document_chunks: HashMap<Uuid, Vec<ChunkMatch>> = HashMap::new();
             let mut document_info: HashMap<Uuid, String> = HashMap::new();
 
-            for (chunk_id, chunk_text, distance, document_id, filename, chunk_index, start_char, end_char) in chunk_results {
-                let similarity = distance.map(|d| 1.0 - d as f32).unwrap_or(0.0);
-
-                document_chunks.entry(document_id).or_default().push(ChunkMatch {
-                    chunk_id,
-                    chunk_text,
-                    similarity,
-                    chunk_index,
-                    start_char,
-                    end_char,
-                });
-
+            for (document_id, filename, chunk) in candidates {
+                document_chunks.entry(document_id).or_default().push(chunk);
                 document_info.insert(document_id, filename);
             }
 
@@ -251,24 +572,26 @@ pub mod projects_service {
                 if let Some(chunks) = document_chunks.get(&doc.id) {
                     let file_size = doc.content.lines().count();
 
-                    // calculate priority based on chunk similarity and file characteristics
-                    let avg_similarity = chunks.iter().map(|c| c.similarity).sum::<f32>() / chunks.len() as f32;
+                    // calculate priority based on chunk relevance and file characteristics
+                    let avg_relevance = chunks.iter().map(|c| c.relevance()).sum::<f32>() / chunks.len() as f32;
                     let chunk_density = chunks.len() as f32 / file_size as f32;
-                    let priority_score = avg_similarity * 0.7 + chunk_density * 0.3;
+                    let priority_score = avg_relevance * 0.7 + chunk_density * 0.3;
 
                     let content = self.select_document_content(&doc, chunks, file_size)?;
-                    let estimated_tokens = content.len() / 4; // Rough token estimation
+                    let token_count = self.token_counter.count(&content);
 
                     contexts.push(DocumentContext {
                         document_id: doc.id,
+                        document_version: doc.indexed_version.unwrap_or(doc.version),
                         filename: doc.filename,
                         content,
                         relevant_chunks: chunks.clone(),
                         file_size,
                         priority_score,
+                        token_count,
                     });
 
-                    total_tokens += estimated_tokens;
+                    total_tokens += token_count;
                 }
             }
 
@@ -280,6 +603,119 @@ pub mod projects_service {
             Ok(working_context)
         }
 
+        /// Reorders candidates by reranker score. If the reranker fails the fused order is kept.
+        async fn rerank(&self, reranker: &dyn Reranker, query: &str, candidates: &mut [(Uuid, String, ChunkMatch)]) {
+            if candidates.is_empty() {
+                return;
+            }
+
+            let inputs: Vec<RerankCandidate> = candidates
+                .iter()
+                .map(|(_, _, chunk)| RerankCandidate { text: &chunk.chunk_text, prior_score: chunk.fused_score })
+                .collect();
+
+            let scores = match reranker.score(query, &inputs).await {
+                Ok(scores) if scores.len() == candidates.len() => scores,
+                Ok(scores) => {
+                    warn!("Reranker {} returned {} scores for {} candidates, keeping retrieval order",
+                          reranker.name(), scores.len(), candidates.len());
+                    return;
+                }
+                Err(e) => {
+                    warn!("Reranker {} failed, keeping retrieval order: {}", reranker.name(), e);
+                    return;
+                }
+            };
+
+            for ((_, _, chunk), score) in candidates.iter_mut().zip(normalise_scores(&scores)) {
+                chunk.rerank_score = Some(score);
+            }
+
+            candidates.sort_by(|a, b| b.2.relevance().partial_cmp(&a.2.relevance()).unwrap_or(std::cmp::Ordering::Equal));
+            debug!("Reranked {} candidates with {}", candidates.len(), reranker.name());
+        }
+
+        /// Chunk ids nearest to the query embedding among vectors from the project's model, best first
+        async fn vector_candidates(
+            &self,
+            conn: &mut diesel_async::AsyncPgConnection,
+            project_id: Uuid,
+            provider: &dyn EmbeddingProvider,
+            query_embedding: &Vector,
+            limit: i64,
+        ) -> Result<Vec<Uuid>, Box<dyn std::error::Error + Send + Sync>> {
+            // casting to the model's dimension lets Postgres use the matching partial HNSW index
+            let (cast, dimension_filter) = match provider.dimensions() {
+                Some(dimensions) => (
+                    format!("::vector({dimensions})"),
+                    format!("AND vector_dims(ce.embedding) = {dimensions}"),
+                ),
+                None => (String::new(), String::new()),
+            };
+
+            let sql = format!(
+                "SELECT ce.chunk_id
+                FROM chunk_embeddings ce
+                JOIN document_chunks dc ON dc.id = ce.chunk_id
+                JOIN project_documents pd ON pd.id = dc.document_id
+                WHERE pd.project_id = $1
+                    AND ce.embedding_model = $2
+                    AND ce.embedding IS NOT NULL
+                    {dimension_filter}
+                    AND (ce.embedding{cast} <=> $3{cast}) < $4
+                ORDER BY ce.embedding{cast} <=> $3{cast}
+                LIMIT $5"
+            );
+
+            let matches: Vec<ChunkIdRow> = diesel::sql_query(sql)
+                .bind::<diesel::sql_types::Uuid, _>(project_id)
+                .bind::<diesel::sql_types::Text, _>(provider.model_id())
+                .bind::<pgvector::sql_types::Vector, _>(query_embedding)
+                .bind::<diesel::sql_types::Double, _>(self.strategy.max_vector_distance)
+                .bind::<diesel::sql_types::BigInt, _>(limit)
+                .load(conn)
+                .await?;
+
+            Ok(matches.into_iter().map(|m| m.chunk_id).collect())
+        }
+
+        /// Chunk ids matching the query's terms in the full-text index, best first
+        async fn keyword_candidates(
+            &self,
+            conn: &mut diesel_async::AsyncPgConnection,
+            project_id: Uuid,
+            query: &str,
+            limit: i64,
+        ) -> Result<Vec<Uuid>, Box<dyn std::error::Error + Send + Sync>> {
+            let Some(tsquery) = keyword_tsquery(query) else {
+                return Ok(Vec::new());
+            };
+
+            // must match the expression of idx_document_chunks_keyword to use the index
+            let sql = format!(
+                "SELECT dc.id AS chunk_id
+                FROM document_chunks dc
+                JOIN project_documents pd ON pd.id = dc.document_id,
+                    to_tsquery('{config}', $2) AS q
+                WHERE pd.project_id = $1
+                    AND to_tsvector('{config}', dc.chunk_text) @@ q
+                    AND ts_rank_cd(to_tsvector('{config}', dc.chunk_text), q, 32) >= $3
+                ORDER BY ts_rank_cd(to_tsvector('{config}', dc.chunk_text), q, 32) DESC
+                LIMIT $4",
+                config = KEYWORD_SEARCH_CONFIG,
+            );
+
+            let matches: Vec<ChunkIdRow> = diesel::sql_query(sql)
+                .bind::<diesel::sql_types::Uuid, _>(project_id)
+                .bind::<diesel::sql_types::Text, _>(tsquery)
+                .bind::<diesel::sql_types::Float4, _>(self.strategy.min_keyword_rank)
+                .bind::<diesel::sql_types::BigInt, _>(limit)
+                .load(conn)
+                .await?;
+
+            Ok(matches.into_iter().map(|m| m.chunk_id).collect())
+        }
+
         // select appropriate content from document based on strategy
         fn select_document_content(
             &self,
@@ -298,9 +734,9 @@ pub mod projects_service {
 
             for chunk in chunks {
                 if let (Some(start_char), Some(end_char)) = (chunk.start_char, chunk.end_char) {
-                    // convert char positions to line numbers (approx)
-                    let start_line = doc.content[..start_char as usize].lines().count();
-                    let end_line = doc.content[..end_char as usize].lines().count();
+                    // offsets count chars, so don't slice the content by them
+                    let start_line = line_at_char(&doc.content, start_char.max(0) as usize);
+                    let end_line = line_at_char(&doc.content, end_char.max(0) as usize) + 1;
 
                     let expanded_start = start_line.saturating_sub(self.strategy.chunk_expansion_lines);
                     let expanded_end = std::cmp::min(
@@ -358,6 +794,7 @@ pub mod projects_service {
                     documents: contexts,
                     total_tokens,
                     summary: None, 
+                    tokenizer: self.token_counter.name().to_string(),
                 });
             }
 
@@ -366,23 +803,27 @@ pub mod projects_service {
             let mut optimized_contexts = Vec::new();
 
             for context in contexts.into_iter().take(self.strategy.max_full_documents) {
-                let estimated_tokens = context.content.len() / 4;
-                if current_tokens + estimated_tokens <= self.strategy.max_total_tokens {
-                    current_tokens += estimated_tokens;
+                if current_tokens + context.token_count <= self.strategy.max_total_tokens {
+                    current_tokens += context.token_count;
                     optimized_contexts.push(context);
                 } else {
                     // if we can't fit the full document, include just the chunks
                     let chunk_summary = context.relevant_chunks
                         .iter()
-                        .map(|c| format!("// {} (similarity: {:.2})\n{}",
-                                context.filename, c.similarity, c.chunk_text))
+                        .map(|c| match &c.location {
+                            Some(location) => format!("// {}, {} (similarity: {:.2})\n{}",
+                                context.filename, location, c.similarity, c.chunk_text),
+                            None => format!("// {} (similarity: {:.2})\n{}",
+                                context.filename, c.similarity, c.chunk_text),
+                        })
                         .collect::<Vec<_>>()
                         .join("\n\n");
 
-                    let summary_tokens = chunk_summary.len() / 4;
+                    let summary_tokens = self.token_counter.count(&chunk_summary);
                     if current_tokens + summary_tokens <= self.strategy.max_total_tokens {
                         optimized_contexts.push(DocumentContext {
                             content: chunk_summary,
+                            token_count: summary_tokens,
                             ..context
                         });
                         current_tokens += summary_tokens;
@@ -394,6 +835,7 @@ pub mod projects_service {
                 documents: optimized_contexts,
                 total_tokens: current_tokens,
                 summary: None,
+                tokenizer: self.token_counter.name().to_string(),
             })
         }
 
@@ -411,6 +853,8 @@ pub mod projects_service {
             
             formatted.push_str("## Relevant Documents\n\n");
             
+            // numbered in the same order as the citations sent to the client
+            let mut source_number = 0;
             for (i, doc) in working_context.documents.iter().enumerate() {
                 formatted.push_str(&format!("### Document {}: {}\n", i + 1, doc.filename));
                 formatted.push_str(&format!("**File size:** {} lines | **Priority:** {:.2}\n\n", 
@@ -424,6 +868,28 @@ pub mod projects_service {
                     formatted.push_str(&format!("**Matching chunks (similarity):** {}\n\n", 
                                                similarities.join(", ")));
                 }
+
+                let locations: Vec<&str> = doc.relevant_chunks
+                    .iter()
+                    .filter_map(|c| c.location.as_deref())
+                    .collect();
+                if !locations.is_empty() {
+                    formatted.push_str(&format!("**Matching locations:** {}\n\n", locations.join("; ")));
+                }
+
+                if !doc.relevant_chunks.is_empty() {
+                    let sources: Vec<String> = doc.relevant_chunks
+                        .iter()
+                        .map(|c| {
+                            source_number += 1;
+                            match &c.location {
+                                Some(location) => format!("[{}] {}", source_number, location),
+                                None => format!("[{}] chunk {}", source_number, c.chunk_index + 1),
+                            }
+                        })
+                        .collect();
+                    formatted.push_str(&format!("**Source numbers:** {}\n\n", sources.join(", ")));
+                }
                 
                 formatted.push_str("```\n");
                 formatted.push_str(&doc.content);
@@ -431,12 +897,61 @@ pub mod projects_service {
                 formatted.push_str("---\n\n");
             }
             
-            formatted.push_str(&format!("\n*Total context: ~{} tokens across {} documents*\n", 
+            formatted.push_str(&format!("\n*Total context: {} tokens across {} documents*\n", 
                                       working_context.total_tokens, working_context.documents.len()));
             
             formatted
         }
     }
+
+    /// Full text of a document, or of one of its earlier versions, if `user_id` may
+    /// read its project
+    pub async fn document_content(
+        conn: &mut diesel_async::AsyncPgConnection,
+        user_id: i32,
+        document_id: Uuid,
+        version: Option<i32>,
+    ) -> Result<DocumentContentView, AccessError> {
+        let document: ProjectDocument = project_documents::table
+            .find(document_id)
+            .first(conn)
+            .await
+            .optional()?
+            .ok_or(AccessError::NotFound("Document"))?;
+        match authorize_project(conn, user_id, document.project_id, Permission::Read).await {
+            Err(AccessError::NotFound(_)) => return Err(AccessError::NotFound("Document")),
+            Err(e) => return Err(e),
+            Ok(_) => {}
+        }
+
+        let mut view = DocumentContentView {
+            document_id: document.id,
+            project_id: document.project_id,
+            filename: document.filename,
+            version: document.version,
+            latest_version: document.version,
+            content: document.content,
+            content_type: document.content_type,
+            page_count: document.page_count,
+        };
+
+        if let Some(version) = version.filter(|&version| version != view.latest_version) {
+            let stored: DocumentVersion = document_versions::table
+                .filter(document_versions::document_id.eq(document_id))
+                .filter(document_versions::version.eq(version))
+                .first(conn)
+                .await
+                .optional()?
+                .ok_or(AccessError::NotFound("Document version"))?;
+
+            view.version = stored.version;
+            view.content = stored.content;
+            view.content_type = stored.content_type;
+            view.page_count = stored.page_count;
+        }
+
+        Ok(view)
+    }
 }
 
 #[cfg(feature = "ssr")]
@@ -8,11 +8,8 @@ pub mod rag_service {
         },
         Client as OpenAIClient,
     };
-    use axum::response::sse::Event;
     use futures::StreamExt;
-    use log::{debug, info, error};
-    use serde::{Deserialize, Serialize};
-    use std::convert::Infallible;
+    use log::{debug, info, error, warn};
     use tokio::sync::mpsc;
     use tokio_util::sync::CancellationToken;
     use anyhow::Result;
@@ -20,24 +17,11 @@ pub mod rag_service {
 
     use crate::database::db::DbPool;
     use crate::models::projects::ProjectSearchResult;
-    use crate::services::projects::{EnhancedProjectsService, ContextStrategy};
-    use crate::models::conversations::Message;
-
-    #[derive(Debug, Serialize, Deserialize, Clone)]
-    pub struct RagResponse {
-        pub message_type: String, // "content", "citations", "error", "done", "status"
-        pub content: Option<String>,
-        pub citations: Option<Vec<DocumentCitation>>,
-        pub status: Option<String>,
-    }
-
-    #[derive(Debug, Serialize, Deserialize, Clone)]
-    pub struct DocumentCitation {
-        pub filename: String,
-        pub chunk_text: String,
-        pub similarity: f32,
-        pub chunk_index: i32,
-    }
+    use crate::services::completions::CompletionEvent;
+    use crate::services::projects::{EnhancedProjectsService, ContextStrategy, WorkingContext};
+    use crate::services::tokens::token_counter_for_model;
+    use crate::models::conversations::{citation_preview, DocumentCitation, Message};
+    use crate::models::traces::{TracedChunk, TracedDocument};
 
     #[derive(Debug, Clone)]
     pub enum LLMProvider {
@@ -54,9 +38,23 @@ pub mod rag_service {
         projects_service: EnhancedProjectsService,
     }
 
+    /// System messages stored in the thread, e.g. from API callers, follow the RAG prompt
+    fn with_caller_instructions(system_prompt: String, history: &[Message]) -> String {
+        let instructions: Vec<&str> = history.iter()
+            .filter(|msg| msg.role == "system")
+            .filter_map(|msg| msg.content.as_deref())
+            .collect();
+        if instructions.is_empty() {
+            system_prompt
+        } else {
+            format!("{system_prompt}\n\n{}", instructions.join("\n\n"))
+        }
+    }
+
     impl ProjectRagService {
         pub fn new_openai(model: String) -> Self {
             let client = OpenAIClient::new();
+            let token_counter = token_counter_for_model(&model);
             Self {
                 openai_client: Some(client),
                 anthropic_client: None,
@@ -69,7 +67,9 @@ pub mod rag_service {
                         max_full_documents: 8,
                         small_file_threshold: 300, // Lines
                         chunk_expansion_lines: 25,
-                    }),
+                        ..Default::default()
+                    })
+                    .with_token_counter(token_counter),
             }
         }
 
@@ -77,6 +77,7 @@ pub mod rag_service {
             let api_key = std::env::var("ANTHROPIC_API_KEY")
                 .map_err(|_| "ANTHROPIC_API_KEY must be set")?;
             let client = reqwest::Client::new();
+            let token_counter = token_counter_for_model(&model);
             
             Ok(Self {
                 openai_client: None,
@@ -90,7 +91,9 @@ pub mod rag_service {
                         max_full_documents: 8,
                         small_file_threshold: 300,
                         chunk_expansion_lines: 25,
-                    }),
+                        ..Default::default()
+                    })
+                    .with_token_counter(token_counter),
             })
         }
 
@@ -100,7 +103,7 @@ pub mod rag_service {
             project_id: Uuid,
             query: String,
             thread_id: &str,
-            tx: mpsc::Sender<Result<Event, Infallible>>,
+            tx: mpsc::Sender<CompletionEvent>,
             cancel_token: CancellationToken,
         ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
             debug!("Processing project RAG query for project {}: {}", project_id, query);
@@ -111,24 +114,14 @@ pub mod rag_service {
             }
 
             // Step 1: Send initial status
-            self.send_response(&tx, RagResponse {
-                message_type: "status".to_string(),
-                content: None,
-                citations: None,
-                status: Some("Analyzing project documents...".to_string()),
-            }).await?;
+            self.send_event(&tx, CompletionEvent::Status("Analyzing project documents...".to_string())).await?;
 
             // Step 2: Use enhanced search with intelligent context
             let working_context = match self.projects_service.search_project_with_context(pool, project_id, &query, 5).await {
                 Ok(context) => context,
                 Err(e) => {
                     error!("Failed to search project documents: {e}");
-                    self.send_response(&tx, RagResponse {
-                        message_type: "error".to_string(),
-                        content: Some("Failed to search project documents".to_string()),
-                        citations: None,
-                        status: None,
-                    }).await?;
+                    self.send_event(&tx, CompletionEvent::Error("Failed to search project documents".to_string())).await?;
                     return Err(e);
                 }
             };
@@ -137,43 +130,63 @@ pub mod rag_service {
                 return Ok(());
             }
 
-            info!("Found {} relevant documents with {} total tokens", 
-                   working_context.documents.len(), working_context.total_tokens);
+            info!("Found {} relevant documents with {} total tokens ({})", 
+                   working_context.documents.len(), working_context.total_tokens, working_context.tokenizer);
+
+            // Step 3: Get conversation history and create enhanced context
+            let conversation_history = self.get_conversation_history(pool, thread_id).await?;
+            let formatted_context = self.projects_service.format_context_for_llm(&working_context);
 
-            // Step 3: Send enhanced citations
-            if !working_context.documents.is_empty() {
+            let history_tokens: usize = conversation_history
+                .iter()
+                .filter_map(|msg| msg.content.as_deref())
+                .map(|content| self.projects_service.count_tokens(content))
+                .sum();
+            let context_tokens = self.projects_service.count_tokens(&formatted_context);
+            info!("Prompt usage for thread {}: {} context tokens + {} history tokens ({})",
+                  thread_id, context_tokens, history_tokens, working_context.tokenizer);
+
+            let trace_id = self.record_trace(
+                pool,
+                project_id,
+                thread_id,
+                &query,
+                &working_context,
+                context_tokens,
+                history_tokens,
+            ).await;
+
+            // Step 4: Send enhanced citations (possibly none), pointing at the stored trace
+            {
                 let citations: Vec<DocumentCitation> = working_context.documents
                     .iter()
                     .flat_map(|doc| {
                         doc.relevant_chunks.iter().map(|chunk| DocumentCitation {
+                            document_id: Some(doc.document_id),
+                            document_version: doc.document_version,
+                            chunk_id: Some(chunk.chunk_id),
                             filename: doc.filename.clone(),
-                            chunk_text: if chunk.chunk_text.len() > 200 {
-                                format!("{}...", &chunk.chunk_text[..200])
-                            } else {
-                                chunk.chunk_text.clone()
-                            },
+                            chunk_text: citation_preview(&chunk.chunk_text),
                             similarity: chunk.similarity,
                             chunk_index: chunk.chunk_index,
+                            start_char: chunk.start_char,
+                            end_char: chunk.end_char,
+                            location: chunk.location.clone(),
                         })
                     })
                     .collect();
 
-                self.send_response(&tx, RagResponse {
-                    message_type: "citations".to_string(),
-                    content: None,
-                    citations: Some(citations),
-                    status: None,
-                }).await?;
+                self.send_event(&tx, CompletionEvent::Citations { citations, trace_id }).await?;
+
+                if !working_context.documents.is_empty() {
+                    self.record_citations(pool, thread_id, &working_context).await;
+                }
             }
 
             if cancel_token.is_cancelled() {
                 return Ok(());
             }
 
-            // Step 4: Get conversation history and create enhanced context
-            let conversation_history = self.get_conversation_history(pool, thread_id).await?;
-            let formatted_context = self.projects_service.format_context_for_llm(&working_context);
-
             // Step 5: Generate response with enhanced context
             match self.provider {
                 LLMProvider::OpenAI => {
@@ -214,6 +227,83 @@ pub mod rag_service {
             Ok(())
         }
 
+        /// Stores what retrieval produced for this query. Best effort like `record_citations`:
+        /// without a trace the answer is still sent, just not linked to one.
+        #[allow(clippy::too_many_arguments)]
+        async fn record_trace(
+            &self,
+            pool: &DbPool,
+            project_id: Uuid,
+            thread_id: &str,
+            query: &str,
+            working_context: &WorkingContext,
+            context_tokens: usize,
+            history_tokens: usize,
+        ) -> Option<Uuid> {
+            use crate::models::traces::NewRetrievalTrace;
+            use crate::schema::retrieval_traces;
+            use diesel_async::RunQueryDsl;
+
+            let trace = NewRetrievalTrace {
+                thread_id: thread_id.to_string(),
+                project_id,
+                query: query.to_string(),
+                strategy: serde_json::to_value(self.projects_service.strategy()).unwrap_or_default(),
+                reranker: self.projects_service.reranker_name().map(str::to_string),
+                tokenizer: working_context.tokenizer.clone(),
+                context_tokens: context_tokens as i32,
+                history_tokens: history_tokens as i32,
+                documents: serde_json::to_value(traced_documents(working_context)).unwrap_or_default(),
+            };
+
+            let result = match pool.get().await {
+                Ok(mut conn) => diesel::insert_into(retrieval_traces::table)
+                    .values(&trace)
+                    .returning(retrieval_traces::id)
+                    .get_result::<Uuid>(&mut conn)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            result
+                .map_err(|e| warn!("Failed to record retrieval trace for thread {}: {}", thread_id, e))
+                .ok()
+        }
+
+        /// Remembers which document versions the thread's answer drew on. Best effort:
+        /// a failure here shouldn't stop the answer.
+        async fn record_citations(&self, pool: &DbPool, thread_id: &str, working_context: &WorkingContext) {
+            use crate::models::conversations::NewThreadCitation;
+            use crate::schema::thread_citations;
+            use diesel_async::RunQueryDsl;
+
+            let rows: Vec<NewThreadCitation> = working_context.documents
+                .iter()
+                .flat_map(|doc| {
+                    doc.relevant_chunks.iter().map(|chunk| NewThreadCitation {
+                        thread_id: thread_id.to_string(),
+                        document_id: Some(doc.document_id),
+                        document_version: doc.document_version,
+                        filename: doc.filename.clone(),
+                        chunk_index: chunk.chunk_index,
+                        location: chunk.location.clone(),
+                    })
+                })
+                .collect();
+
+            let result = match pool.get().await {
+                Ok(mut conn) => diesel::insert_into(thread_citations::table)
+                    .values(&rows)
+                    .execute(&mut conn)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            if let Err(e) = result {
+                warn!("Failed to record citations for thread {}: {}", thread_id, e);
+            }
+        }
+
         // Keep the old method for backward compatibility with legacy search results
         fn _create_project_context(&self, search_results: &[ProjectSearchResult]) -> String {
             if search_results.is_empty() {
@@ -255,6 +345,9 @@ KEY INSTRUCTIONS:
 When referencing code:
 - Use **[Filename]** for file references
 - Use **[Filename:lines X-Y]** for specific line ranges when provided
+- Use **[Filename, page N]** or **[Filename, Section]** when matching locations are listed
+- Cite the matching chunks inline by their source number, e.g. [1] or [2][4], using the numbers listed
+  under **Source numbers** for each document
 - Explain not just what the code does, but how it fits into the larger system
 
 PROJECT CONTEXT:
@@ -305,7 +398,7 @@ accurate answers about the codebase structure, functionality, and relationships
             _query: String,
             context: String,
             history: Vec<Message>,
-            tx: mpsc::Sender<Result<Event, Infallible>>,
+            tx: mpsc::Sender<CompletionEvent>,
             client: &OpenAIClient<OpenAIConfig>,
             cancel_token: CancellationToken,
         ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
@@ -313,15 +406,10 @@ accurate answers about the codebase structure, functionality, and relationships
                 return Ok(());
             }
 
-            self.send_response(&tx, RagResponse {
-                message_type: "status".to_string(),
-                content: None,
-                citations: None,
-                status: Some("Generating response...".to_string()),
-            }).await?;
+            self.send_event(&tx, CompletionEvent::Status("Generating response...".to_string())).await?;
 
             // Use enhanced system prompt for better context understanding
-            let system_prompt = self.create_enhanced_system_prompt(context);
+            let system_prompt = with_caller_instructions(self.create_enhanced_system_prompt(context), &history);
             let system_message = ChatCompletionRequestSystemMessage {
                 content: system_prompt.into(),
                 name: None,
@@ -373,7 +461,7 @@ accurate answers about the codebase structure, functionality, and relationships
 
             while let Some(result) = stream.next().await {
                 if cancel_token.is_cancelled() {
-                    let _ = tx.send(Ok(Event::default().data("[CANCELLED]"))).await;
+                    let _ = tx.send(CompletionEvent::Cancelled).await;
                     return Ok(());
                 }
 
@@ -381,35 +469,20 @@ accurate answers about the codebase structure, functionality, and relationships
                     Ok(response) => {
                         for choice in response.choices {
                             if let Some(delta) = choice.delta.content {
-                                self.send_response(&tx, RagResponse {
-                                    message_type: "content".to_string(),
-                                    content: Some(delta),
-                                    citations: None,
-                                    status: None,
-                                }).await?;
+                                self.send_event(&tx, CompletionEvent::Delta(delta)).await?;
                             }
                         }
                     }
                     Err(e) => {
                         error!("Error in OpenAI streaming response: {}", e);
-                        self.send_response(&tx, RagResponse {
-                            message_type: "error".to_string(),
-                            content: Some(format!("Error generating response: {}", e)),
-                            citations: None,
-                            status: None,
-                        }).await?;
+                        self.send_event(&tx, CompletionEvent::Error(format!("Error generating response: {}", e))).await?;
                         break;
                     }
                 }
             }
 
             // Send completion signal
-            self.send_response(&tx, RagResponse {
-                message_type: "done".to_string(),
-                content: None,
-                citations: None,
-                status: None,
-            }).await?;
+            self.send_event(&tx, CompletionEvent::Done).await?;
 
             Ok(())
         }
@@ -419,7 +492,7 @@ accurate answers about the codebase structure, functionality, and relationships
             _query: String,
             context: String,
             history: Vec<Message>,
-            tx: mpsc::Sender<Result<Event, Infallible>>,
+            tx: mpsc::Sender<CompletionEvent>,
             client: &reqwest::Client,
             api_key: &str,
             cancel_token: CancellationToken,
@@ -428,20 +501,15 @@ accurate answers about the codebase structure, functionality, and relationships
                 return Ok(());
             }
 
-            self.send_response(&tx, RagResponse {
-                message_type: "status".to_string(),
-                content: None,
-                citations: None,
-                status: Some("Generating response with Claude...".to_string()),
-            }).await?;
+            self.send_event(&tx, CompletionEvent::Status("Generating response with Claude...".to_string())).await?;
 
             // Use enhanced system prompt for better context understanding
-            let system_prompt = self.create_enhanced_system_prompt(context);
+            let system_prompt = with_caller_instructions(self.create_enhanced_system_prompt(context), &history);
 
             // Convert conversation history to Anthropic format
             let mut api_messages = Vec::new();
             
-            for msg in history {
+            for msg in history.into_iter().filter(|msg| msg.role != "system") {
                 if let Some(content) = msg.content {
                     api_messages.push(serde_json::json!({
                         "role": msg.role,
@@ -469,12 +537,7 @@ accurate answers about the codebase structure, functionality, and relationships
 
             while let Some(item) = stream.next().await {
                 if cancel_token.is_cancelled() {
-                    self.send_response(&tx, RagResponse {
-                        message_type: "error".to_string(),
-                        content: Some("[CANCELLED]".to_string()),
-                        citations: None,
-                        status: None,
-                    }).await?;
+                    self.send_event(&tx, CompletionEvent::Cancelled).await?;
                     return Ok(());
                 }
 
@@ -487,12 +550,7 @@ accurate answers about the codebase structure, functionality, and relationships
                         for line in event.trim().lines() {
                             if line.trim() == "event: message_stop" {
                                 debug!("Received message_stop event");
-                                self.send_response(&tx, RagResponse {
-                                    message_type: "done".to_string(),
-                                    content: None,
-                                    citations: None,
-                                    status: None,
-                                }).await?;
+                                self.send_event(&tx, CompletionEvent::Done).await?;
                                 return Ok(());
                             } else if line.trim().starts_with("data: ") {
                                 let json_str = &line.trim()[6..];
@@ -503,24 +561,14 @@ accurate answers about the codebase structure, functionality, and relationships
                                     if let Some(delta) = parsed["delta"].as_object() {
                                         if let Some(text) = delta.get("text").and_then(|t| t.as_str()) {
                                             debug!("Extracted Anthropic content: {}", text);
-                                            self.send_response(&tx, RagResponse {
-                                                message_type: "content".to_string(),
-                                                content: Some(text.to_string()),
-                                                citations: None,
-                                                status: None,
-                                            }).await?;
+                                            self.send_event(&tx, CompletionEvent::Delta(text.to_string())).await?;
                                         }
                                     }
                                 } else {
                                     // Fallback parsing using the same method as regular chat
                                     if let Some(content) = self.extract_text_content(json_str) {
                                         debug!("Fallback extracted Anthropic content: {}", content);
-                                        self.send_response(&tx, RagResponse {
-                                            message_type: "content".to_string(),
-                                            content: Some(content),
-                                            citations: None,
-                                            status: None,
-                                        }).await?;
+                                        self.send_event(&tx, CompletionEvent::Delta(content)).await?;
                                     }
                                 }
                             }
@@ -528,12 +576,7 @@ accurate answers about the codebase structure, functionality, and relationships
                     }
                     Err(e) => {
                         error!("Error in Anthropic streaming response: {}", e);
-                        self.send_response(&tx, RagResponse {
-                            message_type: "error".to_string(),
-                            content: Some(format!("Error generating response: {}", e)),
-                            citations: None,
-                            status: None,
-                        }).await?;
+                        self.send_event(&tx, CompletionEvent::Error(format!("Error generating response: {}", e))).await?;
                         break;
                     }
                 }
@@ -581,19 +624,48 @@ accurate answers about the codebase structure, functionality, and relationships
             None
         }
 
-        async fn send_response(
+        async fn send_event(
             &self,
-            tx: &mpsc::Sender<Result<Event, Infallible>>,
-            response: RagResponse,
+            tx: &mpsc::Sender<CompletionEvent>,
+            event: CompletionEvent,
         ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-            let json = serde_json::to_string(&response)?;
-            tx.send(Ok(Event::default().data(json))).await
+            tx.send(event).await
                 .map_err(|e| Box::new(std::io::Error::other(e.to_string())) as Box<dyn std::error::Error + Send + Sync>)?;
             Ok(())
         }
     }
 
     // Factory function to create appropriate service based on provider
+    /// Documents and chunks of a working context, numbered like the citations sent to the client
+    pub fn traced_documents(working_context: &WorkingContext) -> Vec<TracedDocument> {
+        let mut source_number = 0;
+        working_context.documents
+            .iter()
+            .map(|doc| TracedDocument {
+                document_id: doc.document_id,
+                filename: doc.filename.clone(),
+                document_version: doc.document_version,
+                priority_score: doc.priority_score,
+                token_count: doc.token_count,
+                chunks: doc.relevant_chunks
+                    .iter()
+                    .map(|chunk| {
+                        source_number += 1;
+                        TracedChunk {
+                            chunk_id: chunk.chunk_id,
+                            chunk_index: chunk.chunk_index,
+                            source_number,
+                            similarity: chunk.similarity,
+                            fused_score: chunk.fused_score,
+                            rerank_score: chunk.rerank_score,
+                            location: chunk.location.clone(),
+                        }
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
     pub fn create_rag_service(
         provider: &str,
         model: String,
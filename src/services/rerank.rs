@@ -0,0 +1,394 @@
+#[cfg(feature = "ssr")]
+pub mod reranking {
+    use futures::future::BoxFuture;
+    use log::warn;
+    use reqwest::Client;
+    use serde::Deserialize;
+    use serde_json::json;
+    use std::collections::HashSet;
+    use std::env;
+    use std::sync::Arc;
+
+    use crate::services::retrieval::query_terms;
+
+    type RerankResult = Result<Vec<Option<f32>>, Box<dyn std::error::Error + Send + Sync>>;
+
+    /// A retrieved chunk waiting to be reranked
+    #[derive(Debug, Clone, Copy)]
+    pub struct RerankCandidate<'a> {
+        pub text: &'a str,
+        /// Score from the retrieval stage (normalised fused score), best = 1.0
+        pub prior_score: f32,
+    }
+
+    /// Scores retrieved chunks against the query so a larger candidate pool can be
+    /// narrowed down before document content is selected.
+    pub trait Reranker: Send + Sync {
+        /// Short identifier for logs, e.g. "heuristic" or "http:bge-reranker-v2-m3"
+        fn name(&self) -> &str;
+
+        /// One score per candidate in input order; higher is more relevant, `None`
+        /// for candidates the reranker left out
+        fn score<'a>(&'a self, query: &'a str, candidates: &'a [RerankCandidate<'a>]) -> BoxFuture<'a, RerankResult>;
+    }
+
+    /// Deterministic local reranker based on query term coverage.
+    ///
+    /// Rewards chunks that contain more of the query's distinct terms and that contain
+    /// them as a contiguous phrase, blended with the retrieval score. No network calls,
+    /// so it is the implementation used in tests; deployments opt in with `RERANKER=heuristic`.
+    #[derive(Debug, Clone)]
+    pub struct HeuristicReranker {
+        pub coverage_weight: f32,
+        pub phrase_weight: f32,
+        pub prior_weight: f32,
+    }
+
+    impl Default for HeuristicReranker {
+        fn default() -> Self {
+            Self {
+                coverage_weight: 0.55,
+                phrase_weight: 0.15,
+                prior_weight: 0.3,
+            }
+        }
+    }
+
+    impl HeuristicReranker {
+        pub fn new() -> Self {
+            Default::default()
+        }
+
+        pub fn score_one(&self, terms: &[String], candidate: &RerankCandidate) -> f32 {
+            if terms.is_empty() {
+                return candidate.prior_score;
+            }
+
+            let tokens: Vec<String> = candidate
+                .text
+                .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .map(|t| t.trim_matches('_').to_lowercase())
+                .filter(|t| !t.is_empty())
+                .collect();
+            let vocabulary: HashSet<&str> = tokens.iter().map(String::as_str).collect();
+
+            let covered = terms.iter().filter(|t| vocabulary.contains(t.as_str())).count();
+            let coverage = covered as f32 / terms.len() as f32;
+
+            let phrase = if terms.len() > 1 && tokens.windows(terms.len()).any(|w| w == terms) {
+                1.0
+            } else {
+                0.0
+            };
+
+            coverage * self.coverage_weight + phrase * self.phrase_weight + candidate.prior_score * self.prior_weight
+        }
+    }
+
+    impl Reranker for HeuristicReranker {
+        fn name(&self) -> &str {
+            "heuristic"
+        }
+
+        fn score<'a>(&'a self, query: &'a str, candidates: &'a [RerankCandidate<'a>]) -> BoxFuture<'a, RerankResult> {
+            let terms = query_terms(query);
+            let scores = candidates.iter().map(|c| Some(self.score_one(&terms, c))).collect();
+            Box::pin(async move { Ok(scores) })
+        }
+    }
+
+    /// Cross-encoder served over HTTP.
+    ///
+    /// Speaks the `/rerank` shape shared by Cohere, Jina and Text Embeddings Inference:
+    /// `{"model", "query", "documents"}` in, `{"results": [{"index", "relevance_score"}]}`
+    /// or `[{"index", "score"}]` out.
+    pub struct HttpReranker {
+        client: Client,
+        endpoint: String,
+        model: Option<String>,
+        api_key: Option<String>,
+        name: String,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum HttpRerankResponse {
+        Wrapped { results: Vec<HttpRerankScore> },
+        Bare(Vec<HttpRerankScore>),
+    }
+
+    #[derive(Deserialize)]
+    struct HttpRerankScore {
+        index: usize,
+        #[serde(alias = "score")]
+        relevance_score: f32,
+    }
+
+    /// Places each result at its candidate's index; backends that honour `top_n`
+    /// may leave some out
+    fn scores_by_index(results: Vec<HttpRerankScore>, count: usize) -> Vec<Option<f32>> {
+        let mut scores = vec![None; count];
+        for result in results {
+            if let Some(slot) = scores.get_mut(result.index) {
+                *slot = Some(result.relevance_score);
+            }
+        }
+        scores
+    }
+
+    impl HttpReranker {
+        pub fn new(endpoint: String, model: Option<String>, api_key: Option<String>) -> Self {
+            let name = format!("http:{}", model.as_deref().unwrap_or("default"));
+            Self { client: Client::new(), endpoint, model, api_key, name }
+        }
+    }
+
+    impl Reranker for HttpReranker {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn score<'a>(&'a self, query: &'a str, candidates: &'a [RerankCandidate<'a>]) -> BoxFuture<'a, RerankResult> {
+            Box::pin(async move {
+                let documents: Vec<&str> = candidates.iter().map(|c| c.text).collect();
+                let mut body = json!({
+                    "query": query,
+                    "documents": documents,
+                    "top_n": documents.len(),
+                });
+                if let Some(model) = &self.model {
+                    body["model"] = json!(model);
+                }
+
+                let mut request = self.client.post(&self.endpoint).json(&body);
+                if let Some(api_key) = &self.api_key {
+                    request = request.bearer_auth(api_key);
+                }
+
+                let response: HttpRerankResponse = request.send().await?.error_for_status()?.json().await?;
+                let results = match response {
+                    HttpRerankResponse::Wrapped { results } => results,
+                    HttpRerankResponse::Bare(results) => results,
+                };
+                Ok(scores_by_index(results, candidates.len()))
+            })
+        }
+    }
+
+    /// Asks a chat model to grade each passage from 0 to 10
+    pub struct LlmJudgeReranker {
+        client: Client,
+        base_url: String,
+        api_key: String,
+        model: String,
+        name: String,
+        /// Passages are truncated to this many characters to bound prompt size
+        max_passage_chars: usize,
+    }
+
+    #[derive(Deserialize)]
+    struct JudgeScores {
+        scores: Vec<f32>,
+    }
+
+    impl LlmJudgeReranker {
+        pub fn new(base_url: String, api_key: String, model: String) -> Self {
+            let name = format!("llm:{model}");
+            Self {
+                client: Client::new(),
+                base_url,
+                api_key,
+                model,
+                name,
+                max_passage_chars: 1500,
+            }
+        }
+
+        fn build_prompt(&self, query: &str, candidates: &[RerankCandidate]) -> String {
+            let mut prompt = format!("Question: {query}\n\n");
+            for (i, candidate) in candidates.iter().enumerate() {
+                let passage: String = candidate.text.chars().take(self.max_passage_chars).collect();
+                prompt.push_str(&format!("[{i}]\n{passage}\n\n"));
+            }
+            prompt.push_str(&format!(
+                "Rate how useful each of the {} passages is for answering the question, from 0 (irrelevant) to 10 (answers it directly). \
+                 Reply with JSON only: {{\"scores\": [..]}} with one number per passage in order.",
+                candidates.len()
+            ));
+            prompt
+        }
+    }
+
+    impl Reranker for LlmJudgeReranker {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn score<'a>(&'a self, query: &'a str, candidates: &'a [RerankCandidate<'a>]) -> BoxFuture<'a, RerankResult> {
+            Box::pin(async move {
+                let response: serde_json::Value = self.client
+                    .post(format!("{}/chat/completions", self.base_url.trim_end_matches('/')))
+                    .bearer_auth(&self.api_key)
+                    .json(&json!({
+                        "model": self.model,
+                        "messages": [
+                            {
+                                "role": "system",
+                                "content": "You grade search results for a retrieval system. Be strict and consistent."
+                            },
+                            {
+                                "role": "user",
+                                "content": self.build_prompt(query, candidates)
+                            }
+                        ],
+                        "temperature": 0.0,
+                        "response_format": { "type": "json_object" }
+                    }))
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+
+                let content = response["choices"][0]["message"]["content"]
+                    .as_str()
+                    .ok_or("judge response had no content")?;
+                let judged: JudgeScores = serde_json::from_str(content)?;
+
+                if judged.scores.len() != candidates.len() {
+                    return Err(format!(
+                        "judge returned {} scores for {} passages",
+                        judged.scores.len(),
+                        candidates.len()
+                    ).into());
+                }
+
+                Ok(judged.scores.into_iter().map(Some).collect())
+            })
+        }
+    }
+
+    /// Scales scores to 0..=1 within the pool, since rerankers use different ranges
+    /// (logits, 0-10 grades...). Unscored candidates get 0.
+    pub fn normalise_scores(scores: &[Option<f32>]) -> Vec<f32> {
+        let scored = scores.iter().flatten();
+        let min = scored.clone().copied().fold(f32::INFINITY, f32::min);
+        let max = scored.copied().fold(f32::NEG_INFINITY, f32::max);
+        scores
+            .iter()
+            .map(|score| match score {
+                Some(score) if max > min => (score - min) / (max - min),
+                Some(_) => 1.0,
+                None => 0.0,
+            })
+            .collect()
+    }
+
+    /// Builds the reranker selected by `RERANKER` (`heuristic`, `http` or `llm`).
+    /// Unset, unknown or incomplete settings disable reranking.
+    pub fn reranker_from_env() -> Option<Arc<dyn Reranker>> {
+        reranker_from(&|name| env::var(name).ok())
+    }
+
+    fn reranker_from(lookup: &dyn Fn(&str) -> Option<String>) -> Option<Arc<dyn Reranker>> {
+        let kind = lookup("RERANKER").unwrap_or_default();
+        let model = lookup("RERANKER_MODEL").filter(|m| !m.is_empty());
+        let api_key = lookup("RERANKER_API_KEY").filter(|k| !k.is_empty());
+
+        match kind.trim() {
+            "none" | "" => None,
+            "http" => match lookup("RERANKER_URL").filter(|endpoint| !endpoint.is_empty()) {
+                Some(endpoint) => Some(Arc::new(HttpReranker::new(endpoint, model, api_key))),
+                None => {
+                    warn!("RERANKER=http but RERANKER_URL is not set, reranking disabled");
+                    None
+                }
+            },
+            "llm" => match api_key.or_else(|| lookup("OPENAI_API_KEY")).filter(|k| !k.is_empty()) {
+                Some(api_key) => {
+                    let base_url = lookup("RERANKER_URL")
+                        .filter(|url| !url.is_empty())
+                        .unwrap_or_else(|| "https://api.openai.com/v1".to_string());
+                    let model = model.unwrap_or_else(|| "gpt-4o-mini".to_string());
+                    Some(Arc::new(LlmJudgeReranker::new(base_url, api_key, model)))
+                }
+                None => {
+                    warn!("RERANKER=llm but no API key is set, reranking disabled");
+                    None
+                }
+            },
+            "heuristic" => Some(Arc::new(HeuristicReranker::new())),
+            other => {
+                warn!("Unknown RERANKER '{other}', reranking disabled");
+                None
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[tokio::test]
+        async fn test_heuristic_prefers_term_coverage() {
+            let candidates = [
+                RerankCandidate { text: "Unrelated notes about deployment.", prior_score: 0.9 },
+                RerankCandidate { text: "pub fn process_document(&self, pool: &DbPool) splits the document", prior_score: 0.5 },
+            ];
+            let scores = HeuristicReranker::new()
+                .score("how does process_document split", &candidates)
+                .await
+                .unwrap();
+
+            assert!(scores[1] > scores[0], "{scores:?}");
+        }
+
+        #[tokio::test]
+        async fn test_heuristic_is_deterministic() {
+            let candidates = [
+                RerankCandidate { text: "reciprocal rank fusion combines rankings", prior_score: 0.4 },
+                RerankCandidate { text: "rank fusion reciprocal", prior_score: 0.4 },
+            ];
+            let reranker = HeuristicReranker::new();
+            let first = reranker.score("reciprocal rank fusion", &candidates).await.unwrap();
+            let second = reranker.score("reciprocal rank fusion", &candidates).await.unwrap();
+
+            assert_eq!(first, second);
+            // same coverage, but only the first contains the phrase
+            assert!(first[0] > first[1]);
+        }
+
+        #[test]
+        fn test_omitted_indices_stay_unscored() {
+            let results: Vec<HttpRerankScore> =
+                serde_json::from_str(r#"[{"index": 2, "score": -1.5}, {"index": 0, "relevance_score": 4.0}, {"index": 9, "score": 1.0}]"#)
+                    .unwrap();
+            let scores = scores_by_index(results, 4);
+            assert_eq!(scores, [Some(4.0), None, Some(-1.5), None]);
+
+            // the cross-encoder order survives normalisation
+            let normalised = normalise_scores(&[Some(4.0), None, Some(-1.5), Some(1.0)]);
+            assert_eq!(normalised[0], 1.0);
+            assert_eq!(normalised[1], 0.0);
+            assert_eq!(normalised[2], 0.0);
+            assert!(normalised[3] > 0.4 && normalised[3] < 0.6, "{normalised:?}");
+        }
+
+        #[test]
+        fn test_reranking_is_opt_in() {
+            let env = |pairs: &'static [(&'static str, &'static str)]| {
+                move |name: &str| pairs.iter().find(|(key, _)| *key == name).map(|(_, value)| value.to_string())
+            };
+            assert!(reranker_from(&env(&[])).is_none());
+            assert!(reranker_from(&env(&[("RERANKER", "cross-encoder")])).is_none());
+            assert!(reranker_from(&env(&[("RERANKER", "http")])).is_none());
+            let heuristic = reranker_from(&env(&[("RERANKER", "heuristic")])).unwrap();
+            assert_eq!(heuristic.name(), "heuristic");
+            let http = reranker_from(&env(&[("RERANKER", "http"), ("RERANKER_URL", "http://localhost:8080/rerank")])).unwrap();
+            assert_eq!(http.name(), "http:default");
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub use reranking::*;
@@ -0,0 +1,118 @@
+#[cfg(feature = "ssr")]
+pub mod retrieval_service {
+    use std::collections::HashMap;
+    use std::hash::Hash;
+
+    /// Text search configuration used by the keyword index. `simple` keeps identifiers
+    /// and stop words intact, which matters more for code than stemming does.
+    pub const KEYWORD_SEARCH_CONFIG: &str = "simple";
+
+    const STOP_WORDS: &[&str] = &[
+        "a", "an", "and", "are", "as", "at", "be", "by", "can", "do", "does", "for", "from", "how", "i",
+        "in", "is", "it", "of", "on", "or", "that", "the", "this", "to", "was", "what", "when", "where",
+        "which", "who", "why", "with", "you",
+    ];
+
+    /// Lowercased, de-duplicated search terms of a query with stop words removed.
+    /// Identifiers such as `process_document` are kept whole.
+    pub fn query_terms(query: &str) -> Vec<String> {
+        let mut terms: Vec<String> = Vec::new();
+
+        for raw in query.split(|c: char| !(c.is_alphanumeric() || c == '_')) {
+            let term = raw.trim_matches('_').to_lowercase();
+            if term.is_empty() || STOP_WORDS.contains(&term.as_str()) || terms.contains(&term) {
+                continue;
+            }
+            terms.push(term);
+        }
+
+        terms
+    }
+
+    /// Builds a `to_tsquery` expression that matches any meaningful term of the query.
+    ///
+    /// `plainto_tsquery` ANDs every word, so a natural-language question would only match
+    /// chunks containing all of "what", "does", ... Terms are ORed instead and ranking
+    /// decides which chunks matched best. Returns `None` when nothing searchable is left.
+    pub fn keyword_tsquery(query: &str) -> Option<String> {
+        let terms = query_terms(query);
+
+        if terms.is_empty() {
+            None
+        } else {
+            Some(terms.join(" | "))
+        }
+    }
+
+    /// Weighted reciprocal rank fusion.
+    ///
+    /// Each ranking contributes `weight / (k + rank)` for every item it contains (ranks
+    /// start at 1). Items are returned best first with their fused score.
+    pub fn reciprocal_rank_fusion<T: Copy + Eq + Hash>(rankings: &[(&[T], f32)], k: f32) -> Vec<(T, f32)> {
+        let mut scores: HashMap<T, f32> = HashMap::new();
+        let mut first_seen: Vec<T> = Vec::new();
+
+        for (ranking, weight) in rankings {
+            for (position, item) in ranking.iter().enumerate() {
+                let contribution = weight / (k + position as f32 + 1.0);
+                scores
+                    .entry(*item)
+                    .and_modify(|score| *score += contribution)
+                    .or_insert_with(|| {
+                        first_seen.push(*item);
+                        contribution
+                    });
+            }
+        }
+
+        let mut fused: Vec<(T, f32)> = first_seen.into_iter().map(|item| (item, scores[&item])).collect();
+        // stable sort keeps earlier rankings first on ties
+        fused.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        fused
+    }
+
+    /// Best score an item can reach, i.e. ranked first by every retriever
+    pub fn max_fused_score(weights: &[f32], k: f32) -> f32 {
+        weights.iter().map(|w| w / (k + 1.0)).sum()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_keyword_tsquery_keeps_identifiers() {
+            assert_eq!(
+                keyword_tsquery("What does process_document do?").as_deref(),
+                Some("process_document")
+            );
+            assert_eq!(
+                keyword_tsquery("ContextStrategy RRF rrf weights").as_deref(),
+                Some("contextstrategy | rrf | weights")
+            );
+            assert_eq!(keyword_tsquery("what is the?"), None);
+        }
+
+        #[test]
+        fn test_rrf_prefers_items_in_both_rankings() {
+            let vector = [1, 2, 3];
+            let keyword = [4, 3];
+            let fused = reciprocal_rank_fusion(&[(&vector, 1.0), (&keyword, 1.0)], 60.0);
+
+            let order: Vec<i32> = fused.iter().map(|(id, _)| *id).collect();
+            assert_eq!(order, vec![3, 1, 4, 2]);
+            assert!(fused[0].1 <= max_fused_score(&[1.0, 1.0], 60.0));
+        }
+
+        #[test]
+        fn test_rrf_weights() {
+            let vector = [1];
+            let keyword = [2];
+            let fused = reciprocal_rank_fusion(&[(&vector, 0.5), (&keyword, 2.0)], 60.0);
+            assert_eq!(fused[0].0, 2);
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub use retrieval_service::*;
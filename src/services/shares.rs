@@ -0,0 +1,311 @@
+#[cfg(feature = "ssr")]
+pub mod thread_shares {
+    //! Public read-only snapshots of threads.
+    //!
+    //! Sharing copies the thread's messages into `thread_shares`, so the page
+    //! stays the same whatever happens to the thread afterwards. Branches hold
+    //! their own copy of the path they split from, so sharing a branch shares
+    //! that path. Anyone with the link can read it until it expires or is
+    //! revoked; signed-in viewers can copy it into a thread of their own.
+
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+    use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+    use diesel::prelude::*;
+    use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+    use rand::{thread_rng, Rng};
+    use std::fmt;
+    use uuid::Uuid;
+
+    use crate::models::conversations::{Message, NewMessage, Thread};
+    use crate::models::shares::{NewThreadShare, SharedMessage, SharedThreadView, ThreadShare};
+    use crate::models::users::User;
+    use crate::models::workspaces::THREAD_PRIVATE;
+    use crate::schema::{messages, thread_shares, threads, users};
+
+    /// Longest a share link may be set to live; links can also never expire
+    pub const MAX_SHARE_DAYS: i64 = 365;
+
+    #[derive(Debug)]
+    pub enum ShareError {
+        NotFound,
+        Expired,
+        Revoked,
+        EmptyThread,
+        InvalidExpiry,
+        Database(diesel::result::Error),
+    }
+
+    impl fmt::Display for ShareError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                ShareError::NotFound => write!(f, "Shared conversation not found"),
+                ShareError::Expired => write!(f, "This share link has expired"),
+                ShareError::Revoked => write!(f, "This share link was revoked"),
+                ShareError::EmptyThread => write!(f, "There is nothing to share in this thread yet"),
+                ShareError::InvalidExpiry => write!(f, "Share links can last 1 to {MAX_SHARE_DAYS} days"),
+                ShareError::Database(e) => write!(f, "Database error: {e}"),
+            }
+        }
+    }
+
+    impl std::error::Error for ShareError {}
+
+    impl From<diesel::result::Error> for ShareError {
+        fn from(e: diesel::result::Error) -> Self {
+            ShareError::Database(e)
+        }
+    }
+
+    /// 144 random bits, URL-safe; the token is the only thing guarding the page
+    pub fn generate_share_token() -> String {
+        let mut bytes = [0u8; 18];
+        thread_rng().fill(&mut bytes);
+        URL_SAFE_NO_PAD.encode(bytes)
+    }
+
+    /// When a link made at `now` should stop working; `None` days means never
+    pub fn share_expiry(now: NaiveDateTime, expires_in_days: Option<i64>) -> Result<Option<NaiveDateTime>, ShareError> {
+        match expires_in_days {
+            None => Ok(None),
+            Some(days) if (1..=MAX_SHARE_DAYS).contains(&days) => Ok(Some(now + Duration::days(days))),
+            Some(_) => Err(ShareError::InvalidExpiry),
+        }
+    }
+
+    /// Whether the public page may still be shown
+    pub fn check_available(share: &ThreadShare, now: NaiveDateTime) -> Result<(), ShareError> {
+        if share.revoked_at.is_some() {
+            Err(ShareError::Revoked)
+        } else if share.expires_at.is_some_and(|expires_at| expires_at <= now) {
+            Err(ShareError::Expired)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Messages worth showing, oldest first; empty placeholders are skipped
+    pub fn snapshot_messages(mut thread_messages: Vec<Message>) -> Vec<SharedMessage> {
+        thread_messages.sort_by_key(|message| message.id);
+        thread_messages
+            .into_iter()
+            .filter_map(|message| {
+                let content = message.content.filter(|content| !content.trim().is_empty())?;
+                Some(SharedMessage {
+                    role: message.role,
+                    content,
+                    model: message.active_model,
+                    lab: message.active_lab,
+                    created_at: message.created_at.map(|dt| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc)),
+                })
+            })
+            .collect()
+    }
+
+    /// Snapshots one of `user_id`'s threads behind a new link
+    pub async fn create_share(
+        conn: &mut AsyncPgConnection,
+        user_id: i32,
+        thread_id: &str,
+        expires_in_days: Option<i64>,
+    ) -> Result<ThreadShare, ShareError> {
+        let now = Utc::now().naive_utc();
+        let expires_at = share_expiry(now, expires_in_days)?;
+
+        let thread: Thread = threads::table
+            .find(thread_id)
+            .filter(threads::user_id.eq(user_id))
+            .first(conn)
+            .await
+            .optional()?
+            .ok_or(ShareError::NotFound)?;
+        let thread_messages: Vec<Message> = messages::table
+            .filter(messages::thread_id.eq(&thread.id))
+            .load(conn)
+            .await?;
+        let snapshot = snapshot_messages(thread_messages);
+        if snapshot.is_empty() {
+            return Err(ShareError::EmptyThread);
+        }
+
+        let share = diesel::insert_into(thread_shares::table)
+            .values(&NewThreadShare {
+                token: generate_share_token(),
+                user_id,
+                thread_id: Some(thread.id),
+                title: thread.title,
+                messages: serde_json::to_value(&snapshot).unwrap_or_default(),
+                expires_at,
+            })
+            .get_result(conn)
+            .await?;
+        Ok(share)
+    }
+
+    /// Links `user_id` made for a thread, newest first
+    pub async fn list_shares(conn: &mut AsyncPgConnection, user_id: i32, thread_id: &str) -> QueryResult<Vec<ThreadShare>> {
+        thread_shares::table
+            .filter(thread_shares::user_id.eq(user_id))
+            .filter(thread_shares::thread_id.eq(thread_id))
+            .order(thread_shares::created_at.desc())
+            .load(conn)
+            .await
+    }
+
+    /// `false` if the link isn't `user_id`'s or was already revoked
+    pub async fn revoke_share(conn: &mut AsyncPgConnection, user_id: i32, share_id: Uuid) -> QueryResult<bool> {
+        let revoked = diesel::update(
+            thread_shares::table
+                .find(share_id)
+                .filter(thread_shares::user_id.eq(user_id))
+                .filter(thread_shares::revoked_at.is_null()),
+        )
+        .set(thread_shares::revoked_at.eq(diesel::dsl::now))
+        .execute(conn)
+        .await?;
+        Ok(revoked > 0)
+    }
+
+    async fn available_share(conn: &mut AsyncPgConnection, token: &str) -> Result<ThreadShare, ShareError> {
+        let share: ThreadShare = thread_shares::table
+            .filter(thread_shares::token.eq(token))
+            .first(conn)
+            .await
+            .optional()?
+            .ok_or(ShareError::NotFound)?;
+        check_available(&share, Utc::now().naive_utc())?;
+        Ok(share)
+    }
+
+    /// The public page behind a token
+    pub async fn open_share(conn: &mut AsyncPgConnection, token: &str) -> Result<SharedThreadView, ShareError> {
+        let share = available_share(conn, token).await?;
+        let author: Option<User> = users::table.find(share.user_id).first(conn).await.optional()?;
+        let utc = |dt: NaiveDateTime| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc);
+        Ok(SharedThreadView {
+            messages: share.snapshot(),
+            title: share.title,
+            author: author
+                .and_then(|user| user.display_name.or(user.username))
+                .unwrap_or_else(|| "Anonymous".to_string()),
+            created_at: utc(share.created_at),
+            expires_at: share.expires_at.map(utc),
+        })
+    }
+
+    /// Copies a shared conversation into a new private thread of `user_id`'s and returns its id
+    pub async fn continue_share(conn: &mut AsyncPgConnection, token: &str, user_id: i32) -> Result<String, ShareError> {
+        let share = available_share(conn, token).await?;
+        let now = Utc::now().naive_utc();
+        let thread = Thread {
+            id: Uuid::new_v4().to_string(),
+            created_at: Some(now),
+            updated_at: Some(now),
+            user_id: Some(user_id),
+            parent_thread_id: None,
+            branch_point_message_id: None,
+            branch_name: None,
+            title: share.title.clone(),
+            project_id: None,
+            visibility: THREAD_PRIVATE.to_string(),
+        };
+        let copied: Vec<NewMessage> = share
+            .snapshot()
+            .into_iter()
+            .map(|message| NewMessage {
+                thread_id: thread.id.clone(),
+                content: Some(message.content),
+                role: message.role,
+                active_model: message.model,
+                active_lab: message.lab,
+                user_id: Some(user_id),
+                citations: None,
+                retrieval_trace_id: None,
+            })
+            .collect();
+
+        conn.transaction(|conn| {
+            Box::pin(async move {
+                diesel::insert_into(threads::table).values(&thread).execute(conn).await?;
+                diesel::insert_into(messages::table).values(&copied).execute(conn).await?;
+                Ok::<_, ShareError>(thread.id)
+            })
+        })
+        .await
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn share(expires_at: Option<NaiveDateTime>, revoked_at: Option<NaiveDateTime>) -> ThreadShare {
+            ThreadShare {
+                id: Uuid::nil(),
+                token: "t".into(),
+                user_id: 1,
+                thread_id: None,
+                title: None,
+                messages: serde_json::json!([]),
+                created_at: NaiveDateTime::default(),
+                expires_at,
+                revoked_at,
+            }
+        }
+
+        fn message(id: i32, role: &str, content: Option<&str>) -> Message {
+            Message {
+                id,
+                thread_id: "thread".into(),
+                content: content.map(str::to_string),
+                role: role.into(),
+                active_model: "model".into(),
+                active_lab: "lab".into(),
+                created_at: None,
+                updated_at: None,
+                user_id: Some(1),
+                citations: None,
+                retrieval_trace_id: None,
+            }
+        }
+
+        #[test]
+        fn tokens_are_long_and_url_safe() {
+            let token = generate_share_token();
+            assert_eq!(token.len(), 24);
+            assert!(token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+            assert_ne!(token, generate_share_token());
+        }
+
+        #[test]
+        fn expiry_is_bounded() {
+            let now = NaiveDateTime::default();
+            assert_eq!(share_expiry(now, None).unwrap(), None);
+            assert_eq!(share_expiry(now, Some(7)).unwrap(), Some(now + Duration::days(7)));
+            assert!(matches!(share_expiry(now, Some(0)), Err(ShareError::InvalidExpiry)));
+            assert!(matches!(share_expiry(now, Some(MAX_SHARE_DAYS + 1)), Err(ShareError::InvalidExpiry)));
+        }
+
+        #[test]
+        fn revoked_or_expired_links_stop_working() {
+            let now = NaiveDateTime::default() + Duration::days(10);
+            assert!(check_available(&share(None, None), now).is_ok());
+            assert!(check_available(&share(Some(now + Duration::minutes(1)), None), now).is_ok());
+            assert!(matches!(check_available(&share(Some(now), None), now), Err(ShareError::Expired)));
+            assert!(matches!(check_available(&share(None, Some(now)), now), Err(ShareError::Revoked)));
+        }
+
+        #[test]
+        fn snapshots_keep_order_and_skip_empty_messages() {
+            let snapshot = snapshot_messages(vec![
+                message(3, "assistant", Some("Hi!")),
+                message(1, "user", Some("Hello")),
+                message(2, "assistant", Some("  ")),
+                message(4, "assistant", None),
+            ]);
+            let text: Vec<_> = snapshot.iter().map(|m| (m.role.as_str(), m.content.as_str())).collect();
+            assert_eq!(text, [("user", "Hello"), ("assistant", "Hi!")]);
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub use thread_shares::*;
@@ -0,0 +1,250 @@
+#[cfg(feature = "ssr")]
+pub mod token_counting {
+    use std::sync::Arc;
+    use tiktoken_rs::{
+        cl100k_base_singleton, o200k_base_singleton,
+        tokenizer::{get_tokenizer, Tokenizer},
+        CoreBPE,
+    };
+
+    /// Counts tokens the way a given model family would see them.
+    pub trait TokenCounter: Send + Sync {
+        fn count(&self, text: &str) -> usize;
+
+        /// Short identifier for logs and usage records, e.g. "o200k_base" or "estimate:claude"
+        fn name(&self) -> &str;
+
+        /// Whether `count` is exact for the target model or an approximation
+        fn is_exact(&self) -> bool;
+    }
+
+    /// Exact BPE counting for OpenAI models via tiktoken
+    pub struct BpeTokenCounter {
+        bpe: &'static CoreBPE,
+        name: &'static str,
+    }
+
+    impl BpeTokenCounter {
+        pub fn cl100k() -> Self {
+            Self { bpe: cl100k_base_singleton(), name: "cl100k_base" }
+        }
+
+        pub fn o200k() -> Self {
+            Self { bpe: o200k_base_singleton(), name: "o200k_base" }
+        }
+
+        /// Returns `None` for models tiktoken doesn't know about
+        pub fn for_model(model: &str) -> Option<Self> {
+            match get_tokenizer(model)? {
+                Tokenizer::O200kBase => Some(Self::o200k()),
+                Tokenizer::Cl100kBase => Some(Self::cl100k()),
+                // older encodings aren't used by any model we route to
+                _ => None,
+            }
+        }
+    }
+
+    impl TokenCounter for BpeTokenCounter {
+        fn count(&self, text: &str) -> usize {
+            self.bpe.encode_ordinary(text).len()
+        }
+
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn is_exact(&self) -> bool {
+            true
+        }
+    }
+
+    /// Character-class based estimator for models without a public tokenizer.
+    ///
+    /// Text is split into runs of word characters, other letters, CJK, punctuation and
+    /// whitespace, and each run is costed separately. The ratios were calibrated
+    /// against cl100k on English prose, Rust/TypeScript source and CJK text; unlike
+    /// `len() / 4` this doesn't overcount indentation-heavy code or undercount
+    /// multi-byte scripts.
+    #[derive(Debug, Clone)]
+    pub struct EstimatingTokenCounter {
+        name: String,
+        /// ASCII letters, digits and underscores per token within a word
+        pub word_chars_per_token: f32,
+        /// Non-ASCII letters (accented Latin, Cyrillic, Greek, ...) per token
+        pub other_chars_per_token: f32,
+        /// Tokens per CJK / kana / hangul character
+        pub cjk_tokens_per_char: f32,
+        /// Punctuation and symbol characters per token
+        pub punct_chars_per_token: f32,
+        /// Whitespace characters per token, for runs longer than a single space
+        pub space_chars_per_token: f32,
+        /// Multiplier applied to the final count to match the target model family
+        pub scale: f32,
+    }
+
+    impl Default for EstimatingTokenCounter {
+        fn default() -> Self {
+            Self {
+                name: "estimate".to_string(),
+                word_chars_per_token: 6.0,
+                other_chars_per_token: 2.5,
+                cjk_tokens_per_char: 0.9,
+                punct_chars_per_token: 2.4,
+                space_chars_per_token: 16.0,
+                scale: 1.0,
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum CharClass {
+        Word,
+        Cjk,
+        Punct,
+        Space,
+    }
+
+    impl EstimatingTokenCounter {
+        pub fn new() -> Self {
+            Default::default()
+        }
+
+        /// Claude's tokenizer produces roughly 10-15% more tokens than cl100k on
+        /// the same input, so the estimate is scaled up to stay on the safe side.
+        pub fn for_model(model: &str) -> Self {
+            if model.contains("claude") {
+                Self {
+                    name: "estimate:claude".to_string(),
+                    scale: 1.15,
+                    ..Default::default()
+                }
+            } else {
+                Self::default()
+            }
+        }
+
+        fn classify(ch: char) -> CharClass {
+            if ch.is_ascii_alphanumeric() || ch == '_' {
+                CharClass::Word
+            } else if ch.is_whitespace() {
+                CharClass::Space
+            } else if matches!(ch as u32,
+                0x3040..=0x30FF     // hiragana, katakana
+                | 0x3400..=0x4DBF   // CJK extension A
+                | 0x4E00..=0x9FFF   // CJK unified ideographs
+                | 0xAC00..=0xD7AF   // hangul syllables
+                | 0xF900..=0xFAFF   // CJK compatibility ideographs
+            ) {
+                CharClass::Cjk
+            } else if ch.is_alphabetic() {
+                CharClass::Word
+            } else {
+                CharClass::Punct
+            }
+        }
+
+        fn run_tokens(&self, class: CharClass, len: usize, run: &str) -> f32 {
+            let len_f = len as f32;
+            match class {
+                CharClass::Word => {
+                    let other = run.chars().filter(|c| !c.is_ascii()).count() as f32;
+                    ((len_f - other) / self.word_chars_per_token + other / self.other_chars_per_token).ceil()
+                }
+                CharClass::Cjk => len_f * self.cjk_tokens_per_char,
+                CharClass::Punct => (len_f / self.punct_chars_per_token).ceil(),
+                // a single space is merged into the following word by BPE
+                CharClass::Space if run == " " => 0.0,
+                CharClass::Space => (len_f / self.space_chars_per_token).ceil(),
+            }
+        }
+    }
+
+    impl TokenCounter for EstimatingTokenCounter {
+        fn count(&self, text: &str) -> usize {
+            let mut tokens = 0.0f32;
+            let mut run_start = 0;
+            let mut run_len = 0;
+            let mut run_class = None;
+
+            for (idx, ch) in text.char_indices() {
+                let class = Self::classify(ch);
+                if run_class != Some(class) {
+                    if let Some(prev) = run_class {
+                        tokens += self.run_tokens(prev, run_len, &text[run_start..idx]);
+                    }
+                    run_class = Some(class);
+                    run_start = idx;
+                    run_len = 0;
+                }
+                run_len += 1;
+            }
+
+            if let Some(prev) = run_class {
+                tokens += self.run_tokens(prev, run_len, &text[run_start..]);
+            }
+
+            (tokens * self.scale).ceil() as usize
+        }
+
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn is_exact(&self) -> bool {
+            false
+        }
+    }
+
+    /// Picks exact BPE counting for OpenAI models and the estimator for everything else
+    pub fn token_counter_for_model(model: &str) -> Arc<dyn TokenCounter> {
+        match BpeTokenCounter::for_model(model) {
+            Some(counter) => Arc::new(counter),
+            None => Arc::new(EstimatingTokenCounter::for_model(model)),
+        }
+    }
+
+    /// Counter used when the target model isn't known yet (e.g. plain project search)
+    pub fn default_token_counter() -> Arc<dyn TokenCounter> {
+        Arc::new(BpeTokenCounter::cl100k())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_estimator_tracks_bpe_on_prose() {
+            let text = "The quick brown fox jumps over the lazy dog. \
+                        Retrieval augmented generation needs a reliable token budget.";
+            let exact = BpeTokenCounter::cl100k().count(text) as f32;
+            let estimate = EstimatingTokenCounter::new().count(text) as f32;
+            assert!((estimate - exact).abs() / exact < 0.35, "exact {exact}, estimate {estimate}");
+        }
+
+        #[test]
+        fn test_estimator_counts_code_punctuation() {
+            let code = "fn main() {\n    let x: Vec<u8> = vec![1, 2, 3];\n    println!(\"{:?}\", x);\n}\n";
+            let estimate = EstimatingTokenCounter::new().count(code);
+            // the old byte heuristic undercounts symbol-heavy code badly
+            assert!(estimate > code.len() / 4);
+        }
+
+        #[test]
+        fn test_estimator_tracks_bpe_on_cjk() {
+            let text = "日本語のテキストです。これは検索拡張生成のテストです。";
+            let exact = BpeTokenCounter::cl100k().count(text) as f32;
+            let estimate = EstimatingTokenCounter::new().count(text) as f32;
+            assert!((estimate - exact).abs() / exact < 0.35, "exact {exact}, estimate {estimate}");
+        }
+
+        #[test]
+        fn test_counter_selection() {
+            assert!(token_counter_for_model("gpt-4o-mini").is_exact());
+            assert_eq!(token_counter_for_model("gpt-4-turbo").name(), "cl100k_base");
+            assert!(!token_counter_for_model("claude-3-haiku-20240307").is_exact());
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub use token_counting::*;
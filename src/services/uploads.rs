@@ -0,0 +1,347 @@
+#[cfg(feature = "ssr")]
+pub mod document_uploads {
+    use diesel::dsl::sum;
+    use diesel::prelude::*;
+    use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+    use chrono::Utc;
+    use log::{debug, info, warn};
+    use std::env;
+    use std::fmt;
+    use std::path::PathBuf;
+    use tokio::io::AsyncWriteExt;
+    use uuid::Uuid;
+
+    use crate::database::db::DbPool;
+    use crate::models::projects::{NewDocumentVersion, NewProjectDocument, ProjectDocument};
+    use crate::models::workspaces::Permission;
+    use crate::schema::{document_versions, project_documents, projects};
+    use crate::services::archive::ArchiveError;
+    use crate::services::extraction::{extract_document, sniff_format, ExtractionError};
+    use crate::services::jobs::enqueue_document;
+    use crate::services::versioning::content_hash;
+    use crate::services::workspaces::AccessError;
+
+    /// Size limits for uploads, from `MAX_UPLOAD_BYTES` (default 25 MiB) and
+    /// `PROJECT_STORAGE_QUOTA_BYTES` (default 500 MiB)
+    #[derive(Debug, Clone, Copy)]
+    pub struct UploadLimits {
+        pub max_file_bytes: u64,
+        pub project_quota_bytes: u64,
+    }
+
+    impl UploadLimits {
+        pub fn from_env() -> Self {
+            let read = |name: &str, default: u64| {
+                env::var(name)
+                    .ok()
+                    .and_then(|value| value.trim().parse().ok())
+                    .filter(|&bytes: &u64| bytes > 0)
+                    .unwrap_or(default)
+            };
+
+            Self {
+                max_file_bytes: read("MAX_UPLOAD_BYTES", 25 * 1024 * 1024),
+                project_quota_bytes: read("PROJECT_STORAGE_QUOTA_BYTES", 500 * 1024 * 1024),
+            }
+        }
+    }
+
+    #[derive(Debug)]
+    pub enum UploadError {
+        Pool(String),
+        Database(diesel::result::Error),
+        Io(std::io::Error),
+        ProjectNotFound,
+        Forbidden(Permission),
+        MissingFile,
+        FileTooLarge { limit: u64 },
+        QuotaExceeded { used: u64, quota: u64 },
+        Rejected(ExtractionError),
+        Archive(ArchiveError),
+    }
+
+    impl fmt::Display for UploadError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                UploadError::Pool(e) => write!(f, "Pool error: {e}"),
+                UploadError::Database(e) => write!(f, "Database error: {e}"),
+                UploadError::Io(e) => write!(f, "Upload storage error: {e}"),
+                UploadError::ProjectNotFound => write!(f, "Project not found"),
+                UploadError::Forbidden(permission) => write!(f, "{}", AccessError::Forbidden(*permission)),
+                UploadError::MissingFile => write!(f, "No file was uploaded"),
+                UploadError::FileTooLarge { limit } => {
+                    write!(f, "File is larger than the {} upload limit", format_bytes(*limit))
+                }
+                UploadError::QuotaExceeded { used, quota } => write!(
+                    f,
+                    "Project storage quota exceeded ({} of {} used)",
+                    format_bytes(*used),
+                    format_bytes(*quota)
+                ),
+                UploadError::Rejected(e) => write!(f, "{e}"),
+                UploadError::Archive(e) => write!(f, "{e}"),
+            }
+        }
+    }
+
+    impl std::error::Error for UploadError {}
+
+    impl From<diesel::result::Error> for UploadError {
+        fn from(error: diesel::result::Error) -> Self {
+            UploadError::Database(error)
+        }
+    }
+
+    impl From<AccessError> for UploadError {
+        fn from(error: AccessError) -> Self {
+            match error {
+                AccessError::NotFound(_) => UploadError::ProjectNotFound,
+                AccessError::Forbidden(permission) => UploadError::Forbidden(permission),
+                AccessError::Database(e) => UploadError::Database(e),
+            }
+        }
+    }
+
+    impl From<ArchiveError> for UploadError {
+        fn from(error: ArchiveError) -> Self {
+            UploadError::Archive(error)
+        }
+    }
+
+    impl From<std::io::Error> for UploadError {
+        fn from(error: std::io::Error) -> Self {
+            UploadError::Io(error)
+        }
+    }
+
+    pub fn format_bytes(bytes: u64) -> String {
+        const MIB: u64 = 1024 * 1024;
+        if bytes >= MIB {
+            format!("{:.1} MB", bytes as f64 / MIB as f64)
+        } else {
+            format!("{} KB", bytes.div_ceil(1024))
+        }
+    }
+
+    /// Bytes of uploaded files currently stored in the project
+    pub async fn project_storage_used(conn: &mut AsyncPgConnection, project_id: Uuid) -> QueryResult<u64> {
+        let used: Option<i64> = project_documents::table
+            .filter(project_documents::project_id.eq(project_id))
+            .select(sum(project_documents::file_size))
+            .first(conn)
+            .await?;
+        Ok(used.unwrap_or(0).max(0) as u64)
+    }
+
+    /// Upload body spooled to disk under `UPLOAD_TMP_DIR` (default: the system temp
+    /// dir) so large files never sit in memory while they arrive. The file is
+    /// removed when this is dropped.
+    pub struct TempUpload {
+        path: PathBuf,
+        file: tokio::fs::File,
+        size: u64,
+        limit: u64,
+    }
+
+    impl TempUpload {
+        pub async fn create(limit: u64) -> Result<Self, UploadError> {
+            let dir = env::var("UPLOAD_TMP_DIR")
+                .ok()
+                .filter(|dir| !dir.trim().is_empty())
+                .map(PathBuf::from)
+                .unwrap_or_else(|| env::temp_dir().join("l3chat-uploads"));
+            tokio::fs::create_dir_all(&dir).await?;
+
+            let path = dir.join(format!("{}.part", Uuid::new_v4()));
+            let file = tokio::fs::File::create(&path).await?;
+            Ok(Self { path, file, size: 0, limit })
+        }
+
+        /// Appends a chunk, failing as soon as the file goes over the limit
+        pub async fn write(&mut self, chunk: &[u8]) -> Result<(), UploadError> {
+            self.size += chunk.len() as u64;
+            if self.size > self.limit {
+                return Err(UploadError::FileTooLarge { limit: self.limit });
+            }
+            self.file.write_all(chunk).await?;
+            Ok(())
+        }
+
+        pub fn size(&self) -> u64 {
+            self.size
+        }
+
+        pub async fn read(mut self) -> Result<Vec<u8>, UploadError> {
+            self.file.flush().await?;
+            Ok(tokio::fs::read(&self.path).await?)
+        }
+    }
+
+    impl Drop for TempUpload {
+        fn drop(&mut self) {
+            if let Err(e) = std::fs::remove_file(&self.path) {
+                warn!("Failed to remove temporary upload {}: {}", self.path.display(), e);
+            }
+        }
+    }
+
+    /// What `store_document` did with the file
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum StoreOutcome {
+        Created,
+        /// A document with the same path existed; its content became a new version
+        Updated,
+        /// Same path and same text as the current version, nothing was stored
+        Unchanged,
+    }
+
+    /// Validates, extracts and stores an uploaded file, then queues it for indexing.
+    /// Uploading to a path that already exists in the project adds a new version of
+    /// that document. `import_id` links documents created by a bulk import back to it.
+    ///
+    /// The type is sniffed from the bytes; the quota check and insert happen in one
+    /// transaction with the project row locked, so parallel uploads can't overshoot it.
+    pub async fn store_document(
+        pool: &DbPool,
+        project_id: Uuid,
+        filename: String,
+        bytes: Vec<u8>,
+        limits: UploadLimits,
+        import_id: Option<Uuid>,
+    ) -> Result<(ProjectDocument, StoreOutcome), UploadError> {
+        let size = bytes.len() as u64;
+        if size > limits.max_file_bytes {
+            return Err(UploadError::FileTooLarge { limit: limits.max_file_bytes });
+        }
+        if size == 0 {
+            return Err(UploadError::MissingFile);
+        }
+
+        let sniff_name = filename.clone();
+        let (format, extracted) = tokio::task::spawn_blocking(move || {
+            let format = sniff_format(&bytes, &sniff_name)?;
+            Ok::<_, ExtractionError>((format, extract_document(&bytes, format)?))
+        })
+        .await
+        .map_err(|e| UploadError::Io(std::io::Error::other(e)))?
+        .map_err(UploadError::Rejected)?;
+
+        let text_segments = if extracted.segments.is_empty() {
+            None
+        } else {
+            Some(serde_json::to_value(&extracted.segments).map_err(|e| UploadError::Io(e.into()))?)
+        };
+
+        let hash = content_hash(&extracted.text);
+        let new_document = NewProjectDocument {
+            project_id,
+            content_type: Some(format.mime_type(&filename).to_string()),
+            filename,
+            content: extracted.text,
+            file_size: Some(size as i32),
+            text_segments,
+            page_count: extracted.page_count.map(|pages| pages as i32),
+            import_id,
+            content_hash: Some(hash.clone()),
+        };
+
+        let mut conn = pool.get().await.map_err(|e| UploadError::Pool(e.to_string()))?;
+        let (document, outcome) = conn
+            .transaction(|conn| {
+                Box::pin(async move {
+                    projects::table
+                        .find(project_id)
+                        .select(projects::id)
+                        .for_update()
+                        .first::<Uuid>(conn)
+                        .await?;
+
+                    let existing: Option<ProjectDocument> = project_documents::table
+                        .filter(project_documents::project_id.eq(project_id))
+                        .filter(project_documents::filename.eq(&new_document.filename))
+                        .order(project_documents::created_at.desc())
+                        .first(conn)
+                        .await
+                        .optional()?;
+
+                    // documents from before versioning have no stored hash
+                    let unchanged = |doc: &ProjectDocument| match &doc.content_hash {
+                        Some(current) => *current == hash,
+                        None => content_hash(&doc.content) == hash,
+                    };
+                    if let Some(current) = existing.as_ref().filter(|doc| unchanged(doc)) {
+                        return Ok((current.clone(), StoreOutcome::Unchanged));
+                    }
+
+                    let replaced = existing.as_ref().and_then(|doc| doc.file_size).unwrap_or(0).max(0) as u64;
+                    let used = project_storage_used(conn, project_id).await?.saturating_sub(replaced);
+                    if used + size > limits.project_quota_bytes {
+                        return Err(UploadError::QuotaExceeded { used, quota: limits.project_quota_bytes });
+                    }
+
+                    let (document, outcome): (ProjectDocument, _) = match existing {
+                        Some(current) => {
+                            let document = diesel::update(project_documents::table.find(current.id))
+                                .set((
+                                    project_documents::content.eq(&new_document.content),
+                                    project_documents::content_type.eq(&new_document.content_type),
+                                    project_documents::file_size.eq(new_document.file_size),
+                                    project_documents::text_segments.eq(&new_document.text_segments),
+                                    project_documents::page_count.eq(new_document.page_count),
+                                    project_documents::content_hash.eq(&new_document.content_hash),
+                                    project_documents::version.eq(current.version + 1),
+                                    project_documents::updated_at.eq(Some(Utc::now().naive_utc())),
+                                ))
+                                .get_result(conn)
+                                .await?;
+                            (document, StoreOutcome::Updated)
+                        }
+                        None => {
+                            let document = diesel::insert_into(project_documents::table)
+                                .values(&new_document)
+                                .get_result(conn)
+                                .await?;
+                            (document, StoreOutcome::Created)
+                        }
+                    };
+
+                    diesel::insert_into(document_versions::table)
+                        .values(&NewDocumentVersion {
+                            document_id: document.id,
+                            version: document.version,
+                            content: document.content.clone(),
+                            content_type: document.content_type.clone(),
+                            file_size: document.file_size,
+                            text_segments: document.text_segments.clone(),
+                            page_count: document.page_count,
+                            content_hash: document.content_hash.clone(),
+                        })
+                        .execute(conn)
+                        .await?;
+
+                    enqueue_document(conn, document.id).await?;
+
+                    Ok::<_, UploadError>((document, outcome))
+                })
+            })
+            .await?;
+
+        match outcome {
+            StoreOutcome::Created => {
+                info!("Stored {} ({}) in project {}", document.filename, format_bytes(size), project_id)
+            }
+            StoreOutcome::Updated => info!(
+                "Stored {} v{} ({}) in project {}",
+                document.filename,
+                document.version,
+                format_bytes(size),
+                project_id
+            ),
+            StoreOutcome::Unchanged => debug!("{} in project {} is unchanged", document.filename, project_id),
+        }
+        Ok((document, outcome))
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub use document_uploads::*;
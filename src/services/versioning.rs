@@ -0,0 +1,107 @@
+#[cfg(feature = "ssr")]
+pub mod document_versioning {
+    use sha2::{Digest, Sha256};
+    use std::collections::HashMap;
+    use uuid::Uuid;
+
+    /// Hex SHA-256 of a document's or chunk's text
+    pub fn content_hash(text: &str) -> String {
+        format!("{:x}", Sha256::digest(text.as_bytes()))
+    }
+
+    /// How a re-chunked document maps onto the chunks already stored for it
+    #[derive(Debug, Default, PartialEq)]
+    pub struct ChunkReusePlan {
+        /// For each new chunk, the stored chunk with identical text whose embedding can be kept
+        pub reuse: Vec<Option<Uuid>>,
+        /// Stored chunks that no new chunk matched
+        pub stale: Vec<Uuid>,
+    }
+
+    impl ChunkReusePlan {
+        /// Indices of the new chunks that still need embedding
+        pub fn to_embed(&self) -> Vec<usize> {
+            self.reuse.iter().enumerate().filter(|(_, id)| id.is_none()).map(|(i, _)| i).collect()
+        }
+    }
+
+    /// Matches new chunk hashes against `existing` (chunk id and hash, in chunk order).
+    /// Each stored chunk is reused at most once, earliest first, so repeated
+    /// boilerplate chunks pair up in order.
+    pub fn plan_chunk_reuse(existing: &[(Uuid, Option<String>)], new_hashes: &[String]) -> ChunkReusePlan {
+        let mut available: HashMap<&str, Vec<Uuid>> = HashMap::new();
+        for (id, hash) in existing.iter().rev() {
+            if let Some(hash) = hash {
+                available.entry(hash.as_str()).or_default().push(*id);
+            }
+        }
+
+        let reuse: Vec<Option<Uuid>> = new_hashes
+            .iter()
+            .map(|hash| available.get_mut(hash.as_str()).and_then(Vec::pop))
+            .collect();
+
+        let stale = existing
+            .iter()
+            .map(|(id, _)| *id)
+            .filter(|id| !reuse.contains(&Some(*id)))
+            .collect();
+
+        ChunkReusePlan { reuse, stale }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn stored(texts: &[&str]) -> Vec<(Uuid, Option<String>)> {
+            texts.iter().map(|text| (Uuid::new_v4(), Some(content_hash(text)))).collect()
+        }
+
+        fn hashes(texts: &[&str]) -> Vec<String> {
+            texts.iter().map(|text| content_hash(text)).collect()
+        }
+
+        #[test]
+        fn test_unchanged_document_reuses_everything() {
+            let existing = stored(&["intro", "body", "outro"]);
+            let plan = plan_chunk_reuse(&existing, &hashes(&["intro", "body", "outro"]));
+
+            assert_eq!(plan.reuse, existing.iter().map(|(id, _)| Some(*id)).collect::<Vec<_>>());
+            assert!(plan.stale.is_empty());
+            assert!(plan.to_embed().is_empty());
+        }
+
+        #[test]
+        fn test_edit_only_embeds_changed_chunks() {
+            let existing = stored(&["intro", "body", "outro"]);
+            let plan = plan_chunk_reuse(&existing, &hashes(&["intro", "new section", "body v2", "outro"]));
+
+            assert_eq!(plan.to_embed(), vec![1, 2]);
+            assert_eq!(plan.reuse[0], Some(existing[0].0));
+            assert_eq!(plan.reuse[3], Some(existing[2].0));
+            assert_eq!(plan.stale, vec![existing[1].0]);
+        }
+
+        #[test]
+        fn test_duplicate_chunks_pair_in_order() {
+            let existing = stored(&["same", "same"]);
+            let plan = plan_chunk_reuse(&existing, &hashes(&["same", "same", "same"]));
+
+            assert_eq!(plan.reuse, vec![Some(existing[0].0), Some(existing[1].0), None]);
+            assert!(plan.stale.is_empty());
+        }
+
+        #[test]
+        fn test_chunks_without_hash_are_replaced() {
+            let existing = vec![(Uuid::new_v4(), None)];
+            let plan = plan_chunk_reuse(&existing, &hashes(&["text"]));
+
+            assert_eq!(plan.reuse, vec![None]);
+            assert_eq!(plan.stale, vec![existing[0].0]);
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub use document_versioning::*;
@@ -0,0 +1,174 @@
+#[cfg(feature = "ssr")]
+pub mod workspace_access {
+    //! Who may do what with a project or thread.
+    //!
+    //! Personal projects belong to `projects.user_id` alone. Workspace projects
+    //! are governed by the member's role; non-members get `NotFound`, so
+    //! project ids don't leak across workspaces.
+
+    use diesel::prelude::*;
+    use diesel_async::{AsyncPgConnection, RunQueryDsl};
+    use std::fmt;
+    use uuid::Uuid;
+
+    use crate::models::conversations::Thread;
+    use crate::models::projects::Project;
+    use crate::models::workspaces::{thread_readable, thread_writable, Permission, ProjectAccess, WorkspaceRole};
+    use crate::schema::{projects, threads, workspace_members};
+
+    #[derive(Debug)]
+    pub enum AccessError {
+        NotFound(&'static str),
+        Forbidden(Permission),
+        Database(diesel::result::Error),
+    }
+
+    impl fmt::Display for AccessError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                AccessError::NotFound(what) => write!(f, "{what} not found"),
+                AccessError::Forbidden(Permission::Read) => write!(f, "You don't have access to this project"),
+                AccessError::Forbidden(Permission::Edit) => write!(f, "Your workspace role can't change this project"),
+                AccessError::Forbidden(Permission::Manage) => write!(f, "Only workspace owners can do this"),
+                AccessError::Database(e) => write!(f, "Database error: {e}"),
+            }
+        }
+    }
+
+    impl std::error::Error for AccessError {}
+
+    impl From<diesel::result::Error> for AccessError {
+        fn from(e: diesel::result::Error) -> Self {
+            AccessError::Database(e)
+        }
+    }
+
+    pub async fn workspace_role(conn: &mut AsyncPgConnection, workspace_id: Uuid, user_id: i32) -> QueryResult<Option<WorkspaceRole>> {
+        let role: Option<String> = workspace_members::table
+            .find((workspace_id, user_id))
+            .select(workspace_members::role)
+            .first(conn)
+            .await
+            .optional()?;
+        Ok(role.map(|role| WorkspaceRole::parse(&role).unwrap_or(WorkspaceRole::Viewer)))
+    }
+
+    /// The user's role on a project; owners of personal projects count as `Owner`
+    pub async fn project_role(conn: &mut AsyncPgConnection, user_id: i32, project: &Project) -> QueryResult<Option<WorkspaceRole>> {
+        match project.workspace_id {
+            None => Ok((project.user_id == user_id).then_some(WorkspaceRole::Owner)),
+            Some(workspace_id) => workspace_role(conn, workspace_id, user_id).await,
+        }
+    }
+
+    /// The project, if `user_id` may act on it with `permission`
+    pub async fn authorize_project(
+        conn: &mut AsyncPgConnection,
+        user_id: i32,
+        project_id: Uuid,
+        permission: Permission,
+    ) -> Result<Project, AccessError> {
+        let project: Project = projects::table
+            .find(project_id)
+            .first(conn)
+            .await
+            .optional()?
+            .ok_or(AccessError::NotFound("Project"))?;
+        match project_role(conn, user_id, &project).await? {
+            Some(role) if role.allows(permission) => Ok(project),
+            Some(_) => Err(AccessError::Forbidden(permission)),
+            None => Err(AccessError::NotFound("Project")),
+        }
+    }
+
+    /// Ids of the workspaces `user_id` belongs to
+    pub async fn member_workspaces(conn: &mut AsyncPgConnection, user_id: i32) -> QueryResult<Vec<Uuid>> {
+        workspace_members::table
+            .filter(workspace_members::user_id.eq(user_id))
+            .select(workspace_members::workspace_id)
+            .load(conn)
+            .await
+    }
+
+    /// Personal projects plus those of every workspace the user belongs to, newest first
+    pub async fn visible_projects(conn: &mut AsyncPgConnection, user_id: i32) -> QueryResult<Vec<Project>> {
+        let workspace_ids = member_workspaces(conn, user_id).await?;
+        projects::table
+            .filter(
+                projects::workspace_id.is_null().and(projects::user_id.eq(user_id))
+                    .or(projects::workspace_id.eq_any(workspace_ids)),
+            )
+            .order(projects::created_at.desc())
+            .load(conn)
+            .await
+    }
+
+    /// The thread and the user's standing on its project
+    async fn thread_with_access(
+        conn: &mut AsyncPgConnection,
+        user_id: i32,
+        thread_id: &str,
+    ) -> Result<(Thread, ProjectAccess), AccessError> {
+        let thread: Thread = threads::table
+            .find(thread_id)
+            .first(conn)
+            .await
+            .optional()?
+            .ok_or(AccessError::NotFound("Thread"))?;
+
+        let Some(project_id) = thread.project_id else {
+            return Ok((thread, ProjectAccess::NoProject));
+        };
+        let project: Option<Project> = projects::table.find(project_id).first(conn).await.optional()?;
+        let role = match project {
+            Some(project) => project_role(conn, user_id, &project).await?,
+            None => None,
+        };
+        Ok((thread, ProjectAccess::Role(role)))
+    }
+
+    /// The thread, if it is the user's own or shared with a workspace they belong
+    /// to, and they can still open its project
+    pub async fn readable_thread(conn: &mut AsyncPgConnection, user_id: i32, thread_id: &str) -> Result<Thread, AccessError> {
+        let (thread, access) = thread_with_access(conn, user_id, thread_id).await?;
+        if thread_readable(thread.user_id, &thread.visibility, user_id, access) {
+            Ok(thread)
+        } else {
+            Err(AccessError::NotFound("Thread"))
+        }
+    }
+
+    /// The thread, if the user may post in it: their own, or a collaborative one they can edit
+    pub async fn writable_thread(conn: &mut AsyncPgConnection, user_id: i32, thread_id: &str) -> Result<Thread, AccessError> {
+        let (thread, access) = thread_with_access(conn, user_id, thread_id).await?;
+        if thread_writable(thread.user_id, &thread.visibility, user_id, access) {
+            Ok(thread)
+        } else if thread_readable(thread.user_id, &thread.visibility, user_id, access) {
+            Err(AccessError::Forbidden(Permission::Edit))
+        } else {
+            Err(AccessError::NotFound("Thread"))
+        }
+    }
+
+    /// One of the user's own threads, checked as [`readable_thread`] or, for `Edit`,
+    /// [`writable_thread`], so losing access to its project loses the thread too
+    pub async fn own_thread(
+        conn: &mut AsyncPgConnection,
+        user_id: i32,
+        thread_id: &str,
+        permission: Permission,
+    ) -> Result<Thread, AccessError> {
+        let thread = match permission {
+            Permission::Read => readable_thread(conn, user_id, thread_id).await?,
+            Permission::Edit | Permission::Manage => writable_thread(conn, user_id, thread_id).await?,
+        };
+        if thread.user_id == Some(user_id) {
+            Ok(thread)
+        } else {
+            Err(AccessError::NotFound("Thread"))
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub use workspace_access::*;
@@ -6,13 +6,17 @@ cfg_if! {
         use axum::response::sse::Event;
         use dashmap::DashMap;
         use leptos::prelude::LeptosOptions;
+        use chrono::NaiveDateTime;
         use std::convert::Infallible;
         use std::sync::Arc;
         use tokio::sync::mpsc;
+        use uuid::Uuid;
 
         use crate::cancellable_sse::SseState;
         use crate::database::db::DbPool;
+        use crate::auth::identities::PendingMerges;
         use crate::auth::oauth::OAuthState;
+        use crate::services::collab::ThreadRooms;
 
         pub type TitleUpdateSender = mpsc::Sender<Result<Event, Infallible>>;
         pub type TitleUpdateSenders = Arc<DashMap<i32, TitleUpdateSender>>;
@@ -23,7 +27,13 @@ cfg_if! {
             pub pool: DbPool,
             pub sse_state: SseState,
             pub oauth_states: Arc<DashMap<String, OAuthState>>,
+            /// Account merges offered after linking a provider that belongs to another user
+            pub pending_merges: Arc<PendingMerges>,
+            /// Sessions revoked by this process, until their access tokens lapse
+            pub revoked_sessions: Arc<DashMap<Uuid, NaiveDateTime>>,
             pub title_update_senders: TitleUpdateSenders,
+            /// Live viewers of each open thread
+            pub thread_rooms: ThreadRooms,
         }
 
         impl AppState {
@@ -33,7 +43,10 @@ cfg_if! {
                     pool,
                     sse_state: SseState::new(),
                     oauth_states: Arc::new(DashMap::new()),
+                    pending_merges: Arc::new(DashMap::new()),
+                    revoked_sessions: Arc::new(DashMap::new()),
                     title_update_senders: Arc::new(DashMap::new()),
+                    thread_rooms: ThreadRooms::new(),
                 }
             }
         }